@@ -15,6 +15,7 @@ fn startup(mut commands: Commands, asset_server: Res<AssetServer>) {
             // 12 tiles wide and 1 tile tall.
             render_chunk_size: UVec2::new(3, 1),
             y_sort: true,
+            ..Default::default()
         },
         ..Default::default()
     });
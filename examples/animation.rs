@@ -110,16 +110,20 @@ fn create_animated_flowers(mut commands: Commands, asset_server: Res<AssetServer
     }
     let map_type = TilemapType::Square;
 
-    commands.entity(tilemap_entity).insert(TilemapBundle {
-        size: map_size,
-        grid_size,
-        map_type,
-        tile_size,
-        storage: tile_storage,
-        texture: TilemapTexture::Single(texture_handle),
-        transform: get_tilemap_center_transform(&map_size, &grid_size, &map_type, 1.0),
-        ..Default::default()
-    });
+    commands.entity(tilemap_entity).insert((
+        TilemapBundle {
+            size: map_size,
+            grid_size,
+            map_type,
+            tile_size,
+            storage: tile_storage,
+            texture: TilemapTexture::Single(texture_handle),
+            transform: get_tilemap_center_transform(&map_size, &grid_size, &map_type, 1.0),
+            ..Default::default()
+        },
+        // Offsets each flower by up to a whole cycle, so that they don't bloom in lockstep.
+        TilemapAnimationPhase(1.0),
+    ));
 }
 
 fn startup(mut commands: Commands) {
@@ -0,0 +1,205 @@
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    prelude::*,
+};
+use bevy_ecs_tilemap::helpers::distance_field::build_distance_field;
+use bevy_ecs_tilemap::helpers::flow::{build_flow_field, flow_field_at_world_pos};
+use bevy_ecs_tilemap::prelude::*;
+use rand::{thread_rng, Rng};
+
+mod helpers;
+
+// Hundreds of agents walk towards the goal by following a flow field, which is rebuilt whenever
+// the goal moves. Left click to move the goal, right click to toggle walls.
+
+const MAP_SIZE: TilemapSize = TilemapSize { x: 48, y: 48 };
+const AGENT_COUNT: usize = 500;
+const AGENT_SPEED: f32 = 60.0;
+const FLOOR: u32 = 0;
+const WALL: u32 = 3;
+
+#[derive(Component)]
+struct Agent;
+
+/// The goal of the agents, and the flow field leading to it.
+#[derive(Resource)]
+struct Goal {
+    tile_pos: TilePos,
+    flow: TileDataLayer<Option<Dir2>>,
+}
+
+fn startup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn(Camera2d);
+
+    let texture_handle: Handle<Image> = asset_server.load("tiles.png");
+    let tilemap_entity = commands.spawn_empty().id();
+    let mut tile_storage = TileStorage::empty(MAP_SIZE);
+    let mut random = thread_rng();
+    let mut floors = Vec::new();
+
+    for x in 0..MAP_SIZE.x {
+        for y in 0..MAP_SIZE.y {
+            let tile_pos = TilePos { x, y };
+            let texture_index = if random.gen_bool(0.15) { WALL } else { FLOOR };
+            if texture_index == FLOOR {
+                floors.push(tile_pos);
+            }
+            let tile_entity = commands
+                .spawn(TileBundle {
+                    position: tile_pos,
+                    tilemap_id: TilemapId(tilemap_entity),
+                    texture_index: TileTextureIndex(texture_index),
+                    ..Default::default()
+                })
+                .id();
+            tile_storage.set(&tile_pos, tile_entity);
+        }
+    }
+
+    let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
+    let grid_size = tile_size.into();
+    let map_type = TilemapType::default();
+    let transform = get_tilemap_center_transform(&MAP_SIZE, &grid_size, &map_type, 0.0);
+
+    for _ in 0..AGENT_COUNT {
+        let tile_pos = floors[random.gen_range(0..floors.len())];
+        let position =
+            transform.transform_point(tile_pos.center_in_world(&grid_size, &map_type).extend(1.0));
+        commands.spawn((
+            Agent,
+            Sprite::from_color(Color::srgb(0.9, 0.2, 0.2), Vec2::splat(4.0)),
+            Transform::from_translation(position),
+        ));
+    }
+
+    commands.entity(tilemap_entity).insert(TilemapBundle {
+        grid_size,
+        map_type,
+        size: MAP_SIZE,
+        storage: tile_storage,
+        texture: TilemapTexture::Single(texture_handle),
+        tile_size,
+        transform,
+        ..Default::default()
+    });
+
+    commands.insert_resource(Goal {
+        tile_pos: TilePos::new(MAP_SIZE.x / 2, MAP_SIZE.y / 2),
+        flow: TileDataLayer::new(MAP_SIZE, None),
+    });
+}
+
+/// Moves the goal, or toggles walls, under the cursor.
+fn edit_map(
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    tilemaps: Query<(
+        &TileStorage,
+        &TilemapSize,
+        &TilemapGridSize,
+        &TilemapType,
+        &GlobalTransform,
+    )>,
+    mut tiles: Query<&mut TileTextureIndex>,
+    mut goal: ResMut<Goal>,
+) {
+    let left = mouse.just_pressed(MouseButton::Left);
+    let right = mouse.just_pressed(MouseButton::Right);
+    if !left && !right {
+        return;
+    }
+    let Some(cursor) = windows.iter().find_map(|window| window.cursor_position()) else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    let Ok(world_pos) = camera.viewport_to_world_2d(camera_transform, cursor) else {
+        return;
+    };
+
+    for (storage, map_size, grid_size, map_type, map_transform) in tilemaps.iter() {
+        let local_pos = map_transform
+            .affine()
+            .inverse()
+            .transform_point3(world_pos.extend(0.0))
+            .truncate();
+        let Some(tile_pos) = TilePos::from_world_pos(&local_pos, map_size, grid_size, map_type)
+        else {
+            continue;
+        };
+        if left {
+            goal.tile_pos = tile_pos;
+        } else if let Some(mut texture_index) = storage
+            .get(&tile_pos)
+            .and_then(|tile| tiles.get_mut(tile).ok())
+        {
+            texture_index.0 = if texture_index.0 == WALL { FLOOR } else { WALL };
+        }
+    }
+}
+
+/// Rebuilds the flow field when the goal moved or walls changed.
+fn update_flow_field(
+    mut goal: ResMut<Goal>,
+    tilemaps: Query<(&TileStorage, &TilemapSize, &TilemapGridSize, &TilemapType)>,
+    changed_tiles: Query<(), Changed<TileTextureIndex>>,
+    tiles: Query<&TileTextureIndex>,
+) {
+    if !goal.is_changed() && changed_tiles.is_empty() {
+        return;
+    }
+    let Ok((storage, map_size, grid_size, map_type)) = tilemaps.get_single() else {
+        return;
+    };
+    let cost = |tile_pos: &TilePos| {
+        let texture_index = storage
+            .get(tile_pos)
+            .and_then(|tile| tiles.get(tile).ok())?;
+        (texture_index.0 != WALL).then_some(1)
+    };
+    let distances = build_distance_field(map_size, map_type, [(goal.tile_pos, 0)], cost);
+    goal.flow = build_flow_field(&distances, grid_size, map_type);
+}
+
+fn move_agents(
+    time: Res<Time>,
+    goal: Res<Goal>,
+    tilemaps: Query<(&TilemapGridSize, &TilemapType, &GlobalTransform)>,
+    mut agents: Query<&mut Transform, With<Agent>>,
+) {
+    let Ok((grid_size, map_type, map_transform)) = tilemaps.get_single() else {
+        return;
+    };
+    agents.par_iter_mut().for_each(|mut transform| {
+        let position = transform.translation.truncate();
+        if let Some(direction) =
+            flow_field_at_world_pos(position, &goal.flow, grid_size, map_type, map_transform)
+        {
+            transform.translation += (direction * AGENT_SPEED * time.delta_secs()).extend(0.0);
+        }
+    });
+}
+
+fn main() {
+    App::new()
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: String::from("Flow Field Example"),
+                        ..Default::default()
+                    }),
+                    ..default()
+                })
+                .set(ImagePlugin::default_nearest()),
+        )
+        .add_plugins(LogDiagnosticsPlugin::default())
+        .add_plugins(FrameTimeDiagnosticsPlugin)
+        .add_plugins(TilemapPlugin)
+        .add_systems(Startup, startup)
+        .add_systems(Update, helpers::camera::movement)
+        .add_systems(Update, (edit_map, update_flow_field, move_agents).chain())
+        .run();
+}
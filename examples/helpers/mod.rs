@@ -1,3 +1,2 @@
 pub mod camera;
-pub mod ldtk;
 pub mod tiled;
@@ -3,8 +3,7 @@ use bevy_ecs_tilemap::prelude::*;
 mod helpers;
 use helpers::camera::movement as camera_movement;
 
-// Press SPACE to change map type. Hover over a tile to highlight its label (red) and those of its
-// neighbors (blue). Press and hold one of keys 0-5 to mark the neighbor in that direction (green).
+// Press SPACE to change map type.
 
 // You can increase the MAP_SIDE_LENGTH, in order to test larger maps but just make sure that you run
 // in release mode (`cargo run --release --example hexagon_generation`) otherwise things might be too
@@ -1,14 +1,11 @@
-//! This example is capable of spawning tilemaps from [LDtk](https://ldtk.io) files.
+//! This example spawns tilemaps from an [LDtk](https://ldtk.io) file with the `ldtk` feature.
 //!
-//! It can load the AutoTile and Tile layers of simple LDtk levels.
-//! However, it does have limitations.
-//! Some edge cases around tileset definitions and layer definitions haven't been considered here.
-//! Furthermore, since this example is primarily concerned with the tilemap functionality,
-//! there's no solution built in for Entity or Intgrid layers.
+//! Tile, AutoLayer and IntGrid layers are spawned as tilemaps, but Entity layers are left out.
 //!
 //! For a more comprehensive LDtk solution, consider [bevy_ecs_ldtk](https://github.com/Trouv/bevy_ecs_ldtk), which uses bevy_ecs_tilemap internally.
 
 use bevy::prelude::*;
+use bevy_ecs_tilemap::helpers::ldtk::{LdtkMapBundle, LdtkMapHandle, LdtkMapPlugin};
 use bevy_ecs_tilemap::*;
 
 mod helpers;
@@ -16,9 +13,9 @@ mod helpers;
 fn startup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn(Camera2d);
 
-    let handle = helpers::ldtk::LdtkMapHandle(asset_server.load("map.ldtk"));
+    let handle = LdtkMapHandle(asset_server.load("map.ldtk"));
 
-    commands.spawn(helpers::ldtk::LdtkMapBundle {
+    commands.spawn(LdtkMapBundle {
         ldtk_map: handle,
         transform: Transform::from_xyz(0.0, 0.0, 0.0),
         ..Default::default()
@@ -39,7 +36,7 @@ fn main() {
                 .set(ImagePlugin::default_nearest()),
         )
         .add_plugins(TilemapPlugin)
-        .add_plugins(helpers::ldtk::LdtkPlugin)
+        .add_plugins(LdtkMapPlugin)
         .add_systems(Startup, startup)
         .add_systems(Update, helpers::camera::movement)
         .run();
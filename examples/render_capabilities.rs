@@ -0,0 +1,122 @@
+//! Example showing the render capabilities the plugin detected at runtime.
+//!
+//! Run it natively, in a browser with WebGL2, and in one with WebGPU (`--features=bevy/webgpu`),
+//! to see how the texture strategy and shader defs adapt to the device.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+mod helpers;
+
+#[derive(Component)]
+struct CapabilitiesText;
+
+fn startup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn(Camera2d);
+
+    let texture_handle: Handle<Image> = asset_server.load("tiles.png");
+    let map_size = TilemapSize { x: 32, y: 32 };
+    let tilemap_entity = commands.spawn_empty().id();
+    let mut tile_storage = TileStorage::empty(map_size);
+    fill_tilemap(
+        TileTextureIndex(0),
+        map_size,
+        TilemapId(tilemap_entity),
+        &mut commands,
+        &mut tile_storage,
+    );
+
+    let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
+    let grid_size = tile_size.into();
+    let map_type = TilemapType::default();
+
+    commands.entity(tilemap_entity).insert(TilemapBundle {
+        grid_size,
+        map_type,
+        size: map_size,
+        storage: tile_storage,
+        texture: TilemapTexture::Single(texture_handle),
+        tile_size,
+        transform: get_tilemap_center_transform(&map_size, &grid_size, &map_type, 0.0),
+        ..Default::default()
+    });
+
+    commands.spawn((
+        CapabilitiesText,
+        Text::default(),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        },
+    ));
+}
+
+fn show_capabilities(
+    capabilities: Res<TilemapRenderCapabilities>,
+    #[cfg(not(feature = "atlas"))] strategies: Res<TilemapTextureStrategies>,
+    tilemaps: Query<&TilemapTexture>,
+    mut text: Query<&mut Text, With<CapabilitiesText>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+
+    let shader_defs: Vec<String> = capabilities
+        .shader_defs()
+        .iter()
+        .map(|shader_def| format!("{shader_def:?}"))
+        .collect();
+    let mut lines = vec![
+        format!("GL backend: {}", capabilities.gl_backend),
+        format!("Storage buffers: {}", capabilities.storage_buffers),
+        format!(
+            "Max texture size: {} px",
+            capabilities.max_texture_dimension_2d
+        ),
+        format!(
+            "Max texture array layers: {}",
+            capabilities.max_texture_array_layers
+        ),
+        format!("Shader defs: {}", shader_defs.join(", ")),
+    ];
+
+    for texture in tilemaps.iter() {
+        #[cfg(not(feature = "atlas"))]
+        let strategy = strategies
+            .get(texture)
+            .map_or_else(|| String::from("not prepared yet"), |s| format!("{s:?}"));
+        #[cfg(feature = "atlas")]
+        let strategy = String::from("Atlas (feature)");
+        lines.push(format!(
+            "Texture {:?}: {strategy}",
+            texture.image_handles()[0].id()
+        ));
+    }
+
+    let value = lines.join("\n");
+    if text.0 != value {
+        text.0 = value;
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: String::from("Render Capabilities Example"),
+                        ..Default::default()
+                    }),
+                    ..default()
+                })
+                .set(ImagePlugin::default_nearest()),
+        )
+        .add_plugins(TilemapPlugin)
+        .add_systems(Startup, startup)
+        .add_systems(Update, helpers::camera::movement)
+        .add_systems(Update, show_capabilities)
+        .run();
+}
@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::helpers::labels::{TileLabelStyle, TileLabelsPlugin};
+use bevy_ecs_tilemap::prelude::*;
+
+mod helpers;
+
+// Every tile is labelled with its position by the `TileLabelsPlugin`, and the labels follow the
+// tiles as they change. Press SPACE to shuffle the tiles. Zoom out with X to hide the labels, and
+// back in with Z to show them again.
+
+fn startup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn(Camera2d);
+
+    let texture_handle: Handle<Image> = asset_server.load("tiles.png");
+    let map_size = TilemapSize { x: 12, y: 12 };
+    let tilemap_entity = commands.spawn_empty().id();
+    let mut tile_storage = TileStorage::empty(map_size);
+    fill_tilemap(
+        TileTextureIndex(0),
+        map_size,
+        TilemapId(tilemap_entity),
+        &mut commands,
+        &mut tile_storage,
+    );
+
+    let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
+    let grid_size = TilemapGridSize { x: 32.0, y: 32.0 };
+    let map_type = TilemapType::default();
+    commands.entity(tilemap_entity).insert(TilemapBundle {
+        grid_size,
+        map_type,
+        size: map_size,
+        storage: tile_storage,
+        texture: TilemapTexture::Single(texture_handle),
+        tile_size,
+        transform: get_tilemap_center_transform(&map_size, &grid_size, &map_type, 0.0),
+        ..Default::default()
+    });
+}
+
+// Swaps the positions of the tiles in mirrored halves of the map. The labels are updated to the
+// new positions by the plugin.
+fn shuffle_tiles(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut tilemap_query: Query<(&TilemapSize, &mut TileStorage)>,
+    mut tile_query: Query<&mut TilePos>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Space) {
+        return;
+    }
+    for (map_size, mut tile_storage) in tilemap_query.iter_mut() {
+        for x in 0..map_size.x / 2 {
+            for y in 0..map_size.y {
+                let left = TilePos { x, y };
+                let right = TilePos {
+                    x: map_size.x - 1 - x,
+                    y,
+                };
+                let (Some(left_tile), Some(right_tile)) =
+                    (tile_storage.get(&left), tile_storage.get(&right))
+                else {
+                    continue;
+                };
+                if let Ok(mut tile_pos) = tile_query.get_mut(left_tile) {
+                    *tile_pos = right;
+                }
+                if let Ok(mut tile_pos) = tile_query.get_mut(right_tile) {
+                    *tile_pos = left;
+                }
+                tile_storage.set(&left, right_tile);
+                tile_storage.set(&right, left_tile);
+            }
+        }
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: String::from("Tile labels - Press Space to shuffle the tiles"),
+                        ..Default::default()
+                    }),
+                    ..default()
+                })
+                .set(ImagePlugin::default_nearest()),
+        )
+        .add_plugins(TilemapPlugin)
+        .add_plugins(TileLabelsPlugin::<()>::new(TileLabelStyle {
+            color: Color::WHITE,
+            ..Default::default()
+        }))
+        .add_systems(Startup, startup)
+        .add_systems(Update, helpers::camera::movement)
+        .add_systems(Update, shuffle_tiles)
+        .run();
+}
@@ -0,0 +1,245 @@
+use crate::helpers::hex_grid::axial::AxialPos;
+use crate::helpers::square_grid::diamond::DiamondPos;
+use crate::helpers::square_grid::staggered::StaggeredPos;
+use crate::map::{ensure_unlocked, IsoCoordSystem, TilemapLockedError, TilemapSize, TilemapType};
+use crate::tiles::{TilePos, TileStorage};
+use bevy::ecs::world::{EntityWorldMut, World};
+use bevy::prelude::Entity;
+
+/// How the strength of an area effect fades with the distance from its center.
+///
+/// The distance is divided by `radius + 1`, so that tiles on the edge of the area still get a
+/// small, non-zero strength.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Falloff {
+    /// Every tile in the area gets the full strength.
+    Constant,
+    /// The strength fades linearly from the center.
+    #[default]
+    Linear,
+    /// The strength fades quickly near the center and slowly near the edge.
+    Quadratic,
+    /// The strength stays high near the center and fades along a smoothstep curve.
+    Smooth,
+}
+
+impl Falloff {
+    /// Returns the strength, between 0 and 1, of a tile `distance` tiles away from the center of
+    /// an area of `radius` tiles. Tiles outside of the area have a strength of 0.
+    ///
+    /// ```
+    /// # use bevy_ecs_tilemap::helpers::area_effect::Falloff;
+    /// assert_eq!(Falloff::Linear.strength(0, 3), 1.0);
+    /// assert_eq!(Falloff::Linear.strength(2, 3), 0.5);
+    /// assert_eq!(Falloff::Constant.strength(3, 3), 1.0);
+    /// assert_eq!(Falloff::Constant.strength(4, 3), 0.0);
+    /// ```
+    pub fn strength(&self, distance: u32, radius: u32) -> f32 {
+        if distance > radius {
+            return 0.0;
+        }
+        let t = distance as f32 / (radius + 1) as f32;
+        match self {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => 1.0 - t,
+            Falloff::Quadratic => (1.0 - t) * (1.0 - t),
+            Falloff::Smooth => 1.0 - t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// Returns the distance between two tiles in steps between adjacent tiles, counting diagonal
+/// steps on square and isometric maps: the Chebyshev distance on square and isometric maps, and
+/// the hex distance on hexagonal maps.
+///
+/// ```
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::area_effect::tile_distance;
+/// let a = TilePos::new(2, 2);
+/// let b = TilePos::new(5, 3);
+/// assert_eq!(tile_distance(&a, &b, &TilemapType::Square), 3);
+/// assert_eq!(tile_distance(&a, &b, &TilemapType::Hexagon(HexCoordSystem::Row)), 4);
+/// ```
+pub fn tile_distance(a: &TilePos, b: &TilePos, map_type: &TilemapType) -> u32 {
+    match map_type {
+        TilemapType::Square | TilemapType::Isometric(IsoCoordSystem::Diamond) => {
+            let a = DiamondPos::from(a);
+            let b = DiamondPos::from(b);
+            (a.x - b.x).unsigned_abs().max((a.y - b.y).unsigned_abs())
+        }
+        TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+            let a = DiamondPos::from(StaggeredPos::from(a));
+            let b = DiamondPos::from(StaggeredPos::from(b));
+            (a.x - b.x).unsigned_abs().max((a.y - b.y).unsigned_abs())
+        }
+        TilemapType::Hexagon(hex_coord_sys) => {
+            let a = AxialPos::from_tile_pos_given_coord_system(a, *hex_coord_sys);
+            let b = AxialPos::from_tile_pos_given_coord_system(b, *hex_coord_sys);
+            a.distance_from(&b).unsigned_abs()
+        }
+    }
+}
+
+/// Returns the tiles of the map within `radius` of `center`, as measured by [`tile_distance`],
+/// along with their distance.
+pub fn tiles_in_radius(
+    center: &TilePos,
+    radius: u32,
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+) -> impl Iterator<Item = (TilePos, u32)> {
+    // Staggered and offset hex coordinates shear the area, so that it can reach up to twice the
+    // radius away along the y axis.
+    let reach = radius.saturating_mul(2);
+    let min_x = center.x.saturating_sub(reach);
+    let min_y = center.y.saturating_sub(reach);
+    let max_x = center
+        .x
+        .saturating_add(reach)
+        .min(map_size.x.saturating_sub(1));
+    let max_y = center
+        .y
+        .saturating_add(reach)
+        .min(map_size.y.saturating_sub(1));
+    let (center, map_size, map_type) = (*center, *map_size, *map_type);
+    (min_y..=max_y)
+        .flat_map(move |y| (min_x..=max_x).map(move |x| TilePos::new(x, y)))
+        .filter(move |tile_pos| tile_pos.within_map_bounds(&map_size))
+        .filter_map(move |tile_pos| {
+            let distance = tile_distance(&center, &tile_pos, &map_type);
+            (distance <= radius).then_some((tile_pos, distance))
+        })
+}
+
+/// Calls `f` on every tile entity of `tilemap` within `radius` of `center`, with the strength
+/// given by `falloff` at its distance, e.g. to damage tiles caught in an explosion, raise
+/// terrain, or paint with a soft brush.
+///
+/// Distances are measured on the grid the tiles are drawn on, so the area is the same on maps
+/// with y-down [`TilemapAxes`](crate::map::TilemapAxes).
+///
+/// The tilemap must have a [`TileStorage`], a [`TilemapSize`] and a [`TilemapType`], or nothing
+/// happens. Positions without a tile are skipped. Run it from an exclusive system, or queue it
+/// with [`Commands::queue`](bevy::prelude::Commands::queue). Fails if the tilemap is
+/// [`TilemapLocked`](crate::map::TilemapLocked).
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::area_effect::{apply_to_radius, Falloff};
+/// fn explode(mut commands: Commands, tilemap: Single<Entity, With<TileStorage>>) {
+///     let tilemap = *tilemap;
+///     commands.queue(move |world: &mut World| {
+///         let explosion = apply_to_radius(world, tilemap, TilePos::new(8, 8), 3, Falloff::Linear, |tile, strength| {
+///             if let Some(mut color) = tile.get_mut::<TileColor>() {
+///                 color.0 = color.0.darker(strength * 0.5);
+///             }
+///         });
+///         if let Err(error) = explosion {
+///             warn!("{error}");
+///         }
+///     });
+/// }
+/// ```
+pub fn apply_to_radius(
+    world: &mut World,
+    tilemap: Entity,
+    center: TilePos,
+    radius: u32,
+    falloff: Falloff,
+    mut f: impl FnMut(&mut EntityWorldMut, f32),
+) -> Result<(), TilemapLockedError> {
+    ensure_unlocked(world, tilemap)?;
+    let Ok(tilemap) = world.get_entity(tilemap) else {
+        return Ok(());
+    };
+    let (Some(storage), Some(map_size), Some(map_type)) = (
+        tilemap.get::<TileStorage>(),
+        tilemap.get::<TilemapSize>(),
+        tilemap.get::<TilemapType>(),
+    ) else {
+        return Ok(());
+    };
+    // Distances are measured on the grid the tiles are drawn on.
+    let axes = storage.axes;
+    let grid_center = axes.to_grid_pos(&center, map_size);
+    let tiles: Vec<(Entity, u32)> = tiles_in_radius(&grid_center, radius, map_size, map_type)
+        .filter_map(|(grid_pos, distance)| {
+            let tile_pos = axes.to_grid_pos(&grid_pos, map_size);
+            Some((storage.get(&tile_pos)?, distance))
+        })
+        .collect();
+
+    for (tile_entity, distance) in tiles {
+        if let Ok(mut tile) = world.get_entity_mut(tile_entity) {
+            f(&mut tile, falloff.strength(distance, radius));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{HexCoordSystem, TilemapAxes};
+    use crate::test_utils::spawn_empty_test_map;
+    use crate::tiles::TileRect;
+
+    #[test]
+    fn areas_have_the_size_of_their_metric() {
+        let map_size = TilemapSize { x: 32, y: 32 };
+        let center = TilePos::new(16, 16);
+        for (map_type, expected) in [
+            (TilemapType::Square, 49),
+            (TilemapType::Isometric(IsoCoordSystem::Diamond), 49),
+            (TilemapType::Isometric(IsoCoordSystem::Staggered), 49),
+            (TilemapType::Hexagon(HexCoordSystem::Row), 37),
+            (TilemapType::Hexagon(HexCoordSystem::ColumnOdd), 37),
+        ] {
+            let count = tiles_in_radius(&center, 3, &map_size, &map_type).count();
+            assert_eq!(count, expected, "{map_type:?}");
+        }
+    }
+
+    #[test]
+    fn areas_are_measured_on_the_grid_of_y_down_maps() {
+        let mut world = World::new();
+        let map_size = TilemapSize { x: 5, y: 5 };
+        let map_type = TilemapType::Hexagon(HexCoordSystem::Row);
+        let tilemap = spawn_empty_test_map(&mut world, map_size, map_type);
+        let mut storage = TileStorage::empty_with_axes(map_size, TilemapAxes::Y_DOWN);
+        for tile_pos in TileRect::from_map_size(map_size).iter() {
+            storage.set(&tile_pos, world.spawn(tile_pos).id());
+        }
+        world.entity_mut(tilemap).insert(storage);
+
+        let center = TilePos::new(2, 1);
+        let mut hit = Vec::new();
+        apply_to_radius(
+            &mut world,
+            tilemap,
+            center,
+            1,
+            Falloff::Constant,
+            |tile, _| {
+                hit.push(*tile.get::<TilePos>().unwrap());
+            },
+        )
+        .unwrap();
+        hit.sort_by_key(|tile_pos| (tile_pos.y, tile_pos.x));
+
+        let axes = TilemapAxes::Y_DOWN;
+        let grid_center = axes.to_grid_pos(&center, &map_size);
+        let mut expected: Vec<TilePos> = tiles_in_radius(&grid_center, 1, &map_size, &map_type)
+            .map(|(grid_pos, _)| axes.to_grid_pos(&grid_pos, &map_size))
+            .collect();
+        expected.sort_by_key(|tile_pos| (tile_pos.y, tile_pos.x));
+        assert_eq!(hit, expected);
+        // Hexagons aren't symmetric along y, so the area isn't the one around the tile position.
+        let mut around_tile_pos: Vec<TilePos> = tiles_in_radius(&center, 1, &map_size, &map_type)
+            .map(|(tile_pos, _)| tile_pos)
+            .collect();
+        around_tile_pos.sort_by_key(|tile_pos| (tile_pos.y, tile_pos.x));
+        assert_ne!(hit, around_tile_pos);
+    }
+}
@@ -0,0 +1,533 @@
+use crate::map::{TilemapSpacing, TilemapTexture, TilemapTileSize};
+use crate::tiles::{AnimatedTile, AnimatedTileFrames, TileStorage, TileTextureIndex};
+use bevy::app::{App, Plugin, Update};
+use bevy::asset::{AssetServer, Assets, Handle, RenderAssetUsages};
+use bevy::image::Image;
+use bevy::log::warn;
+use bevy::prelude::{
+    Commands, Component, Entity, Query, Reflect, ReflectComponent, Res, ResMut, With,
+};
+use bevy::render::render_resource::{Extent3d, TextureDimension};
+use bevy::utils::HashMap;
+use std::fmt;
+
+/// Marks a tilemap whose tileset should be packed into an atlas shared with the other marked
+/// tilemaps, by the [`TilesetPackingPlugin`].
+///
+/// Only [`TilemapTexture::Single`] tilesets can be packed.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component)]
+pub struct PackTileset;
+
+/// Added to tilemaps whose tileset was packed into a shared atlas. The texture indices of their
+/// tiles were shifted by this offset, and tiles set later on must be shifted by it too.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct PackedTilesetOffset(pub u32);
+
+impl PackedTilesetOffset {
+    /// Returns the index in the shared atlas of the tile at `texture_index` in the original
+    /// tileset.
+    pub fn apply(&self, texture_index: TileTextureIndex) -> TileTextureIndex {
+        TileTextureIndex(texture_index.0 + self.0)
+    }
+}
+
+/// Copies the tiles of several tilesets into a new atlas with `columns` columns, without
+/// spacing, and returns it along with the index of the first tile of every tileset in it.
+///
+/// Tiles are copied in index order, so the tile at index `i` of tileset `n` ends up at index
+/// `offsets[n] + i`. Returns `None` if the tilesets don't all have the same uncompressed format.
+///
+/// ```
+/// # use bevy::image::Image;
+/// # use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+/// # use bevy::asset::RenderAssetUsages;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::atlas_packing::pack_tilesets;
+/// let tileset = |width: u32, height: u32, color: [u8; 4]| {
+///     let size = Extent3d { width, height, depth_or_array_layers: 1 };
+///     let format = TextureFormat::Rgba8UnormSrgb;
+///     Image::new_fill(size, TextureDimension::D2, &color, format, RenderAssetUsages::default())
+/// };
+/// let grass = tileset(48, 16, [0, 255, 0, 255]);
+/// let water = tileset(16, 32, [0, 0, 255, 255]);
+/// let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
+/// let spacing = TilemapSpacing::zero();
+///
+/// let (atlas, offsets) =
+///     pack_tilesets(&[(&grass, spacing), (&water, spacing)], tile_size, 4).unwrap();
+/// assert_eq!(offsets, [0, 3]);
+/// assert_eq!(atlas.size().to_array(), [64, 32]);
+/// // The first water tile follows the three grass tiles.
+/// let pixel = |x: usize, y: usize| &atlas.data[(y * 64 + x) * 4..][..4];
+/// assert_eq!(pixel(47, 15), [0, 255, 0, 255]);
+/// assert_eq!(pixel(48, 0), [0, 0, 255, 255]);
+/// assert_eq!(pixel(0, 16), [0, 0, 255, 255]);
+/// ```
+pub fn pack_tilesets(
+    tilesets: &[(&Image, TilemapSpacing)],
+    tile_size: TilemapTileSize,
+    columns: u32,
+) -> Option<(Image, Vec<u32>)> {
+    let (first, _) = tilesets.first()?;
+    let format = first.texture_descriptor.format;
+    if format.is_compressed()
+        || tilesets
+            .iter()
+            .any(|(image, _)| image.texture_descriptor.format != format)
+    {
+        return None;
+    }
+    let pixel_size = format.block_copy_size(None)? as usize;
+    let (tile_width, tile_height) = (tile_size.x as usize, tile_size.y as usize);
+    let columns = columns.max(1) as usize;
+
+    let mut offsets = Vec::with_capacity(tilesets.len());
+    let mut tiles = Vec::new();
+    for (image, spacing) in tilesets.iter() {
+        offsets.push(tiles.len() as u32);
+        let (tileset_columns, tileset_rows) = tileset_grid(image, spacing, &tile_size);
+        for index in 0..tileset_columns * tileset_rows {
+            let x = spacing.x + (index % tileset_columns) as f32 * (tile_size.x + spacing.x);
+            let y = spacing.y + (index / tileset_columns) as f32 * (tile_size.y + spacing.y);
+            tiles.push((image, x as usize, y as usize));
+        }
+    }
+
+    let rows = tiles.len().div_ceil(columns).max(1);
+    let (atlas_width, atlas_height) = (columns * tile_width, rows * tile_height);
+    let mut data = vec![0; atlas_width * atlas_height * pixel_size];
+    for (index, (image, source_x, source_y)) in tiles.into_iter().enumerate() {
+        let source_width = image.width() as usize;
+        let (target_x, target_y) = (index % columns * tile_width, index / columns * tile_height);
+        for row in 0..tile_height {
+            let source = ((source_y + row) * source_width + source_x) * pixel_size;
+            let target = ((target_y + row) * atlas_width + target_x) * pixel_size;
+            let len = tile_width * pixel_size;
+            if let Some(source) = image.data.get(source..source + len) {
+                data[target..target + len].copy_from_slice(source);
+            }
+        }
+    }
+
+    let mut atlas = Image::new(
+        Extent3d {
+            width: atlas_width as u32,
+            height: atlas_height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        format,
+        RenderAssetUsages::default(),
+    );
+    atlas.sampler = first.sampler.clone();
+    Some((atlas, offsets))
+}
+
+/// Packs the tilesets of the tilemaps marked with [`PackTileset`] into shared atlases, so that
+/// many small tilemaps on screen don't each need their own texture bind.
+///
+/// Once the tilesets of all marked tilemaps are loaded, tilemaps with the same tile size and
+/// texture format are switched to one new atlas. The texture indices of their tiles, including
+/// [`AnimatedTile`] and [`AnimatedTileFrames`] frames, are shifted to point into it, and a
+/// [`PackedTilesetOffset`] is added for tiles set later on. Tilesets must keep their data in the
+/// main world, which is the default when loading images. Tilemaps marked later are packed into
+/// new atlases.
+pub struct TilesetPackingPlugin;
+
+impl Plugin for TilesetPackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<PackTileset>()
+            .register_type::<PackedTilesetOffset>()
+            .add_systems(Update, pack_marked_tilesets);
+    }
+}
+
+fn pack_marked_tilesets(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    asset_server: Option<Res<AssetServer>>,
+    tilemaps: Query<
+        (
+            Entity,
+            &TilemapTexture,
+            &TilemapTileSize,
+            &TilemapSpacing,
+            &TileStorage,
+        ),
+        With<PackTileset>,
+    >,
+    mut tiles: Query<(
+        &mut TileTextureIndex,
+        Option<&mut AnimatedTile>,
+        Option<&mut AnimatedTileFrames>,
+    )>,
+) {
+    if tilemaps.is_empty() {
+        return;
+    }
+
+    // Wait for every tileset, so that they all end up in the same atlas.
+    let mut groups: HashMap<_, Vec<(Entity, Handle<Image>, TilemapSpacing)>> = HashMap::new();
+    for (tilemap, texture, tile_size, spacing, _) in tilemaps.iter() {
+        #[cfg(feature = "atlas")]
+        let handle = texture.image_handle();
+        #[cfg(not(feature = "atlas"))]
+        let TilemapTexture::Single(handle) = texture
+        else {
+            warn!("Only single image tilesets can be packed, not packing tilemap {tilemap}.");
+            commands.entity(tilemap).remove::<PackTileset>();
+            continue;
+        };
+        let Some(image) = images.get(handle) else {
+            let failed = asset_server
+                .as_ref()
+                .is_some_and(|asset_server| asset_server.load_state(handle).is_failed());
+            if failed {
+                commands.entity(tilemap).remove::<PackTileset>();
+                continue;
+            }
+            return;
+        };
+        let key = (
+            tile_size.x.to_bits(),
+            tile_size.y.to_bits(),
+            image.texture_descriptor.format,
+        );
+        groups
+            .entry(key)
+            .or_default()
+            .push((tilemap, handle.clone(), *spacing));
+    }
+
+    for ((tile_width, tile_height, _), members) in groups {
+        let tile_size = TilemapTileSize {
+            x: f32::from_bits(tile_width),
+            y: f32::from_bits(tile_height),
+        };
+        let mut sources: Vec<(Handle<Image>, TilemapSpacing)> = Vec::new();
+        for (_, handle, spacing) in members.iter() {
+            if !sources.iter().any(|(other, _)| other == handle) {
+                sources.push((handle.clone(), *spacing));
+            }
+        }
+        let tilesets: Vec<(&Image, TilemapSpacing)> = sources
+            .iter()
+            .filter_map(|(handle, spacing)| Some((images.get(handle)?, *spacing)))
+            .collect();
+        let Some(tile_count) = count_tiles(&tilesets, tile_size) else {
+            continue;
+        };
+        let columns = (tile_count as f32).sqrt().ceil() as u32;
+        let Some((atlas, offsets)) = pack_tilesets(&tilesets, tile_size, columns) else {
+            warn!("Tilesets with a tile size of {tile_size:?} could not be packed.");
+            continue;
+        };
+        let atlas = images.add(atlas);
+
+        for (tilemap, handle, _) in members {
+            let Some(offset) = sources
+                .iter()
+                .position(|(other, _)| *other == handle)
+                .map(|source| offsets[source])
+            else {
+                continue;
+            };
+            if let Ok((.., storage)) = tilemaps.get(tilemap) {
+                for tile_entity in storage.iter().flatten() {
+                    let Ok((mut texture_index, animated_tile, animated_frames)) =
+                        tiles.get_mut(*tile_entity)
+                    else {
+                        continue;
+                    };
+                    texture_index.0 += offset;
+                    if let Some(mut animated_tile) = animated_tile {
+                        animated_tile.start += offset;
+                        animated_tile.end += offset;
+                    }
+                    if let Some(mut animated_frames) = animated_frames {
+                        for frame in animated_frames.frames.iter_mut() {
+                            frame.texture_index += offset;
+                        }
+                    }
+                }
+            }
+            commands.entity(tilemap).remove::<PackTileset>().insert((
+                TilemapTexture::Single(atlas.clone()),
+                TilemapSpacing::zero(),
+                PackedTilesetOffset(offset),
+            ));
+        }
+    }
+}
+
+/// Returns the number of tiles in all tilesets.
+fn count_tiles(tilesets: &[(&Image, TilemapSpacing)], tile_size: TilemapTileSize) -> Option<u32> {
+    let count: usize = tilesets
+        .iter()
+        .map(|(image, spacing)| {
+            let (columns, rows) = tileset_grid(image, spacing, &tile_size);
+            columns * rows
+        })
+        .sum();
+    (count > 0).then_some(count as u32)
+}
+
+/// Returns the number of tile columns and rows of a tileset, following the atlas lookup of the
+/// vertex shader.
+fn tileset_grid(
+    image: &Image,
+    spacing: &TilemapSpacing,
+    tile_size: &TilemapTileSize,
+) -> (usize, usize) {
+    let size = image.size_f32();
+    let columns = ((size.x - spacing.x) / (tile_size.x + spacing.x)).round();
+    let rows = ((size.y - spacing.y) / (tile_size.y + spacing.y)).round();
+    (columns.max(0.0) as usize, rows.max(0.0) as usize)
+}
+
+/// The error returned by [`TilesetAtlasBuilder::build`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TilesetAtlasError {
+    /// No tiles were added, or the tilesets are smaller than a tile.
+    Empty,
+    /// The image of a tileset isn't loaded, or its data was only kept in the render world.
+    NotLoaded(Handle<Image>),
+    /// The tilesets don't all have the same uncompressed format.
+    IncompatibleFormats,
+}
+
+impl fmt::Display for TilesetAtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TilesetAtlasError::Empty => write!(f, "the tilesets have no tiles"),
+            TilesetAtlasError::NotLoaded(handle) => {
+                write!(f, "the tileset {handle:?} is not loaded")
+            }
+            TilesetAtlasError::IncompatibleFormats => {
+                write!(f, "the tilesets don't share an uncompressed format")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TilesetAtlasError {}
+
+/// Maps the tile indices of the tilesets stitched by a [`TilesetAtlasBuilder`] to indices in
+/// the atlas, and back.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TileIndexRemap {
+    /// The index of the first tile of every tileset in the atlas.
+    offsets: Vec<u32>,
+    /// The number of tiles of every tileset.
+    counts: Vec<u32>,
+}
+
+impl TileIndexRemap {
+    /// Returns the index in the atlas of the tile at `index` in the tileset `tileset`, as
+    /// numbered by [`TilesetAtlasBuilder::add_tileset`].
+    pub fn get(&self, tileset: usize, index: u32) -> Option<TileTextureIndex> {
+        (index < *self.counts.get(tileset)?)
+            .then(|| TileTextureIndex(self.offsets[tileset] + index))
+    }
+
+    /// Returns the tileset and the index in it of the tile at `index` in the atlas.
+    pub fn source(&self, index: TileTextureIndex) -> Option<(usize, u32)> {
+        // Tilesets without tiles share their offset with the next tileset, so look for the range
+        // holding the index rather than the last offset before it.
+        self.offsets
+            .iter()
+            .zip(self.counts.iter())
+            .position(|(offset, count)| (*offset..*offset + *count).contains(&index.0))
+            .map(|tileset| (tileset, index.0 - self.offsets[tileset]))
+    }
+
+    /// Returns the index of the first tile of the tileset `tileset` in the atlas, e.g. for a
+    /// [`PackedTilesetOffset`].
+    pub fn offset(&self, tileset: usize) -> Option<u32> {
+        self.offsets.get(tileset).copied()
+    }
+
+    /// Returns the number of stitched tilesets.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns true if no tilesets were stitched.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+}
+
+/// Stitches tilesets loaded at runtime, e.g. from mods or DLC, into a single atlas texture, for
+/// tilemaps that draw tiles from several of them.
+///
+/// Unlike the [`TilesetPackingPlugin`], which repacks the tilesets of whole tilemaps, this leaves
+/// the texture indices of tiles to the caller, through the returned [`TileIndexRemap`]. Tilesets
+/// must be loaded, with their data kept in the main world.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+/// # use bevy::asset::RenderAssetUsages;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::atlas_packing::TilesetAtlasBuilder;
+/// # let mut images = Assets::<Image>::default();
+/// # let mut tileset = |width: u32, height: u32| {
+/// #     let size = Extent3d { width, height, depth_or_array_layers: 1 };
+/// #     let format = TextureFormat::Rgba8UnormSrgb;
+/// #     images.add(Image::new_fill(size, TextureDimension::D2, &[0; 4], format, RenderAssetUsages::default()))
+/// # };
+/// # let (base_handle, mod_handle) = (tileset(32, 32), tileset(16, 16));
+/// let mut builder = TilesetAtlasBuilder::new(TilemapTileSize { x: 16.0, y: 16.0 });
+/// let base = builder.add_tileset(base_handle);
+/// let modded = builder.add_tileset(mod_handle);
+///
+/// let (texture, remap) = builder.build(&mut images).unwrap();
+/// assert_eq!(remap.get(modded, 0), Some(TileTextureIndex(4)));
+/// assert_eq!(remap.source(TileTextureIndex(2)), Some((base, 2)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct TilesetAtlasBuilder {
+    pub tile_size: TilemapTileSize,
+    /// The number of tile columns of the atlas, or `None` for a roughly square atlas.
+    pub columns: Option<u32>,
+    tilesets: Vec<(Handle<Image>, TilemapSpacing)>,
+}
+
+impl TilesetAtlasBuilder {
+    pub fn new(tile_size: TilemapTileSize) -> Self {
+        Self {
+            tile_size,
+            columns: None,
+            tilesets: Vec::new(),
+        }
+    }
+
+    /// Sets the number of tile columns of the atlas, which is at least one.
+    pub fn with_columns(mut self, columns: u32) -> Self {
+        self.columns = Some(columns.max(1));
+        self
+    }
+
+    /// Adds a tileset without spacing, and returns its number in the [`TileIndexRemap`].
+    pub fn add_tileset(&mut self, image: Handle<Image>) -> usize {
+        self.add_tileset_with_spacing(image, TilemapSpacing::zero())
+    }
+
+    /// Adds a tileset whose tiles are `spacing` apart, and returns its number in the
+    /// [`TileIndexRemap`].
+    pub fn add_tileset_with_spacing(
+        &mut self,
+        image: Handle<Image>,
+        spacing: TilemapSpacing,
+    ) -> usize {
+        self.tilesets.push((image, spacing));
+        self.tilesets.len() - 1
+    }
+
+    /// Returns true once every tileset is loaded, so that [`Self::build`] can't fail on it.
+    pub fn is_ready(&self, images: &Assets<Image>) -> bool {
+        self.tilesets.iter().all(|(handle, _)| {
+            images
+                .get(handle)
+                .is_some_and(|image| !image.data.is_empty())
+        })
+    }
+
+    /// Stitches the tilesets into a new atlas added to `images`, and returns its texture along
+    /// with the remap table of the tile indices.
+    pub fn build(
+        &self,
+        images: &mut Assets<Image>,
+    ) -> Result<(TilemapTexture, TileIndexRemap), TilesetAtlasError> {
+        let mut tilesets = Vec::with_capacity(self.tilesets.len());
+        for (handle, spacing) in self.tilesets.iter() {
+            match images.get(handle) {
+                Some(image) if !image.data.is_empty() => tilesets.push((image, *spacing)),
+                _ => return Err(TilesetAtlasError::NotLoaded(handle.clone())),
+            }
+        }
+        let tile_count = count_tiles(&tilesets, self.tile_size).ok_or(TilesetAtlasError::Empty)?;
+        let columns = self
+            .columns
+            .unwrap_or_else(|| (tile_count as f32).sqrt().ceil() as u32)
+            .max(1);
+        let counts = tilesets
+            .iter()
+            .map(|(image, spacing)| {
+                let (columns, rows) = tileset_grid(image, spacing, &self.tile_size);
+                (columns * rows) as u32
+            })
+            .collect();
+        let (atlas, offsets) = pack_tilesets(&tilesets, self.tile_size, columns)
+            .ok_or(TilesetAtlasError::IncompatibleFormats)?;
+        Ok((
+            TilemapTexture::Single(images.add(atlas)),
+            TileIndexRemap { offsets, counts },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::render_resource::TextureFormat;
+
+    use super::*;
+
+    fn tileset(images: &mut Assets<Image>, width: u32, height: u32) -> Handle<Image> {
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let format = TextureFormat::Rgba8UnormSrgb;
+        images.add(Image::new_fill(
+            size,
+            TextureDimension::D2,
+            &[0; 4],
+            format,
+            RenderAssetUsages::default(),
+        ))
+    }
+
+    #[test]
+    fn remap_skips_empty_tilesets() {
+        let mut images = Assets::<Image>::default();
+        let mut builder = TilesetAtlasBuilder::new(TilemapTileSize { x: 16.0, y: 16.0 });
+        let empty_first = builder.add_tileset(tileset(&mut images, 4, 4));
+        let first = builder.add_tileset(tileset(&mut images, 32, 16));
+        let empty_between = builder.add_tileset(tileset(&mut images, 4, 4));
+        let second = builder.add_tileset(tileset(&mut images, 16, 16));
+        let empty_last = builder.add_tileset(tileset(&mut images, 4, 4));
+
+        let (_, remap) = builder.build(&mut images).unwrap();
+        assert_eq!(remap.get(empty_first, 0), None);
+        assert_eq!(remap.get(empty_between, 0), None);
+        assert_eq!(remap.get(empty_last, 0), None);
+        assert_eq!(remap.source(TileTextureIndex(0)), Some((first, 0)));
+        assert_eq!(remap.source(TileTextureIndex(1)), Some((first, 1)));
+        assert_eq!(remap.source(TileTextureIndex(2)), Some((second, 0)));
+        assert_eq!(remap.source(TileTextureIndex(3)), None);
+    }
+
+    #[test]
+    fn zero_columns_are_one_column() {
+        let mut images = Assets::<Image>::default();
+        let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
+        let mut builder = TilesetAtlasBuilder::new(tile_size).with_columns(0);
+        builder.add_tileset(tileset(&mut images, 32, 16));
+        assert_eq!(builder.columns, Some(1));
+
+        builder.columns = Some(0);
+        let (texture, _) = builder.build(&mut images).unwrap();
+        let atlas = match &texture {
+            TilemapTexture::Single(atlas) => images.get(atlas).unwrap(),
+            #[cfg(not(feature = "atlas"))]
+            _ => panic!("the atlas is a single image"),
+        };
+        assert_eq!(atlas.size().to_array(), [16, 32]);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::helpers::square_grid::line::segment_tiles;
+use crate::map::{TilemapGridSize, TilemapId, TilemapType};
+use crate::tiles::{TilePos, TileStorage, TileTextureIndex};
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::math::Vec3;
+use bevy::prelude::{
+    Added, Entity, Event, EventWriter, GlobalTransform, IntoSystemConfigs, OnRemove, Query, Trigger,
+};
+
+use crate::TilemapSystemSet;
+
+/// Sent when a tile is placed, with the world space position of its center.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TilePlacedAudioEvent {
+    pub tilemap: Entity,
+    pub tile_pos: TilePos,
+    pub world_pos: Vec3,
+    pub texture_index: TileTextureIndex,
+}
+
+/// Sent when a tile is removed, with the world space position of its center.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TileRemovedAudioEvent {
+    pub tilemap: Entity,
+    pub tile_pos: TilePos,
+    pub world_pos: Vec3,
+    pub texture_index: TileTextureIndex,
+}
+
+/// Sends a [`TilePlacedAudioEvent`] or [`TileRemovedAudioEvent`] whenever a tile is placed or
+/// removed, so that sounds can be played at the tile's location.
+///
+/// Placed tiles are reported in `PostUpdate`, after transform propagation. Removed tiles are
+/// reported right away, and are skipped if their tilemap is removed at the same time.
+pub struct TileAudioEventsPlugin;
+
+impl Plugin for TileAudioEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TilePlacedAudioEvent>()
+            .add_event::<TileRemovedAudioEvent>()
+            .add_systems(
+                PostUpdate,
+                send_tile_placed_events.after(TilemapSystemSet::TransformTracking),
+            )
+            .add_observer(send_tile_removed_event);
+    }
+}
+
+/// Counts the tiles for which `blocks` returns true along the straight line between two world
+/// positions, on a square tilemap.
+///
+/// The tiles containing `from` and `to` are not counted, so that sounds played inside a wall tile,
+/// like a door, aren't muffled by it. `blocks` is given each tile's position and entity.
+pub fn blocking_tiles_between(
+    from: Vec3,
+    to: Vec3,
+    tile_storage: &TileStorage,
+    grid_size: &TilemapGridSize,
+    map_transform: &GlobalTransform,
+    blocks: impl Fn(&TilePos, Entity) -> bool,
+) -> u32 {
+    let inverse = map_transform.affine().inverse();
+    let from = inverse.transform_point3(from).truncate();
+    let to = inverse.transform_point3(to).truncate();
+
+    let tiles: Vec<_> = segment_tiles(from, to, grid_size).collect();
+    let inner = tiles
+        .get(1..tiles.len().saturating_sub(1))
+        .unwrap_or_default();
+    inner
+        .iter()
+        .filter_map(|square_pos| {
+            let grid_pos = square_pos.as_tile_pos(&tile_storage.size)?;
+            let tile_pos = tile_storage.axes.to_grid_pos(&grid_pos, &tile_storage.size);
+            let tile_entity = tile_storage.get(&tile_pos)?;
+            blocks(&tile_pos, tile_entity).then_some(())
+        })
+        .count() as u32
+}
+
+/// Returns how much the tiles between a listener and a sound source muffle the sound, from `0.0`
+/// for a clear line to `1.0` for a sound that is completely blocked, on a square tilemap.
+///
+/// Each tile counted by [`blocking_tiles_between`] lets `1.0 - absorption` of the sound through,
+/// so that thick walls muffle more than thin ones without a physics engine. The factor can drive
+/// the volume or a low-pass filter of the sound.
+///
+/// ```
+/// # use bevy::prelude::{GlobalTransform, Vec3, World};
+/// # use bevy_ecs_tilemap::helpers::audio::audio_occlusion;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::test_utils::spawn_test_map;
+/// # let mut world = World::new();
+/// let map = spawn_test_map(&mut world, TilemapSize { x: 8, y: 8 }, TilemapType::Square);
+/// let storage = world.get::<TileStorage>(map).unwrap();
+/// let grid_size = world.get::<TilemapGridSize>(map).unwrap();
+/// let transform = GlobalTransform::default();
+/// // Walls along x = 3 and x = 4.
+/// let walls = |tile_pos: &TilePos, _| tile_pos.x == 3 || tile_pos.x == 4;
+///
+/// let listener = Vec3::new(0.0, 16.0, 0.0);
+/// let source = Vec3::new(112.0, 16.0, 0.0);
+/// let occlusion = audio_occlusion(listener, source, storage, grid_size, &transform, 0.5, walls);
+/// assert_eq!(occlusion, 0.75);
+///
+/// let source = Vec3::new(32.0, 16.0, 0.0);
+/// let occlusion = audio_occlusion(listener, source, storage, grid_size, &transform, 0.5, walls);
+/// assert_eq!(occlusion, 0.0);
+/// ```
+pub fn audio_occlusion(
+    listener: Vec3,
+    source: Vec3,
+    tile_storage: &TileStorage,
+    grid_size: &TilemapGridSize,
+    map_transform: &GlobalTransform,
+    absorption: f32,
+    blocks: impl Fn(&TilePos, Entity) -> bool,
+) -> f32 {
+    let count = blocking_tiles_between(
+        listener,
+        source,
+        tile_storage,
+        grid_size,
+        map_transform,
+        blocks,
+    );
+    1.0 - (1.0 - absorption.clamp(0.0, 1.0)).powi(count as i32)
+}
+
+type TilemapQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static TileStorage,
+        &'static TilemapGridSize,
+        &'static TilemapType,
+        &'static GlobalTransform,
+    ),
+>;
+
+/// Returns the world space center of `tile_pos` in `tilemap`.
+fn tile_world_pos(tile_pos: &TilePos, tilemap: TilemapId, tilemaps: &TilemapQuery) -> Option<Vec3> {
+    let (tile_storage, grid_size, map_type, map_transform) = tilemaps.get(tilemap.0).ok()?;
+    let local_pos = tile_pos.center_in_world_with_axes(
+        &tile_storage.size,
+        grid_size,
+        map_type,
+        &tile_storage.axes,
+    );
+    Some(map_transform.transform_point(local_pos.extend(0.0)))
+}
+
+fn send_tile_placed_events(
+    mut events: EventWriter<TilePlacedAudioEvent>,
+    placed_tiles: Query<(&TilePos, &TilemapId, &TileTextureIndex), Added<TilePos>>,
+    tilemaps: TilemapQuery,
+) {
+    for (tile_pos, tilemap_id, texture_index) in placed_tiles.iter() {
+        if let Some(world_pos) = tile_world_pos(tile_pos, *tilemap_id, &tilemaps) {
+            events.send(TilePlacedAudioEvent {
+                tilemap: tilemap_id.0,
+                tile_pos: *tile_pos,
+                world_pos,
+                texture_index: *texture_index,
+            });
+        }
+    }
+}
+
+fn send_tile_removed_event(
+    trigger: Trigger<OnRemove, TilePos>,
+    mut events: EventWriter<TileRemovedAudioEvent>,
+    tiles: Query<(&TilePos, &TilemapId, &TileTextureIndex)>,
+    tilemaps: TilemapQuery,
+) {
+    let Ok((tile_pos, tilemap_id, texture_index)) = tiles.get(trigger.entity()) else {
+        return;
+    };
+    if let Some(world_pos) = tile_world_pos(tile_pos, *tilemap_id, &tilemaps) {
+        events.send(TileRemovedAudioEvent {
+            tilemap: tilemap_id.0,
+            tile_pos: *tile_pos,
+            world_pos,
+            texture_index: *texture_index,
+        });
+    }
+}
@@ -0,0 +1,116 @@
+use crate::helpers::tactics::adjacent_tiles;
+use crate::map::TilemapType;
+use crate::tiles::{TileDataLayer, TilePos, TileStorage, TileTextureIndex};
+use bevy::prelude::Query;
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+
+/// Runs one step of a cellular automaton over `read`, writing the next state of every tile to
+/// `write`, e.g. to smooth random noise into caves or to spread fluids.
+///
+/// `rule` is given a tile position, its current value, and the current values of its adjacent
+/// tiles that lie on the map, and returns the tile's next value. Diagonal tiles are only included
+/// on square and isometric maps, if `diagonals` is true. Tiles on the map's border have fewer
+/// neighbors, which rules counting walls usually treat as walls.
+///
+/// Rows are split over the compute task pool, so `rule` must not depend on the order tiles are
+/// visited in. `write` is resized to the size of `read` if needed, and the two can be swapped
+/// between steps to avoid allocating.
+///
+/// ```
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::automata::step_automata;
+/// let map_size = TilemapSize { x: 5, y: 5 };
+/// // A wall everywhere, except for a hole in the middle and a lonely wall inside it.
+/// let mut read = TileDataLayer::new(map_size, true);
+/// for x in 1..4 {
+///     for y in 1..4 {
+///         read.set(&TilePos::new(x, y), false);
+///     }
+/// }
+/// read.set(&TilePos::new(2, 2), true);
+/// let mut write = TileDataLayer::new(map_size, false);
+///
+/// // Tiles become walls when most of the 8 tiles around them are walls.
+/// step_automata(&read, &mut write, &TilemapType::Square, true, |_, _, neighbors| {
+///     let walls = neighbors.iter().filter(|wall| ***wall).count() + 8 - neighbors.len();
+///     walls >= 5
+/// });
+///
+/// assert_eq!(write.get(&TilePos::new(2, 2)), Some(&false));
+/// assert_eq!(write.get(&TilePos::new(1, 1)), Some(&true));
+/// assert_eq!(write.get(&TilePos::new(2, 1)), Some(&false));
+/// ```
+pub fn step_automata<T: Clone + Send + Sync + 'static>(
+    read: &TileDataLayer<T>,
+    write: &mut TileDataLayer<T>,
+    map_type: &TilemapType,
+    diagonals: bool,
+    rule: impl Fn(&TilePos, &T, &[&T]) -> T + Sync,
+) {
+    let map_size = read.size();
+    if map_size.count() == 0 {
+        return;
+    }
+    if write.size() != map_size {
+        *write = read.clone();
+    }
+
+    let width = map_size.x as usize;
+    let task_pool = ComputeTaskPool::get_or_init(TaskPool::default);
+    let rows_per_batch = map_size
+        .y
+        .div_ceil(task_pool.thread_num().max(1) as u32)
+        .max(1) as usize;
+    let rule = &rule;
+    task_pool.scope(|scope| {
+        for (batch, values) in write
+            .as_mut_slice()
+            .chunks_mut(width * rows_per_batch)
+            .enumerate()
+        {
+            scope.spawn(async move {
+                let mut neighbors = Vec::new();
+                for (offset, value) in values.iter_mut().enumerate() {
+                    let index = batch * width * rows_per_batch + offset;
+                    let tile_pos = TilePos::new((index % width) as u32, (index / width) as u32);
+                    neighbors.clear();
+                    neighbors.extend(
+                        adjacent_tiles(&tile_pos, &map_size, map_type, diagonals)
+                            .iter()
+                            .filter_map(|(_, neighbor)| read.get(neighbor)),
+                    );
+                    *value = rule(&tile_pos, &read.as_slice()[index], &neighbors);
+                }
+            });
+        }
+    });
+}
+
+/// Sets the [`TileTextureIndex`] of every tile of `tile_storage` to the texture `texture_index`
+/// picks for its value in `layer`, e.g. to show the result of [`step_automata`].
+///
+/// Tiles for which `texture_index` returns `None` are left as they are. Only tiles whose index
+/// actually changes are written to, so that unchanged tiles don't trigger change detection.
+/// Returns the number of tiles that changed.
+pub fn apply_to_texture_indices<T: Send + Sync + 'static>(
+    layer: &TileDataLayer<T>,
+    tile_storage: &TileStorage,
+    tiles: &mut Query<&mut TileTextureIndex>,
+    texture_index: impl Fn(&T) -> Option<TileTextureIndex>,
+) -> usize {
+    let mut changed = 0;
+    for (tile_pos, value) in layer.iter() {
+        let Some(tile_entity) = tile_storage.checked_get(&tile_pos) else {
+            continue;
+        };
+        let (Some(new_index), Ok(mut current)) = (texture_index(value), tiles.get_mut(tile_entity))
+        else {
+            continue;
+        };
+        if *current != new_index {
+            *current = new_index;
+            changed += 1;
+        }
+    }
+    changed
+}
@@ -0,0 +1,178 @@
+use crate::helpers::serialization::{SerializedTilemap, TileArchetype};
+use crate::map::{
+    TilemapGridSize, TilemapId, TilemapRenderSettings, TilemapSize, TilemapSpacing, TilemapTexture,
+    TilemapTileSize, TilemapType,
+};
+use crate::tiles::{TilePos, TileStorage};
+use bevy::hierarchy::BuildChildren;
+use bevy::prelude::{Commands, Entity, Transform, World};
+
+#[cfg(feature = "render")]
+type BlueprintBundle = crate::TilemapBundle;
+#[cfg(not(feature = "render"))]
+type BlueprintBundle = crate::StandardTilemapBundle;
+
+/// A tilemap as plain data, without any entity, which can be built on a background thread, e.g.
+/// by world generation running in an async task, and spawned into the world afterwards.
+///
+/// Spawning creates the tilemap and all of its tiles in a single batch, which is much faster than
+/// spawning tiles one by one.
+///
+/// ```
+/// # use bevy::prelude::World;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::blueprint::TilemapBlueprint;
+/// # use bevy_ecs_tilemap::helpers::serialization::TileArchetype;
+/// let size = TilemapSize { x: 64, y: 64 };
+/// let worldgen = std::thread::spawn(move || {
+///     let mut blueprint =
+///         TilemapBlueprint::new(size, TilemapTexture::default(), TilemapTileSize { x: 16.0, y: 16.0 });
+///     blueprint.fill(TileArchetype { texture_index: TileTextureIndex(2), ..Default::default() });
+///     blueprint.remove_tile(&TilePos::new(3, 3));
+///     blueprint
+/// });
+///
+/// let mut world = World::new();
+/// let tilemap = worldgen.join().unwrap().spawn(&mut world);
+///
+/// let storage = world.get::<TileStorage>(tilemap).unwrap();
+/// assert_eq!(storage.iter().flatten().count(), 64 * 64 - 1);
+/// assert!(storage.get(&TilePos::new(3, 3)).is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct TilemapBlueprint {
+    pub size: TilemapSize,
+    pub map_type: TilemapType,
+    pub grid_size: TilemapGridSize,
+    pub tile_size: TilemapTileSize,
+    pub spacing: TilemapSpacing,
+    pub texture: TilemapTexture,
+    pub transform: Transform,
+    pub render_settings: TilemapRenderSettings,
+    tiles: Vec<Option<TileArchetype>>,
+}
+
+impl TilemapBlueprint {
+    /// Creates a square tilemap blueprint without any tile, whose grid size matches the tile
+    /// size.
+    pub fn new(size: TilemapSize, texture: TilemapTexture, tile_size: TilemapTileSize) -> Self {
+        Self {
+            size,
+            map_type: TilemapType::default(),
+            grid_size: tile_size.into(),
+            tile_size,
+            spacing: TilemapSpacing::default(),
+            texture,
+            transform: Transform::default(),
+            render_settings: TilemapRenderSettings::default(),
+            tiles: vec![None; size.count()],
+        }
+    }
+
+    /// Creates a blueprint with the tiles of a serialized tilemap, e.g. to load a save in the
+    /// background.
+    pub fn from_serialized(
+        serialized: &SerializedTilemap,
+        texture: TilemapTexture,
+        tile_size: TilemapTileSize,
+    ) -> Self {
+        let mut blueprint = Self::new(serialized.size, texture, tile_size);
+        for (tile_pos, archetype) in serialized.tiles() {
+            blueprint.set_tile(&tile_pos, *archetype);
+        }
+        blueprint
+    }
+
+    /// Returns the tile at `tile_pos`, if there is one.
+    pub fn get_tile(&self, tile_pos: &TilePos) -> Option<&TileArchetype> {
+        if !tile_pos.within_map_bounds(&self.size) {
+            return None;
+        }
+        self.tiles[tile_pos.to_index(&self.size)].as_ref()
+    }
+
+    /// Places a tile at `tile_pos`, replacing any tile already there. Does nothing if it lies
+    /// outside of the tilemap.
+    pub fn set_tile(&mut self, tile_pos: &TilePos, tile: TileArchetype) {
+        if tile_pos.within_map_bounds(&self.size) {
+            self.tiles[tile_pos.to_index(&self.size)] = Some(tile);
+        }
+    }
+
+    /// Removes the tile at `tile_pos`, and returns it.
+    pub fn remove_tile(&mut self, tile_pos: &TilePos) -> Option<TileArchetype> {
+        if !tile_pos.within_map_bounds(&self.size) {
+            return None;
+        }
+        self.tiles[tile_pos.to_index(&self.size)].take()
+    }
+
+    /// Places `tile` at every position of the tilemap.
+    pub fn fill(&mut self, tile: TileArchetype) {
+        self.tiles.fill(Some(tile));
+    }
+
+    /// Returns an iterator over the positions that have a tile, and their tiles, in row order.
+    pub fn tiles(&self) -> impl Iterator<Item = (TilePos, &TileArchetype)> {
+        let width = self.size.x.max(1);
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, tile)| {
+                let index = index as u32;
+                Some((TilePos::new(index % width, index / width), tile.as_ref()?))
+            })
+    }
+
+    /// Spawns the tilemap and its tiles, and returns the tilemap entity. Tiles are spawned as
+    /// children of the tilemap.
+    pub fn spawn(self, world: &mut World) -> Entity {
+        let tilemap_entity = world.spawn_empty().id();
+        self.spawn_into(world, tilemap_entity);
+        tilemap_entity
+    }
+
+    /// Queues spawning the tilemap and its tiles, and returns the tilemap entity right away.
+    pub fn queue_spawn(self, commands: &mut Commands) -> Entity {
+        let tilemap_entity = commands.spawn_empty().id();
+        commands.queue(move |world: &mut World| {
+            if world.get_entity(tilemap_entity).is_ok() {
+                self.spawn_into(world, tilemap_entity);
+            }
+        });
+        tilemap_entity
+    }
+
+    fn spawn_into(self, world: &mut World, tilemap_entity: Entity) {
+        let tilemap_id = TilemapId(tilemap_entity);
+        let positions: Vec<TilePos> = self.tiles().map(|(tile_pos, _)| tile_pos).collect();
+        let tile_entities: Vec<Entity> = world
+            .spawn_batch(
+                self.tiles()
+                    .map(|(tile_pos, tile)| tile.bundle(tile_pos, tilemap_id))
+                    .collect::<Vec<_>>(),
+            )
+            .collect();
+
+        let mut storage = TileStorage::empty(self.size);
+        for (tile_pos, tile_entity) in positions.iter().zip(tile_entities.iter()) {
+            storage.set(tile_pos, *tile_entity);
+        }
+
+        world
+            .entity_mut(tilemap_entity)
+            .insert(BlueprintBundle {
+                grid_size: self.grid_size,
+                map_type: self.map_type,
+                size: self.size,
+                spacing: self.spacing,
+                storage,
+                texture: self.texture,
+                tile_size: self.tile_size,
+                transform: self.transform,
+                render_settings: self.render_settings,
+                ..Default::default()
+            })
+            .add_children(&tile_entities);
+    }
+}
@@ -0,0 +1,496 @@
+use crate::map::{TilemapGridSize, TilemapSize};
+use bevy::app::{App, AppExit, Last, Plugin, Update};
+use bevy::ecs::event::EventCursor;
+use bevy::hierarchy::DespawnRecursiveExt;
+use bevy::log::error;
+use bevy::math::{IVec2, Vec2};
+use bevy::prelude::{
+    Component, Entity, Events, GlobalTransform, Local, Mut, Reflect, ReflectComponent, Resource,
+    World,
+};
+use bevy::tasks::{block_on, futures_lite::future, IoTaskPool, Task, TaskPool};
+use bevy::utils::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Keeps the chunks of a [`ChunkLoader`] loaded around this entity, usually a camera.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct CameraChunkLoader {
+    /// How many chunks to load in each direction around the chunk the entity is in.
+    pub radius: u32,
+}
+
+/// The position of a chunk tilemap spawned by the [`ChunkLoader`], in chunks.
+#[derive(Component, Reflect, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct TilemapChunk(pub IVec2);
+
+/// Spawns the tilemap of the chunk at the given position, and returns its entity.
+///
+/// Receives the data the chunk was saved with when it was last unloaded, if any. The
+/// [`ChunkLoader`] resource itself is not available in the world while the callback runs.
+pub type SpawnChunkFn = dyn Fn(&mut World, IVec2, Option<Vec<u8>>) -> Entity + Send + Sync;
+
+/// Saves the chunk tilemap with the given entity right before it is despawned.
+pub type UnloadChunkFn = dyn Fn(&World, Entity) -> Vec<u8> + Send + Sync;
+
+/// Stores the data of unloaded chunks outside of the [`ChunkLoader`], e.g. on disk, so that
+/// chunks survive restarts and infinite worlds don't keep every visited chunk in memory.
+///
+/// The loader saves a chunk right after handing it to its unload callback, and loads it before
+/// spawning it. Both run on the [`IoTaskPool`], so a chunk whose data has to be loaded is spawned
+/// a few frames after it came into range. Data stored with [`ChunkLoader::insert_saved`] takes
+/// precedence over the persisted data.
+///
+/// ```
+/// # use bevy::math::IVec2;
+/// # use bevy::utils::HashMap;
+/// # use bevy_ecs_tilemap::helpers::chunk_loader::ChunkPersistence;
+/// /// Keeps chunks compressed, or in a save game database.
+/// #[derive(Default)]
+/// struct SaveGame {
+///     chunks: HashMap<IVec2, Vec<u8>>,
+/// }
+///
+/// impl ChunkPersistence for SaveGame {
+///     fn load(&mut self, chunk_pos: IVec2) -> std::io::Result<Option<Vec<u8>>> {
+///         Ok(self.chunks.get(&chunk_pos).cloned())
+///     }
+///
+///     fn save(&mut self, chunk_pos: IVec2, data: &[u8]) -> std::io::Result<()> {
+///         self.chunks.insert(chunk_pos, data.to_vec());
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait ChunkPersistence: Send + Sync + 'static {
+    /// Returns the data the chunk at `chunk_pos` was last saved with, or `None` if it was never
+    /// saved.
+    fn load(&mut self, chunk_pos: IVec2) -> std::io::Result<Option<Vec<u8>>>;
+
+    /// Saves the data of the chunk at `chunk_pos`, replacing any previous data.
+    fn save(&mut self, chunk_pos: IVec2, data: &[u8]) -> std::io::Result<()>;
+}
+
+/// Persists every chunk in its own file in a directory, named after the chunk's position.
+#[cfg(feature = "file_persistence")]
+#[derive(Clone, Debug)]
+pub struct FileChunkPersistence {
+    directory: std::path::PathBuf,
+}
+
+#[cfg(feature = "file_persistence")]
+impl FileChunkPersistence {
+    /// Creates a persistence storing chunks in `directory`, which is created when the first
+    /// chunk is saved.
+    pub fn new(directory: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// Returns the path of the file the chunk at `chunk_pos` is saved in.
+    pub fn chunk_path(&self, chunk_pos: IVec2) -> std::path::PathBuf {
+        self.directory
+            .join(format!("chunk_{}_{}.bin", chunk_pos.x, chunk_pos.y))
+    }
+}
+
+#[cfg(feature = "file_persistence")]
+impl ChunkPersistence for FileChunkPersistence {
+    fn load(&mut self, chunk_pos: IVec2) -> std::io::Result<Option<Vec<u8>>> {
+        match std::fs::read(self.chunk_path(chunk_pos)) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn save(&mut self, chunk_pos: IVec2, data: &[u8]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.directory)?;
+        std::fs::write(self.chunk_path(chunk_pos), data)
+    }
+}
+
+/// Streams a world made of one tilemap per chunk in and out around [`CameraChunkLoader`]s.
+///
+/// Chunks are laid out on a square grid, and every chunk is a tilemap of `chunk_size` tiles. The
+/// spawn callback is responsible for spawning the tilemap, positioned at
+/// [`ChunkLoader::chunk_origin`]. Chunks that are more than `radius + unload_margin` chunks away
+/// from every loader are despawned, after being handed to the unload callback, if any. The data
+/// it returns is kept in memory and handed back to the spawn callback when the chunk is loaded
+/// again, or to the [`ChunkPersistence`] set with [`with_persistence`](Self::with_persistence).
+///
+/// Requires the [`ChunkLoaderPlugin`], which also saves the chunks that are still loaded when
+/// the app exits.
+#[derive(Resource)]
+pub struct ChunkLoader {
+    /// The size of a chunk in world units.
+    pub chunk_world_size: Vec2,
+    /// How many chunks beyond a loader's radius are kept before being despawned. This avoids
+    /// chunks being despawned and respawned when a loader moves back and forth along an edge.
+    pub unload_margin: u32,
+    spawn_chunk: Box<SpawnChunkFn>,
+    unload_chunk: Option<Box<UnloadChunkFn>>,
+    persistence: Option<Arc<Mutex<dyn ChunkPersistence>>>,
+    loaded: HashMap<IVec2, Entity>,
+    saved: HashMap<IVec2, Vec<u8>>,
+    /// Chunks whose data is being loaded from the persistence.
+    loading: HashMap<IVec2, Task<Option<Vec<u8>>>>,
+    /// Chunks whose data is being saved to the persistence, which hand the data back if saving
+    /// failed.
+    saving: HashMap<IVec2, Task<Option<Vec<u8>>>>,
+}
+
+impl ChunkLoader {
+    /// Creates a loader for chunks of `chunk_size` tiles of `grid_size`, using `spawn_chunk` to
+    /// spawn them.
+    pub fn new(
+        chunk_size: TilemapSize,
+        grid_size: TilemapGridSize,
+        spawn_chunk: impl Fn(&mut World, IVec2, Option<Vec<u8>>) -> Entity + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            chunk_world_size: Vec2::from(chunk_size) * Vec2::from(grid_size),
+            unload_margin: 1,
+            spawn_chunk: Box::new(spawn_chunk),
+            unload_chunk: None,
+            persistence: None,
+            loaded: HashMap::default(),
+            saved: HashMap::default(),
+            loading: HashMap::default(),
+            saving: HashMap::default(),
+        }
+    }
+
+    /// Sets the callback saving chunks before they are despawned.
+    pub fn with_unload(
+        mut self,
+        unload_chunk: impl Fn(&World, Entity) -> Vec<u8> + Send + Sync + 'static,
+    ) -> Self {
+        self.unload_chunk = Some(Box::new(unload_chunk));
+        self
+    }
+
+    /// Sets where the data of unloaded chunks is stored, instead of keeping it in memory. Saving
+    /// only happens for chunks with data, so an unload callback has to be set too.
+    pub fn with_persistence(mut self, persistence: impl ChunkPersistence) -> Self {
+        self.persistence = Some(Arc::new(Mutex::new(persistence)));
+        self
+    }
+
+    /// Returns the position of the chunk containing `world_pos`.
+    pub fn chunk_at(&self, world_pos: Vec2) -> IVec2 {
+        (world_pos / self.chunk_world_size).floor().as_ivec2()
+    }
+
+    /// Returns the world position the tilemap of the chunk at `chunk_pos` should be placed at.
+    pub fn chunk_origin(&self, chunk_pos: IVec2) -> Vec2 {
+        chunk_pos.as_vec2() * self.chunk_world_size
+    }
+
+    /// Returns the tilemap entity of the chunk at `chunk_pos`, if it is loaded.
+    pub fn get(&self, chunk_pos: IVec2) -> Option<Entity> {
+        self.loaded.get(&chunk_pos).copied()
+    }
+
+    /// Returns an iterator over the positions and tilemap entities of all loaded chunks.
+    pub fn loaded_chunks(&self) -> impl Iterator<Item = (IVec2, Entity)> + '_ {
+        self.loaded
+            .iter()
+            .map(|(chunk_pos, entity)| (*chunk_pos, *entity))
+    }
+
+    /// Returns the data the chunk at `chunk_pos` was saved with, if it is currently unloaded and
+    /// kept in memory.
+    pub fn saved(&self, chunk_pos: IVec2) -> Option<&[u8]> {
+        self.saved.get(&chunk_pos).map(Vec::as_slice)
+    }
+
+    /// Takes all saved chunk data out of the loader, e.g. to write it to disk.
+    pub fn take_saved(&mut self) -> HashMap<IVec2, Vec<u8>> {
+        std::mem::take(&mut self.saved)
+    }
+
+    /// Stores data for the chunk at `chunk_pos`, which is handed to the spawn callback the next
+    /// time the chunk is loaded.
+    pub fn insert_saved(&mut self, chunk_pos: IVec2, data: Vec<u8>) {
+        self.saved.insert(chunk_pos, data);
+    }
+
+    /// Saves every loaded chunk with the unload callback, without unloading it, and waits for
+    /// all saves to finish. This happens automatically when the app exits.
+    pub fn save_all(&mut self, world: &World) {
+        if let Some(unload_chunk) = &self.unload_chunk {
+            let chunks: Vec<(IVec2, Vec<u8>)> = self
+                .loaded_chunks()
+                .filter(|(_, entity)| world.get_entity(*entity).is_ok())
+                .map(|(chunk_pos, entity)| (chunk_pos, unload_chunk(world, entity)))
+                .collect();
+            for (chunk_pos, data) in chunks {
+                self.save(chunk_pos, data);
+            }
+        }
+        for (chunk_pos, task) in std::mem::take(&mut self.saving) {
+            if let Some(data) = block_on(task) {
+                self.saved.insert(chunk_pos, data);
+            }
+        }
+    }
+
+    /// Hands the data of an unloaded chunk to the persistence, or keeps it in memory if there is
+    /// none or saving failed.
+    fn save(&mut self, chunk_pos: IVec2, data: Vec<u8>) {
+        let Some(persistence) = self.persistence.clone() else {
+            self.saved.insert(chunk_pos, data);
+            return;
+        };
+        let task = io_task_pool().spawn(async move {
+            let mut persistence = persistence.lock().unwrap_or_else(PoisonError::into_inner);
+            match persistence.save(chunk_pos, &data) {
+                Ok(()) => None,
+                Err(err) => {
+                    error!("Failed to save chunk {chunk_pos}: {err}");
+                    Some(data)
+                }
+            }
+        });
+        self.saving.insert(chunk_pos, task);
+    }
+
+    /// Starts loading the data of a chunk from the persistence.
+    fn load(&mut self, chunk_pos: IVec2, persistence: Arc<Mutex<dyn ChunkPersistence>>) {
+        let task = io_task_pool().spawn(async move {
+            let mut persistence = persistence.lock().unwrap_or_else(PoisonError::into_inner);
+            persistence.load(chunk_pos).unwrap_or_else(|err| {
+                error!("Failed to load chunk {chunk_pos}: {err}");
+                None
+            })
+        });
+        self.loading.insert(chunk_pos, task);
+    }
+
+    /// Spawns the chunk at `chunk_pos` with the spawn callback.
+    fn spawn(&mut self, world: &mut World, chunk_pos: IVec2, data: Option<Vec<u8>>) {
+        let entity = (self.spawn_chunk)(world, chunk_pos, data);
+        world.flush();
+        if let Ok(mut entity_mut) = world.get_entity_mut(entity) {
+            entity_mut.insert(TilemapChunk(chunk_pos));
+        }
+        self.loaded.insert(chunk_pos, entity);
+    }
+}
+
+fn io_task_pool() -> &'static TaskPool {
+    IoTaskPool::get_or_init(TaskPool::default)
+}
+
+/// Removes the finished tasks from `tasks`, and returns their results.
+fn finished_tasks<T>(tasks: &mut HashMap<IVec2, Task<T>>) -> Vec<(IVec2, T)> {
+    let mut finished = Vec::new();
+    tasks.retain(
+        |chunk_pos, task| match block_on(future::poll_once(&mut *task)) {
+            Some(result) => {
+                finished.push((*chunk_pos, result));
+                false
+            }
+            None => true,
+        },
+    );
+    finished
+}
+
+/// Adds the system loading and unloading the chunks of the [`ChunkLoader`] resource.
+pub struct ChunkLoaderPlugin;
+
+impl Plugin for ChunkLoaderPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<CameraChunkLoader>()
+            .register_type::<TilemapChunk>()
+            .add_systems(Update, update_chunk_loader)
+            .add_systems(Last, save_chunks_on_exit);
+    }
+}
+
+fn update_chunk_loader(world: &mut World) {
+    if !world.contains_resource::<ChunkLoader>() {
+        return;
+    }
+
+    let loaders: Vec<(Vec2, u32)> = world
+        .query::<(&GlobalTransform, &CameraChunkLoader)>()
+        .iter(world)
+        .map(|(transform, loader)| (transform.translation().truncate(), loader.radius))
+        .collect();
+
+    world.resource_scope(|world, mut chunk_loader: Mut<ChunkLoader>| {
+        for (chunk_pos, data) in finished_tasks(&mut chunk_loader.saving) {
+            if let Some(data) = data {
+                chunk_loader.saved.insert(chunk_pos, data);
+            }
+        }
+
+        let mut wanted: Vec<(IVec2, f32)> = Vec::new();
+        let mut kept = HashSet::new();
+        for (position, radius) in loaders.iter() {
+            let center = chunk_loader.chunk_at(*position);
+            for chunk_pos in chunks_around(center, *radius) {
+                let distance = (chunk_loader.chunk_origin(chunk_pos)
+                    + chunk_loader.chunk_world_size / 2.0)
+                    .distance_squared(*position);
+                wanted.push((chunk_pos, distance));
+            }
+            kept.extend(chunks_around(center, radius + chunk_loader.unload_margin));
+        }
+
+        // Forget about chunks that were despawned by someone else.
+        chunk_loader
+            .loaded
+            .retain(|_, entity| world.get_entity(*entity).is_ok());
+
+        let far_chunks: Vec<(IVec2, Entity)> = chunk_loader
+            .loaded_chunks()
+            .filter(|(chunk_pos, _)| !kept.contains(chunk_pos))
+            .collect();
+        for (chunk_pos, entity) in far_chunks {
+            if let Some(unload_chunk) = &chunk_loader.unload_chunk {
+                let data = unload_chunk(world, entity);
+                chunk_loader.save(chunk_pos, data);
+            }
+            chunk_loader.loaded.remove(&chunk_pos);
+            if let Ok(entity_mut) = world.get_entity_mut(entity) {
+                entity_mut.despawn_recursive();
+            }
+        }
+
+        // Chunks which went out of range while their data was loading are dropped.
+        for (chunk_pos, data) in finished_tasks(&mut chunk_loader.loading) {
+            if kept.contains(&chunk_pos) && !chunk_loader.loaded.contains_key(&chunk_pos) {
+                chunk_loader.spawn(world, chunk_pos, data);
+            }
+        }
+
+        // Load the nearest chunks first. Chunks that are still being saved are loaded once
+        // saving is done, so that they don't load stale data.
+        wanted.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        for (chunk_pos, _) in wanted {
+            if chunk_loader.loaded.contains_key(&chunk_pos)
+                || chunk_loader.loading.contains_key(&chunk_pos)
+                || chunk_loader.saving.contains_key(&chunk_pos)
+            {
+                continue;
+            }
+            if let Some(data) = chunk_loader.saved.remove(&chunk_pos) {
+                chunk_loader.spawn(world, chunk_pos, Some(data));
+            } else if let Some(persistence) = chunk_loader.persistence.clone() {
+                chunk_loader.load(chunk_pos, persistence);
+            } else {
+                chunk_loader.spawn(world, chunk_pos, None);
+            }
+        }
+    });
+    world.flush();
+}
+
+/// Saves the loaded chunks when the app exits, as they would otherwise never be unloaded.
+fn save_chunks_on_exit(world: &mut World, mut exit_events: Local<EventCursor<AppExit>>) {
+    let Some(events) = world.get_resource::<Events<AppExit>>() else {
+        return;
+    };
+    if exit_events.read(events).count() == 0 || !world.contains_resource::<ChunkLoader>() {
+        return;
+    }
+    world.resource_scope(|world, mut chunk_loader: Mut<ChunkLoader>| {
+        chunk_loader.save_all(world);
+    });
+}
+
+/// Returns the positions of all chunks within `radius` chunks of `center`.
+fn chunks_around(center: IVec2, radius: u32) -> impl Iterator<Item = IVec2> {
+    let radius = radius as i32;
+    (-radius..=radius).flat_map(move |y| (-radius..=radius).map(move |x| center + IVec2::new(x, y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{MinimalTilemapPlugins, StepApp};
+
+    /// Keeps chunks in a map shared with the test.
+    #[derive(Clone, Default)]
+    struct SharedPersistence(Arc<Mutex<HashMap<IVec2, Vec<u8>>>>);
+
+    impl ChunkPersistence for SharedPersistence {
+        fn load(&mut self, chunk_pos: IVec2) -> std::io::Result<Option<Vec<u8>>> {
+            Ok(self.0.lock().unwrap().get(&chunk_pos).cloned())
+        }
+
+        fn save(&mut self, chunk_pos: IVec2, data: &[u8]) -> std::io::Result<()> {
+            self.0.lock().unwrap().insert(chunk_pos, data.to_vec());
+            Ok(())
+        }
+    }
+
+    /// The data a chunk was spawned with.
+    #[derive(Component)]
+    struct ChunkData(Option<Vec<u8>>);
+
+    fn chunk_app(persistence: SharedPersistence) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalTilemapPlugins, ChunkLoaderPlugin));
+        app.insert_resource(
+            ChunkLoader::new(
+                TilemapSize { x: 4, y: 4 },
+                TilemapGridSize { x: 16.0, y: 16.0 },
+                |world, _, data| world.spawn(ChunkData(data)).id(),
+            )
+            .with_unload(|world, entity| {
+                let chunk_pos = world.get::<TilemapChunk>(entity).unwrap().0;
+                vec![chunk_pos.x as u8, chunk_pos.y as u8, 1]
+            })
+            .with_persistence(persistence),
+        );
+        app.world_mut().spawn((
+            GlobalTransform::from_xyz(8.0, 8.0, 0.0),
+            CameraChunkLoader { radius: 0 },
+        ));
+        app
+    }
+
+    /// Steps `app` until the chunk at `chunk_pos` is loaded, as loading runs on another thread.
+    fn wait_for_chunk(app: &mut App, chunk_pos: IVec2) -> Entity {
+        for _ in 0..1000 {
+            app.step_frames(1);
+            if let Some(entity) = app.world().resource::<ChunkLoader>().get(chunk_pos) {
+                return entity;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        panic!("chunk {chunk_pos} was never loaded");
+    }
+
+    #[test]
+    fn chunks_are_spawned_with_their_persisted_data() {
+        let persistence = SharedPersistence::default();
+        persistence.0.lock().unwrap().insert(IVec2::ZERO, vec![7]);
+        let mut app = chunk_app(persistence);
+        let chunk = wait_for_chunk(&mut app, IVec2::ZERO);
+        let data = app.world().get::<ChunkData>(chunk).unwrap();
+        assert_eq!(data.0, Some(vec![7]));
+    }
+
+    #[test]
+    fn loaded_chunks_are_saved_when_the_app_exits() {
+        let persistence = SharedPersistence::default();
+        let mut app = chunk_app(persistence.clone());
+        wait_for_chunk(&mut app, IVec2::ZERO);
+        assert!(persistence.0.lock().unwrap().is_empty());
+
+        app.world_mut().send_event(AppExit::Success);
+        app.step_frames(1);
+        assert_eq!(
+            persistence.0.lock().unwrap().get(&IVec2::ZERO),
+            Some(&vec![0, 0, 1])
+        );
+    }
+}
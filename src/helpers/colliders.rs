@@ -0,0 +1,288 @@
+use crate::map::{TilemapGridSize, TilemapId, TilemapSize};
+use crate::tiles::{TileCollisionShape, TilePos, TileStorage};
+use crate::TilemapSystemSet;
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::math::{Rect, UVec2, Vec2};
+use bevy::prelude::{
+    Changed, Component, DetectChanges, Entity, Event, EventWriter, IntoSystemConfigs, Query, Ref,
+    RemovedComponents,
+};
+use bevy::utils::HashSet;
+
+/// The chunk size used by [`TileColliders::default`].
+pub const DEFAULT_COLLIDER_CHUNK_SIZE: UVec2 = UVec2::new(16, 16);
+
+/// Identifies a [`ColliderRect`] of [`TileColliders`]. Ids are never reused, so physics bodies
+/// can be keyed by them.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ColliderId(pub u64);
+
+/// A box covering solid tiles, from `min` to `max` inclusive, in y-up grid positions.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct ColliderRect {
+    pub min: TilePos,
+    pub max: TilePos,
+}
+
+impl ColliderRect {
+    /// Returns the box in the tilemap's local space, on a square map with the given grid size.
+    pub fn local_rect(&self, grid_size: &TilemapGridSize) -> Rect {
+        let grid_size = Vec2::from(grid_size);
+        Rect::from_corners(
+            (Vec2::from(&self.min) - 0.5) * grid_size,
+            (Vec2::from(&self.max) + 0.5) * grid_size,
+        )
+    }
+}
+
+/// A change to the colliders of a tilemap, returned by [`TileColliders::update`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColliderChange {
+    /// A box was added, and needs a collider.
+    Added(ColliderId, ColliderRect),
+    /// A box was removed, and its collider should be despawned.
+    Removed(ColliderId),
+}
+
+/// The solid tiles of a square tilemap, merged into as few boxes as possible, e.g. to create
+/// physics colliders for them.
+///
+/// Boxes never cross chunk borders, so that changed tiles only merge their chunk again, and
+/// boxes whose tiles didn't change keep their [`ColliderId`]. [`update`](Self::update) returns
+/// what changed, so that physics can be kept in sync at interactive rates.
+///
+/// ```
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::colliders::{ColliderChange, TileColliders};
+/// let map_size = TilemapSize { x: 32, y: 8 };
+/// let mut colliders = TileColliders::default();
+///
+/// // A floor along y = 0.
+/// let changes = colliders.update(&map_size, |tile_pos| tile_pos.y == 0);
+/// assert_eq!(changes.len(), 2);
+///
+/// // Digging a hole only merges the first chunk again.
+/// let changes = colliders.update(&map_size, |tile_pos| tile_pos.y == 0 && tile_pos.x != 4);
+/// assert_eq!(changes.len(), 3);
+/// assert!(matches!(changes[0], ColliderChange::Removed(_)));
+/// assert_eq!(colliders.colliders().count(), 3);
+/// ```
+#[derive(Component, Clone, Debug)]
+pub struct TileColliders {
+    map_size: TilemapSize,
+    chunk_size: UVec2,
+    chunk_count: UVec2,
+    /// Whether every tile was solid in the last update.
+    solid: Vec<bool>,
+    chunks: Vec<Vec<(ColliderId, ColliderRect)>>,
+    next_id: u64,
+}
+
+impl Default for TileColliders {
+    /// By default, boxes are merged in chunks of [`DEFAULT_COLLIDER_CHUNK_SIZE`] tiles.
+    fn default() -> Self {
+        Self::new(DEFAULT_COLLIDER_CHUNK_SIZE)
+    }
+}
+
+impl TileColliders {
+    /// Creates colliders without any box, merging boxes in chunks of `chunk_size` tiles. Boxes
+    /// are added by the first [`update`](Self::update).
+    pub fn new(chunk_size: UVec2) -> Self {
+        Self {
+            map_size: TilemapSize { x: 0, y: 0 },
+            chunk_size: chunk_size.max(UVec2::ONE),
+            chunk_count: UVec2::ZERO,
+            solid: Vec::new(),
+            chunks: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Updates the boxes to cover the tiles for which `solid` returns true, given y-up grid
+    /// positions, and returns the boxes that were removed and added.
+    ///
+    /// Only chunks in which a tile became solid or stopped being solid are merged again. If the
+    /// map size changed, every box is replaced.
+    pub fn update(
+        &mut self,
+        map_size: &TilemapSize,
+        solid: impl Fn(&TilePos) -> bool,
+    ) -> Vec<ColliderChange> {
+        let mut changes = Vec::new();
+        if *map_size != self.map_size {
+            for (id, _) in self.chunks.drain(..).flatten() {
+                changes.push(ColliderChange::Removed(id));
+            }
+            self.map_size = *map_size;
+            self.chunk_count =
+                (UVec2::from(*map_size) + self.chunk_size - UVec2::ONE) / self.chunk_size;
+            self.chunks = vec![Vec::new(); (self.chunk_count.x * self.chunk_count.y) as usize];
+            self.solid = vec![false; map_size.count()];
+        }
+
+        let mut dirty = HashSet::new();
+        for y in 0..map_size.y {
+            for x in 0..map_size.x {
+                let tile_pos = TilePos::new(x, y);
+                let is_solid = solid(&tile_pos);
+                let was_solid = &mut self.solid[tile_pos.to_index(map_size)];
+                if *was_solid != is_solid {
+                    *was_solid = is_solid;
+                    dirty.insert(UVec2::new(x, y) / self.chunk_size);
+                }
+            }
+        }
+
+        let mut dirty: Vec<UVec2> = dirty.into_iter().collect();
+        dirty.sort_unstable_by_key(|chunk| (chunk.y, chunk.x));
+        for chunk in dirty {
+            self.merge_chunk(chunk, &mut changes);
+        }
+        changes
+    }
+
+    /// Returns the size of the tilemap the boxes were last updated for.
+    pub fn map_size(&self) -> TilemapSize {
+        self.map_size
+    }
+
+    /// Returns the size of the chunks, in tiles.
+    pub fn chunk_size(&self) -> UVec2 {
+        self.chunk_size
+    }
+
+    /// Returns the box with the given id.
+    pub fn get(&self, id: ColliderId) -> Option<&ColliderRect> {
+        self.colliders()
+            .find(|(other, _)| *other == id)
+            .map(|(_, rect)| rect)
+    }
+
+    /// Returns an iterator over all boxes.
+    pub fn colliders(&self) -> impl Iterator<Item = (ColliderId, &ColliderRect)> {
+        self.chunks.iter().flatten().map(|(id, rect)| (*id, rect))
+    }
+
+    /// Merges the solid tiles of a chunk into boxes, greedily growing each box along x first,
+    /// then along y. Boxes that are the same as before keep their id.
+    fn merge_chunk(&mut self, chunk: UVec2, changes: &mut Vec<ColliderChange>) {
+        let origin = chunk * self.chunk_size;
+        let end = (origin + self.chunk_size).min(UVec2::from(self.map_size));
+        let size = end - origin;
+        let local_index = |x: u32, y: u32| ((y - origin.y) * size.x + (x - origin.x)) as usize;
+
+        let mut open = vec![false; (size.x * size.y) as usize];
+        for y in origin.y..end.y {
+            for x in origin.x..end.x {
+                open[local_index(x, y)] = self.solid[TilePos::new(x, y).to_index(&self.map_size)];
+            }
+        }
+
+        let mut rects = Vec::new();
+        for y in origin.y..end.y {
+            for x in origin.x..end.x {
+                if !open[local_index(x, y)] {
+                    continue;
+                }
+                let mut max_x = x;
+                while max_x + 1 < end.x && open[local_index(max_x + 1, y)] {
+                    max_x += 1;
+                }
+                let mut max_y = y;
+                while max_y + 1 < end.y
+                    && (x..=max_x).all(|row_x| open[local_index(row_x, max_y + 1)])
+                {
+                    max_y += 1;
+                }
+                for rect_y in y..=max_y {
+                    for rect_x in x..=max_x {
+                        open[local_index(rect_x, rect_y)] = false;
+                    }
+                }
+                rects.push(ColliderRect {
+                    min: TilePos::new(x, y),
+                    max: TilePos::new(max_x, max_y),
+                });
+            }
+        }
+
+        let slot = (chunk.y * self.chunk_count.x + chunk.x) as usize;
+        let mut old = std::mem::take(&mut self.chunks[slot]);
+        let mut added = Vec::new();
+        let mut merged = Vec::with_capacity(rects.len());
+        for rect in rects {
+            if let Some(index) = old.iter().position(|(_, old_rect)| *old_rect == rect) {
+                merged.push(old.swap_remove(index));
+            } else {
+                let id = ColliderId(self.next_id);
+                self.next_id += 1;
+                added.push(ColliderChange::Added(id, rect));
+                merged.push((id, rect));
+            }
+        }
+        changes.extend(old.into_iter().map(|(id, _)| ColliderChange::Removed(id)));
+        changes.extend(added);
+        self.chunks[slot] = merged;
+    }
+}
+
+/// Sent by the [`TileCollidersPlugin`] for every box of a tilemap's [`TileColliders`] that was
+/// added or removed.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileColliderEvent {
+    pub tilemap: Entity,
+    pub change: ColliderChange,
+}
+
+/// Keeps the [`TileColliders`] of tilemaps in sync with their tiles, and sends a
+/// [`TileColliderEvent`] for every box added or removed.
+///
+/// Add [`TileColliders`] to the tilemaps that need colliders. Tiles are solid if their
+/// [`TileCollisionShape`] is [`TileCollisionShape::Full`], which includes tiles without one.
+/// Other shapes are left to the game, e.g. to be sampled with
+/// [`sample_ground_height`](crate::helpers::collision::sample_ground_height). Colliders are
+/// updated in `PostUpdate`, for tilemaps whose storage or tile shapes changed.
+pub struct TileCollidersPlugin;
+
+impl Plugin for TileCollidersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TileColliderEvent>().add_systems(
+            PostUpdate,
+            update_tile_colliders.after(TilemapSystemSet::StorageMaintenance),
+        );
+    }
+}
+
+fn update_tile_colliders(
+    mut events: EventWriter<TileColliderEvent>,
+    mut tilemaps: Query<(Entity, Ref<TileStorage>, &mut TileColliders)>,
+    changed_shapes: Query<&TilemapId, Changed<TileCollisionShape>>,
+    mut removed_shapes: RemovedComponents<TileCollisionShape>,
+    shapes: Query<&TileCollisionShape>,
+) {
+    let changed_tilemaps: HashSet<Entity> = changed_shapes.iter().map(|id| id.0).collect();
+    // Removed shapes can't be traced back to their tilemap, so every tilemap is checked.
+    let any_removed = removed_shapes.read().count() > 0;
+
+    for (tilemap, storage, mut colliders) in tilemaps.iter_mut() {
+        if !storage.is_changed()
+            && !colliders.is_added()
+            && !any_removed
+            && !changed_tilemaps.contains(&tilemap)
+        {
+            continue;
+        }
+        let changes = colliders.update(&storage.size, |grid_pos| {
+            let tile_pos = storage.axes.to_grid_pos(grid_pos, &storage.size);
+            storage.get(&tile_pos).is_some_and(|tile| {
+                shapes.get(tile).copied().unwrap_or_default() == TileCollisionShape::Full
+            })
+        });
+        events.send_batch(
+            changes
+                .into_iter()
+                .map(|change| TileColliderEvent { tilemap, change }),
+        );
+    }
+}
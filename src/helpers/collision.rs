@@ -0,0 +1,80 @@
+use crate::map::TilemapGridSize;
+use crate::tiles::{TileCollisionShape, TilePos, TileStorage};
+use bevy::math::Vec3;
+use bevy::prelude::{GlobalTransform, Query};
+
+/// Finds the height, in world space, of the first ground surface at or below
+/// `(world_x, world_y)` on a square tilemap.
+///
+/// Tiles are sampled using their [`TileCollisionShape`]; tiles without one are treated as
+/// [`TileCollisionShape::Full`]. Returns `None` if there is no ground below the given point.
+///
+/// This is intended for platformer controllers: `world_y` is usually the position of the feet of
+/// the character.
+///
+/// Example:
+/// ```
+/// # use bevy::ecs::system::SystemState;
+/// # use bevy::prelude::{GlobalTransform, Query, World};
+/// # use bevy_ecs_tilemap::helpers::collision::sample_ground_height;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::test_utils::{spawn_test_map, tile_at};
+/// # let mut world = World::new();
+/// // A 4x4 map of 16x16 tiles, completely filled with full tiles.
+/// let map = spawn_test_map(&mut world, TilemapSize { x: 4, y: 4 }, TilemapType::Square);
+/// let slope = tile_at(&world, map, TilePos { x: 1, y: 3 }).unwrap();
+/// world.entity_mut(slope).insert(TileCollisionShape::SlopeUpRight);
+///
+/// let mut state: SystemState<Query<&TileCollisionShape>> = SystemState::new(&mut world);
+/// let shapes = state.get(&world);
+/// let storage = world.get::<TileStorage>(map).unwrap();
+/// let grid_size = world.get::<TilemapGridSize>(map).unwrap();
+/// let transform = GlobalTransform::default();
+///
+/// // The top of the map is at y = 3.5 * 16.0.
+/// let height = sample_ground_height(0.0, 100.0, storage, grid_size, &transform, &shapes);
+/// assert_eq!(height, Some(56.0));
+/// // Halfway up the slope.
+/// let height = sample_ground_height(16.0, 100.0, storage, grid_size, &transform, &shapes);
+/// assert_eq!(height, Some(48.0));
+/// ```
+pub fn sample_ground_height(
+    world_x: f32,
+    world_y: f32,
+    tile_storage: &TileStorage,
+    grid_size: &TilemapGridSize,
+    map_transform: &GlobalTransform,
+    shapes: &Query<&TileCollisionShape>,
+) -> Option<f32> {
+    let local = map_transform
+        .affine()
+        .inverse()
+        .transform_point3(Vec3::new(world_x, world_y, 0.0));
+
+    // Tile centers lie at `grid_size * tile_pos`, so shift by half a tile to get the tile edges.
+    let column = (local.x / grid_size.x + 0.5).floor();
+    let row = (local.y / grid_size.y + 0.5).floor();
+    if column < 0.0 || column >= tile_storage.size.x as f32 || row < 0.0 {
+        return None;
+    }
+    let x_in_tile = local.x / grid_size.x + 0.5 - column;
+    let top_row = (row as u32).min(tile_storage.size.y.saturating_sub(1));
+
+    for y in (0..=top_row).rev() {
+        let grid_pos = TilePos::new(column as u32, y);
+        let tile_pos = tile_storage.axes.to_grid_pos(&grid_pos, &tile_storage.size);
+        let Some(tile_entity) = tile_storage.get(&tile_pos) else {
+            continue;
+        };
+        let shape = shapes.get(tile_entity).copied().unwrap_or_default();
+
+        let bottom = (y as f32 - 0.5) * grid_size.y;
+        let surface = bottom + shape.surface_height(x_in_tile) * grid_size.y;
+        if surface <= local.y {
+            let world = map_transform.transform_point(Vec3::new(local.x, surface, local.z));
+            return Some(world.y);
+        }
+    }
+
+    None
+}
@@ -0,0 +1,119 @@
+use crate::helpers::hex_grid::neighbors::HexNeighbors;
+use crate::helpers::registry::tile_hash;
+use crate::helpers::square_grid::neighbors::Neighbors;
+use crate::map::{IsoCoordSystem, TilemapId, TilemapSize, TilemapType};
+use crate::tiles::{TileBundle, TilePos, TileRect, TileStorage, TileTextureIndex};
+use bevy::hierarchy::BuildChildren;
+use bevy::prelude::{ChildBuild, Commands, Query};
+
+/// A rule for [`scatter_decorations`], e.g. "flowers on grass, not next to water, on 10% of the
+/// tiles".
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DecorationRule {
+    /// The decoration tile to place.
+    pub decoration: TileTextureIndex,
+    /// The base tiles the decoration may be placed on.
+    pub on: Vec<TileTextureIndex>,
+    /// The base tiles the decoration may not be placed next to, diagonals included.
+    pub not_adjacent_to: Vec<TileTextureIndex>,
+    /// The fraction of matching tiles that get the decoration, from `0.0` to `1.0`.
+    pub density: f32,
+}
+
+/// Places decoration tiles on a layer above `base_storage`, following `rules`.
+///
+/// Rules are tried in order, and the first rule that matches a tile and passes its density roll
+/// places its decoration. Tiles that already have a decoration are skipped. The result only
+/// depends on `seed` and the base layer, so scattering again gives the same decorations.
+///
+/// Returns the number of decorations placed.
+#[allow(clippy::too_many_arguments)]
+pub fn scatter_decorations(
+    rules: &[DecorationRule],
+    seed: u64,
+    base_storage: &TileStorage,
+    base_tiles: &Query<&TileTextureIndex>,
+    map_type: &TilemapType,
+    decoration_id: TilemapId,
+    commands: &mut Commands,
+    decoration_storage: &mut TileStorage,
+) -> usize {
+    let base_at = |tile_pos: &TilePos| {
+        base_storage
+            .checked_get(tile_pos)
+            .and_then(|tile_entity| base_tiles.get(tile_entity).ok())
+            .copied()
+    };
+    let size = base_storage.size;
+
+    let mut placed = 0;
+    commands.entity(decoration_id.0).with_children(|parent| {
+        for tile_pos in TileRect::from_map_size(size).iter() {
+            if decoration_storage.checked_get(&tile_pos).is_some() {
+                continue;
+            }
+            let Some(base) = base_at(&tile_pos) else {
+                continue;
+            };
+
+            for (index, rule) in rules.iter().enumerate() {
+                if !rule.on.contains(&base) {
+                    continue;
+                }
+                // Mix the rule in, so that rules with the same density don't pick the same tiles.
+                let hash = tile_hash(seed.wrapping_add(index as u64), &tile_pos);
+                if (hash >> 40) as f32 / (1u64 << 24) as f32 >= rule.density {
+                    continue;
+                }
+                let near_excluded = neighboring_positions(&tile_pos, &size, map_type)
+                    .iter()
+                    .filter_map(base_at)
+                    .any(|neighbor| rule.not_adjacent_to.contains(&neighbor));
+                if near_excluded {
+                    continue;
+                }
+
+                let tile_entity = parent
+                    .spawn(TileBundle {
+                        position: tile_pos,
+                        tilemap_id: decoration_id,
+                        texture_index: rule.decoration,
+                        ..Default::default()
+                    })
+                    .id();
+                decoration_storage.checked_set(&tile_pos, tile_entity);
+                placed += 1;
+                break;
+            }
+        }
+    });
+    placed
+}
+
+/// Returns the positions of all tiles touching `tile_pos`, diagonals included.
+fn neighboring_positions(
+    tile_pos: &TilePos,
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+) -> Vec<TilePos> {
+    match map_type {
+        TilemapType::Square | TilemapType::Isometric(IsoCoordSystem::Diamond) => {
+            Neighbors::get_square_neighboring_positions(tile_pos, map_size, true)
+                .iter()
+                .copied()
+                .collect()
+        }
+        TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+            Neighbors::get_staggered_neighboring_positions(tile_pos, map_size, true)
+                .iter()
+                .copied()
+                .collect()
+        }
+        TilemapType::Hexagon(hex_coord_sys) => {
+            HexNeighbors::get_neighboring_positions(tile_pos, map_size, hex_coord_sys)
+                .iter()
+                .copied()
+                .collect()
+        }
+    }
+}
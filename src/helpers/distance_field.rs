@@ -0,0 +1,165 @@
+use crate::helpers::path::neighbors;
+use crate::map::{TilemapSize, TilemapType};
+use crate::tiles::{TileDataLayer, TilePos};
+use bevy::utils::HashSet;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// The distance of tiles from which no goal can be reached.
+pub const UNREACHABLE: u16 = u16::MAX;
+
+/// Computes the distance of every tile to the nearest of `goals`, e.g. a dijkstra map that many
+/// agents can walk down at once.
+///
+/// Each goal is a tile along with the distance it starts at, so that some goals can be made less
+/// attractive than others. Stepping onto a tile adds its `cost`, with `None` for tiles that
+/// can't be entered. Adjacency follows the map type: four neighbors on square and isometric maps,
+/// six on hexagonal maps. Distances saturate just below [`UNREACHABLE`].
+///
+/// ```
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::distance_field::{build_distance_field, UNREACHABLE};
+/// let map_size = TilemapSize { x: 5, y: 3 };
+/// // A wall along x = 2, with a gap at y = 2.
+/// let cost = |tile_pos: &TilePos| (tile_pos.x != 2 || tile_pos.y == 2).then_some(1);
+/// let goals = [(TilePos::new(0, 0), 0)];
+/// let field = build_distance_field(&map_size, &TilemapType::Square, goals, cost);
+///
+/// assert_eq!(field.get(&TilePos::new(0, 0)), Some(&0));
+/// assert_eq!(field.get(&TilePos::new(4, 0)), Some(&8));
+/// assert_eq!(field.get(&TilePos::new(2, 0)), Some(&UNREACHABLE));
+/// ```
+pub fn build_distance_field(
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+    goals: impl IntoIterator<Item = (TilePos, u16)>,
+    cost: impl Fn(&TilePos) -> Option<u16>,
+) -> TileDataLayer<u16> {
+    let mut field = TileDataLayer::new(*map_size, UNREACHABLE);
+    let mut open = BinaryHeap::new();
+    seed_goals(&mut field, &mut open, goals, &cost);
+    relax(&mut field, &mut open, map_type, &cost);
+    field
+}
+
+/// Updates a distance field computed by [`build_distance_field`] after the costs of
+/// `changed_tiles` changed, only revisiting the tiles whose distance could depend on them.
+///
+/// `goals` and `cost` must describe the new state. Goals that were added or removed, or whose
+/// distance changed, must be part of `changed_tiles` too. If the field doesn't have the size of
+/// `map_size`, it is rebuilt from scratch.
+pub fn update_distance_field(
+    field: &mut TileDataLayer<u16>,
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+    goals: impl IntoIterator<Item = (TilePos, u16)>,
+    changed_tiles: impl IntoIterator<Item = TilePos>,
+    cost: impl Fn(&TilePos) -> Option<u16>,
+) {
+    if field.size() != *map_size {
+        *field = build_distance_field(map_size, map_type, goals, cost);
+        return;
+    }
+
+    // Clears the changed tiles and every tile whose shortest path may lead through one of them,
+    // i.e. whose distance is exactly the distance of a cleared neighbor plus its own cost.
+    let mut cleared: HashSet<TilePos> = HashSet::default();
+    let mut stack: Vec<(TilePos, u16)> = Vec::new();
+    for tile_pos in changed_tiles {
+        if let Some(distance) = field.set(&tile_pos, UNREACHABLE) {
+            if cleared.insert(tile_pos) {
+                stack.push((tile_pos, distance));
+            }
+        }
+    }
+    while let Some((tile_pos, distance)) = stack.pop() {
+        if distance == UNREACHABLE {
+            continue;
+        }
+        for (_, next) in neighbors(&tile_pos, map_size, map_type) {
+            if cleared.contains(&next) {
+                continue;
+            }
+            let Some(next_cost) = cost(&next) else {
+                continue;
+            };
+            let next_distance = field.get(&next).copied().unwrap_or(UNREACHABLE);
+            if next_distance != UNREACHABLE && next_distance == step(distance, next_cost) {
+                field.set(&next, UNREACHABLE);
+                cleared.insert(next);
+                stack.push((next, next_distance));
+            }
+        }
+    }
+
+    // Refills the cleared tiles from the goals, and from the tiles around them which kept their
+    // distance. Tiles whose cost went down lower the distance of the tiles beyond them as well.
+    let mut open = BinaryHeap::new();
+    seed_goals(field, &mut open, goals, &cost);
+    for tile_pos in cleared.iter() {
+        for (_, next) in neighbors(tile_pos, map_size, map_type) {
+            if let Some(&distance) = field.get(&next) {
+                if distance != UNREACHABLE && !cleared.contains(&next) {
+                    open.push(Reverse((distance, next)));
+                }
+            }
+        }
+    }
+    relax(field, &mut open, map_type, &cost);
+}
+
+/// The distance of a tile entered from a tile at `distance`.
+fn step(distance: u16, cost: u16) -> u16 {
+    distance.saturating_add(cost).min(UNREACHABLE - 1)
+}
+
+fn seed_goals(
+    field: &mut TileDataLayer<u16>,
+    open: &mut BinaryHeap<Reverse<(u16, TilePos)>>,
+    goals: impl IntoIterator<Item = (TilePos, u16)>,
+    cost: &impl Fn(&TilePos) -> Option<u16>,
+) {
+    for (tile_pos, distance) in goals {
+        let distance = distance.min(UNREACHABLE - 1);
+        if cost(&tile_pos).is_none() {
+            continue;
+        }
+        if let Some(current) = field.get_mut(&tile_pos) {
+            if distance < *current {
+                *current = distance;
+                open.push(Reverse((distance, tile_pos)));
+            }
+        }
+    }
+}
+
+/// Runs dijkstra's algorithm from the tiles in `open`, lowering the distance of every tile that
+/// can be reached more cheaply.
+fn relax(
+    field: &mut TileDataLayer<u16>,
+    open: &mut BinaryHeap<Reverse<(u16, TilePos)>>,
+    map_type: &TilemapType,
+    cost: &impl Fn(&TilePos) -> Option<u16>,
+) {
+    let map_size = field.size();
+    while let Some(Reverse((distance, tile_pos))) = open.pop() {
+        if field
+            .get(&tile_pos)
+            .is_some_and(|current| distance > *current)
+        {
+            continue;
+        }
+        for (_, next) in neighbors(&tile_pos, &map_size, map_type) {
+            let Some(next_cost) = cost(&next) else {
+                continue;
+            };
+            let next_distance = step(distance, next_cost);
+            if let Some(current) = field.get_mut(&next) {
+                if next_distance < *current {
+                    *current = next_distance;
+                    open.push(Reverse((next_distance, next)));
+                }
+            }
+        }
+    }
+}
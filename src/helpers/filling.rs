@@ -1,13 +1,16 @@
 use crate::helpers::hex_grid::axial::AxialPos;
 use crate::helpers::hex_grid::neighbors::{HexDirection, HEX_DIRECTIONS};
-use crate::map::TilemapId;
+use crate::helpers::registry::TileVariantSet;
+use crate::map::{ensure_unlocked, TilemapId, TilemapLockedError};
 use crate::prelude::HexCoordSystem;
 use crate::tiles::{TileBundle, TileColor, TilePos, TileTextureIndex};
 use crate::{TileStorage, TilemapSize};
 use bevy::hierarchy::BuildChildren;
-use bevy::prelude::{ChildBuild, Color, Commands};
+use bevy::prelude::{ChildBuild, Color, Commands, Entity, World};
 
 /// Fills an entire tile storage with the given tile.
+///
+/// Doesn't check for [`TilemapLocked`](crate::map::TilemapLocked), see [`try_fill_tilemap`].
 pub fn fill_tilemap(
     texture_index: TileTextureIndex,
     size: TilemapSize,
@@ -33,6 +36,201 @@ pub fn fill_tilemap(
     });
 }
 
+/// Fills an entire tile storage with the given tile, like [`fill_tilemap`], but much faster for
+/// large maps, see [`spawn_tile_batch`].
+///
+/// Example:
+/// ```
+/// # use bevy::ecs::world::CommandQueue;
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// let mut world = World::new();
+/// let tilemap_entity = world.spawn_empty().id();
+/// let size = TilemapSize { x: 512, y: 512 };
+/// let mut tile_storage = TileStorage::empty(size);
+///
+/// let mut queue = CommandQueue::default();
+/// let mut commands = Commands::new(&mut queue, &world);
+/// fill_tilemap_batch(
+///     TileTextureIndex(0),
+///     size,
+///     TilemapId(tilemap_entity),
+///     &mut commands,
+///     &mut tile_storage,
+/// );
+/// queue.apply(&mut world);
+///
+/// let tile_pos = TilePos::new(300, 17);
+/// let tile_entity = tile_storage.get(&tile_pos).unwrap();
+/// assert_eq!(world.get::<TilePos>(tile_entity), Some(&tile_pos));
+/// assert_eq!(world.get::<Children>(tilemap_entity).unwrap().len(), 512 * 512);
+/// ```
+pub fn fill_tilemap_batch(
+    texture_index: TileTextureIndex,
+    size: TilemapSize,
+    tilemap_id: TilemapId,
+    commands: &mut Commands,
+    tile_storage: &mut TileStorage,
+) {
+    let tiles = (0..size.y).flat_map(|y| {
+        (0..size.x).map(move |x| TileBundle {
+            position: TilePos { x, y },
+            tilemap_id,
+            texture_index,
+            ..Default::default()
+        })
+    });
+    spawn_tile_batch(tiles, tilemap_id, commands, tile_storage);
+}
+
+/// Fills the [`TileStorage`] of `tilemap` with the given tile, like [`fill_tilemap_batch`], from
+/// an exclusive system or a queued command. Positions that already have a tile are skipped.
+///
+/// The tilemap must have a [`TileStorage`], or nothing happens. Fails if the tilemap is
+/// [`TilemapLocked`](crate::map::TilemapLocked), unlike the helpers taking [`Commands`], which
+/// can't see the lock.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// let mut world = World::new();
+/// let size = TilemapSize { x: 4, y: 4 };
+/// let tilemap = world.spawn(TileStorage::empty(size)).id();
+///
+/// try_fill_tilemap(&mut world, tilemap, TileTextureIndex(3)).unwrap();
+/// let tile = world.get::<TileStorage>(tilemap).unwrap().get(&TilePos::new(2, 1)).unwrap();
+/// assert_eq!(world.get::<TileTextureIndex>(tile), Some(&TileTextureIndex(3)));
+/// ```
+pub fn try_fill_tilemap(
+    world: &mut World,
+    tilemap: Entity,
+    texture_index: TileTextureIndex,
+) -> Result<(), TilemapLockedError> {
+    ensure_unlocked(world, tilemap)?;
+    let Some(tile_storage) = world.get::<TileStorage>(tilemap) else {
+        return Ok(());
+    };
+    let size = tile_storage.size;
+    let positions: Vec<TilePos> = (0..size.y)
+        .flat_map(|y| (0..size.x).map(move |x| TilePos { x, y }))
+        .filter(|tile_pos| tile_storage.get(tile_pos).is_none())
+        .collect();
+    let tilemap_id = TilemapId(tilemap);
+    let tiles: Vec<Entity> = world
+        .spawn_batch(positions.iter().map(|position| TileBundle {
+            position: *position,
+            tilemap_id,
+            texture_index,
+            ..Default::default()
+        }))
+        .collect();
+    world.entity_mut(tilemap).add_children(&tiles);
+    let mut tile_storage = world.get_mut::<TileStorage>(tilemap).unwrap();
+    for (tile_pos, tile_entity) in positions.iter().zip(tiles) {
+        tile_storage.set(tile_pos, tile_entity);
+    }
+    Ok(())
+}
+
+/// Spawns a tile for each bundle as children of the tilemap, and adds them to the tile storage.
+///
+/// Rather than spawning each tile with its own command, the entities are reserved up front, so
+/// that the storage can be filled right away, and their bundles are inserted by one batched
+/// command. Spawning hundreds of thousands of tiles this way is many times faster. Bundles
+/// outside of the storage are skipped.
+pub fn spawn_tile_batch(
+    tiles: impl IntoIterator<Item = TileBundle>,
+    tilemap_id: TilemapId,
+    commands: &mut Commands,
+    tile_storage: &mut TileStorage,
+) {
+    let size = tile_storage.size;
+    let batch: Vec<(Entity, TileBundle)> = tiles
+        .into_iter()
+        .filter(|tile| tile.position.within_map_bounds(&size))
+        .map(|tile| {
+            let tile_entity = commands.spawn_empty().id();
+            tile_storage.set(&tile.position, tile_entity);
+            (tile_entity, tile)
+        })
+        .collect();
+    if batch.is_empty() {
+        return;
+    }
+    let children: Vec<Entity> = batch.iter().map(|(tile_entity, _)| *tile_entity).collect();
+    commands.insert_batch(batch);
+    commands.entity(tilemap_id.0).add_children(&children);
+}
+
+/// Fills an entire tile storage with tiles picked from a [`TileVariantSet`].
+///
+/// Each tile's variant is picked from a hash of `seed` and its position, so filling again with
+/// the same seed gives the same result. Nothing is spawned if the set has no variants.
+///
+/// Example:
+/// ```
+/// # use bevy::ecs::world::CommandQueue;
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::registry::{TileVariant, TileVariantSet};
+/// # use bevy_ecs_tilemap::prelude::*;
+/// let grass = TileVariantSet {
+///     variants: vec![
+///         TileVariant { texture_index: 0, weight: 8.0 },
+///         TileVariant { texture_index: 1, weight: 1.0 },
+///     ],
+/// };
+/// let mut world = World::new();
+/// let tilemap_entity = world.spawn_empty().id();
+/// let size = TilemapSize { x: 16, y: 16 };
+/// let mut tile_storage = TileStorage::empty(size);
+///
+/// let mut queue = CommandQueue::default();
+/// let mut commands = Commands::new(&mut queue, &world);
+/// fill_tilemap_variants(
+///     &grass,
+///     42,
+///     size,
+///     TilemapId(tilemap_entity),
+///     &mut commands,
+///     &mut tile_storage,
+/// );
+/// queue.apply(&mut world);
+///
+/// for (tile_pos, tile_entity) in tile_storage.iter_with_pos() {
+///     let texture_index = world.get::<TileTextureIndex>(tile_entity.unwrap()).unwrap();
+///     assert_eq!(Some(*texture_index), grass.pick_for(42, &tile_pos));
+/// }
+/// assert_eq!(world.get::<Children>(tilemap_entity).unwrap().len(), 16 * 16);
+/// ```
+pub fn fill_tilemap_variants(
+    variant_set: &TileVariantSet,
+    seed: u64,
+    size: TilemapSize,
+    tilemap_id: TilemapId,
+    commands: &mut Commands,
+    tile_storage: &mut TileStorage,
+) {
+    commands.entity(tilemap_id.0).with_children(|parent| {
+        for x in 0..size.x {
+            for y in 0..size.y {
+                let tile_pos = TilePos { x, y };
+                let Some(texture_index) = variant_set.pick_for(seed, &tile_pos) else {
+                    return;
+                };
+                let tile_entity = parent
+                    .spawn(TileBundle {
+                        position: tile_pos,
+                        tilemap_id,
+                        texture_index,
+                        ..Default::default()
+                    })
+                    .id();
+                tile_storage.set(&tile_pos, tile_entity);
+            }
+        }
+    });
+}
+
 /// Fills a rectangular region with the given tile.
 ///
 /// The rectangular region is defined by an `origin` in [`TilePos`], and a
@@ -178,3 +376,26 @@ pub fn fill_tilemap_hexagon(
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{TilemapLocked, TilemapType};
+    use crate::test_utils::{spawn_empty_test_map, tile_at};
+
+    #[test]
+    fn locked_tilemaps_reject_fills() {
+        let mut world = World::new();
+        let size = TilemapSize { x: 4, y: 4 };
+        let tilemap = spawn_empty_test_map(&mut world, size, TilemapType::Square);
+        world.entity_mut(tilemap).insert(TilemapLocked);
+
+        let result = try_fill_tilemap(&mut world, tilemap, TileTextureIndex(1));
+        assert_eq!(result, Err(TilemapLockedError(tilemap)));
+        assert_eq!(tile_at(&world, tilemap, TilePos::new(0, 0)), None);
+
+        world.entity_mut(tilemap).remove::<TilemapLocked>();
+        try_fill_tilemap(&mut world, tilemap, TileTextureIndex(1)).unwrap();
+        assert!(tile_at(&world, tilemap, TilePos::new(0, 0)).is_some());
+    }
+}
@@ -0,0 +1,274 @@
+use crate::helpers::distance_field::UNREACHABLE;
+use crate::helpers::path::neighbors;
+use crate::map::{TilemapGridSize, TilemapSize, TilemapType};
+use crate::tiles::{TileDataLayer, TileFlow, TilePos, TileStorage};
+use bevy::asset::Assets;
+use bevy::hierarchy::BuildChildren;
+use bevy::math::{Dir2, Vec2, Vec3};
+use bevy::prelude::{
+    Added, App, Changed, Color, ColorMaterial, Commands, Component, DetectChanges, Entity,
+    GlobalTransform, Handle, Mesh, Mesh2d, MeshMaterial2d, Or, Plugin, Query, Ref,
+    RemovedComponents, ResMut, Transform, Update,
+};
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+
+/// Returns the flow direction of the tile under `world_pos`, rotated into world space.
+pub fn flow_at_world_pos(
+    world_pos: Vec2,
+    tile_storage: &TileStorage,
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    map_type: &TilemapType,
+    map_transform: &GlobalTransform,
+    flows: &Query<&TileFlow>,
+) -> Option<Vec2> {
+    let local_pos = map_transform
+        .affine()
+        .inverse()
+        .transform_point3(world_pos.extend(0.0))
+        .truncate();
+    let tile_pos = TilePos::from_world_pos_with_axes(
+        &local_pos,
+        map_size,
+        grid_size,
+        map_type,
+        &tile_storage.axes,
+    )?;
+    let flow = flows.get(tile_storage.get(&tile_pos)?).ok()?;
+    let direction = map_transform
+        .affine()
+        .transform_vector3(flow.0.extend(0.0))
+        .truncate();
+    Some(direction.normalize_or_zero())
+}
+
+/// Moves `transform` by `distance` along the flow of the tile it stands on.
+///
+/// `transform` is treated as being in world space, so the entity should not have a parent.
+/// Returns `false` if the entity is not standing on a flow tile.
+#[allow(clippy::too_many_arguments)]
+pub fn advance_on_flow(
+    transform: &mut Transform,
+    distance: f32,
+    tile_storage: &TileStorage,
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    map_type: &TilemapType,
+    map_transform: &GlobalTransform,
+    flows: &Query<&TileFlow>,
+) -> bool {
+    let Some(direction) = flow_at_world_pos(
+        transform.translation.truncate(),
+        tile_storage,
+        map_size,
+        grid_size,
+        map_type,
+        map_transform,
+        flows,
+    ) else {
+        return false;
+    };
+    transform.translation += (direction * distance).extend(0.0);
+    true
+}
+
+/// Builds a flow field from a distance field computed by
+/// [`build_distance_field`](crate::helpers::distance_field::build_distance_field): every tile
+/// points towards its neighbor closest to a goal, in tilemap local space.
+///
+/// Goals, and tiles from which no goal can be reached, have no direction. Directions follow the
+/// tile centers of the map type, so they work the same on square, isometric and hexagonal maps.
+/// Like distance fields, flow fields are indexed by grid position, i.e. they assume the default
+/// [`TilemapAxes`](crate::map::TilemapAxes).
+pub fn build_flow_field(
+    distances: &TileDataLayer<u16>,
+    grid_size: &TilemapGridSize,
+    map_type: &TilemapType,
+) -> TileDataLayer<Option<Dir2>> {
+    let map_size = distances.size();
+    let mut field = TileDataLayer::new(map_size, None);
+    for (tile_pos, distance) in distances.iter() {
+        if *distance == UNREACHABLE {
+            continue;
+        }
+        let mut closest = (*distance, None);
+        for (_, next) in neighbors(&tile_pos, &map_size, map_type) {
+            let next_distance = distances.get(&next).copied().unwrap_or(UNREACHABLE);
+            if next_distance < closest.0 {
+                closest = (next_distance, Some(next));
+            }
+        }
+        if let Some(next) = closest.1 {
+            let offset = next.center_in_world(grid_size, map_type)
+                - tile_pos.center_in_world(grid_size, map_type);
+            field.set(&tile_pos, Dir2::new(offset).ok());
+        }
+    }
+    field
+}
+
+/// Returns the direction of the flow field tile under `world_pos`, rotated into world space.
+///
+/// Returns `None` outside of the map, on goals, and where no goal can be reached.
+pub fn flow_field_at_world_pos(
+    world_pos: Vec2,
+    field: &TileDataLayer<Option<Dir2>>,
+    grid_size: &TilemapGridSize,
+    map_type: &TilemapType,
+    map_transform: &GlobalTransform,
+) -> Option<Vec2> {
+    let local_pos = map_transform
+        .affine()
+        .inverse()
+        .transform_point3(world_pos.extend(0.0))
+        .truncate();
+    let tile_pos = TilePos::from_world_pos(&local_pos, &field.size(), grid_size, map_type)?;
+    let direction = (*field.get(&tile_pos)?)?;
+    let direction = map_transform
+        .affine()
+        .transform_vector3(direction.extend(0.0))
+        .truncate();
+    Some(direction.normalize_or_zero())
+}
+
+/// Draws an arrow for every [`TileFlow`] of the tilemap this is attached to.
+///
+/// Requires the [`TileFlowDebugPlugin`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TileFlowDebug {
+    /// The color of the arrows.
+    pub color: Color,
+}
+
+impl Default for TileFlowDebug {
+    /// By default, arrows are drawn in white.
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+        }
+    }
+}
+
+/// The arrow mesh and material of a [`TileFlowDebug`] tilemap, added once the overlay has been
+/// spawned.
+#[derive(Component, Clone, Debug)]
+pub struct TileFlowDebugMesh {
+    mesh: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+}
+
+/// Adds the systems drawing [`TileFlowDebug`] overlays.
+pub struct TileFlowDebugPlugin;
+
+impl Plugin for TileFlowDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_tile_flow_debug);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_tile_flow_debug(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    tilemap_query: Query<(
+        Entity,
+        Ref<TileFlowDebug>,
+        &TileStorage,
+        &TilemapGridSize,
+        &TilemapType,
+        Option<&TileFlowDebugMesh>,
+    )>,
+    added_query: Query<(), Or<(Added<TileFlowDebug>, Changed<TileStorage>)>>,
+    changed_flows: Query<(), Changed<TileFlow>>,
+    mut removed_flows: RemovedComponents<TileFlow>,
+    flows: Query<(&TilePos, &TileFlow)>,
+) {
+    let flows_changed = !changed_flows.is_empty() || removed_flows.read().next().is_some();
+
+    for (entity, debug, tile_storage, grid_size, map_type, debug_mesh) in tilemap_query.iter() {
+        if let Some(debug_mesh) = debug_mesh.filter(|_| debug.is_changed()) {
+            if let Some(material) = materials.get_mut(&debug_mesh.material) {
+                material.color = debug.color;
+            }
+        }
+        if !flows_changed && !added_query.contains(entity) {
+            continue;
+        }
+
+        let mesh = arrow_mesh(tile_storage, grid_size, map_type, &flows);
+        match debug_mesh {
+            Some(debug_mesh) => {
+                meshes.insert(&debug_mesh.mesh, mesh);
+            }
+            None => {
+                let mesh = meshes.add(mesh);
+                let material = materials.add(ColorMaterial::from(debug.color));
+                // The arrows are drawn slightly above the tilemap itself.
+                let overlay = commands
+                    .spawn((
+                        Mesh2d(mesh.clone()),
+                        MeshMaterial2d(material.clone()),
+                        Transform::from_xyz(0.0, 0.0, 1.0),
+                    ))
+                    .id();
+                commands
+                    .entity(entity)
+                    .insert(TileFlowDebugMesh { mesh, material })
+                    .add_child(overlay);
+            }
+        }
+    }
+}
+
+/// Builds a mesh containing one arrow per flow tile, in tilemap local space.
+fn arrow_mesh(
+    tile_storage: &TileStorage,
+    grid_size: &TilemapGridSize,
+    map_type: &TilemapType,
+    flows: &Query<(&TilePos, &TileFlow)>,
+) -> Mesh {
+    let length = Vec2::from(grid_size).min_element() * 0.4;
+    let width = length * 0.5;
+
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    for tile_entity in tile_storage.iter().flatten() {
+        let Ok((tile_pos, flow)) = flows.get(*tile_entity) else {
+            continue;
+        };
+        let center = tile_pos.center_in_world_with_axes(
+            &tile_storage.size,
+            grid_size,
+            map_type,
+            &tile_storage.axes,
+        );
+        let forward = flow.0.as_vec2();
+        let side = forward.perp();
+
+        let base = positions.len() as u32;
+        for point in [
+            // Shaft.
+            center - forward * length - side * width * 0.25,
+            center - side * width * 0.25,
+            center + side * width * 0.25,
+            center - forward * length + side * width * 0.25,
+            // Head.
+            center - side * width,
+            center + forward * length,
+            center + side * width,
+        ] {
+            positions.push(Vec3::from((point, 0.0)).to_array());
+        }
+        indices.extend([0, 1, 2, 0, 2, 3, 4, 5, 6].map(|index| base + index));
+    }
+
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
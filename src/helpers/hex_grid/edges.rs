@@ -0,0 +1,221 @@
+//! Edges and corners of hexagonal cells, e.g. for roads along the borders of cells and
+//! settlements where cells meet.
+
+use crate::helpers::hex_grid::axial::AxialPos;
+use crate::helpers::hex_grid::neighbors::{HexDirection, HEX_DIRECTIONS};
+use crate::map::HexCoordSystem;
+use crate::tiles::{TilePosOffset, TileRotation};
+use crate::TilemapGridSize;
+use bevy::math::Vec2;
+
+/// The edge between two neighboring hex cells.
+///
+/// Every edge has a single representation: the cell it belongs to, and a `direction` of
+/// [`HexDirection::Zero`], [`HexDirection::One`] or [`HexDirection::Two`]. Edges built with any
+/// other direction belong to the neighbor in that direction instead.
+///
+/// ```
+/// # use bevy_ecs_tilemap::helpers::hex_grid::axial::AxialPos;
+/// # use bevy_ecs_tilemap::helpers::hex_grid::edges::HexEdge;
+/// # use bevy_ecs_tilemap::helpers::hex_grid::neighbors::HexDirection;
+/// let cell = AxialPos::new(2, 3);
+/// let edge = HexEdge::new(cell, HexDirection::Three);
+/// assert_eq!(edge, HexEdge::new(cell.offset(HexDirection::Three), HexDirection::Zero));
+/// assert!(edge.cells().contains(&cell));
+/// ```
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HexEdge {
+    pub cell: AxialPos,
+    pub direction: HexDirection,
+}
+
+/// The corner where three hex cells meet.
+///
+/// The corner `i` of a cell lies between its neighbors in the directions `i` and `i + 1`. Every
+/// corner has a single representation: the cell it belongs to, and a `corner` of
+/// [`HexDirection::Zero`] or [`HexDirection::One`].
+///
+/// ```
+/// # use bevy_ecs_tilemap::helpers::hex_grid::axial::AxialPos;
+/// # use bevy_ecs_tilemap::helpers::hex_grid::edges::HexCorner;
+/// # use bevy_ecs_tilemap::helpers::hex_grid::neighbors::HexDirection;
+/// let corner = HexCorner::new(AxialPos::new(0, 0), HexDirection::Zero);
+/// for cell in corner.cells() {
+///     assert!(cell.corners().contains(&corner));
+/// }
+/// assert_eq!(corner.adjacent_corners().len(), 3);
+/// ```
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HexCorner {
+    pub cell: AxialPos,
+    pub corner: HexDirection,
+}
+
+/// Returns the axial offset of `direction` as a vector.
+#[inline]
+fn offset_vec(direction: HexDirection) -> Vec2 {
+    let offset = AxialPos::from(direction);
+    Vec2::new(offset.q as f32, offset.r as f32)
+}
+
+/// Projects a fractional axial position into world space, for the orientation of `coord_sys`.
+#[inline]
+fn project(axial_pos: Vec2, grid_size: &TilemapGridSize, coord_sys: HexCoordSystem) -> Vec2 {
+    match coord_sys {
+        HexCoordSystem::Row | HexCoordSystem::RowEven | HexCoordSystem::RowOdd => {
+            AxialPos::project_row(axial_pos, grid_size)
+        }
+        HexCoordSystem::Column | HexCoordSystem::ColumnEven | HexCoordSystem::ColumnOdd => {
+            AxialPos::project_col(axial_pos, grid_size)
+        }
+    }
+}
+
+impl HexEdge {
+    /// Returns the edge of `cell` facing its neighbor in `direction`.
+    pub fn new(cell: AxialPos, direction: HexDirection) -> Self {
+        if (direction as usize) < 3 {
+            Self { cell, direction }
+        } else {
+            Self {
+                cell: cell.offset(direction),
+                direction: direction + 3,
+            }
+        }
+    }
+
+    /// Returns the two cells on either side of the edge.
+    pub fn cells(&self) -> [AxialPos; 2] {
+        [self.cell, self.cell.offset(self.direction)]
+    }
+
+    /// Returns the two corners at the ends of the edge.
+    pub fn corners(&self) -> [HexCorner; 2] {
+        [
+            HexCorner::new(self.cell, self.direction - 1),
+            HexCorner::new(self.cell, self.direction),
+        ]
+    }
+
+    /// Returns the four edges sharing a corner with the edge.
+    pub fn adjacent_edges(&self) -> [HexEdge; 4] {
+        let [start, end] = self.corners();
+        let mut adjacent = start
+            .edges()
+            .into_iter()
+            .chain(end.edges())
+            .filter(|edge| edge != self);
+        std::array::from_fn(|_| adjacent.next().unwrap())
+    }
+
+    /// Returns the center of the edge in world space, with the center of the cell `(0, 0)` at
+    /// `[0.0, 0.0]`.
+    pub fn center_in_world(&self, grid_size: &TilemapGridSize, coord_sys: HexCoordSystem) -> Vec2 {
+        let cell = Vec2::new(self.cell.q as f32, self.cell.r as f32);
+        project(
+            cell + 0.5 * offset_vec(self.direction),
+            grid_size,
+            coord_sys,
+        )
+    }
+
+    /// Returns the offset and rotation that draw a tile of the cell of the edge along the edge,
+    /// e.g. a road strip whose texture runs along its x axis.
+    ///
+    /// Every cell has up to three edges, so strips are best kept in three thin companion tilemaps
+    /// of the same type and size as the hex map, one per `direction`, with the strip of each
+    /// edge at the tile position of its `cell`.
+    pub fn strip_transform(
+        &self,
+        grid_size: &TilemapGridSize,
+        coord_sys: HexCoordSystem,
+    ) -> (TilePosOffset, TileRotation) {
+        let [start, end] = self
+            .corners()
+            .map(|corner| corner.center_in_world(grid_size, coord_sys));
+        let along = end - start;
+        let cell_center = project(
+            Vec2::new(self.cell.q as f32, self.cell.r as f32),
+            grid_size,
+            coord_sys,
+        );
+        (
+            TilePosOffset(self.center_in_world(grid_size, coord_sys) - cell_center),
+            TileRotation(along.y.atan2(along.x)),
+        )
+    }
+}
+
+impl HexCorner {
+    /// Returns the corner `corner` of `cell`, between its neighbors in the directions `corner`
+    /// and `corner + 1`.
+    pub fn new(cell: AxialPos, corner: HexDirection) -> Self {
+        // Each corner is shared by three cells, whose corners with the same parity coincide.
+        let (offset, corner) = match corner {
+            HexDirection::Zero => (AxialPos::new(0, 0), HexDirection::Zero),
+            HexDirection::Two => (AxialPos::new(-1, 0), HexDirection::Zero),
+            HexDirection::Four => (AxialPos::new(0, -1), HexDirection::Zero),
+            HexDirection::One => (AxialPos::new(0, 0), HexDirection::One),
+            HexDirection::Three => (AxialPos::new(0, -1), HexDirection::One),
+            HexDirection::Five => (AxialPos::new(1, -1), HexDirection::One),
+        };
+        Self {
+            cell: cell + offset,
+            corner,
+        }
+    }
+
+    /// Returns the three cells meeting at the corner.
+    pub fn cells(&self) -> [AxialPos; 3] {
+        [
+            self.cell,
+            self.cell.offset(self.corner),
+            self.cell.offset(self.corner + 1),
+        ]
+    }
+
+    /// Returns the three edges meeting at the corner.
+    pub fn edges(&self) -> [HexEdge; 3] {
+        [
+            HexEdge::new(self.cell, self.corner),
+            HexEdge::new(self.cell, self.corner + 1),
+            // The edge between the two other cells.
+            HexEdge::new(self.cell.offset(self.corner), self.corner + 2),
+        ]
+    }
+
+    /// Returns the three corners at the other ends of the edges meeting at the corner.
+    pub fn adjacent_corners(&self) -> [HexCorner; 3] {
+        self.edges().map(|edge| {
+            let [start, end] = edge.corners();
+            if start == *self {
+                end
+            } else {
+                start
+            }
+        })
+    }
+
+    /// Returns the position of the corner in world space, with the center of the cell `(0, 0)`
+    /// at `[0.0, 0.0]`.
+    pub fn center_in_world(&self, grid_size: &TilemapGridSize, coord_sys: HexCoordSystem) -> Vec2 {
+        let cell = Vec2::new(self.cell.q as f32, self.cell.r as f32);
+        // A corner is the centroid of the cells meeting at it.
+        let offset = offset_vec(self.corner) + offset_vec(self.corner + 1);
+        project(cell + offset / 3.0, grid_size, coord_sys)
+    }
+}
+
+impl AxialPos {
+    /// Returns the six edges of the cell, in the order of [`HEX_DIRECTIONS`].
+    pub fn edges(&self) -> [HexEdge; 6] {
+        HEX_DIRECTIONS.map(|direction| HexEdge::new(*self, direction))
+    }
+
+    /// Returns the six corners of the cell, in the order of [`HEX_DIRECTIONS`].
+    pub fn corners(&self) -> [HexCorner; 6] {
+        HEX_DIRECTIONS.map(|direction| HexCorner::new(*self, direction))
+    }
+}
@@ -1,5 +1,6 @@
 pub mod axial;
 pub mod consts;
 pub mod cube;
+pub mod edges;
 pub mod neighbors;
 pub mod offset;
@@ -18,6 +18,7 @@ use std::ops::{Add, Sub};
 ///
 /// [`HexDirection`]s can be converted from/into `usize`, `u32`, `isize`, `i32`.
 #[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HexDirection {
     Zero,
     One,
@@ -0,0 +1,264 @@
+use crate::map::{TilemapAxes, TilemapGridSize, TilemapId, TilemapSize, TilemapType};
+use crate::tiles::{TilePos, TileStorage};
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::color::Color;
+use bevy::ecs::query::QueryFilter;
+use bevy::prelude::{
+    BuildChildren, Camera, Commands, Component, DespawnRecursiveExt, DetectChanges, Entity,
+    IntoSystemConfigs, Local, OrthographicProjection, Query, Ref, ResMut, Resource, Transform,
+    TransformSystem, Visibility, With,
+};
+use bevy::text::{JustifyText, Text2d, TextColor, TextFont, TextLayout};
+use bevy::utils::HashMap;
+use std::marker::PhantomData;
+
+/// How the labels of a [`TileLabelsPlugin`] look.
+#[derive(Clone, Debug)]
+pub struct TileLabelStyle {
+    /// The text of the label of a tile.
+    pub text: fn(&TilePos) -> String,
+    pub font_size: f32,
+    pub color: Color,
+    /// The z of labels, relative to their tilemap.
+    pub z_offset: f32,
+    /// Labels are hidden while every active camera is zoomed out further than this
+    /// [`OrthographicProjection::scale`], where they would be too small to read anyway.
+    pub max_camera_scale: f32,
+}
+
+impl Default for TileLabelStyle {
+    /// By default, labels show the tile position as `x,y` in small black text, and are hidden
+    /// when zoomed out more than twice.
+    fn default() -> Self {
+        Self {
+            text: |tile_pos| format!("{},{}", tile_pos.x, tile_pos.y),
+            font_size: 14.0,
+            color: Color::BLACK,
+            z_offset: 1.0,
+            max_camera_scale: 2.0,
+        }
+    }
+}
+
+/// A text label spawned by a [`TileLabelsPlugin`], as a child of the tilemap of its tile.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileLabel(pub Entity);
+
+/// The labels of the tiles matching the filter `F`, keyed by tile.
+#[derive(Resource)]
+pub struct TileLabels<F: QueryFilter + 'static = ()> {
+    pub style: TileLabelStyle,
+    labels: HashMap<Entity, Entity>,
+    visible: bool,
+    _filter: PhantomData<fn() -> F>,
+}
+
+impl<F: QueryFilter + 'static> TileLabels<F> {
+    /// Returns the label entity of `tile`, if it has one.
+    pub fn label(&self, tile: Entity) -> Option<Entity> {
+        self.labels.get(&tile).copied()
+    }
+}
+
+/// Spawns a [`Text2d`] label on every tile matching the query filter `F`, centered on the tile,
+/// and keeps it up to date as tiles move, get added or removed, or stop matching `F`. Labels
+/// follow the [`TilemapAxes`] of the tilemap's [`TileStorage`].
+///
+/// This checks every matching tile each frame, so it is meant for debugging and small maps. It
+/// needs bevy's text plugins, and a font: either bevy's `default_font` feature, or a
+/// [`TextFont`] set on the labels afterwards.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::labels::{TileLabelStyle, TileLabelsPlugin};
+/// #[derive(Component)]
+/// struct Door;
+///
+/// fn plugin(app: &mut App) {
+///     // Label the positions of doors only.
+///     app.add_plugins(TileLabelsPlugin::<With<Door>>::new(TileLabelStyle {
+///         text: |tile_pos| format!("door {},{}", tile_pos.x, tile_pos.y),
+///         ..Default::default()
+///     }));
+/// }
+/// ```
+pub struct TileLabelsPlugin<F: QueryFilter + 'static = ()> {
+    pub style: TileLabelStyle,
+    _filter: PhantomData<fn() -> F>,
+}
+
+impl<F: QueryFilter + 'static> TileLabelsPlugin<F> {
+    pub fn new(style: TileLabelStyle) -> Self {
+        Self {
+            style,
+            _filter: PhantomData,
+        }
+    }
+}
+
+impl<F: QueryFilter + 'static> Default for TileLabelsPlugin<F> {
+    fn default() -> Self {
+        Self::new(TileLabelStyle::default())
+    }
+}
+
+impl<F: QueryFilter + 'static> Plugin for TileLabelsPlugin<F> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TileLabels::<F> {
+            style: self.style.clone(),
+            labels: HashMap::default(),
+            visible: true,
+            _filter: PhantomData,
+        })
+        .add_systems(
+            PostUpdate,
+            (sync_tile_labels::<F>, update_tile_label_visibility::<F>)
+                .chain()
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn sync_tile_labels<F: QueryFilter + 'static>(
+    mut commands: Commands,
+    mut labels: ResMut<TileLabels<F>>,
+    mut cached_axes: Local<HashMap<Entity, (TilemapAxes, TilemapSize)>>,
+    tiles: Query<(Entity, Ref<TilePos>, Ref<TilemapId>), F>,
+    tilemaps: Query<(Entity, Ref<TilemapGridSize>, Ref<TilemapType>, &TileStorage)>,
+    mut label_query: Query<(&mut Transform, &mut Text2d), With<TileLabel>>,
+) {
+    // Storages change with every tile, so only a change of their axes or size counts.
+    cached_axes.retain(|tilemap, _| tilemaps.contains(*tilemap));
+    let mut axes_changed = Vec::new();
+    for (tilemap, .., storage) in tilemaps.iter() {
+        let layout = (storage.axes, storage.size);
+        if cached_axes.insert(tilemap, layout) != Some(layout) {
+            axes_changed.push(tilemap);
+        }
+    }
+
+    let labels = &mut *labels;
+    for (tile, tile_pos, tilemap_id) in tiles.iter() {
+        let Ok((_, grid_size, map_type, storage)) = tilemaps.get(tilemap_id.0) else {
+            continue;
+        };
+        let translation = tile_pos
+            .center_in_world_with_axes(&storage.size, &grid_size, &map_type, &storage.axes)
+            .extend(labels.style.z_offset);
+
+        let Some(&label) = labels.labels.get(&tile) else {
+            let visibility = if labels.visible {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+            let label = commands
+                .spawn((
+                    TileLabel(tile),
+                    Text2d::new((labels.style.text)(&tile_pos)),
+                    TextFont {
+                        font_size: labels.style.font_size,
+                        ..Default::default()
+                    },
+                    TextColor(labels.style.color),
+                    TextLayout::new_with_justify(JustifyText::Center),
+                    Transform::from_translation(translation),
+                    visibility,
+                ))
+                .set_parent(tilemap_id.0)
+                .id();
+            labels.labels.insert(tile, label);
+            continue;
+        };
+
+        if tilemap_id.is_changed() {
+            commands.entity(label).set_parent(tilemap_id.0);
+        }
+        if tile_pos.is_changed()
+            || tilemap_id.is_changed()
+            || grid_size.is_changed()
+            || map_type.is_changed()
+            || axes_changed.contains(&tilemap_id.0)
+        {
+            if let Ok((mut transform, mut text)) = label_query.get_mut(label) {
+                transform.translation = translation;
+                text.0 = (labels.style.text)(&tile_pos);
+            }
+        }
+    }
+
+    // Tiles which were despawned, or no longer match the filter.
+    labels.labels.retain(|tile, label| {
+        if tiles.contains(*tile) {
+            return true;
+        }
+        if let Some(label) = commands.get_entity(*label) {
+            label.despawn_recursive();
+        }
+        false
+    });
+}
+
+fn update_tile_label_visibility<F: QueryFilter + 'static>(
+    mut labels: ResMut<TileLabels<F>>,
+    cameras: Query<(&Camera, &OrthographicProjection)>,
+    mut label_query: Query<&mut Visibility, With<TileLabel>>,
+) {
+    let max_scale = labels.style.max_camera_scale;
+    let (mut any_active, mut zoomed_in) = (false, false);
+    for (camera, projection) in cameras.iter() {
+        if camera.is_active {
+            any_active = true;
+            zoomed_in |= projection.scale <= max_scale;
+        }
+    }
+    let visible = !any_active || zoomed_in;
+    if visible == labels.visible {
+        return;
+    }
+    labels.visible = visible;
+
+    for label in labels.labels.values() {
+        if let Ok(mut visibility) = label_query.get_mut(*label) {
+            *visibility = if visible {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+    use bevy::prelude::App;
+
+    use super::*;
+    use crate::test_utils::{spawn_test_map, tile_at, MinimalTilemapPlugins, StepApp};
+
+    #[test]
+    fn labels_follow_the_axes() {
+        let mut app = App::new();
+        app.add_plugins((MinimalTilemapPlugins, TileLabelsPlugin::<()>::default()));
+        let size = TilemapSize { x: 4, y: 3 };
+        let map = spawn_test_map(app.world_mut(), size, TilemapType::Square);
+        let tile = tile_at(app.world(), map, TilePos::new(1, 0)).unwrap();
+        app.step_frames(1);
+
+        let label_position = |app: &App| {
+            let label = app.world().resource::<TileLabels>().label(tile).unwrap();
+            let transform = app.world().get::<Transform>(label).unwrap();
+            transform.translation.truncate()
+        };
+        assert_eq!(label_position(&app), Vec2::new(16.0, 0.0));
+
+        // Storages of y-down maps count rows from the top, so the first row is drawn at the top.
+        let mut storage = app.world_mut().get_mut::<TileStorage>(map).unwrap();
+        storage.axes = TilemapAxes::Y_DOWN;
+        app.step_frames(1);
+        assert_eq!(label_position(&app), Vec2::new(16.0, 32.0));
+    }
+}
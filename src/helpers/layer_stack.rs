@@ -0,0 +1,197 @@
+use crate::map::{is_frozen, TilemapId, TilemapStatic};
+use crate::tiles::{TilePos, TilePosOld, TileRect, TileStorage, TileVisible};
+use crate::TilemapSize;
+use bevy::ecs::change_detection::DetectChanges;
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::ecs::reflect::ReflectMapEntities;
+use bevy::math::UVec2;
+use bevy::prelude::{
+    Changed, Commands, Component, Entity, Has, Or, Query, Ref, Reflect, ReflectComponent,
+    RemovedComponents,
+};
+use bevy::utils::HashSet;
+
+/// Stacks tilemap layers on top of each other, so that tiles hidden behind [`TileOpaque`] tiles
+/// of a higher layer are not rendered.
+///
+/// The layers must share the same tile positions, i.e. have the same grid size, map type and
+/// transform apart from their z position. Coverage is tracked per chunk of `chunk_size` tiles,
+/// and only chunks with changed tiles are recomputed. Tiles of a layer that is taken out of the
+/// stack keep their last [`TileOccluded`] state.
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component, MapEntities)]
+pub struct LayerStack {
+    /// The tilemap entities, from the bottom layer to the top layer.
+    pub layers: Vec<Entity>,
+    pub chunk_size: UVec2,
+}
+
+impl MapEntities for LayerStack {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        for layer in &mut self.layers {
+            *layer = entity_mapper.map_entity(*layer);
+        }
+    }
+}
+
+impl Default for LayerStack {
+    /// By default, the stack has no layers, and chunks of `32 x 32` tiles.
+    fn default() -> Self {
+        Self {
+            layers: Vec::new(),
+            chunk_size: UVec2::new(32, 32),
+        }
+    }
+}
+
+impl LayerStack {
+    /// Creates a stack of `layers`, from bottom to top.
+    pub fn new(layers: Vec<Entity>) -> Self {
+        Self {
+            layers,
+            ..Default::default()
+        }
+    }
+}
+
+/// Marks a tile whose texture covers its whole cell, hiding the tiles of lower layers in a
+/// [`LayerStack`].
+#[derive(Component, Reflect, Default, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct TileOpaque;
+
+/// Whether a tile is hidden by opaque tiles above it in a [`LayerStack`]. Occluded tiles are not
+/// rendered.
+///
+/// This is managed by the [`LayerStack`], and should not be changed by hand.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct TileOccluded(pub bool);
+
+/// Recomputes [`TileOccluded`] for the chunks of every [`LayerStack`] that have changed tiles.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_layer_occlusion(
+    mut commands: Commands,
+    stack_query: Query<Ref<LayerStack>>,
+    storage_query: Query<&TileStorage>,
+    changed_tiles: Query<
+        (&TilePos, Option<&TilePosOld>, &TilemapId),
+        Or<(Changed<TilePos>, Changed<TileVisible>, Changed<TileOpaque>)>,
+    >,
+    static_tilemaps: Query<Ref<TilemapStatic>>,
+    mut removed_opaque: RemovedComponents<TileOpaque>,
+    mut removed_tiles: RemovedComponents<TilePos>,
+    mut tile_query: Query<(
+        Option<&TileVisible>,
+        Has<TileOpaque>,
+        Option<&mut TileOccluded>,
+    )>,
+) {
+    // The positions of removed tiles are gone, so any removal recomputes every chunk.
+    let anything_removed = removed_opaque.read().count() > 0 || removed_tiles.read().count() > 0;
+
+    for stack in stack_query.iter() {
+        let chunk_size = stack.chunk_size.max(UVec2::ONE);
+        let Some(map_size) = stack
+            .layers
+            .iter()
+            .filter_map(|layer| storage_query.get(*layer).ok())
+            .map(|storage| UVec2::from(storage.size))
+            .reduce(UVec2::max)
+        else {
+            continue;
+        };
+
+        let dirty_chunks: HashSet<UVec2> = if stack.is_changed() || anything_removed {
+            let chunk_count = (map_size + chunk_size - UVec2::ONE) / chunk_size;
+            TileRect::new(TilePos::new(0, 0), chunk_count.into())
+                .iter()
+                .map(UVec2::from)
+                .collect()
+        } else {
+            changed_tiles
+                .iter()
+                .filter(|(_, _, tilemap_id)| {
+                    stack.layers.contains(&tilemap_id.0)
+                        && !is_frozen(&static_tilemaps, tilemap_id.0)
+                })
+                .flat_map(|(tile_pos, tile_pos_old, _)| {
+                    // A moved tile also uncovers the chunk it came from.
+                    [Some(tile_pos), tile_pos_old.map(|old| &old.0)]
+                        .into_iter()
+                        .flatten()
+                        .map(|tile_pos| UVec2::from(tile_pos) / chunk_size)
+                })
+                .collect()
+        };
+
+        for chunk_pos in dirty_chunks {
+            let mut coverage = ChunkCoverage::new(chunk_size);
+            // Walk down from the top layer, so that coverage only ever grows.
+            for layer in stack.layers.iter().rev() {
+                let Ok(storage) = storage_query.get(*layer) else {
+                    continue;
+                };
+                for tile_pos in chunk_rect(chunk_pos, chunk_size, &storage.size).iter() {
+                    let Some(tile_entity) = storage.get(&tile_pos) else {
+                        continue;
+                    };
+                    let Ok((visible, opaque, occluded)) = tile_query.get_mut(tile_entity) else {
+                        continue;
+                    };
+
+                    let bit = coverage.bit(UVec2::from(tile_pos) - chunk_pos * chunk_size);
+                    let covered = coverage.is_covered(bit);
+                    match occluded {
+                        Some(mut occluded) => {
+                            if occluded.0 != covered {
+                                occluded.0 = covered;
+                            }
+                        }
+                        None => {
+                            if covered {
+                                commands.entity(tile_entity).insert(TileOccluded(true));
+                            }
+                        }
+                    }
+
+                    if opaque && visible.is_none_or(|visible| visible.0) {
+                        coverage.cover(bit);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A bitmask of the tiles of a chunk that are covered by an opaque tile.
+struct ChunkCoverage {
+    width: u32,
+    bits: Vec<u64>,
+}
+
+impl ChunkCoverage {
+    fn new(chunk_size: UVec2) -> Self {
+        Self {
+            width: chunk_size.x,
+            bits: vec![0; (chunk_size.x * chunk_size.y).div_ceil(64) as usize],
+        }
+    }
+
+    fn bit(&self, chunk_tile_pos: UVec2) -> usize {
+        (chunk_tile_pos.y * self.width + chunk_tile_pos.x) as usize
+    }
+
+    fn is_covered(&self, bit: usize) -> bool {
+        self.bits[bit / 64] & (1 << (bit % 64)) != 0
+    }
+
+    fn cover(&mut self, bit: usize) {
+        self.bits[bit / 64] |= 1 << (bit % 64);
+    }
+}
+
+/// Returns the tile positions covered by the chunk at `chunk_pos`, clipped to the map.
+fn chunk_rect(chunk_pos: UVec2, chunk_size: UVec2, map_size: &TilemapSize) -> TileRect {
+    TileRect::new((chunk_pos * chunk_size).into(), chunk_size.into()).clamp_to_map(map_size)
+}
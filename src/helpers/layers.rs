@@ -0,0 +1,334 @@
+use crate::helpers::layer_stack::LayerStack;
+use crate::map::{
+    TilemapGridSize, TilemapInvalidate, TilemapRenderSettings, TilemapSize, TilemapSpacing,
+    TilemapTexture, TilemapTileSize, TilemapType,
+};
+use crate::tiles::{TilePos, TileStorage};
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::ecs::reflect::ReflectMapEntities;
+use bevy::ecs::system::SystemParam;
+use bevy::hierarchy::BuildChildren;
+use bevy::prelude::{
+    Changed, Commands, Component, Entity, Mut, OnRemove, Query, Reflect, ReflectComponent,
+    Transform, Trigger, Visibility, With,
+};
+
+#[cfg(feature = "render")]
+type LayerBundle = crate::TilemapBundle;
+#[cfg(not(feature = "render"))]
+type LayerBundle = crate::StandardTilemapBundle;
+
+/// The position of a tilemap in its [`TilemapLayers`], from `0` for the bottom layer.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[reflect(Component)]
+pub struct LayerIndex(pub u32);
+
+/// The tilemaps of a layered map, on the entity they are children of, from the bottom layer to
+/// the top layer. Spawn them with a [`LayeredTilemapBuilder`], and look them up with a
+/// [`TilemapLayerQuery`].
+#[derive(Component, Reflect, Default, Clone, Debug)]
+#[reflect(Component, MapEntities)]
+pub struct TilemapLayers {
+    pub layers: Vec<Entity>,
+}
+
+impl MapEntities for TilemapLayers {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        for layer in &mut self.layers {
+            *layer = entity_mapper.map_entity(*layer);
+        }
+    }
+}
+
+impl TilemapLayers {
+    /// Returns the tilemap of the layer at `index`.
+    pub fn get(&self, index: u32) -> Option<Entity> {
+        self.layers.get(index as usize).copied()
+    }
+
+    /// Returns the number of layers.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns true if there are no layers.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+}
+
+/// Draws the tiles of other tilemaps in the render chunks of the tilemap it is on, above its own
+/// tiles and in the order of `layers`, so that static stacks of layers (e.g. ground, details and
+/// shadows) take one draw call per chunk instead of one per layer and chunk.
+///
+/// The composed tilemaps are not drawn on their own, and must match the size, grid, map type,
+/// tile size and texture of the tilemap they are composed into, whose transform, color and render
+/// settings they are drawn with. Tiles of composed tilemaps can still change, but each change
+/// remeshes the shared chunk.
+///
+/// Changing the composition rebuilds the render chunks of all the tilemaps involved.
+#[derive(Component, Reflect, Default, Clone, Debug, PartialEq, Eq)]
+#[reflect(Component, MapEntities)]
+pub struct TilemapComposition {
+    /// The composed tilemaps, from the lowest to the highest.
+    pub layers: Vec<Entity>,
+}
+
+impl MapEntities for TilemapComposition {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        for layer in &mut self.layers {
+            *layer = entity_mapper.map_entity(*layer);
+        }
+    }
+}
+
+impl TilemapComposition {
+    pub fn new(layers: Vec<Entity>) -> Self {
+        Self { layers }
+    }
+}
+
+/// Rebuilds the render chunks of tilemaps whose [`TilemapComposition`] changed.
+pub(crate) fn invalidate_composed_tilemaps(
+    mut commands: Commands,
+    compositions: Query<(Entity, &TilemapComposition), Changed<TilemapComposition>>,
+) {
+    for (tilemap, composition) in compositions.iter() {
+        invalidate_composition(&mut commands, tilemap, composition);
+    }
+}
+
+/// Rebuilds the render chunks of tilemaps that lost their [`TilemapComposition`].
+pub(crate) fn invalidate_removed_composition(
+    trigger: Trigger<OnRemove, TilemapComposition>,
+    mut commands: Commands,
+    compositions: Query<&TilemapComposition>,
+) {
+    if let Ok(composition) = compositions.get(trigger.entity()) {
+        invalidate_composition(&mut commands, trigger.entity(), composition);
+    }
+}
+
+fn invalidate_composition(
+    commands: &mut Commands,
+    tilemap: Entity,
+    composition: &TilemapComposition,
+) {
+    // The tilemaps may be despawning along with the composition.
+    for entity in std::iter::once(tilemap).chain(composition.layers.iter().copied()) {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.try_insert(TilemapInvalidate::All);
+        }
+    }
+}
+
+/// Spawns a map made of several stacked tilemaps that share their size, grid and map type, e.g.
+/// ground, decorations and roofs.
+///
+/// The layers are spawned empty, as children of a root entity holding the [`TilemapLayers`] and
+/// the transform of the whole map, and each layer is `z_spacing` above the one below it.
+///
+/// ```
+/// # use bevy::ecs::world::CommandQueue;
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::layers::{LayerIndex, LayeredTilemapBuilder, TilemapLayers};
+/// # let mut world = World::new();
+/// # let mut queue = CommandQueue::default();
+/// # let mut commands = Commands::new(&mut queue, &world);
+/// let size = TilemapSize { x: 32, y: 32 };
+/// let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
+/// let map = LayeredTilemapBuilder::new(size, TilemapTexture::default(), tile_size, 3)
+///     .spawn(&mut commands);
+/// # queue.apply(&mut world);
+///
+/// let roofs = world.get::<TilemapLayers>(map).unwrap().get(2).unwrap();
+/// assert_eq!(world.get::<LayerIndex>(roofs), Some(&LayerIndex(2)));
+/// assert_eq!(world.get::<Transform>(roofs).unwrap().translation.z, 2.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct LayeredTilemapBuilder {
+    pub size: TilemapSize,
+    pub map_type: TilemapType,
+    pub grid_size: TilemapGridSize,
+    pub tile_size: TilemapTileSize,
+    pub spacing: TilemapSpacing,
+    pub texture: TilemapTexture,
+    pub render_settings: TilemapRenderSettings,
+    /// The number of layers.
+    pub layer_count: u32,
+    /// The distance between the z of a layer and the z of the layer below it.
+    pub z_spacing: f32,
+    /// The transform of the root entity, which all layers are relative to.
+    pub transform: Transform,
+    /// Adds a [`LayerStack`] of the layers to the root entity, so that tiles hidden behind
+    /// opaque tiles of higher layers are not rendered.
+    pub occlusion: bool,
+    /// Adds a [`TilemapComposition`] of the other layers to the bottom layer, so that all layers
+    /// are drawn in the render chunks of the bottom layer, at its z.
+    pub composition: bool,
+}
+
+impl LayeredTilemapBuilder {
+    /// Creates a builder for `layer_count` square layers whose grid size matches the tile size,
+    /// one unit of z apart.
+    pub fn new(
+        size: TilemapSize,
+        texture: TilemapTexture,
+        tile_size: TilemapTileSize,
+        layer_count: u32,
+    ) -> Self {
+        Self {
+            size,
+            map_type: TilemapType::default(),
+            grid_size: tile_size.into(),
+            tile_size,
+            spacing: TilemapSpacing::default(),
+            texture,
+            render_settings: TilemapRenderSettings::default(),
+            layer_count,
+            z_spacing: 1.0,
+            transform: Transform::default(),
+            occlusion: false,
+            composition: false,
+        }
+    }
+
+    pub fn with_map_type(mut self, map_type: TilemapType) -> Self {
+        self.map_type = map_type;
+        self
+    }
+
+    pub fn with_grid_size(mut self, grid_size: TilemapGridSize) -> Self {
+        self.grid_size = grid_size;
+        self
+    }
+
+    pub fn with_z_spacing(mut self, z_spacing: f32) -> Self {
+        self.z_spacing = z_spacing;
+        self
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_occlusion(mut self, occlusion: bool) -> Self {
+        self.occlusion = occlusion;
+        self
+    }
+
+    pub fn with_composition(mut self, composition: bool) -> Self {
+        self.composition = composition;
+        self
+    }
+
+    /// Spawns the root entity and its layers, and returns the root entity.
+    pub fn spawn(self, commands: &mut Commands) -> Entity {
+        let root = commands.spawn((self.transform, Visibility::default())).id();
+        let layers: Vec<Entity> = (0..self.layer_count)
+            .map(|index| {
+                commands
+                    .spawn((
+                        LayerBundle {
+                            grid_size: self.grid_size,
+                            map_type: self.map_type,
+                            size: self.size,
+                            spacing: self.spacing,
+                            storage: TileStorage::empty(self.size),
+                            texture: self.texture.clone(),
+                            tile_size: self.tile_size,
+                            transform: Transform::from_xyz(0.0, 0.0, index as f32 * self.z_spacing),
+                            render_settings: self.render_settings,
+                            ..Default::default()
+                        },
+                        LayerIndex(index),
+                    ))
+                    .set_parent(root)
+                    .id()
+            })
+            .collect();
+        if self.occlusion {
+            commands
+                .entity(root)
+                .insert(LayerStack::new(layers.clone()));
+        }
+        if let Some((bottom, others)) = layers.split_first().filter(|_| self.composition) {
+            commands
+                .entity(*bottom)
+                .insert(TilemapComposition::new(others.to_vec()));
+        }
+        commands.entity(root).insert(TilemapLayers { layers });
+        root
+    }
+}
+
+/// Looks up the layers of the maps spawned by a [`LayeredTilemapBuilder`], and their tiles.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::layers::{TilemapLayerQuery, TilemapLayers};
+/// const DECORATIONS: u32 = 1;
+///
+/// fn clear_decorations(
+///     mut commands: Commands,
+///     maps: Query<Entity, With<TilemapLayers>>,
+///     mut layers: TilemapLayerQuery,
+/// ) {
+///     for map in maps.iter() {
+///         let Some(mut storage) = layers.get_storage_mut(map, DECORATIONS) else {
+///             continue;
+///         };
+///         for tile_entity in storage.drain() {
+///             commands.entity(tile_entity).despawn();
+///         }
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct TilemapLayerQuery<'w, 's> {
+    maps: Query<'w, 's, &'static TilemapLayers>,
+    storages: Query<'w, 's, &'static mut TileStorage, With<LayerIndex>>,
+}
+
+impl TilemapLayerQuery<'_, '_> {
+    /// Returns the tilemap of the layer at `index` of `map`.
+    pub fn get_layer(&self, map: Entity, index: u32) -> Option<Entity> {
+        self.maps.get(map).ok()?.get(index)
+    }
+
+    /// Returns the tile storage of the layer at `index` of `map`.
+    pub fn get_storage(&self, map: Entity, index: u32) -> Option<&TileStorage> {
+        self.storages.get(self.get_layer(map, index)?).ok()
+    }
+
+    /// Returns the tile storage of the layer at `index` of `map`, to place or remove tiles.
+    pub fn get_storage_mut(&mut self, map: Entity, index: u32) -> Option<Mut<'_, TileStorage>> {
+        let layer = self.get_layer(map, index)?;
+        self.storages.get_mut(layer).ok()
+    }
+
+    /// Returns the tile at `tile_pos` in the layer at `index` of `map`.
+    pub fn get_tile(&self, map: Entity, index: u32, tile_pos: &TilePos) -> Option<Entity> {
+        self.get_storage(map, index)?.checked_get(tile_pos)
+    }
+
+    /// Returns the tiles at `tile_pos` in every layer of `map` along with their layer index, from
+    /// the bottom layer to the top layer.
+    pub fn tiles_at(&self, map: Entity, tile_pos: TilePos) -> Vec<(u32, Entity)> {
+        let Ok(layers) = self.maps.get(map) else {
+            return Vec::new();
+        };
+        layers
+            .layers
+            .iter()
+            .enumerate()
+            .filter_map(|(index, layer)| {
+                let tile_entity = self.storages.get(*layer).ok()?.checked_get(&tile_pos)?;
+                Some((index as u32, tile_entity))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,487 @@
+use std::fmt;
+
+use bevy::asset::{io::Reader, AssetLoader, AssetPath, LoadContext};
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension};
+use bevy::utils::HashMap;
+
+use crate::map::{
+    TilemapColor, TilemapGridSize, TilemapId, TilemapSize, TilemapSpacing, TilemapTexture,
+    TilemapTileSize, TilemapType,
+};
+use crate::tiles::{TileBundle, TileFlip, TilePos, TileStorage, TileTextureIndex, TileVisible};
+
+#[cfg(feature = "render")]
+type LdtkTilemapBundle = crate::TilemapBundle;
+#[cfg(not(feature = "render"))]
+type LdtkTilemapBundle = crate::StandardTilemapBundle;
+
+/// Loads [LDtk](https://ldtk.io) projects from `.ldtk` files, and spawns the layers of a level
+/// for every [`LdtkMapHandle`].
+///
+/// ```no_run
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::ldtk::{LdtkMapBundle, LdtkMapHandle, LdtkMapPlugin};
+/// fn spawn_map(mut commands: Commands, asset_server: Res<AssetServer>) {
+///     commands.spawn(LdtkMapBundle {
+///         ldtk_map: LdtkMapHandle(asset_server.load("map.ldtk")),
+///         ..Default::default()
+///     });
+/// }
+///
+/// App::new()
+///     .add_plugins((DefaultPlugins, TilemapPlugin, LdtkMapPlugin))
+///     .add_systems(Startup, spawn_map)
+///     .run();
+/// ```
+pub struct LdtkMapPlugin;
+
+impl Plugin for LdtkMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LdtkMap>()
+            .register_asset_loader(LdtkMapLoader)
+            .register_type::<LdtkMapHandle>()
+            .register_type::<LdtkMapConfig>()
+            .register_type::<LdtkLayer>()
+            .register_type::<LdtkIntGridValue>()
+            .add_systems(Update, spawn_ldtk_levels);
+    }
+}
+
+/// An LDtk project, along with the images of its tilesets.
+///
+/// The renderer expects the margin around the tiles of an image to be as wide as the spacing
+/// between them, so the images of tilesets with a different padding are loaded with their
+/// margin changed to the spacing.
+#[derive(Asset, TypePath)]
+pub struct LdtkMap {
+    pub project: ldtk_rust::Project,
+    /// The images of the tilesets, by tileset uid.
+    pub tilesets: HashMap<i64, Handle<Image>>,
+}
+
+/// The LDtk project whose level is spawned as children of the entity.
+#[derive(Component, Reflect, Default, Clone, Debug)]
+#[reflect(Component)]
+pub struct LdtkMapHandle(pub Handle<LdtkMap>);
+
+/// Chooses the level of the project to spawn. Changing it respawns the layers.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct LdtkMapConfig {
+    /// The index of the level in the project.
+    pub selected_level: usize,
+}
+
+/// The components of an entity spawning a level of an LDtk project.
+#[derive(Bundle, Default)]
+pub struct LdtkMapBundle {
+    pub ldtk_map: LdtkMapHandle,
+    pub ldtk_map_config: LdtkMapConfig,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub inherited_visibility: InheritedVisibility,
+    pub view_visibility: ViewVisibility,
+}
+
+/// Identifies the LDtk layer a tilemap was spawned from.
+#[derive(Component, Reflect, Default, Clone, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct LdtkLayer {
+    /// The name of the layer in the project.
+    pub identifier: String,
+    /// The uid of the layer definition.
+    pub layer_def_uid: i64,
+}
+
+/// The value of an IntGrid layer cell, added to the tile at that cell.
+///
+/// Cells of IntGrid layers without an auto-layer tileset get a tile to hold their value. These
+/// layers have no texture and aren't rendered: their tilemaps and tiles are never synced to the
+/// render world.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+pub struct LdtkIntGridValue(pub i64);
+
+/// Loads an [`LdtkMap`] from a `.ldtk` file, along with the images of its tilesets.
+#[derive(Default)]
+pub struct LdtkMapLoader;
+
+/// The error returned when an [`LdtkMap`] could not be loaded.
+#[derive(Debug)]
+pub enum LdtkMapLoaderError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// The image of a tileset whose padding differs from its spacing could not be loaded.
+    Tileset(String),
+}
+
+impl fmt::Display for LdtkMapLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LdtkMapLoaderError::Io(error) => write!(f, "could not read LDtk project: {error}"),
+            LdtkMapLoaderError::Json(error) => write!(f, "could not parse LDtk project: {error}"),
+            LdtkMapLoaderError::Tileset(error) => {
+                write!(f, "could not load LDtk tileset image: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LdtkMapLoaderError {}
+
+impl AssetLoader for LdtkMapLoader {
+    type Asset = LdtkMap;
+    type Settings = ();
+    type Error = LdtkMapLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(LdtkMapLoaderError::Io)?;
+        let project: ldtk_rust::Project =
+            serde_json::from_slice(&bytes).map_err(LdtkMapLoaderError::Json)?;
+
+        // Tileset paths are relative to the project file.
+        let directory = load_context.path().parent().map(|path| path.to_path_buf());
+        let mut tilesets = HashMap::default();
+        for tileset in project.defs.tilesets.iter() {
+            let Some(rel_path) = tileset.rel_path.as_ref() else {
+                continue;
+            };
+            let path: AssetPath = match directory.as_ref() {
+                Some(directory) => directory.join(rel_path).into(),
+                None => rel_path.clone().into(),
+            };
+            if tileset.padding == tileset.spacing {
+                tilesets.insert(tileset.uid, load_context.load(path));
+                continue;
+            }
+            let image = load_context
+                .loader()
+                .immediate()
+                .load::<Image>(path)
+                .await
+                .map_err(|error| LdtkMapLoaderError::Tileset(error.to_string()))?;
+            let image =
+                with_margin(image.get(), tileset.padding, tileset.spacing).ok_or_else(|| {
+                    LdtkMapLoaderError::Tileset(format!(
+                        "the padding of {rel_path} can't be changed in its texture format"
+                    ))
+                })?;
+            let handle = load_context.add_labeled_asset(format!("tileset{}", tileset.uid), image);
+            tilesets.insert(tileset.uid, handle);
+        }
+
+        Ok(LdtkMap { project, tilesets })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ldtk"]
+    }
+}
+
+/// Returns a copy of a tileset `image` whose tiles are surrounded by a margin of `padding` pixels,
+/// with the margin changed to `margin` pixels. Returns `None` for compressed texture formats.
+fn with_margin(image: &Image, padding: i64, margin: i64) -> Option<Image> {
+    let format = image.texture_descriptor.format;
+    if format.block_dimensions() != (1, 1) {
+        return None;
+    }
+    let pixel_size = format.block_copy_size(None)? as usize;
+    let (width, height) = (image.width() as i64, image.height() as i64);
+    // The pixel of the source image at the origin of the new one.
+    let shift = padding - margin;
+    let (new_width, new_height) = ((width - 2 * shift).max(0), (height - 2 * shift).max(0));
+
+    let mut data = vec![0; (new_width * new_height) as usize * pixel_size];
+    let (start_x, end_x) = ((-shift).max(0), new_width.min(width - shift));
+    for y in 0..new_height {
+        let source_y = y + shift;
+        if source_y < 0 || source_y >= height || start_x >= end_x {
+            continue;
+        }
+        let target = (y * new_width + start_x) as usize * pixel_size;
+        let source = (source_y * width + start_x + shift) as usize * pixel_size;
+        let len = (end_x - start_x) as usize * pixel_size;
+        data[target..target + len].copy_from_slice(&image.data[source..source + len]);
+    }
+
+    let mut new_image = Image::new(
+        Extent3d {
+            width: new_width as u32,
+            height: new_height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        format,
+        image.asset_usage,
+    );
+    new_image.sampler = image.sampler.clone();
+    Some(new_image)
+}
+
+/// Respawns the layers of the selected level when a project is loaded or modified, or when the
+/// map entity or its config changes.
+#[allow(clippy::type_complexity)]
+fn spawn_ldtk_levels(
+    mut commands: Commands,
+    mut map_events: EventReader<AssetEvent<LdtkMap>>,
+    maps: Res<Assets<LdtkMap>>,
+    map_entities: Query<(Entity, Ref<LdtkMapHandle>, Option<Ref<LdtkMapConfig>>)>,
+) {
+    let mut changed_maps: Vec<AssetId<LdtkMap>> = Vec::new();
+    for event in map_events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+                changed_maps.push(*id);
+            }
+            _ => {}
+        }
+    }
+
+    for (entity, map_handle, config) in map_entities.iter() {
+        let config_changed = config.as_ref().is_some_and(|config| config.is_changed());
+        if !changed_maps.contains(&map_handle.0.id()) && !map_handle.is_changed() && !config_changed
+        {
+            continue;
+        }
+        let Some(ldtk_map) = maps.get(&map_handle.0) else {
+            continue;
+        };
+        let selected_level = config.map_or(0, |config| config.selected_level);
+        commands.entity(entity).despawn_descendants();
+        spawn_level(&mut commands, entity, ldtk_map, selected_level);
+    }
+}
+
+/// Spawns a tilemap per layer of a level, as children of `map_entity`.
+fn spawn_level(
+    commands: &mut Commands,
+    map_entity: Entity,
+    ldtk_map: &LdtkMap,
+    selected_level: usize,
+) {
+    let Some(level) = ldtk_map.project.levels.get(selected_level) else {
+        warn!("LDtk project has no level {selected_level}.");
+        return;
+    };
+    let Some(layers) = level.layer_instances.as_ref() else {
+        warn!(
+            "LDtk level {} is stored in a separate file, which isn't supported.",
+            level.identifier
+        );
+        return;
+    };
+
+    // LDtk lists layers from top to bottom.
+    for (z, layer) in layers.iter().rev().enumerate() {
+        let size = TilemapSize {
+            x: layer.c_wid.max(0) as u32,
+            y: layer.c_hei.max(0) as u32,
+        };
+        let cell_size = layer.grid_size.max(1);
+        // LDtk rows go down, tilemap rows go up.
+        let cell_pos = |px: i64, py: i64| {
+            TilePos::from_i32_pair(
+                (px / cell_size) as i32,
+                size.y as i32 - 1 - (py / cell_size) as i32,
+                &size,
+            )
+        };
+
+        let tileset = layer.tileset_def_uid.and_then(|uid| {
+            let definition = ldtk_map
+                .project
+                .defs
+                .tilesets
+                .iter()
+                .find(|tileset| tileset.uid == uid)?;
+            Some((definition, ldtk_map.tilesets.get(&uid)?.clone()))
+        });
+
+        let tilemap_entity = commands.spawn_empty().set_parent(map_entity).id();
+        let tilemap_id = TilemapId(tilemap_entity);
+        let mut storage = TileStorage::empty(size);
+        // Layers without a tileset, e.g. plain IntGrid layers, only hold data.
+        let rendered = tileset.is_some();
+        let spawn_tile = |commands: &mut Commands, tile: TileBundle| {
+            let mut tile_commands = if rendered {
+                commands.spawn(tile)
+            } else {
+                commands.spawn((
+                    tile.position,
+                    tile.texture_index,
+                    tile.tilemap_id,
+                    tile.visible,
+                    tile.flip,
+                    tile.color,
+                    tile.old_position,
+                ))
+            };
+            tile_commands.set_parent(tilemap_entity).id()
+        };
+        for tile in layer.grid_tiles.iter().chain(layer.auto_layer_tiles.iter()) {
+            let (Some(&px), Some(&py)) = (tile.px.first(), tile.px.get(1)) else {
+                continue;
+            };
+            let Some(position) = cell_pos(px, py) else {
+                continue;
+            };
+            // Stacked tiles on the same cell replace each other, the last one is on top.
+            if let Some(replaced) = storage.get(&position) {
+                commands.entity(replaced).despawn_recursive();
+            }
+            let tile_entity = spawn_tile(
+                commands,
+                TileBundle {
+                    position,
+                    tilemap_id,
+                    texture_index: TileTextureIndex(tile.t.max(0) as u32),
+                    flip: TileFlip {
+                        x: tile.f & 1 != 0,
+                        y: tile.f & 2 != 0,
+                        d: false,
+                    },
+                    ..Default::default()
+                },
+            );
+            storage.set(&position, tile_entity);
+        }
+
+        for (index, value) in layer.int_grid_csv.iter().enumerate() {
+            if *value == 0 {
+                continue;
+            }
+            let index = index as i64;
+            let Some(position) = cell_pos(
+                index % layer.c_wid.max(1) * cell_size,
+                index / layer.c_wid.max(1) * cell_size,
+            ) else {
+                continue;
+            };
+            match storage.get(&position) {
+                Some(tile_entity) => {
+                    commands
+                        .entity(tile_entity)
+                        .insert(LdtkIntGridValue(*value));
+                }
+                None => {
+                    let tile_entity = spawn_tile(
+                        commands,
+                        TileBundle {
+                            position,
+                            tilemap_id,
+                            visible: TileVisible(false),
+                            ..Default::default()
+                        },
+                    );
+                    commands
+                        .entity(tile_entity)
+                        .insert(LdtkIntGridValue(*value));
+                    storage.set(&position, tile_entity);
+                }
+            }
+        }
+
+        let grid_size = TilemapGridSize {
+            x: cell_size as f32,
+            y: cell_size as f32,
+        };
+        let offset = Vec3::new(
+            layer.px_total_offset_x as f32,
+            -layer.px_total_offset_y as f32,
+            z as f32,
+        );
+        let visibility = if layer.visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        let ldtk_layer = LdtkLayer {
+            identifier: layer.identifier.clone(),
+            layer_def_uid: layer.layer_def_uid,
+        };
+        let Some((definition, image)) = tileset else {
+            commands.entity(tilemap_entity).insert((
+                grid_size,
+                TilemapType::Square,
+                size,
+                storage,
+                Transform::from_translation(offset),
+                visibility,
+                ldtk_layer,
+            ));
+            continue;
+        };
+        commands.entity(tilemap_entity).insert((
+            LdtkTilemapBundle {
+                grid_size,
+                map_type: TilemapType::Square,
+                size,
+                spacing: TilemapSpacing {
+                    x: definition.spacing as f32,
+                    y: definition.spacing as f32,
+                },
+                storage,
+                texture: TilemapTexture::Single(image),
+                tile_size: TilemapTileSize {
+                    x: definition.tile_grid_size as f32,
+                    y: definition.tile_grid_size as f32,
+                },
+                transform: Transform::from_translation(offset),
+                visibility,
+                ..Default::default()
+            },
+            TilemapColor(Color::WHITE.with_alpha(layer.opacity as f32)),
+            ldtk_layer,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::{render_asset::RenderAssetUsages, render_resource::TextureFormat};
+
+    fn image(width: u32, height: u32, data: Vec<u8>) -> Image {
+        Image::new(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::R8Unorm,
+            RenderAssetUsages::default(),
+        )
+    }
+
+    #[test]
+    fn tileset_margins_match_the_spacing() {
+        #[rustfmt::skip]
+        let padded = image(4, 4, vec![
+            0, 0, 0, 0,
+            0, 1, 2, 0,
+            0, 3, 4, 0,
+            0, 0, 0, 0,
+        ]);
+        let cropped = with_margin(&padded, 1, 0).unwrap();
+        assert_eq!(cropped.size(), UVec2::new(2, 2));
+        assert_eq!(cropped.data, vec![1, 2, 3, 4]);
+
+        let repadded = with_margin(&cropped, 0, 1).unwrap();
+        assert_eq!(repadded.data, padded.data);
+    }
+}
@@ -1,7 +1,43 @@
+pub mod area_effect;
+pub mod atlas_packing;
+pub mod audio;
+pub mod automata;
+pub mod blueprint;
+pub mod chunk_loader;
+pub mod colliders;
+pub mod collision;
+pub mod decoration;
+pub mod distance_field;
 pub mod filling;
+pub mod flow;
 pub mod geometry;
 pub mod hex_grid;
+#[cfg(feature = "labels")]
+pub mod labels;
+pub mod layer_stack;
+pub mod layers;
+#[cfg(feature = "ldtk")]
+pub mod ldtk;
+pub mod navmesh;
+pub mod nearest_chunks;
+pub mod occupants;
+pub mod passes;
+pub mod path;
+pub mod platform;
 pub mod projection;
+pub mod regions;
+pub mod registry;
+pub mod rng;
 pub mod selection;
+pub mod serialization;
+pub mod snapshot;
+pub mod split_merge;
 pub mod square_grid;
+pub mod storage_repair;
+pub mod subgrid;
+pub mod symmetry;
+pub mod tactics;
+pub mod template;
+pub mod tile_group;
+pub mod tint;
 pub mod transform;
@@ -0,0 +1,396 @@
+use crate::map::TilemapSize;
+use crate::tiles::{TilePos, TileStorage};
+use bevy::math::{Rect, UVec2, Vec2};
+use bevy::prelude::Entity;
+use bevy::utils::HashSet;
+use std::ops::RangeInclusive;
+
+/// The chunk size used by [`build`].
+pub const DEFAULT_NAV_CHUNK_SIZE: UVec2 = UVec2::splat(16);
+
+/// Identifies a [`NavRect`] of a [`NavMesh`]. Ids of a chunk change when it is rebuilt.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct NavRectId {
+    /// The index of the chunk the rectangle belongs to.
+    pub chunk: UVec2,
+    /// The index of the rectangle within its chunk.
+    pub index: u32,
+}
+
+/// A rectangle of walkable tiles, from `min` to `max` inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NavRect {
+    pub min: TilePos,
+    pub max: TilePos,
+}
+
+impl NavRect {
+    /// Returns true if the rectangle contains `tile_pos`.
+    pub fn contains(&self, tile_pos: &TilePos) -> bool {
+        (self.min.x..=self.max.x).contains(&tile_pos.x)
+            && (self.min.y..=self.max.y).contains(&tile_pos.y)
+    }
+
+    /// Returns the area covered by the rectangle in tile space, where the tile at `(x, y)` spans
+    /// from `(x - 0.5, y - 0.5)` to `(x + 0.5, y + 0.5)`. On square maps, multiplying by the grid
+    /// size gives the rectangle relative to the tilemap.
+    pub fn tile_space_rect(&self) -> Rect {
+        Rect::new(
+            self.min.x as f32 - 0.5,
+            self.min.y as f32 - 0.5,
+            self.max.x as f32 + 0.5,
+            self.max.y as f32 + 0.5,
+        )
+    }
+}
+
+/// An edge shared by two adjacent [`NavRect`]s, which agents can cross in both directions.
+///
+/// `start` and `end` are in the tile space of [`NavRect::tile_space_rect`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NavPortal {
+    pub from: NavRectId,
+    pub to: NavRectId,
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+#[derive(Clone, Debug, Default)]
+struct NavChunk {
+    rects: Vec<NavRect>,
+    /// The portals on the right and top edges of the chunk's rectangles.
+    portals: Vec<NavPortal>,
+}
+
+/// The walkable area of a tilemap, described as rectangles of walkable tiles connected by
+/// portals, e.g. for path smoothing or steering.
+///
+/// Rectangles never cross chunk borders, so that changed tiles only rebuild their chunk, see
+/// [`NavMesh::update`]. The mesh works in tile space, and treats the map as a square grid.
+///
+/// ```
+/// # use bevy::prelude::World;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::navmesh;
+/// # use bevy_ecs_tilemap::test_utils::spawn_test_map;
+/// # let mut world = World::new();
+/// let map = spawn_test_map(&mut world, TilemapSize { x: 8, y: 8 }, TilemapType::Square);
+/// let storage = world.get::<TileStorage>(map).unwrap();
+///
+/// // A wall along x = 3, with a gap at y = 7.
+/// let walkable = |tile_pos: &TilePos, _tile| tile_pos.x != 3 || tile_pos.y == 7;
+/// let mesh = navmesh::build(storage, walkable);
+///
+/// let left = mesh.rect_at(&TilePos::new(0, 0)).unwrap();
+/// let right = mesh.rect_at(&TilePos::new(7, 0)).unwrap();
+/// assert_ne!(left, right);
+/// assert!(mesh.portals().all(|portal| portal.start.y >= 6.5));
+/// ```
+#[derive(Clone, Debug)]
+pub struct NavMesh {
+    map_size: TilemapSize,
+    chunk_size: UVec2,
+    chunk_count: UVec2,
+    chunks: Vec<NavChunk>,
+    /// The rectangle of every tile, if it is walkable.
+    lookup: Vec<Option<NavRectId>>,
+}
+
+/// Builds the [`NavMesh`] of a tilemap, with chunks of [`DEFAULT_NAV_CHUNK_SIZE`] tiles.
+///
+/// `walkable` is given each tile position along with its entity, if there is one.
+pub fn build(
+    storage: &TileStorage,
+    walkable: impl Fn(&TilePos, Option<Entity>) -> bool,
+) -> NavMesh {
+    NavMesh::build(storage, DEFAULT_NAV_CHUNK_SIZE, walkable)
+}
+
+impl NavMesh {
+    /// Builds the mesh of a tilemap, with chunks of `chunk_size` tiles.
+    pub fn build(
+        storage: &TileStorage,
+        chunk_size: UVec2,
+        walkable: impl Fn(&TilePos, Option<Entity>) -> bool,
+    ) -> Self {
+        let map_size = storage.size;
+        let chunk_size = chunk_size.max(UVec2::ONE);
+        let chunk_count = (UVec2::from(map_size) + chunk_size - UVec2::ONE) / chunk_size;
+        let mut mesh = Self {
+            map_size,
+            chunk_size,
+            chunk_count,
+            chunks: vec![NavChunk::default(); (chunk_count.x * chunk_count.y) as usize],
+            lookup: vec![None; map_size.count()],
+        };
+        let all_chunks: Vec<UVec2> = (0..chunk_count.y)
+            .flat_map(|y| (0..chunk_count.x).map(move |x| UVec2::new(x, y)))
+            .collect();
+        for chunk in all_chunks.iter() {
+            mesh.build_rects(*chunk, storage, &walkable);
+        }
+        for chunk in all_chunks {
+            mesh.build_portals(chunk);
+        }
+        mesh
+    }
+
+    /// Rebuilds the chunks containing `changed_tiles`, e.g. the tiles placed or removed since the
+    /// last update, or whose walkability changed.
+    ///
+    /// If the tilemap was resized, the whole mesh is rebuilt.
+    pub fn update(
+        &mut self,
+        storage: &TileStorage,
+        changed_tiles: impl IntoIterator<Item = TilePos>,
+        walkable: impl Fn(&TilePos, Option<Entity>) -> bool,
+    ) {
+        if storage.size != self.map_size {
+            *self = Self::build(storage, self.chunk_size, walkable);
+            return;
+        }
+
+        let dirty: HashSet<UVec2> = changed_tiles
+            .into_iter()
+            .filter(|tile_pos| tile_pos.within_map_bounds(&self.map_size))
+            .map(|tile_pos| UVec2::from(tile_pos) / self.chunk_size)
+            .collect();
+        for chunk in dirty.iter() {
+            self.build_rects(*chunk, storage, &walkable);
+        }
+
+        // The chunks to the left and below also have portals into the rebuilt chunks.
+        let mut portal_chunks = dirty.clone();
+        for chunk in dirty.iter() {
+            if chunk.x > 0 {
+                portal_chunks.insert(*chunk - UVec2::X);
+            }
+            if chunk.y > 0 {
+                portal_chunks.insert(*chunk - UVec2::Y);
+            }
+        }
+        for chunk in portal_chunks {
+            self.build_portals(chunk);
+        }
+    }
+
+    /// Returns the size of the tilemap the mesh was built for.
+    pub fn map_size(&self) -> TilemapSize {
+        self.map_size
+    }
+
+    /// Returns the size of the chunks, in tiles.
+    pub fn chunk_size(&self) -> UVec2 {
+        self.chunk_size
+    }
+
+    /// Returns the rectangle containing `tile_pos`, if the tile is walkable.
+    pub fn rect_at(&self, tile_pos: &TilePos) -> Option<NavRectId> {
+        if !tile_pos.within_map_bounds(&self.map_size) {
+            return None;
+        }
+        self.lookup[tile_pos.to_index(&self.map_size)]
+    }
+
+    /// Returns the rectangle with the given id.
+    pub fn rect(&self, id: NavRectId) -> Option<&NavRect> {
+        self.chunk(id.chunk)?.rects.get(id.index as usize)
+    }
+
+    /// Returns an iterator over all rectangles.
+    pub fn rects(&self) -> impl Iterator<Item = (NavRectId, &NavRect)> {
+        self.chunk_indices().flat_map(move |chunk| {
+            self.chunks[self.chunk_slot(chunk)]
+                .rects
+                .iter()
+                .enumerate()
+                .map(move |(index, rect)| {
+                    let id = NavRectId {
+                        chunk,
+                        index: index as u32,
+                    };
+                    (id, rect)
+                })
+        })
+    }
+
+    /// Returns an iterator over all portals. Every portal appears once, although it can be
+    /// crossed in both directions.
+    pub fn portals(&self) -> impl Iterator<Item = &NavPortal> {
+        self.chunks.iter().flat_map(|chunk| chunk.portals.iter())
+    }
+
+    /// Returns the portals of the rectangle `id`, along with the rectangle on their other side.
+    pub fn neighbors(&self, id: NavRectId) -> impl Iterator<Item = (NavRectId, &NavPortal)> {
+        // Portals are stored with the rectangle on their left or bottom side.
+        let candidates = [
+            Some(id.chunk),
+            id.chunk.x.checked_sub(1).map(|x| UVec2::new(x, id.chunk.y)),
+            id.chunk.y.checked_sub(1).map(|y| UVec2::new(id.chunk.x, y)),
+        ];
+        candidates
+            .into_iter()
+            .flatten()
+            .filter_map(|chunk| self.chunk(chunk))
+            .flat_map(|chunk| chunk.portals.iter())
+            .filter_map(move |portal| {
+                if portal.from == id {
+                    Some((portal.to, portal))
+                } else if portal.to == id {
+                    Some((portal.from, portal))
+                } else {
+                    None
+                }
+            })
+    }
+
+    fn chunk_indices(&self) -> impl Iterator<Item = UVec2> {
+        let chunk_count = self.chunk_count;
+        (0..chunk_count.y).flat_map(move |y| (0..chunk_count.x).map(move |x| UVec2::new(x, y)))
+    }
+
+    fn chunk_slot(&self, chunk: UVec2) -> usize {
+        (chunk.y * self.chunk_count.x + chunk.x) as usize
+    }
+
+    fn chunk(&self, chunk: UVec2) -> Option<&NavChunk> {
+        if chunk.x < self.chunk_count.x && chunk.y < self.chunk_count.y {
+            Some(&self.chunks[self.chunk_slot(chunk)])
+        } else {
+            None
+        }
+    }
+
+    /// Merges the walkable tiles of a chunk into rectangles, greedily growing each rectangle
+    /// along x first, then along y.
+    fn build_rects(
+        &mut self,
+        chunk: UVec2,
+        storage: &TileStorage,
+        walkable: &impl Fn(&TilePos, Option<Entity>) -> bool,
+    ) {
+        let origin = chunk * self.chunk_size;
+        let end = (origin + self.chunk_size).min(UVec2::from(self.map_size));
+        let size = end - origin;
+        let local_index = |x: u32, y: u32| ((y - origin.y) * size.x + (x - origin.x)) as usize;
+
+        let mut open = vec![false; (size.x * size.y) as usize];
+        for y in origin.y..end.y {
+            for x in origin.x..end.x {
+                let tile_pos = TilePos::new(x, y);
+                open[local_index(x, y)] = walkable(&tile_pos, storage.get(&tile_pos));
+                self.lookup[tile_pos.to_index(&self.map_size)] = None;
+            }
+        }
+
+        let mut rects = Vec::new();
+        for y in origin.y..end.y {
+            for x in origin.x..end.x {
+                if !open[local_index(x, y)] {
+                    continue;
+                }
+                let mut max_x = x;
+                while max_x + 1 < end.x && open[local_index(max_x + 1, y)] {
+                    max_x += 1;
+                }
+                let mut max_y = y;
+                while max_y + 1 < end.y
+                    && (x..=max_x).all(|row_x| open[local_index(row_x, max_y + 1)])
+                {
+                    max_y += 1;
+                }
+
+                let id = NavRectId {
+                    chunk,
+                    index: rects.len() as u32,
+                };
+                for rect_y in y..=max_y {
+                    for rect_x in x..=max_x {
+                        open[local_index(rect_x, rect_y)] = false;
+                        let tile_pos = TilePos::new(rect_x, rect_y);
+                        self.lookup[tile_pos.to_index(&self.map_size)] = Some(id);
+                    }
+                }
+                rects.push(NavRect {
+                    min: TilePos::new(x, y),
+                    max: TilePos::new(max_x, max_y),
+                });
+            }
+        }
+
+        let slot = self.chunk_slot(chunk);
+        self.chunks[slot].rects = rects;
+    }
+
+    /// Finds the portals on the right and top edges of a chunk's rectangles.
+    fn build_portals(&mut self, chunk: UVec2) {
+        let slot = self.chunk_slot(chunk);
+        let mut portals = Vec::new();
+        for (index, rect) in self.chunks[slot].rects.iter().enumerate() {
+            let from = NavRectId {
+                chunk,
+                index: index as u32,
+            };
+
+            if rect.max.x + 1 < self.map_size.x {
+                let x = rect.max.x + 1;
+                let edge = x as f32 - 0.5;
+                self.push_portals(from, rect.min.y..=rect.max.y, Vec2::Y, &mut portals, |y| {
+                    (TilePos::new(x, y), Vec2::new(edge, y as f32))
+                });
+            }
+            if rect.max.y + 1 < self.map_size.y {
+                let y = rect.max.y + 1;
+                let edge = y as f32 - 0.5;
+                self.push_portals(from, rect.min.x..=rect.max.x, Vec2::X, &mut portals, |x| {
+                    (TilePos::new(x, y), Vec2::new(x as f32, edge))
+                });
+            }
+        }
+        self.chunks[slot].portals = portals;
+    }
+
+    /// Walks along one edge of the rectangle `from`, and adds a portal for every run of tiles
+    /// beyond the edge that belong to the same rectangle. `beyond` returns the tile beyond the
+    /// edge at `i`, and the point of the edge next to it. The edge runs along `direction`.
+    fn push_portals(
+        &self,
+        from: NavRectId,
+        range: RangeInclusive<u32>,
+        direction: Vec2,
+        portals: &mut Vec<NavPortal>,
+        beyond: impl Fn(u32) -> (TilePos, Vec2),
+    ) {
+        let along = |point: Vec2, offset: f32| point + direction * offset;
+
+        let mut run: Option<(NavRectId, Vec2, Vec2)> = None;
+        for i in range {
+            let (tile_pos, point) = beyond(i);
+            let to = self.lookup[tile_pos.to_index(&self.map_size)];
+            match (&mut run, to) {
+                (Some((run_to, _, end)), Some(to)) if *run_to == to => {
+                    *end = along(point, 0.5);
+                }
+                _ => {
+                    if let Some((to, start, end)) = run.take() {
+                        portals.push(NavPortal {
+                            from,
+                            to,
+                            start,
+                            end,
+                        });
+                    }
+                    run = to.map(|to| (to, along(point, -0.5), along(point, 0.5)));
+                }
+            }
+        }
+        if let Some((to, start, end)) = run {
+            portals.push(NavPortal {
+                from,
+                to,
+                start,
+                end,
+            });
+        }
+    }
+}
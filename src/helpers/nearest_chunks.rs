@@ -0,0 +1,260 @@
+use crate::helpers::transform::{chunk_aabb, chunk_index_to_world_space};
+use crate::map::{
+    TilemapAxes, TilemapGridSize, TilemapRenderSettings, TilemapSize, TilemapTileSize, TilemapType,
+};
+use crate::tiles::{TilePos, TileRect, TileStorage};
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::math::{UVec2, Vec2};
+use bevy::prelude::{
+    Camera, DetectChanges, Entity, GlobalTransform, IntoSystemConfigs, Query, Ref, ResMut,
+    Resource, TransformSystem,
+};
+use bevy::utils::HashMap;
+
+/// A render chunk of a tilemap, and its distance to a camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NearChunk {
+    /// The index of the chunk, in chunks of [`TilemapRenderSettings::render_chunk_size`] tiles.
+    /// Like the render chunks, chunks are laid out from the bottom left of the map, whatever its
+    /// [`TilemapAxes`].
+    pub index: UVec2,
+    /// The tiles of the chunk, in the tile positions of the tilemap, which are flipped
+    /// vertically on tilemaps with y-down [`TilemapAxes`].
+    pub tiles: TileRect,
+    /// The world space position of the center of the chunk.
+    pub center: Vec2,
+    /// The distance between the center of the chunk and the camera, ignoring z.
+    pub distance: f32,
+}
+
+/// The render chunks of every tilemap, sorted by their distance to each active camera, nearest
+/// first.
+///
+/// This uses the same chunks and chunk centers as [`RemeshPolicy::NearestCameraFirst`], so that
+/// systems which activate AI, play ambient sounds or stream data around the cameras agree with
+/// what is rebuilt first. The tiles of a chunk are given in tile positions, following the
+/// [`TilemapAxes`] of the tilemap's [`TileStorage`]. Lists are only recomputed when a camera or
+/// tilemap moved or changed.
+///
+/// Requires the [`NearestChunksPlugin`].
+///
+/// [`RemeshPolicy::NearestCameraFirst`]: crate::map::RemeshPolicy::NearestCameraFirst
+#[derive(Resource, Default, Debug)]
+pub struct NearestChunks {
+    cameras: HashMap<Entity, HashMap<Entity, Vec<NearChunk>>>,
+    /// The axes of each tilemap when its chunks were last sorted.
+    axes: HashMap<Entity, TilemapAxes>,
+}
+
+impl NearestChunks {
+    /// Returns the chunks of `tilemap`, nearest to `camera` first. Empty if the camera isn't
+    /// active, or either entity doesn't exist.
+    pub fn get(&self, camera: Entity, tilemap: Entity) -> &[NearChunk] {
+        self.cameras
+            .get(&camera)
+            .and_then(|tilemaps| tilemaps.get(&tilemap))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the chunks of `tilemap` within `radius` world units of `camera`, nearest first.
+    pub fn within(
+        &self,
+        camera: Entity,
+        tilemap: Entity,
+        radius: f32,
+    ) -> impl Iterator<Item = &NearChunk> {
+        self.get(camera, tilemap)
+            .iter()
+            .take_while(move |chunk| chunk.distance <= radius)
+    }
+
+    /// Returns the chunks of `tilemap` sorted by their distance to the nearest active camera.
+    pub fn nearest_to_any_camera(&self, tilemap: Entity) -> Vec<NearChunk> {
+        let mut nearest: HashMap<UVec2, NearChunk> = HashMap::default();
+        for tilemaps in self.cameras.values() {
+            for chunk in tilemaps.get(&tilemap).into_iter().flatten() {
+                nearest
+                    .entry(chunk.index)
+                    .and_modify(|near| {
+                        if chunk.distance < near.distance {
+                            *near = *chunk;
+                        }
+                    })
+                    .or_insert(*chunk);
+            }
+        }
+        let mut chunks: Vec<NearChunk> = nearest.into_values().collect();
+        sort_chunks(&mut chunks);
+        chunks
+    }
+
+    /// Returns an iterator over the active cameras that chunks are sorted for.
+    pub fn cameras(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.cameras.keys().copied()
+    }
+}
+
+/// Adds the [`NearestChunks`] resource, and the system keeping it up to date.
+pub struct NearestChunksPlugin;
+
+impl Plugin for NearestChunksPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NearestChunks>().add_systems(
+            PostUpdate,
+            update_nearest_chunks.after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+fn update_nearest_chunks(
+    mut nearest_chunks: ResMut<NearestChunks>,
+    cameras: Query<(Entity, Ref<GlobalTransform>, &Camera)>,
+    tilemaps: Query<(
+        Entity,
+        Ref<GlobalTransform>,
+        Ref<TilemapSize>,
+        Ref<TilemapGridSize>,
+        Ref<TilemapTileSize>,
+        Ref<TilemapType>,
+        Ref<TilemapRenderSettings>,
+        Option<&TileStorage>,
+    )>,
+) {
+    let nearest_chunks = nearest_chunks.as_mut();
+    // Forget inactive or despawned cameras, and despawned tilemaps.
+    nearest_chunks.cameras.retain(|camera, sorted| {
+        sorted.retain(|tilemap, _| tilemaps.contains(*tilemap));
+        cameras
+            .get(*camera)
+            .is_ok_and(|(_, _, camera)| camera.is_active)
+    });
+    // Storages change with every tile, so only a change of their axes counts.
+    let mut axes_changed = Vec::new();
+    nearest_chunks
+        .axes
+        .retain(|tilemap, _| tilemaps.contains(*tilemap));
+    for (tilemap, .., storage) in tilemaps.iter() {
+        let axes = storage.map(|storage| storage.axes).unwrap_or_default();
+        if nearest_chunks.axes.insert(tilemap, axes) != Some(axes) {
+            axes_changed.push(tilemap);
+        }
+    }
+
+    for (camera, camera_transform, _) in cameras.iter().filter(|(_, _, camera)| camera.is_active) {
+        let position = camera_transform.translation().truncate();
+        let sorted = nearest_chunks.cameras.entry(camera).or_default();
+        for (tilemap, transform, map_size, grid_size, tile_size, map_type, render_settings, _) in
+            tilemaps.iter()
+        {
+            let changed = axes_changed.contains(&tilemap)
+                || camera_transform.is_changed()
+                || transform.is_changed()
+                || map_size.is_changed()
+                || grid_size.is_changed()
+                || tile_size.is_changed()
+                || map_type.is_changed()
+                || render_settings.is_changed();
+            if !changed && sorted.contains_key(&tilemap) {
+                continue;
+            }
+
+            let chunk_size = render_settings.render_chunk_size.max(UVec2::ONE);
+            let chunk_count = (UVec2::from(*map_size) + chunk_size - UVec2::ONE) / chunk_size;
+            // The center of a chunk, relative to the center of its bottom left tile.
+            let chunk_center = chunk_aabb(chunk_size, &grid_size, &tile_size, &map_type)
+                .center
+                .truncate();
+
+            let axes = nearest_chunks.axes[&tilemap];
+            let chunks = sorted.entry(tilemap).or_default();
+            chunks.clear();
+            for y in 0..chunk_count.y {
+                for x in 0..chunk_count.x {
+                    let index = UVec2::new(x, y);
+                    let grid_rect = TileRect::new(
+                        TilePos::from(index * chunk_size),
+                        TilemapSize::from(chunk_size),
+                    )
+                    .clamp_to_map(&map_size);
+                    let last = TilePos::new(grid_rect.end().x - 1, grid_rect.end().y - 1);
+                    let tiles = TileRect::from_corners(
+                        axes.to_grid_pos(&grid_rect.origin, &map_size),
+                        axes.to_grid_pos(&last, &map_size),
+                    );
+                    let local =
+                        chunk_index_to_world_space(index, chunk_size, &grid_size, &map_type)
+                            + chunk_center;
+                    let center = transform.transform_point(local.extend(0.0)).truncate();
+                    chunks.push(NearChunk {
+                        index,
+                        tiles,
+                        center,
+                        distance: center.distance(position),
+                    });
+                }
+            }
+            sort_chunks(chunks);
+        }
+    }
+}
+
+/// Sorts chunks by distance, and by index between chunks at the same distance, so that the order
+/// doesn't depend on iteration order.
+fn sort_chunks(chunks: &mut [NearChunk]) {
+    chunks.sort_by(|a, b| {
+        a.distance
+            .total_cmp(&b.distance)
+            .then_with(|| (a.index.y, a.index.x).cmp(&(b.index.y, b.index.x)))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{App, Transform};
+
+    use super::*;
+    use crate::test_utils::{spawn_empty_test_map, MinimalTilemapPlugins, StepApp};
+
+    #[test]
+    fn chunk_tiles_follow_the_axes() {
+        let mut app = App::new();
+        app.add_plugins((MinimalTilemapPlugins, NearestChunksPlugin));
+        app.world_mut()
+            .spawn((Camera::default(), Transform::default()));
+        let size = TilemapSize { x: 8, y: 6 };
+        let map = spawn_empty_test_map(app.world_mut(), size, TilemapType::Square);
+        app.world_mut()
+            .entity_mut(map)
+            .insert(TilemapRenderSettings {
+                render_chunk_size: UVec2::new(4, 4),
+                ..Default::default()
+            });
+        app.step_frames(1);
+
+        let camera = app
+            .world()
+            .resource::<NearestChunks>()
+            .cameras()
+            .next()
+            .unwrap();
+        let tiles = |app: &App| {
+            let nearest_chunks = app.world().resource::<NearestChunks>();
+            let chunk = nearest_chunks.get(camera, map)[0];
+            assert_eq!(chunk.index, UVec2::ZERO);
+            chunk.tiles
+        };
+        assert_eq!(
+            tiles(&app),
+            TileRect::new(TilePos::new(0, 0), TilemapSize { x: 4, y: 4 })
+        );
+
+        app.world_mut()
+            .entity_mut(map)
+            .insert(TileStorage::empty_with_axes(size, TilemapAxes::Y_DOWN));
+        app.step_frames(1);
+        assert_eq!(
+            tiles(&app),
+            TileRect::new(TilePos::new(0, 2), TilemapSize { x: 4, y: 4 })
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use crate::map::{TilemapGridSize, TilemapType};
+use crate::tiles::{TilePos, TileStorage};
+use crate::TilemapSystemSet;
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::prelude::{
+    Component, DetectChanges, Entity, Event, EventWriter, GlobalTransform, IntoSystemConfigs,
+    Query, Ref, Reflect, ReflectComponent, RemovedComponents,
+};
+use bevy::utils::HashMap;
+
+/// Marks an entity that isn't a tile, like an actor or an item, whose tile on a tilemap is
+/// tracked in the tilemap's [`OccupantsIndex`].
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct TileOccupant {
+    /// The tilemap the entity stands on.
+    pub tilemap: Entity,
+}
+
+/// Tracks which occupants stand on which tiles of a tilemap.
+///
+/// Add this to a tilemap, along with the [`TileOccupantsPlugin`], to keep it in sync with the
+/// [`GlobalTransform`] of the entities marked with [`TileOccupant`]. Occupants can also be placed
+/// by hand, e.g. in turn based games that don't move entities through their transforms.
+///
+/// ```
+/// # use bevy::prelude::Entity;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::occupants::OccupantsIndex;
+/// let mut index = OccupantsIndex::default();
+/// let (knight, chest) = (Entity::from_raw(1), Entity::from_raw(2));
+/// index.insert(knight, TilePos::new(2, 3));
+/// index.insert(chest, TilePos::new(2, 3));
+/// assert_eq!(index.occupants_of(&TilePos::new(2, 3)), &[knight, chest]);
+///
+/// assert_eq!(index.insert(knight, TilePos::new(3, 3)), Some(TilePos::new(2, 3)));
+/// assert_eq!(index.occupants_of(&TilePos::new(2, 3)), &[chest]);
+/// assert_eq!(index.tile_of(knight), Some(TilePos::new(3, 3)));
+/// ```
+#[derive(Component, Default, Clone, Debug)]
+pub struct OccupantsIndex {
+    tiles: HashMap<TilePos, Vec<Entity>>,
+    occupants: HashMap<Entity, TilePos>,
+}
+
+impl OccupantsIndex {
+    /// Returns the occupants standing on `tile_pos`, in the order they arrived.
+    pub fn occupants_of(&self, tile_pos: &TilePos) -> &[Entity] {
+        self.tiles.get(tile_pos).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns true if any occupant stands on `tile_pos`.
+    pub fn is_occupied(&self, tile_pos: &TilePos) -> bool {
+        self.tiles.contains_key(tile_pos)
+    }
+
+    /// Returns the tile `occupant` stands on, if it is on the map.
+    pub fn tile_of(&self, occupant: Entity) -> Option<TilePos> {
+        self.occupants.get(&occupant).copied()
+    }
+
+    /// Returns an iterator over all occupants and the tiles they stand on.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, TilePos)> + '_ {
+        self.occupants
+            .iter()
+            .map(|(occupant, tile_pos)| (*occupant, *tile_pos))
+    }
+
+    /// Returns the number of occupants on the map.
+    pub fn len(&self) -> usize {
+        self.occupants.len()
+    }
+
+    /// Returns true if no occupant is on the map.
+    pub fn is_empty(&self) -> bool {
+        self.occupants.is_empty()
+    }
+
+    /// Places `occupant` on `tile_pos`, and returns the tile it stood on before, if any.
+    pub fn insert(&mut self, occupant: Entity, tile_pos: TilePos) -> Option<TilePos> {
+        let previous = self.occupants.insert(occupant, tile_pos);
+        if previous == Some(tile_pos) {
+            return previous;
+        }
+        if let Some(previous) = previous {
+            self.remove_from_tile(occupant, &previous);
+        }
+        self.tiles.entry(tile_pos).or_default().push(occupant);
+        previous
+    }
+
+    /// Takes `occupant` off the map, and returns the tile it stood on, if any.
+    pub fn remove(&mut self, occupant: Entity) -> Option<TilePos> {
+        let previous = self.occupants.remove(&occupant)?;
+        self.remove_from_tile(occupant, &previous);
+        Some(previous)
+    }
+
+    fn remove_from_tile(&mut self, occupant: Entity, tile_pos: &TilePos) {
+        if let Some(occupants) = self.tiles.get_mut(tile_pos) {
+            occupants.retain(|other| *other != occupant);
+            if occupants.is_empty() {
+                self.tiles.remove(tile_pos);
+            }
+        }
+    }
+}
+
+/// Sent by the [`TileOccupantsPlugin`] when an occupant arrives on a tile.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OccupantEnteredTile {
+    pub occupant: Entity,
+    pub tilemap: Entity,
+    pub tile_pos: TilePos,
+}
+
+/// Sent by the [`TileOccupantsPlugin`] when an occupant leaves a tile, including when it leaves
+/// the map, is despawned, or loses its [`TileOccupant`].
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OccupantLeftTile {
+    pub occupant: Entity,
+    pub tilemap: Entity,
+    pub tile_pos: TilePos,
+}
+
+/// Keeps the [`OccupantsIndex`] of tilemaps in sync with the [`GlobalTransform`] of their
+/// [`TileOccupant`]s, and sends [`OccupantEnteredTile`] and [`OccupantLeftTile`] events.
+///
+/// Occupants are placed on the tile containing their translation. Indexes are updated in
+/// `PostUpdate`, after transforms have been propagated, for occupants and tilemaps that moved.
+pub struct TileOccupantsPlugin;
+
+impl Plugin for TileOccupantsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TileOccupant>()
+            .add_event::<OccupantEnteredTile>()
+            .add_event::<OccupantLeftTile>()
+            .add_systems(
+                PostUpdate,
+                update_occupants_indexes.after(TilemapSystemSet::TransformTracking),
+            );
+    }
+}
+
+fn update_occupants_indexes(
+    mut entered_events: EventWriter<OccupantEnteredTile>,
+    mut left_events: EventWriter<OccupantLeftTile>,
+    mut tilemaps: Query<(
+        Entity,
+        &mut OccupantsIndex,
+        &TileStorage,
+        &TilemapGridSize,
+        &TilemapType,
+        Ref<GlobalTransform>,
+    )>,
+    occupants: Query<(Entity, Ref<TileOccupant>, Ref<GlobalTransform>)>,
+    mut removed_occupants: RemovedComponents<TileOccupant>,
+) {
+    for occupant in removed_occupants.read() {
+        for (tilemap, mut index, ..) in tilemaps.iter_mut() {
+            if let Some(tile_pos) = index.remove(occupant) {
+                left_events.send(OccupantLeftTile {
+                    occupant,
+                    tilemap,
+                    tile_pos,
+                });
+            }
+        }
+    }
+
+    for (occupant, tile_occupant, global_transform) in occupants.iter() {
+        // Occupants that moved to another tilemap leave the tilemap they were on.
+        if tile_occupant.is_changed() {
+            for (tilemap, mut index, ..) in tilemaps.iter_mut() {
+                if tilemap == tile_occupant.tilemap || index.tile_of(occupant).is_none() {
+                    continue;
+                }
+                if let Some(tile_pos) = index.remove(occupant) {
+                    left_events.send(OccupantLeftTile {
+                        occupant,
+                        tilemap,
+                        tile_pos,
+                    });
+                }
+            }
+        }
+
+        let Ok((tilemap, mut index, storage, grid_size, map_type, map_transform)) =
+            tilemaps.get_mut(tile_occupant.tilemap)
+        else {
+            continue;
+        };
+        if !tile_occupant.is_changed()
+            && !global_transform.is_changed()
+            && !map_transform.is_changed()
+            && !index.is_added()
+        {
+            continue;
+        }
+
+        let local_pos = map_transform
+            .affine()
+            .inverse()
+            .transform_point3(global_transform.translation())
+            .truncate();
+        let tile_pos = TilePos::from_world_pos_with_axes(
+            &local_pos,
+            &storage.size,
+            grid_size,
+            map_type,
+            &storage.axes,
+        );
+        let previous = index.tile_of(occupant);
+        if previous == tile_pos {
+            continue;
+        }
+
+        if let Some(previous) = previous {
+            index.remove(occupant);
+            left_events.send(OccupantLeftTile {
+                occupant,
+                tilemap,
+                tile_pos: previous,
+            });
+        }
+        if let Some(tile_pos) = tile_pos {
+            index.insert(occupant, tile_pos);
+            entered_events.send(OccupantEnteredTile {
+                occupant,
+                tilemap,
+                tile_pos,
+            });
+        }
+    }
+}
@@ -0,0 +1,351 @@
+//! Passes deriving tilemaps from other tilemaps, e.g. autotile indices from a terrain layer or a
+//! shadow layer from a wall layer, re-run whenever their inputs change.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::ecs::component::Tick;
+use bevy::ecs::system::SystemState;
+use bevy::prelude::{
+    Changed, Component, DetectChanges, Entity, IntoSystemConfigs, Local, Mut, Or, Query, Ref,
+    Resource, World,
+};
+use bevy::utils::HashSet;
+
+use crate::map::TilemapId;
+use crate::tiles::{TileColor, TileFlip, TilePos, TileStorage, TileTextureIndex, TileVisible};
+use crate::TilemapSystemSet;
+
+/// The tilemaps a [`TilemapPass`] reads and writes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TilemapPassLayers {
+    pub inputs: Vec<Entity>,
+    pub outputs: Vec<Entity>,
+}
+
+/// When a [`TilemapPass`] runs, besides when it is requested.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TilemapPassTrigger {
+    /// Runs in the frames where the tiles or the storage of one of its inputs changed. Tiles
+    /// count as changed when their [`TilePos`], [`TileTextureIndex`], [`TileVisible`],
+    /// [`TileColor`], [`TileFlip`] or [`TilemapId`] change, or one of the components given to
+    /// [`TilemapPass::watching`].
+    #[default]
+    InputsChanged,
+    /// Only runs when requested, with [`TilemapPasses::request`].
+    OnDemand,
+}
+
+/// A transformation of input tilemaps into output tilemaps, run by the [`TilemapPassesPlugin`].
+///
+/// The pass is a function with exclusive access to the world, given the entities of its layers.
+/// It runs once after being added, then whenever its [`TilemapPassTrigger`] says so.
+#[derive(Clone)]
+pub struct TilemapPass {
+    pub name: Cow<'static, str>,
+    pub layers: TilemapPassLayers,
+    pub trigger: TilemapPassTrigger,
+    run: Arc<dyn Fn(&mut World, &TilemapPassLayers) + Send + Sync>,
+    /// Adds the tilemaps of the tiles whose watched component changed since a tick.
+    watched: Vec<fn(&mut World, Tick, &mut HashSet<Entity>)>,
+    requested: bool,
+}
+
+impl TilemapPass {
+    pub fn new(
+        name: impl Into<Cow<'static, str>>,
+        inputs: impl IntoIterator<Item = Entity>,
+        outputs: impl IntoIterator<Item = Entity>,
+        run: impl Fn(&mut World, &TilemapPassLayers) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            layers: TilemapPassLayers {
+                inputs: inputs.into_iter().collect(),
+                outputs: outputs.into_iter().collect(),
+            },
+            trigger: TilemapPassTrigger::default(),
+            run: Arc::new(run),
+            watched: Vec::new(),
+            requested: true,
+        }
+    }
+
+    /// Also runs the pass when the `C` component of a tile of one of its inputs changes, e.g. a
+    /// height or a terrain type read by the pass.
+    pub fn watching<C: Component>(mut self) -> Self {
+        self.watched.push(changed_tilemaps::<C>);
+        self
+    }
+
+    /// Only runs the pass when requested, e.g. for expensive passes like baking lighting.
+    pub fn on_demand(mut self) -> Self {
+        self.trigger = TilemapPassTrigger::OnDemand;
+        self
+    }
+
+    /// Runs the pass right away, outside of the [`TilemapPassesPlugin`].
+    pub fn run(&self, world: &mut World) {
+        (self.run)(world, &self.layers);
+    }
+}
+
+impl fmt::Debug for TilemapPass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TilemapPass")
+            .field("name", &self.name)
+            .field("layers", &self.layers)
+            .field("trigger", &self.trigger)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The [`TilemapPass`]es of the app, run in the order they were added, so that a pass sees the
+/// outputs of the passes before it as changed inputs in the same frame.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::passes::{TilemapPass, TilemapPasses};
+/// fn add_shadow_pass(passes: &mut TilemapPasses, walls: Entity, shadows: Entity) {
+///     // Tiles below a wall are in its shadow.
+///     passes.add(TilemapPass::new("shadows", [walls], [shadows], |world, layers| {
+///         let walls = world.get::<TileStorage>(layers.inputs[0]).unwrap().clone();
+///         let shadows = world.get::<TileStorage>(layers.outputs[0]).unwrap().clone();
+///         for (tile_pos, shadow) in shadows.iter_some() {
+///             let shaded = TilePos::new(tile_pos.x, tile_pos.y + 1);
+///             let visible = walls.checked_get(&shaded).is_some();
+///             world.entity_mut(shadow).insert(TileVisible(visible));
+///         }
+///     }));
+/// }
+/// ```
+#[derive(Resource, Clone, Debug, Default)]
+pub struct TilemapPasses {
+    passes: Vec<TilemapPass>,
+}
+
+impl TilemapPasses {
+    /// Adds a pass after the others, replacing the pass with the same name, if any.
+    pub fn add(&mut self, pass: TilemapPass) -> &mut Self {
+        match self.passes.iter_mut().find(|other| other.name == pass.name) {
+            Some(other) => *other = pass,
+            None => self.passes.push(pass),
+        }
+        self
+    }
+
+    /// Removes the pass named `name`, and returns it.
+    pub fn remove(&mut self, name: &str) -> Option<TilemapPass> {
+        let index = self.passes.iter().position(|pass| pass.name == name)?;
+        Some(self.passes.remove(index))
+    }
+
+    /// Returns the pass named `name`.
+    pub fn get(&self, name: &str) -> Option<&TilemapPass> {
+        self.passes.iter().find(|pass| pass.name == name)
+    }
+
+    /// Runs the pass named `name` during the next update, whatever its trigger. Returns false if
+    /// there is no such pass.
+    pub fn request(&mut self, name: &str) -> bool {
+        let pass = self.passes.iter_mut().find(|pass| pass.name == name);
+        pass.map(|pass| pass.requested = true).is_some()
+    }
+
+    /// Runs every pass during the next update.
+    pub fn request_all(&mut self) {
+        for pass in self.passes.iter_mut() {
+            pass.requested = true;
+        }
+    }
+
+    /// Returns an iterator over the passes, in the order they run.
+    pub fn iter(&self) -> impl Iterator<Item = &TilemapPass> {
+        self.passes.iter()
+    }
+}
+
+/// Adds the [`TilemapPasses`] resource, and runs its passes in `PostUpdate`, before the tilemaps
+/// are prepared for rendering.
+pub struct TilemapPassesPlugin;
+
+impl Plugin for TilemapPassesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TilemapPasses>().add_systems(
+            PostUpdate,
+            run_tilemap_passes.before(TilemapSystemSet::ExtractionPrep),
+        );
+    }
+}
+
+type ChangedTiles = Or<(
+    Changed<TilePos>,
+    Changed<TileTextureIndex>,
+    Changed<TileVisible>,
+    Changed<TileColor>,
+    Changed<TileFlip>,
+    Changed<TilemapId>,
+)>;
+
+/// Adds the tilemaps of the tiles whose `C` component changed after `since` to `changed`.
+fn changed_tilemaps<C: Component>(world: &mut World, since: Tick, changed: &mut HashSet<Entity>) {
+    let this_run = world.change_tick();
+    let mut tiles = world.query::<(&TilemapId, Ref<C>)>();
+    for (tilemap_id, component) in tiles.iter(world) {
+        if component.last_changed().is_newer_than(since, this_run) {
+            changed.insert(tilemap_id.0);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn run_tilemap_passes(
+    world: &mut World,
+    changes: &mut SystemState<(
+        Query<&TilemapId, ChangedTiles>,
+        Query<Entity, Changed<TileStorage>>,
+    )>,
+    mut last_run: Local<Tick>,
+) {
+    let (changed_tiles, changed_storages) = changes.get(world);
+    let mut changed: HashSet<Entity> = changed_tiles
+        .iter()
+        .map(|tilemap_id| tilemap_id.0)
+        .collect();
+    changed.extend(changed_storages.iter());
+
+    world.resource_scope(|world, mut passes: Mut<TilemapPasses>| {
+        let watched: Vec<_> = passes
+            .passes
+            .iter()
+            .flat_map(|pass| pass.watched.iter().copied())
+            .collect();
+        for changed_tilemaps in watched {
+            changed_tilemaps(world, *last_run, &mut changed);
+        }
+
+        for pass in passes.passes.iter_mut() {
+            let inputs_changed = pass.trigger == TilemapPassTrigger::InputsChanged
+                && pass
+                    .layers
+                    .inputs
+                    .iter()
+                    .any(|input| changed.contains(input));
+            if !pass.requested && !inputs_changed {
+                continue;
+            }
+            pass.requested = false;
+            (pass.run)(world, &pass.layers);
+            changed.extend(pass.layers.outputs.iter().copied());
+        }
+    });
+
+    // Reading the changes again moves past the changes made by the passes, so that they don't
+    // trigger the passes again in the next frame.
+    changes.get(world);
+    *last_run = world.change_tick();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{TilemapSize, TilemapType};
+    use crate::test_utils::{spawn_test_map, tile_at, MinimalTilemapPlugins, StepApp};
+
+    #[derive(Resource, Default)]
+    struct Runs(u32);
+
+    #[derive(Component)]
+    struct Height(u32);
+
+    fn test_app() -> (App, Entity, Entity) {
+        let mut app = App::new();
+        app.add_plugins((MinimalTilemapPlugins, TilemapPassesPlugin))
+            .init_resource::<Runs>();
+        let size = TilemapSize { x: 4, y: 4 };
+        let input = spawn_test_map(app.world_mut(), size, TilemapType::Square);
+        let output = spawn_test_map(app.world_mut(), size, TilemapType::Square);
+        app.step_frames(1);
+        (app, input, output)
+    }
+
+    /// Counts its runs, and hides the output tiles over input tiles with a texture index other
+    /// than 0.
+    fn counting_pass(input: Entity, output: Entity) -> TilemapPass {
+        TilemapPass::new("count", [input], [output], |world, layers| {
+            world.resource_mut::<Runs>().0 += 1;
+            let input = world.get::<TileStorage>(layers.inputs[0]).unwrap().clone();
+            let output = world.get::<TileStorage>(layers.outputs[0]).unwrap().clone();
+            for (tile_pos, tile) in output.iter_some() {
+                let input_tile = input.get(&tile_pos).unwrap();
+                let texture_index = world.get::<TileTextureIndex>(input_tile).unwrap().0;
+                world
+                    .entity_mut(tile)
+                    .insert(TileVisible(texture_index == 0));
+            }
+        })
+    }
+
+    fn runs(app: &App) -> u32 {
+        app.world().resource::<Runs>().0
+    }
+
+    #[test]
+    fn passes_run_once_after_being_added() {
+        let (mut app, input, output) = test_app();
+        app.world_mut()
+            .resource_mut::<TilemapPasses>()
+            .add(counting_pass(input, output));
+        app.step_frames(3);
+        assert_eq!(runs(&app), 1);
+    }
+
+    #[test]
+    fn passes_rerun_when_their_inputs_change() {
+        let (mut app, input, output) = test_app();
+        app.world_mut()
+            .resource_mut::<TilemapPasses>()
+            .add(counting_pass(input, output));
+        app.step_frames(1);
+
+        let tile = tile_at(app.world(), input, TilePos::new(2, 2)).unwrap();
+        app.world_mut().get_mut::<TileTextureIndex>(tile).unwrap().0 = 1;
+        app.step_frames(2);
+        assert_eq!(runs(&app), 2);
+        let hidden = tile_at(app.world(), output, TilePos::new(2, 2)).unwrap();
+        assert_eq!(
+            app.world().get::<TileVisible>(hidden),
+            Some(&TileVisible(false))
+        );
+    }
+
+    #[test]
+    fn passes_dont_loop_on_their_own_outputs() {
+        let (mut app, input, _) = test_app();
+        // The pass writes to the tilemap it reads.
+        app.world_mut()
+            .resource_mut::<TilemapPasses>()
+            .add(counting_pass(input, input));
+        app.step_frames(4);
+        assert_eq!(runs(&app), 1);
+    }
+
+    #[test]
+    fn passes_rerun_when_watched_components_change() {
+        let (mut app, input, output) = test_app();
+        let tile = tile_at(app.world(), input, TilePos::new(0, 3)).unwrap();
+        app.world_mut().entity_mut(tile).insert(Height(1));
+        app.world_mut()
+            .resource_mut::<TilemapPasses>()
+            .add(counting_pass(input, output).watching::<Height>());
+        app.step_frames(2);
+        assert_eq!(runs(&app), 1);
+
+        app.world_mut().get_mut::<Height>(tile).unwrap().0 = 2;
+        app.step_frames(2);
+        assert_eq!(runs(&app), 2);
+    }
+}
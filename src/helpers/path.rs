@@ -0,0 +1,283 @@
+use crate::helpers::hex_grid::axial::AxialPos;
+use crate::helpers::hex_grid::neighbors::{HexDirection, HEX_DIRECTIONS};
+use crate::helpers::square_grid::neighbors::SquareDirection;
+use crate::helpers::square_grid::staggered::StaggeredPos;
+use crate::helpers::square_grid::SquarePos;
+use crate::map::{IsoCoordSystem, TilemapId, TilemapSize, TilemapType};
+use crate::tiles::{TileBundle, TilePos, TileStorage, TileTextureIndex};
+use bevy::hierarchy::BuildChildren;
+use bevy::math::Vec2;
+use bevy::prelude::{Commands, Component, Query, Reflect, ReflectComponent};
+use bevy::utils::HashMap;
+
+const CARDINAL_DIRECTIONS: [SquareDirection; 4] = [
+    SquareDirection::East,
+    SquareDirection::North,
+    SquareDirection::West,
+    SquareDirection::South,
+];
+
+/// The directions a path tile connects to, one bit per direction.
+///
+/// On square and isometric maps, bit `n` stands for the [`SquareDirection`] with index `n`, so
+/// only the bits of the cardinal directions are used. On hexagonal maps, bit `n` stands for the
+/// [`HexDirection`] with index `n`.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilePathConnections(pub u8);
+
+impl TilePathConnections {
+    /// Creates the connections of a square or isometric tile connecting to `directions`.
+    pub fn from_square(directions: &[SquareDirection]) -> Self {
+        Self(
+            directions
+                .iter()
+                .fold(0, |mask, direction| mask | 1 << *direction as u8),
+        )
+    }
+
+    /// Creates the connections of a hexagonal tile connecting to `directions`.
+    pub fn from_hex(directions: &[HexDirection]) -> Self {
+        Self(
+            directions
+                .iter()
+                .fold(0, |mask, direction| mask | 1 << *direction as u8),
+        )
+    }
+}
+
+/// A rule table choosing the texture of a path tile from its [`TilePathConnections`], e.g. a
+/// straight piece for east and west, a corner for north and east, or a crossing for all four.
+///
+/// Example:
+/// ```
+/// # use bevy_ecs_tilemap::helpers::path::{PathTileRules, TilePathConnections};
+/// # use bevy_ecs_tilemap::helpers::square_grid::neighbors::SquareDirection::*;
+/// # use bevy_ecs_tilemap::prelude::TileTextureIndex;
+/// let rules = PathTileRules::default()
+///     .with_square(&[East, West], TileTextureIndex(0))
+///     .with_square(&[North, South], TileTextureIndex(1))
+///     .with_square(&[North, East, South, West], TileTextureIndex(2));
+/// let crossing = TilePathConnections::from_square(&[East, North, West, South]);
+/// assert_eq!(rules.get(crossing), Some(TileTextureIndex(2)));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PathTileRules {
+    tiles: HashMap<u8, TileTextureIndex>,
+    /// The texture used for connections without a rule. If `None`, such tiles are not written.
+    pub fallback: Option<TileTextureIndex>,
+}
+
+impl PathTileRules {
+    /// Adds a rule for square or isometric tiles connecting to exactly `directions`.
+    pub fn with_square(
+        mut self,
+        directions: &[SquareDirection],
+        texture_index: TileTextureIndex,
+    ) -> Self {
+        self.insert(TilePathConnections::from_square(directions), texture_index);
+        self
+    }
+
+    /// Adds a rule for hexagonal tiles connecting to exactly `directions`.
+    pub fn with_hex(
+        mut self,
+        directions: &[HexDirection],
+        texture_index: TileTextureIndex,
+    ) -> Self {
+        self.insert(TilePathConnections::from_hex(directions), texture_index);
+        self
+    }
+
+    /// Adds a rule, replacing any rule for the same connections.
+    pub fn insert(&mut self, connections: TilePathConnections, texture_index: TileTextureIndex) {
+        self.tiles.insert(connections.0, texture_index);
+    }
+
+    /// Gets the texture for the given connections.
+    pub fn get(&self, connections: TilePathConnections) -> Option<TileTextureIndex> {
+        self.tiles.get(&connections.0).copied().or(self.fallback)
+    }
+}
+
+/// Routes a path through `waypoints` in order, and returns every tile on the path along with the
+/// directions it connects to.
+///
+/// Consecutive waypoints are joined by the straightest chain of adjacent tiles. Tiles the path
+/// visits more than once, e.g. where it crosses itself, appear once, with all of their
+/// connections combined. The path stops early if a waypoint lies outside of the map.
+pub fn route_path(
+    waypoints: &[TilePos],
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+) -> Vec<(TilePos, TilePathConnections)> {
+    let mut route: Vec<(TilePos, TilePathConnections)> = Vec::new();
+    let mut indices: HashMap<TilePos, usize> = HashMap::default();
+    let mut connect = |tile_pos: TilePos, bit: Option<u8>| {
+        let index = *indices.entry(tile_pos).or_insert_with(|| {
+            route.push((tile_pos, TilePathConnections::default()));
+            route.len() - 1
+        });
+        if let Some(bit) = bit {
+            route[index].1 .0 |= 1 << bit;
+        }
+    };
+
+    let Some(first) = waypoints.first() else {
+        return Vec::new();
+    };
+    if !first.within_map_bounds(map_size) {
+        return Vec::new();
+    }
+    connect(*first, None);
+
+    let mut current = *first;
+    for target in waypoints.iter().skip(1) {
+        if !target.within_map_bounds(map_size) {
+            break;
+        }
+        let target_pos = lattice_pos(target, map_type);
+        while current != *target {
+            let distance = lattice_pos(&current, map_type).distance_squared(target_pos);
+            let Some((bit, next)) = neighbors(&current, map_size, map_type)
+                .into_iter()
+                .map(|(bit, next)| {
+                    let next_distance = lattice_pos(&next, map_type).distance_squared(target_pos);
+                    (bit, next, next_distance)
+                })
+                .filter(|(_, _, next_distance)| *next_distance < distance)
+                .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+                .map(|(bit, next, _)| (bit, next))
+            else {
+                break;
+            };
+            connect(current, Some(bit));
+            connect(next, Some(opposite(bit, map_type)));
+            current = next;
+        }
+    }
+
+    route
+}
+
+/// Routes a path through `waypoints` with [`route_path`], and writes it onto a tilemap.
+///
+/// Path tiles that already exist keep their [`TilePathConnections`], combined with the new ones,
+/// so that paths crossing each other get crossing tiles. Other tiles in the way are overwritten.
+pub fn write_path(
+    waypoints: &[TilePos],
+    rules: &PathTileRules,
+    map_type: &TilemapType,
+    tilemap_id: TilemapId,
+    commands: &mut Commands,
+    tile_storage: &mut TileStorage,
+    existing: &Query<&TilePathConnections>,
+) {
+    for (tile_pos, connections) in route_path(waypoints, &tile_storage.size, map_type) {
+        let tile_entity = tile_storage.get(&tile_pos);
+        let previous = tile_entity
+            .and_then(|tile_entity| existing.get(tile_entity).ok())
+            .copied()
+            .unwrap_or_default();
+        let connections = TilePathConnections(connections.0 | previous.0);
+        let Some(texture_index) = rules.get(connections) else {
+            continue;
+        };
+
+        match tile_entity {
+            Some(tile_entity) => {
+                commands
+                    .entity(tile_entity)
+                    .insert((texture_index, connections));
+            }
+            None => {
+                let tile_entity = commands
+                    .spawn((
+                        TileBundle {
+                            position: tile_pos,
+                            tilemap_id,
+                            texture_index,
+                            ..Default::default()
+                        },
+                        connections,
+                    ))
+                    .set_parent(tilemap_id.0)
+                    .id();
+                tile_storage.set(&tile_pos, tile_entity);
+            }
+        }
+    }
+}
+
+/// Returns the tiles adjacent to `tile_pos` that a path can step to, along with the bit of the
+/// direction they lie in.
+pub(crate) fn neighbors(
+    tile_pos: &TilePos,
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+) -> Vec<(u8, TilePos)> {
+    match map_type {
+        TilemapType::Square | TilemapType::Isometric(IsoCoordSystem::Diamond) => {
+            let square_pos = SquarePos::from(tile_pos);
+            CARDINAL_DIRECTIONS
+                .iter()
+                .filter_map(|direction| {
+                    let next = square_pos.offset(direction).as_tile_pos(map_size)?;
+                    Some((*direction as u8, next))
+                })
+                .collect()
+        }
+        TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+            let staggered_pos = StaggeredPos::from(tile_pos);
+            CARDINAL_DIRECTIONS
+                .iter()
+                .filter_map(|direction| {
+                    let next = staggered_pos.offset(direction).as_tile_pos(map_size)?;
+                    Some((*direction as u8, next))
+                })
+                .collect()
+        }
+        TilemapType::Hexagon(hex_coord_sys) => {
+            let axial_pos = AxialPos::from_tile_pos_given_coord_system(tile_pos, *hex_coord_sys);
+            HEX_DIRECTIONS
+                .iter()
+                .filter_map(|direction| {
+                    let next = axial_pos
+                        .offset(*direction)
+                        .as_tile_pos_given_coord_system_and_map_size(*hex_coord_sys, map_size)?;
+                    Some((*direction as u8, next))
+                })
+                .collect()
+        }
+    }
+}
+
+/// Returns the bit of the direction opposite to `bit`.
+fn opposite(bit: u8, map_type: &TilemapType) -> u8 {
+    match map_type {
+        TilemapType::Hexagon(_) => (bit + 3) % 6,
+        _ => (bit + 4) % 8,
+    }
+}
+
+/// Places `tile_pos` on a regular lattice in which adjacent tiles are all the same distance
+/// apart, so that straight lines between tiles can be followed by comparing distances.
+fn lattice_pos(tile_pos: &TilePos, map_type: &TilemapType) -> Vec2 {
+    match map_type {
+        TilemapType::Square | TilemapType::Isometric(IsoCoordSystem::Diamond) => {
+            Vec2::from(tile_pos)
+        }
+        TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+            // Staggered neighbors are found by stepping in square coordinates.
+            let square_pos = SquarePos::from(StaggeredPos::from(tile_pos));
+            Vec2::new(square_pos.x as f32, square_pos.y as f32)
+        }
+        TilemapType::Hexagon(hex_coord_sys) => {
+            let axial_pos = AxialPos::from_tile_pos_given_coord_system(tile_pos, *hex_coord_sys);
+            // The axial axes are 60 degrees apart.
+            Vec2::new(axial_pos.q as f32, 0.0)
+                + Vec2::new(0.5, 3.0_f32.sqrt() / 2.0) * axial_pos.r as f32
+        }
+    }
+}
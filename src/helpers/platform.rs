@@ -0,0 +1,132 @@
+use bevy::hierarchy::{Children, HierarchyQueryExt};
+use bevy::math::{Affine3A, Vec3};
+use bevy::prelude::{
+    Component, Entity, GlobalTransform, Query, Reflect, ReflectComponent, Transform, Without,
+};
+
+/// Tracks how a tilemap's [`GlobalTransform`] changed since the previous frame.
+///
+/// Add this to tilemaps that move, e.g. elevators or ships that are children of a moving parent.
+/// It is updated in `PostUpdate`, after transforms have been propagated, so it always matches the
+/// transform that chunks are rendered with and that picking and collision helpers see.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct TilemapTransformDelta {
+    /// The tilemap's global transform in the previous frame, if there was one.
+    pub previous: Option<GlobalTransform>,
+    /// Maps world-space points attached to the tilemap last frame to where they are now.
+    pub delta: Affine3A,
+}
+
+impl TilemapTransformDelta {
+    /// The world-space translation of the tilemap's origin over the last frame.
+    pub fn translation(&self) -> Vec3 {
+        self.delta.translation.into()
+    }
+
+    /// Moves a world-space point along with the tilemap.
+    pub fn carry_point(&self, point: Vec3) -> Vec3 {
+        self.delta.transform_point3(point)
+    }
+
+    /// Moves a world-space transform along with the tilemap, including any rotation or scaling.
+    pub fn carry_transform(&self, transform: &Transform) -> Transform {
+        Transform::from_matrix((self.delta * transform.compute_affine()).into())
+    }
+}
+
+/// Marks an entity that is standing on a tilemap, and should move along with it.
+///
+/// The tilemap must have a [`TilemapTransformDelta`]. Riders should not have a parent, since
+/// their [`Transform`] is treated as being in world space.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct TilemapRider {
+    /// The tilemap the entity is standing on.
+    pub tilemap: Entity,
+}
+
+/// Updates [`TilemapTransformDelta`] from the tilemap's current global transform.
+pub(crate) fn update_tilemap_transform_deltas(
+    mut tilemap_query: Query<(&GlobalTransform, &mut TilemapTransformDelta)>,
+) {
+    for (global_transform, mut transform_delta) in tilemap_query.iter_mut() {
+        let delta = match transform_delta.previous {
+            Some(previous) => global_transform.affine() * previous.affine().inverse(),
+            None => Affine3A::IDENTITY,
+        };
+        transform_delta.delta = delta;
+        transform_delta.previous = Some(*global_transform);
+    }
+}
+
+/// Moves every [`TilemapRider`] by its tilemap's [`TilemapTransformDelta`].
+///
+/// Both the [`Transform`] and the already propagated [`GlobalTransform`] are updated, along with
+/// the [`GlobalTransform`] of the riders' descendants, so riders don't lag a frame behind the
+/// tilemap.
+pub(crate) fn carry_tilemap_riders(
+    tilemap_query: Query<&TilemapTransformDelta>,
+    mut rider_query: Query<
+        (Entity, &TilemapRider, &mut Transform, &mut GlobalTransform),
+        Without<TilemapTransformDelta>,
+    >,
+    children_query: Query<&Children>,
+    mut descendant_query: Query<&mut GlobalTransform, Without<TilemapRider>>,
+) {
+    for (rider_entity, rider, mut transform, mut global_transform) in rider_query.iter_mut() {
+        let Ok(transform_delta) = tilemap_query.get(rider.tilemap) else {
+            continue;
+        };
+        if transform_delta.delta == Affine3A::IDENTITY {
+            continue;
+        }
+
+        *transform = transform_delta.carry_transform(&transform);
+        *global_transform =
+            GlobalTransform::from(transform_delta.delta * global_transform.affine());
+        for descendant in children_query.iter_descendants(rider_entity) {
+            if let Ok(mut global_transform) = descendant_query.get_mut(descendant) {
+                *global_transform =
+                    GlobalTransform::from(transform_delta.delta * global_transform.affine());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::hierarchy::BuildChildren;
+    use bevy::prelude::App;
+
+    use super::*;
+    use crate::test_utils::{MinimalTilemapPlugins, StepApp};
+
+    #[test]
+    fn riders_carry_their_children() {
+        let mut app = App::new();
+        app.add_plugins(MinimalTilemapPlugins);
+        let tilemap = app
+            .world_mut()
+            .spawn((Transform::default(), TilemapTransformDelta::default()))
+            .id();
+        let child = app
+            .world_mut()
+            .spawn(Transform::from_xyz(0.0, 2.0, 0.0))
+            .id();
+        app.world_mut()
+            .spawn((TilemapRider { tilemap }, Transform::from_xyz(1.0, 0.0, 0.0)))
+            .add_child(child);
+        app.step_frames(1);
+
+        app.world_mut()
+            .get_mut::<Transform>(tilemap)
+            .unwrap()
+            .translation
+            .x += 10.0;
+        app.step_frames(1);
+
+        let child_transform = app.world().get::<GlobalTransform>(child).unwrap();
+        assert_eq!(child_transform.translation(), Vec3::new(11.0, 2.0, 0.0));
+    }
+}
@@ -2,7 +2,7 @@ use crate::helpers::hex_grid::axial::AxialPos;
 use crate::helpers::hex_grid::offset::{ColEvenPos, ColOddPos, RowEvenPos, RowOddPos};
 use crate::helpers::square_grid::diamond::DiamondPos;
 use crate::helpers::square_grid::staggered::StaggeredPos;
-use crate::map::{HexCoordSystem, IsoCoordSystem};
+use crate::map::{HexCoordSystem, IsoCoordSystem, TilemapAxes};
 use crate::tiles::TilePos;
 use crate::{TilemapGridSize, TilemapSize, TilemapType};
 use bevy::math::Vec2;
@@ -31,6 +31,18 @@ impl TilePos {
         }
     }
 
+    /// Get the center of this tile in world space, on a tilemap using the given [`TilemapAxes`].
+    pub fn center_in_world_with_axes(
+        &self,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        map_type: &TilemapType,
+        axes: &TilemapAxes,
+    ) -> Vec2 {
+        axes.to_grid_pos(self, map_size)
+            .center_in_world(grid_size, map_type)
+    }
+
     /// Try converting a pair of `i32` numbers into a `TilePos`.
     ///
     /// Returns `None` if either one of `x` or `y` is negative, or lies out of the bounds of
@@ -52,6 +64,30 @@ impl TilePos {
         }
     }
 
+    /// Returns the tile containing the given world position, if it lies on the map.
+    ///
+    /// The position is relative to the tilemap, i.e. it does not take the map's transform into
+    /// account.
+    ///
+    /// Example:
+    /// ```
+    /// # use bevy::prelude::{Vec2, World};
+    /// # use bevy_ecs_tilemap::prelude::*;
+    /// # use bevy_ecs_tilemap::test_utils::spawn_test_map;
+    /// # let mut world = World::new();
+    /// let map_type = TilemapType::Hexagon(HexCoordSystem::Row);
+    /// let map = spawn_test_map(&mut world, TilemapSize { x: 8, y: 8 }, map_type);
+    /// let map_size = world.get::<TilemapSize>(map).unwrap();
+    /// let grid_size = world.get::<TilemapGridSize>(map).unwrap();
+    ///
+    /// let tile_pos = TilePos { x: 3, y: 5 };
+    /// let center = tile_pos.center_in_world(grid_size, &map_type);
+    /// let picked = TilePos::from_world_pos(&center, map_size, grid_size, &map_type);
+    /// assert_eq!(picked, Some(tile_pos));
+    ///
+    /// let outside = TilePos::from_world_pos(&Vec2::new(-100.0, 0.0), map_size, grid_size, &map_type);
+    /// assert_eq!(outside, None);
+    /// ```
     pub fn from_world_pos(
         world_pos: &Vec2,
         map_size: &TilemapSize,
@@ -89,4 +125,163 @@ impl TilePos {
             },
         }
     }
+
+    /// Returns the tile containing the given world position on a tilemap using the given
+    /// [`TilemapAxes`], if it lies on the map.
+    pub fn from_world_pos_with_axes(
+        world_pos: &Vec2,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        map_type: &TilemapType,
+        axes: &TilemapAxes,
+    ) -> Option<TilePos> {
+        TilePos::from_world_pos(world_pos, map_size, grid_size, map_type)
+            .map(|grid_pos| axes.to_grid_pos(&grid_pos, map_size))
+    }
+}
+
+/// Checks that the CPU helpers agree with the vertex shader projections in
+/// `render/shaders/projection.wgsl`, which are ported to Rust below.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::transform::chunk_index_to_world_space;
+    use bevy::math::UVec2;
+
+    const SQRT_3_2: f32 = 0.8660254;
+
+    const MAP_TYPES: [TilemapType; 9] = [
+        TilemapType::Square,
+        TilemapType::Isometric(IsoCoordSystem::Diamond),
+        TilemapType::Isometric(IsoCoordSystem::Staggered),
+        TilemapType::Hexagon(HexCoordSystem::Row),
+        TilemapType::Hexagon(HexCoordSystem::RowEven),
+        TilemapType::Hexagon(HexCoordSystem::RowOdd),
+        TilemapType::Hexagon(HexCoordSystem::Column),
+        TilemapType::Hexagon(HexCoordSystem::ColumnEven),
+        TilemapType::Hexagon(HexCoordSystem::ColumnOdd),
+    ];
+
+    const GRID_SIZES: [TilemapGridSize; 3] = [
+        TilemapGridSize { x: 16.0, y: 16.0 },
+        TilemapGridSize { x: 17.0, y: 15.0 },
+        TilemapGridSize { x: 64.0, y: 32.0 },
+    ];
+
+    const MAP_SIZE: TilemapSize = TilemapSize { x: 24, y: 24 };
+
+    fn diamond(pos: Vec2, grid_size: Vec2) -> Vec2 {
+        let unscaled = pos.x * Vec2::new(0.5, -0.5) + pos.y * Vec2::new(0.5, 0.5);
+        grid_size * unscaled
+    }
+
+    fn hex_row(pos: Vec2, grid_size: Vec2) -> Vec2 {
+        let unscaled = pos.x * Vec2::new(1.0, 0.0) + pos.y * Vec2::new(0.5, SQRT_3_2);
+        Vec2::new(
+            grid_size.x * unscaled.x,
+            SQRT_3_2 * grid_size.y * unscaled.y,
+        )
+    }
+
+    fn hex_col(pos: Vec2, grid_size: Vec2) -> Vec2 {
+        let unscaled = pos.x * Vec2::new(SQRT_3_2, 0.5) + pos.y * Vec2::new(0.0, 1.0);
+        Vec2::new(
+            SQRT_3_2 * grid_size.x * unscaled.x,
+            grid_size.y * unscaled.y,
+        )
+    }
+
+    /// `tile_pos_to_world_pos` of `projection.wgsl`.
+    fn shader_tile_pos_to_world_pos(pos: Vec2, grid_size: Vec2, map_type: &TilemapType) -> Vec2 {
+        match map_type {
+            TilemapType::Square => pos * grid_size,
+            TilemapType::Isometric(IsoCoordSystem::Diamond) => diamond(pos, grid_size),
+            TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+                diamond(Vec2::new(pos.x, pos.y + pos.x), grid_size)
+            }
+            TilemapType::Hexagon(HexCoordSystem::Row) => hex_row(pos, grid_size),
+            TilemapType::Hexagon(HexCoordSystem::RowEven) => {
+                hex_row(Vec2::new(pos.x - (pos.y / 2.0).ceil(), pos.y), grid_size)
+            }
+            TilemapType::Hexagon(HexCoordSystem::RowOdd) => {
+                hex_row(Vec2::new(pos.x - (pos.y / 2.0).floor(), pos.y), grid_size)
+            }
+            TilemapType::Hexagon(HexCoordSystem::Column) => hex_col(pos, grid_size),
+            TilemapType::Hexagon(HexCoordSystem::ColumnEven) => {
+                hex_col(Vec2::new(pos.x, pos.y - (pos.x / 2.0).ceil()), grid_size)
+            }
+            TilemapType::Hexagon(HexCoordSystem::ColumnOdd) => {
+                hex_col(Vec2::new(pos.x, pos.y - (pos.x / 2.0).floor()), grid_size)
+            }
+        }
+    }
+
+    fn assert_close(cpu: Vec2, gpu: Vec2, context: impl std::fmt::Debug) {
+        assert!(
+            cpu.abs_diff_eq(gpu, 1e-3),
+            "CPU {cpu} and shader {gpu} disagree for {context:?}"
+        );
+    }
+
+    fn tile_positions() -> impl Iterator<Item = TilePos> {
+        (0..MAP_SIZE.y).flat_map(|y| (0..MAP_SIZE.x).map(move |x| TilePos::new(x, y)))
+    }
+
+    #[test]
+    fn center_in_world_matches_shader() {
+        for map_type in &MAP_TYPES {
+            for grid_size in &GRID_SIZES {
+                for tile_pos in tile_positions() {
+                    let cpu = tile_pos.center_in_world(grid_size, map_type);
+                    let gpu =
+                        shader_tile_pos_to_world_pos(tile_pos.into(), grid_size.into(), map_type);
+                    assert_close(cpu, gpu, (map_type, grid_size, tile_pos));
+                }
+            }
+        }
+    }
+
+    /// Chunks are meshed with chunk-local tile positions, and placed at the center of their
+    /// bottom left tile.
+    #[test]
+    fn chunked_shader_positions_match_center_in_world() {
+        for chunk_size in [UVec2::new(4, 4), UVec2::new(8, 6)] {
+            for map_type in &MAP_TYPES {
+                for grid_size in &GRID_SIZES {
+                    for tile_pos in tile_positions() {
+                        let tile = UVec2::from(tile_pos);
+                        let chunk_origin = chunk_index_to_world_space(
+                            tile / chunk_size,
+                            chunk_size,
+                            grid_size,
+                            map_type,
+                        );
+                        let local = (tile % chunk_size).as_vec2();
+                        let gpu = chunk_origin
+                            + shader_tile_pos_to_world_pos(local, grid_size.into(), map_type);
+                        let cpu = tile_pos.center_in_world(grid_size, map_type);
+                        assert_close(cpu, gpu, (chunk_size, map_type, grid_size, tile_pos));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn from_world_pos_inverts_shader_projection() {
+        for map_type in &MAP_TYPES {
+            for grid_size in &GRID_SIZES {
+                for tile_pos in tile_positions() {
+                    let center =
+                        shader_tile_pos_to_world_pos(tile_pos.into(), grid_size.into(), map_type);
+                    let picked = TilePos::from_world_pos(&center, &MAP_SIZE, grid_size, map_type);
+                    assert_eq!(
+                        picked,
+                        Some(tile_pos),
+                        "picking the shader center of {tile_pos:?} for {map_type:?}, {grid_size:?}"
+                    );
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,106 @@
+use crate::helpers::path::neighbors;
+use crate::helpers::registry::tile_hash;
+use crate::map::{TilemapSize, TilemapType};
+use crate::tiles::{TileDataLayer, TilePos, TileRect};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// How [`grow_regions`] partitions a map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegionGrowth {
+    /// The number of regions. Fewer regions are grown if the map has fewer tiles.
+    pub count: u32,
+    /// Decides where regions start, and the shape of their borders.
+    pub seed: u64,
+    /// How ragged the borders between regions are. With `0`, every tile belongs to the region
+    /// whose start is the fewest steps away, like a voronoi diagram over the tiles. Higher values
+    /// make stepping onto each tile cost up to `roughness` more, picked from the seed.
+    pub roughness: u32,
+}
+
+impl Default for RegionGrowth {
+    /// By default, 8 regions are grown from seed 0, with slightly ragged borders.
+    fn default() -> Self {
+        Self {
+            count: 8,
+            seed: 0,
+            roughness: 3,
+        }
+    }
+}
+
+/// Partitions a map into regions, e.g. for biomes or territories, and returns the region id of
+/// every tile, from `0` to `count - 1`.
+///
+/// Regions start from tiles picked from the seed, and grow one step at a time over adjacent
+/// tiles, following the adjacency of the map type. Only tiles for which `include` returns true
+/// get a region, so regions can be grown over land only. Included tiles that can't be reached
+/// from any start, like small islands, get no region. The result only depends on the settings,
+/// the map and `include`, so growing again gives the same regions.
+///
+/// ```
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::regions::{grow_regions, RegionGrowth};
+/// let map_size = TilemapSize { x: 32, y: 32 };
+/// let growth = RegionGrowth {
+///     count: 4,
+///     seed: 7,
+///     ..Default::default()
+/// };
+/// let regions = grow_regions(&growth, &map_size, &TilemapType::Square, |_| true);
+///
+/// assert!(regions.iter().all(|(_, region)| region.is_some_and(|id| id < 4)));
+/// ```
+pub fn grow_regions(
+    growth: &RegionGrowth,
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+    include: impl Fn(&TilePos) -> bool,
+) -> TileDataLayer<Option<u32>> {
+    let mut regions = TileDataLayer::new(*map_size, None);
+
+    // The tiles with the lowest hashes start the regions.
+    let mut starts: Vec<(u64, TilePos)> = TileRect::from_map_size(*map_size)
+        .iter()
+        .filter(|tile_pos| include(tile_pos))
+        .map(|tile_pos| (tile_hash(growth.seed, &tile_pos), tile_pos))
+        .collect();
+    starts.sort_unstable();
+    starts.truncate(growth.count as usize);
+
+    // A different seed for the step costs, so they don't correlate with the starts.
+    let cost_seed = growth.seed ^ 0x5851_f42d_4c95_7f2d;
+    let step_cost = |tile_pos: &TilePos| match growth.roughness {
+        0 => 1,
+        roughness => 1 + tile_hash(cost_seed, tile_pos) % (roughness as u64 + 1),
+    };
+
+    let mut costs = TileDataLayer::new(*map_size, u64::MAX);
+    let mut open = BinaryHeap::new();
+    for (region, (_, tile_pos)) in starts.into_iter().enumerate() {
+        costs.set(&tile_pos, 0);
+        open.push(Reverse((0, region as u32, tile_pos)));
+    }
+    while let Some(Reverse((cost, region, tile_pos))) = open.pop() {
+        // Every tile is claimed by the first region to pop it, which is the cheapest one.
+        if regions.get(&tile_pos).is_some_and(Option::is_some) {
+            continue;
+        }
+        regions.set(&tile_pos, Some(region));
+
+        for (_, next) in neighbors(&tile_pos, map_size, map_type) {
+            if !include(&next) || regions.get(&next).is_some_and(Option::is_some) {
+                continue;
+            }
+            let next_cost = cost + step_cost(&next);
+            if let Some(current) = costs.get_mut(&next) {
+                // Ties go to the lowest region id, so equal costs are pushed too.
+                if next_cost <= *current {
+                    *current = next_cost;
+                    open.push(Reverse((next_cost, region, next)));
+                }
+            }
+        }
+    }
+    regions
+}
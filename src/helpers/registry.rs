@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::map::TilemapId;
+use crate::tiles::{TileBundle, TilePos, TileStorage, TileTextureIndex};
+use bevy::app::{App, Plugin};
+use bevy::asset::{Asset, AssetApp};
+use bevy::hierarchy::BuildChildren;
+use bevy::prelude::{Commands, Entity};
+use bevy::reflect::TypePath;
+
+/// A named tile in a [`TileRegistry`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileDefinition {
+    /// The index of the tile's texture in the tilemap's atlas or texture array.
+    pub texture_index: u32,
+    /// Free form data attached to the tile, e.g. `"walkable": "false"`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// One choice of a [`TileVariantSet`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileVariant {
+    pub texture_index: u32,
+    /// How likely this variant is to be picked, relative to the other variants of the set.
+    pub weight: f32,
+}
+
+/// A weighted list of interchangeable textures, e.g. several kinds of grass, used to give
+/// terrain some variety.
+///
+/// See [`fill_tilemap_variants`](crate::helpers::filling::fill_tilemap_variants).
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileVariantSet {
+    pub variants: Vec<TileVariant>,
+}
+
+impl TileVariantSet {
+    /// Picks a variant from a uniformly distributed `hash`, e.g. one from [`tile_hash`].
+    ///
+    /// Returns `None` if the set has no variant with a positive weight.
+    pub fn pick(&self, hash: u64) -> Option<TileTextureIndex> {
+        let total: f32 = self
+            .variants
+            .iter()
+            .map(|variant| variant.weight.max(0.0))
+            .sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        // Use the top 24 bits, which is all the precision an f32 has.
+        let mut target = (hash >> 40) as f32 / (1u64 << 24) as f32 * total;
+        let mut picked = None;
+        for variant in self.variants.iter().filter(|variant| variant.weight > 0.0) {
+            picked = Some(TileTextureIndex(variant.texture_index));
+            if target < variant.weight {
+                break;
+            }
+            target -= variant.weight;
+        }
+        picked
+    }
+
+    /// Picks the variant for the tile at `tile_pos`. The same `seed` and position always give
+    /// the same variant.
+    pub fn pick_for(&self, seed: u64, tile_pos: &TilePos) -> Option<TileTextureIndex> {
+        self.pick(tile_hash(seed, tile_pos))
+    }
+}
+
+/// Hashes a tile position together with a `seed`.
+///
+/// The hash only depends on its inputs, so it gives stable results no matter in which order
+/// tiles are visited.
+pub fn tile_hash(seed: u64, tile_pos: &TilePos) -> u64 {
+    // SplitMix64 finalizer.
+    let position = ((tile_pos.x as u64) << 32) | tile_pos.y as u64;
+    let mut z = seed ^ position.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Maps tile names like `"grass"` or `"water_edge_n"` to texture indices, so content code doesn't
+/// have to hardcode atlas indices.
+///
+/// With the `serde` feature, registries can be loaded from `.tiles.ron` files:
+/// ```ron
+/// (
+///     tiles: {
+///         "grass": (texture_index: 0),
+///         "water_edge_n": (texture_index: 5, metadata: {"walkable": "false"}),
+///     },
+///     variant_sets: {
+///         "grass": (variants: [
+///             (texture_index: 0, weight: 8.0),
+///             (texture_index: 1, weight: 1.0),
+///         ]),
+///     },
+/// )
+/// ```
+///
+/// Example:
+/// ```
+/// # use bevy_ecs_tilemap::helpers::registry::{TileDefinition, TileRegistry};
+/// # use bevy_ecs_tilemap::prelude::TileTextureIndex;
+/// let mut registry = TileRegistry::default();
+/// registry.insert("grass", TileDefinition { texture_index: 3, ..Default::default() });
+/// assert_eq!(registry.texture_index("grass"), Some(TileTextureIndex(3)));
+/// assert_eq!(registry.texture_index("lava"), None);
+/// ```
+#[derive(Asset, TypePath, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileRegistry {
+    tiles: BTreeMap<String, TileDefinition>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    variant_sets: BTreeMap<String, TileVariantSet>,
+}
+
+impl TileRegistry {
+    /// Adds a named tile, replacing any tile of the same name.
+    pub fn insert(&mut self, name: impl Into<String>, definition: TileDefinition) {
+        self.tiles.insert(name.into(), definition);
+    }
+
+    /// Gets the tile with the given name.
+    pub fn get(&self, name: &str) -> Option<&TileDefinition> {
+        self.tiles.get(name)
+    }
+
+    /// Gets the texture index of the tile with the given name.
+    pub fn texture_index(&self, name: &str) -> Option<TileTextureIndex> {
+        self.get(name)
+            .map(|definition| TileTextureIndex(definition.texture_index))
+    }
+
+    /// Gets the name of the first tile using `texture_index`, if any.
+    pub fn name_of(&self, texture_index: TileTextureIndex) -> Option<&str> {
+        self.tiles
+            .iter()
+            .find(|(_, definition)| definition.texture_index == texture_index.0)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Adds a named variant set, replacing any set of the same name.
+    pub fn insert_variant_set(&mut self, name: impl Into<String>, variant_set: TileVariantSet) {
+        self.variant_sets.insert(name.into(), variant_set);
+    }
+
+    /// Gets the variant set with the given name.
+    pub fn variant_set(&self, name: &str) -> Option<&TileVariantSet> {
+        self.variant_sets.get(name)
+    }
+
+    /// Returns an iterator over all named tiles, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TileDefinition)> {
+        self.tiles
+            .iter()
+            .map(|(name, definition)| (name.as_str(), definition))
+    }
+}
+
+/// The error returned when a tile name is not in the [`TileRegistry`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownTileError(pub String);
+
+impl fmt::Display for UnknownTileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no tile named \"{}\" in the registry", self.0)
+    }
+}
+
+impl std::error::Error for UnknownTileError {}
+
+/// Spawns the tile with the given name at `tile_pos`, and adds it to `tile_storage`.
+pub fn spawn_named_tile(
+    registry: &TileRegistry,
+    name: &str,
+    tile_pos: TilePos,
+    tilemap_id: TilemapId,
+    commands: &mut Commands,
+    tile_storage: &mut TileStorage,
+) -> Result<Entity, UnknownTileError> {
+    let texture_index = registry
+        .texture_index(name)
+        .ok_or_else(|| UnknownTileError(name.to_string()))?;
+    let tile_entity = commands
+        .spawn(TileBundle {
+            position: tile_pos,
+            tilemap_id,
+            texture_index,
+            ..Default::default()
+        })
+        .set_parent(tilemap_id.0)
+        .id();
+    tile_storage.set(&tile_pos, tile_entity);
+    Ok(tile_entity)
+}
+
+/// Changes the texture of an existing tile to the tile with the given name.
+pub fn set_named_tile(
+    registry: &TileRegistry,
+    name: &str,
+    texture_index: &mut TileTextureIndex,
+) -> Result<(), UnknownTileError> {
+    *texture_index = registry
+        .texture_index(name)
+        .ok_or_else(|| UnknownTileError(name.to_string()))?;
+    Ok(())
+}
+
+/// Adds the [`TileRegistry`] asset, and with the `serde` feature, its `.tiles.ron` loader.
+pub struct TileRegistryPlugin;
+
+impl Plugin for TileRegistryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<TileRegistry>();
+        #[cfg(feature = "serde")]
+        app.register_asset_loader(loader::TileRegistryLoader);
+    }
+}
+
+#[cfg(feature = "serde")]
+pub use loader::*;
+
+#[cfg(feature = "serde")]
+mod loader {
+    use std::fmt;
+
+    use super::TileRegistry;
+    use bevy::asset::{io::Reader, AssetLoader, LoadContext};
+
+    /// Loads a [`TileRegistry`] from a `.tiles.ron` file.
+    #[derive(Default)]
+    pub struct TileRegistryLoader;
+
+    /// The error returned when a [`TileRegistry`] could not be loaded.
+    #[derive(Debug)]
+    pub enum TileRegistryLoaderError {
+        Io(std::io::Error),
+        Ron(ron::error::SpannedError),
+    }
+
+    impl fmt::Display for TileRegistryLoaderError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                TileRegistryLoaderError::Io(error) => {
+                    write!(f, "could not read tile registry: {error}")
+                }
+                TileRegistryLoaderError::Ron(error) => {
+                    write!(f, "could not parse tile registry: {error}")
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for TileRegistryLoaderError {}
+
+    impl From<std::io::Error> for TileRegistryLoaderError {
+        fn from(error: std::io::Error) -> Self {
+            TileRegistryLoaderError::Io(error)
+        }
+    }
+
+    impl From<ron::error::SpannedError> for TileRegistryLoaderError {
+        fn from(error: ron::error::SpannedError) -> Self {
+            TileRegistryLoaderError::Ron(error)
+        }
+    }
+
+    impl AssetLoader for TileRegistryLoader {
+        type Asset = TileRegistry;
+        type Settings = ();
+        type Error = TileRegistryLoaderError;
+
+        async fn load(
+            &self,
+            reader: &mut dyn Reader,
+            _settings: &Self::Settings,
+            _load_context: &mut LoadContext<'_>,
+        ) -> Result<Self::Asset, Self::Error> {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["tiles.ron"]
+        }
+    }
+}
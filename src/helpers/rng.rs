@@ -0,0 +1,106 @@
+/// A tiny seeded random number generator (SplitMix64), for procedural maps that have to come out
+/// the same on every run and platform.
+///
+/// The randomized helpers of this crate, like
+/// [`fill_tilemap_variants`](crate::helpers::filling::fill_tilemap_variants) or
+/// [`scatter_decorations`](crate::helpers::decoration::scatter_decorations), take a seed rather
+/// than a generator. Use [`next_seed`](Self::next_seed) to derive one per helper call from a
+/// single world seed. With the `rand` feature, the generator implements
+/// [`rand_core::RngCore`] and can drive anything from the `rand` ecosystem.
+///
+/// ```
+/// # use bevy_ecs_tilemap::prelude::*;
+/// let mut rng = TilemapRng::new(1234);
+/// let mut noise = TileDataLayer::new(TilemapSize { x: 32, y: 32 }, false);
+/// for wall in noise.as_mut_slice() {
+///     *wall = rng.chance(0.45);
+/// }
+///
+/// let mut again = TilemapRng::new(1234);
+/// assert!(noise.as_slice().iter().all(|wall| *wall == again.chance(0.45)));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TilemapRng {
+    state: u64,
+}
+
+impl TilemapRng {
+    /// Creates a generator from a seed. The same seed always gives the same numbers.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next random `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns the next random `u32`.
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Returns a random `f32` from `0.0` (inclusive) to `1.0` (exclusive).
+    pub fn next_f32(&mut self) -> f32 {
+        // Use the top 24 bits, which is all the precision an f32 has.
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Returns a random number below `bound`, or `0` if `bound` is `0`.
+    pub fn below(&mut self, bound: u32) -> u32 {
+        ((self.next_u32() as u64 * bound as u64) >> 32) as u32
+    }
+
+    /// Returns true with a probability of `probability`, from `0.0` to `1.0`.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// Returns a seed for one of the seeded helpers, or for another generator.
+    pub fn next_seed(&mut self) -> u64 {
+        self.next_u64()
+    }
+
+    /// Creates a generator seeded from `rng`.
+    #[cfg(feature = "rand")]
+    pub fn from_rng(rng: &mut impl rand_core::RngCore) -> Self {
+        Self::new(rng.next_u64())
+    }
+}
+
+#[cfg(feature = "rand")]
+impl rand_core::RngCore for TilemapRng {
+    fn next_u32(&mut self) -> u32 {
+        TilemapRng::next_u32(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        TilemapRng::next_u64(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_core::impls::fill_bytes_via_next(self, dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rand")]
+impl rand_core::SeedableRng for TilemapRng {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self::new(u64::from_le_bytes(seed))
+    }
+
+    fn seed_from_u64(state: u64) -> Self {
+        Self::new(state)
+    }
+}
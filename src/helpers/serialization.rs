@@ -0,0 +1,531 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+use crate::map::{TilemapId, TilemapSize};
+use crate::tiles::{
+    TileBundle, TileColor, TileFlip, TilePos, TileStorage, TileTextureIndex, TileVisible,
+};
+use bevy::color::{Color, ColorToComponents, LinearRgba};
+use bevy::hierarchy::BuildChildren;
+use bevy::prelude::{Commands, Query};
+use bevy::utils::HashMap;
+
+/// The version of the format written by [`SerializedTilemap::to_bytes`].
+pub const TILEMAP_FORMAT_VERSION: u16 = 1;
+
+/// The bytes every serialized tilemap starts with.
+const MAGIC: [u8; 4] = *b"BETM";
+
+/// The length of the magic bytes and the format version.
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+/// A distinct combination of tile components, stored once in the palette of a
+/// [`SerializedTilemap`] no matter how many tiles use it.
+///
+/// Colors are stored as linear RGBA.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileArchetype {
+    pub texture_index: TileTextureIndex,
+    pub color: TileColor,
+    pub flip: TileFlip,
+    pub visible: TileVisible,
+}
+
+impl TileArchetype {
+    /// Returns a bundle for a tile of this archetype at `position`.
+    pub fn bundle(&self, position: TilePos, tilemap_id: TilemapId) -> TileBundle {
+        TileBundle {
+            position,
+            tilemap_id,
+            texture_index: self.texture_index,
+            color: self.color,
+            flip: self.flip,
+            visible: self.visible,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the archetype's color as linear RGBA components.
+    fn linear_rgba(&self) -> [f32; 4] {
+        self.color.0.to_linear().to_f32_array()
+    }
+
+    /// Returns a key that is equal for archetypes that serialize to the same bytes.
+    fn key(&self) -> (u32, [u32; 4], u8) {
+        (
+            self.texture_index.0,
+            self.linear_rgba().map(f32::to_bits),
+            self.flags(),
+        )
+    }
+
+    fn flags(&self) -> u8 {
+        self.flip.x as u8
+            | (self.flip.y as u8) << 1
+            | (self.flip.d as u8) << 2
+            | (self.visible.0 as u8) << 3
+    }
+}
+
+/// A run of `len` consecutive tiles, in row order, of the same palette entry, or without a tile
+/// if `archetype` is `None`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileRun {
+    pub len: u32,
+    pub archetype: Option<u32>,
+}
+
+/// The tiles of a tilemap in a compact form for saving: a palette of the distinct
+/// [`TileArchetype`]s on the map, and the run-length encoded palette entries of all positions.
+///
+/// Large homogeneous maps only take a few runs, so they serialize to kilobytes. Use
+/// [`to_bytes`](Self::to_bytes) for a compact binary form, or serde with the `serde` feature,
+/// which serializes the same bytes so that deserializing runs the
+/// [global migrations](TilemapMigrations::global).
+///
+/// ```
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::serialization::{SerializedTilemap, TileArchetype};
+/// let map_size = TilemapSize { x: 1000, y: 1000 };
+/// let water = TileArchetype { texture_index: TileTextureIndex(4), ..Default::default() };
+/// let sand = TileArchetype { texture_index: TileTextureIndex(7), ..Default::default() };
+/// let serialized = SerializedTilemap::encode(map_size, |tile_pos| {
+///     Some(if tile_pos.y < 10 { sand } else { water })
+/// });
+///
+/// let bytes = serialized.to_bytes();
+/// assert!(bytes.len() < 1024);
+///
+/// let decoded = SerializedTilemap::from_bytes(&bytes).unwrap();
+/// assert_eq!(decoded.get(&TilePos::new(3, 2)), Some(&sand));
+/// assert_eq!(decoded.get(&TilePos::new(3, 500)), Some(&water));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(into = "SerializedTilemapBytes", try_from = "SerializedTilemapBytes")
+)]
+pub struct SerializedTilemap {
+    /// The version of the format the tilemap was written with.
+    pub version: u16,
+    pub size: TilemapSize,
+    pub palette: Vec<TileArchetype>,
+    pub runs: Vec<TileRun>,
+}
+
+impl SerializedTilemap {
+    /// Encodes a tilemap of `size`, with `tile` returning the archetype of the tile at each
+    /// position, or `None` for positions without a tile.
+    pub fn encode(size: TilemapSize, tile: impl Fn(&TilePos) -> Option<TileArchetype>) -> Self {
+        let mut palette = Vec::new();
+        let mut palette_indices = HashMap::new();
+        let mut runs: Vec<TileRun> = Vec::new();
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let archetype = tile(&TilePos::new(x, y)).map(|archetype| {
+                    *palette_indices.entry(archetype.key()).or_insert_with(|| {
+                        palette.push(archetype);
+                        palette.len() as u32 - 1
+                    })
+                });
+                match runs.last_mut() {
+                    Some(run) if run.archetype == archetype => run.len += 1,
+                    _ => runs.push(TileRun { len: 1, archetype }),
+                }
+            }
+        }
+        Self {
+            version: TILEMAP_FORMAT_VERSION,
+            size,
+            palette,
+            runs,
+        }
+    }
+
+    /// Encodes the tiles of a tilemap.
+    pub fn from_tilemap(
+        tile_storage: &TileStorage,
+        tiles: &Query<(&TileTextureIndex, &TileColor, &TileFlip, &TileVisible)>,
+    ) -> Self {
+        Self::encode(tile_storage.size, |tile_pos| {
+            let tile_entity = tile_storage.get(tile_pos)?;
+            let (texture_index, color, flip, visible) = tiles.get(tile_entity).ok()?;
+            Some(TileArchetype {
+                texture_index: *texture_index,
+                color: *color,
+                flip: *flip,
+                visible: *visible,
+            })
+        })
+    }
+
+    /// Returns an iterator over the positions that have a tile, and their archetypes, in row
+    /// order.
+    pub fn tiles(&self) -> impl Iterator<Item = (TilePos, &TileArchetype)> + '_ {
+        let width = self.size.x.max(1);
+        let mut start = 0;
+        self.runs
+            .iter()
+            .flat_map(move |run| {
+                let run_start = start;
+                start += run.len;
+                (run_start..run_start + run.len).map(move |index| (index, run.archetype))
+            })
+            .filter_map(move |(index, archetype)| {
+                let archetype = self.palette.get(archetype? as usize)?;
+                Some((TilePos::new(index % width, index / width), archetype))
+            })
+    }
+
+    /// Returns the archetype of the tile at `tile_pos`, if there is one.
+    pub fn get(&self, tile_pos: &TilePos) -> Option<&TileArchetype> {
+        if !tile_pos.within_map_bounds(&self.size) {
+            return None;
+        }
+        let index = tile_pos.to_index(&self.size) as u32;
+        let mut start = 0;
+        for run in self.runs.iter() {
+            if index < start + run.len {
+                return self.palette.get(run.archetype? as usize);
+            }
+            start += run.len;
+        }
+        None
+    }
+
+    /// Spawns the tiles as children of the tilemap, and adds them to `tile_storage`.
+    pub fn spawn(
+        &self,
+        tilemap_id: TilemapId,
+        commands: &mut Commands,
+        tile_storage: &mut TileStorage,
+    ) {
+        for (tile_pos, archetype) in self.tiles() {
+            if !tile_pos.within_map_bounds(&tile_storage.size) {
+                continue;
+            }
+            let tile_entity = commands
+                .spawn(archetype.bundle(tile_pos, tilemap_id))
+                .set_parent(tilemap_id.0)
+                .id();
+            tile_storage.set(&tile_pos, tile_entity);
+        }
+    }
+
+    /// Writes the tilemap in the binary format of [`TILEMAP_FORMAT_VERSION`], starting with a
+    /// header holding the version.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&TILEMAP_FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.size.x.to_le_bytes());
+        bytes.extend_from_slice(&self.size.y.to_le_bytes());
+
+        bytes.extend_from_slice(&(self.palette.len() as u32).to_le_bytes());
+        for archetype in self.palette.iter() {
+            bytes.extend_from_slice(&archetype.texture_index.0.to_le_bytes());
+            for component in archetype.linear_rgba() {
+                bytes.extend_from_slice(&component.to_le_bytes());
+            }
+            bytes.push(archetype.flags());
+        }
+
+        bytes.extend_from_slice(&(self.runs.len() as u32).to_le_bytes());
+        for run in self.runs.iter() {
+            bytes.extend_from_slice(&run.len.to_le_bytes());
+            bytes.extend_from_slice(&run.archetype.unwrap_or(u32::MAX).to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Reads a tilemap written by [`to_bytes`](Self::to_bytes) with the current or an older
+    /// format version, upgrading older data with the [global migrations](TilemapMigrations::global).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TilemapDecodeError> {
+        let migrations = TilemapMigrations::global()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        Self::from_bytes_migrated(bytes, &migrations)
+    }
+
+    /// Reads a tilemap written by [`to_bytes`](Self::to_bytes) with the current or an older
+    /// format version, upgrading older data with `migrations` first.
+    pub fn from_bytes_migrated(
+        bytes: &[u8],
+        migrations: &TilemapMigrations,
+    ) -> Result<Self, TilemapDecodeError> {
+        let version = read_format_version(bytes)?;
+        let body = migrations.migrate(version, &bytes[HEADER_LEN..])?;
+        let mut tilemap = Self::decode_body(&body)?;
+        tilemap.version = version;
+        Ok(tilemap)
+    }
+
+    /// Reads everything after the header, in the current format version.
+    fn decode_body(body: &[u8]) -> Result<Self, TilemapDecodeError> {
+        let mut reader = ByteReader(body);
+        let size = TilemapSize {
+            x: reader.u32()?,
+            y: reader.u32()?,
+        };
+
+        let palette_len = reader.u32()?;
+        let mut palette = Vec::new();
+        for _ in 0..palette_len {
+            let texture_index = TileTextureIndex(reader.u32()?);
+            let [r, g, b, a] = [reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?];
+            let flags = reader.take::<1>()?[0];
+            palette.push(TileArchetype {
+                texture_index,
+                color: TileColor(Color::LinearRgba(LinearRgba::new(r, g, b, a))),
+                flip: TileFlip {
+                    x: flags & 1 != 0,
+                    y: flags & 2 != 0,
+                    d: flags & 4 != 0,
+                },
+                visible: TileVisible(flags & 8 != 0),
+            });
+        }
+
+        let runs_len = reader.u32()?;
+        let mut runs = Vec::new();
+        let mut count = 0u64;
+        for _ in 0..runs_len {
+            let len = reader.u32()?;
+            let archetype = reader.u32()?;
+            if archetype != u32::MAX && archetype >= palette_len {
+                return Err(TilemapDecodeError::InvalidArchetype(archetype));
+            }
+            let archetype = (archetype != u32::MAX).then_some(archetype);
+            count += len as u64;
+            runs.push(TileRun { len, archetype });
+        }
+        if count != size.count() as u64 {
+            return Err(TilemapDecodeError::SizeMismatch);
+        }
+
+        Ok(Self {
+            version: TILEMAP_FORMAT_VERSION,
+            size,
+            palette,
+            runs,
+        })
+    }
+}
+
+/// The serde representation of a [`SerializedTilemap`], the bytes written by
+/// [`SerializedTilemap::to_bytes`].
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedTilemapBytes(Vec<u8>);
+
+#[cfg(feature = "serde")]
+impl From<SerializedTilemap> for SerializedTilemapBytes {
+    fn from(tilemap: SerializedTilemap) -> Self {
+        Self(tilemap.to_bytes())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerializedTilemapBytes> for SerializedTilemap {
+    type Error = TilemapDecodeError;
+
+    fn try_from(bytes: SerializedTilemapBytes) -> Result<Self, Self::Error> {
+        Self::from_bytes(&bytes.0)
+    }
+}
+
+/// Returns the format version a tilemap was written with by
+/// [`SerializedTilemap::to_bytes`], read from its header.
+pub fn read_format_version(bytes: &[u8]) -> Result<u16, TilemapDecodeError> {
+    let mut reader = ByteReader(bytes);
+    if reader.take::<4>()? != MAGIC {
+        return Err(TilemapDecodeError::InvalidHeader);
+    }
+    Ok(u16::from_le_bytes(reader.take()?))
+}
+
+/// Upgrades the data of a serialized tilemap, everything after the header, from one format
+/// version to the next.
+pub type TilemapMigrationFn = dyn Fn(&[u8]) -> Result<Vec<u8>, String> + Send + Sync;
+
+/// Migration functions upgrading tilemaps written with older format versions, so that saves
+/// shipped with older versions of a game keep loading after the format changed.
+///
+/// Every migration upgrades the data from one version to the next, and they are chained to
+/// reach the current [`TILEMAP_FORMAT_VERSION`]. Register them with the
+/// [global migrations](Self::global) at startup, so that every way of loading a tilemap
+/// upgrades old data, or pass them to [`SerializedTilemap::from_bytes_migrated`].
+///
+/// ```
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::serialization::{
+/// #     SerializedTilemap, TilemapMigrations, TILEMAP_FORMAT_VERSION,
+/// # };
+/// let tilemap = SerializedTilemap::encode(TilemapSize { x: 4, y: 4 }, |_| Some(Default::default()));
+/// let mut bytes = tilemap.to_bytes();
+/// // Pretend the tilemap was saved by a version of the format that ended with a checksum.
+/// let old_version = TILEMAP_FORMAT_VERSION - 1;
+/// bytes[4..6].copy_from_slice(&old_version.to_le_bytes());
+/// bytes.push(0xAB);
+///
+/// assert!(SerializedTilemap::from_bytes(&bytes).is_err());
+///
+/// TilemapMigrations::global()
+///     .write()
+///     .unwrap()
+///     .register(old_version, |data| Ok(data[..data.len() - 1].to_vec()));
+/// let migrated = SerializedTilemap::from_bytes(&bytes).unwrap();
+/// assert_eq!(migrated.version, old_version);
+/// assert_eq!(migrated.runs, tilemap.runs);
+/// ```
+#[derive(Default)]
+pub struct TilemapMigrations {
+    migrations: HashMap<u16, Box<TilemapMigrationFn>>,
+}
+
+impl TilemapMigrations {
+    /// Returns the migrations used by [`SerializedTilemap::from_bytes`] and, with the `serde`
+    /// feature, when deserializing a [`SerializedTilemap`], neither of which can be handed
+    /// migrations.
+    pub fn global() -> &'static RwLock<TilemapMigrations> {
+        static GLOBAL: OnceLock<RwLock<TilemapMigrations>> = OnceLock::new();
+        GLOBAL.get_or_init(Default::default)
+    }
+
+    /// Registers the migration upgrading data of format version `from` to version `from + 1`,
+    /// replacing any migration registered for the same version.
+    pub fn register(
+        &mut self,
+        from: u16,
+        migration: impl Fn(&[u8]) -> Result<Vec<u8>, String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.migrations.insert(from, Box::new(migration));
+        self
+    }
+
+    /// Returns true if data of format `version` can be upgraded to the current version.
+    pub fn can_migrate(&self, version: u16) -> bool {
+        (version..TILEMAP_FORMAT_VERSION).all(|version| self.migrations.contains_key(&version))
+    }
+
+    /// Upgrades `data` of format `version` to the current version.
+    pub fn migrate<'a>(
+        &self,
+        version: u16,
+        data: &'a [u8],
+    ) -> Result<Cow<'a, [u8]>, TilemapDecodeError> {
+        if version > TILEMAP_FORMAT_VERSION || !self.can_migrate(version) {
+            return Err(TilemapDecodeError::UnsupportedVersion(version));
+        }
+        let mut data = Cow::Borrowed(data);
+        for version in version..TILEMAP_FORMAT_VERSION {
+            let migrated = self.migrations[&version](&data)
+                .map_err(|reason| TilemapDecodeError::MigrationFailed { version, reason })?;
+            data = Cow::Owned(migrated);
+        }
+        Ok(data)
+    }
+}
+
+/// The error returned when a [`SerializedTilemap`] could not be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TilemapDecodeError {
+    /// The bytes don't start with the header of a serialized tilemap.
+    InvalidHeader,
+    /// The tilemap was written with a newer format version, or an older one without the
+    /// migrations to upgrade it.
+    UnsupportedVersion(u16),
+    /// The migration from format `version` to the next one failed.
+    MigrationFailed { version: u16, reason: String },
+    /// The bytes end in the middle of the tilemap.
+    UnexpectedEnd,
+    /// A run refers to a palette entry that doesn't exist.
+    InvalidArchetype(u32),
+    /// The runs don't cover exactly the size of the tilemap.
+    SizeMismatch,
+}
+
+impl fmt::Display for TilemapDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TilemapDecodeError::InvalidHeader => write!(f, "not a serialized tilemap"),
+            TilemapDecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported tilemap format version {version}")
+            }
+            TilemapDecodeError::MigrationFailed { version, reason } => {
+                write!(
+                    f,
+                    "could not migrate tilemap from format version {version}: {reason}"
+                )
+            }
+            TilemapDecodeError::UnexpectedEnd => write!(f, "serialized tilemap is truncated"),
+            TilemapDecodeError::InvalidArchetype(archetype) => {
+                write!(
+                    f,
+                    "serialized tilemap uses unknown palette entry {archetype}"
+                )
+            }
+            TilemapDecodeError::SizeMismatch => {
+                write!(f, "serialized tilemap runs don't match its size")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TilemapDecodeError {}
+
+struct ByteReader<'a>(&'a [u8]);
+
+impl ByteReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], TilemapDecodeError> {
+        let Some((bytes, rest)) = self.0.split_first_chunk::<N>() else {
+            return Err(TilemapDecodeError::UnexpectedEnd);
+        };
+        self.0 = rest;
+        Ok(*bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, TilemapDecodeError> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Result<f32, TilemapDecodeError> {
+        self.take().map(f32::from_le_bytes)
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializing_runs_the_global_migrations() {
+        let tilemap = SerializedTilemap::encode(TilemapSize { x: 3, y: 2 }, |tile_pos| {
+            (tile_pos.x > 0).then(TileArchetype::default)
+        });
+        let mut bytes = tilemap.to_bytes();
+        let old_version = TILEMAP_FORMAT_VERSION - 1;
+        bytes[4..6].copy_from_slice(&old_version.to_le_bytes());
+        bytes.push(0xAB);
+        let json = serde_json::to_string(&bytes).unwrap();
+        assert!(serde_json::from_str::<SerializedTilemap>(&json).is_err());
+
+        TilemapMigrations::global()
+            .write()
+            .unwrap()
+            .register(old_version, |data| Ok(data[..data.len() - 1].to_vec()));
+        let migrated: SerializedTilemap = serde_json::from_str(&json).unwrap();
+        assert_eq!(migrated.version, old_version);
+        assert_eq!(migrated.runs, tilemap.runs);
+
+        let json = serde_json::to_string(&tilemap).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SerializedTilemap>(&json).unwrap(),
+            tilemap
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use crate::map::{TilemapGridSize, TilemapSize, TilemapTileSize, TilemapType};
+use crate::tiles::TilePos;
+use bevy::asset::{Assets, Handle, RenderAssetUsages};
+use bevy::ecs::system::SystemParam;
+use bevy::image::{BevyDefault, Image};
+use bevy::math::{Rect, UVec2, Vec2};
+use bevy::prelude::{
+    Camera, Camera2d, ClearColorConfig, Color, Commands, Component, Entity, Event, GlobalTransform,
+    OrthographicProjection, Query, ResMut, Transform, Trigger, World,
+};
+use bevy::render::camera::{RenderTarget, ScalingMode};
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+use bevy::render::view::screenshot::{Screenshot, ScreenshotCaptured};
+use bevy::render::view::RenderLayers;
+
+/// Sent once the image returned by [`TilemapSnapshots::render_tilemap_to_image`] holds the
+/// captured tilemap.
+#[derive(Event, Clone, Debug)]
+pub struct TilemapSnapshotReady {
+    pub tilemap: Entity,
+    pub image: Handle<Image>,
+}
+
+/// Renders tilemaps into images, e.g. for thumbnails, save slot previews or visual tests.
+#[derive(SystemParam)]
+pub struct TilemapSnapshots<'w, 's> {
+    commands: Commands<'w, 's>,
+    images: ResMut<'w, Assets<Image>>,
+}
+
+impl TilemapSnapshots<'_, '_> {
+    /// Renders a tilemap into a new image of `resolution` pixels.
+    ///
+    /// A temporary camera framing the whole tilemap, as it is positioned at the end of this
+    /// frame, renders it once into the returned image, which is then read back, so that the image
+    /// asset also holds the pixels on the CPU. The camera is despawned afterwards, and a
+    /// [`TilemapSnapshotReady`] event is sent. Until then, the image is transparent.
+    ///
+    /// The camera uses the tilemap's [`RenderLayers`], if any, so give the tilemap a render layer
+    /// of its own to leave everything else out of the snapshot. The tilemap's texture must
+    /// already be loaded. If the tilemap doesn't exist, nothing is rendered.
+    pub fn render_tilemap_to_image(
+        &mut self,
+        map_entity: Entity,
+        resolution: UVec2,
+    ) -> Handle<Image> {
+        let resolution = resolution.max(UVec2::ONE);
+        let mut target = Image::new_fill(
+            Extent3d {
+                width: resolution.x,
+                height: resolution.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0; 4],
+            TextureFormat::bevy_default(),
+            RenderAssetUsages::default(),
+        );
+        target.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_DST
+            | TextureUsages::COPY_SRC
+            | TextureUsages::RENDER_ATTACHMENT;
+        let image = self.images.add(target);
+
+        // The camera is framed by `frame_snapshot_cameras`, once the tilemap's transform has been
+        // propagated.
+        self.commands.spawn((
+            Camera2d,
+            Camera {
+                target: RenderTarget::Image(image.clone()),
+                order: -1,
+                clear_color: ClearColorConfig::Custom(Color::NONE),
+                is_active: false,
+                ..Default::default()
+            },
+            PendingSnapshot {
+                tilemap: map_entity,
+                image: image.clone(),
+            },
+        ));
+        image
+    }
+}
+
+/// A camera which hasn't been framed on the tilemap it captures yet.
+#[derive(Component)]
+pub(crate) struct PendingSnapshot {
+    tilemap: Entity,
+    image: Handle<Image>,
+}
+
+/// Frames the cameras spawned by [`TilemapSnapshots::render_tilemap_to_image`] on their tilemaps,
+/// and requests their capture.
+pub(crate) fn frame_snapshot_cameras(
+    mut commands: Commands,
+    mut cameras: Query<(
+        Entity,
+        &PendingSnapshot,
+        &mut Camera,
+        &mut OrthographicProjection,
+        &mut Transform,
+        &mut GlobalTransform,
+    )>,
+    tilemaps: Query<(
+        &TilemapSize,
+        &TilemapGridSize,
+        &TilemapTileSize,
+        &TilemapType,
+        &GlobalTransform,
+        Option<&RenderLayers>,
+    )>,
+) {
+    for (camera_entity, pending, mut camera, mut projection, mut transform, mut global_transform) in
+        &mut cameras
+    {
+        let Ok((map_size, grid_size, tile_size, map_type, map_transform, render_layers)) =
+            tilemaps.get(pending.tilemap)
+        else {
+            commands.entity(camera_entity).despawn();
+            continue;
+        };
+
+        let bounds = tilemap_world_bounds(map_size, grid_size, tile_size, map_type, map_transform);
+        projection.scaling_mode = ScalingMode::AutoMin {
+            min_width: bounds.width(),
+            min_height: bounds.height(),
+        };
+        *transform = Transform::from_translation(bounds.center().extend(0.0));
+        // Transforms have already been propagated this frame.
+        *global_transform = GlobalTransform::from(*transform);
+        camera.is_active = true;
+
+        let mut camera_commands = commands.entity(camera_entity);
+        camera_commands.remove::<PendingSnapshot>();
+        if let Some(render_layers) = render_layers {
+            camera_commands.insert(render_layers.clone());
+        }
+
+        let tilemap = pending.tilemap;
+        let image = pending.image.clone();
+        commands.spawn(Screenshot::image(image.clone())).observe(
+            move |trigger: Trigger<ScreenshotCaptured>, mut commands: Commands| {
+                let captured = trigger.event().0.clone();
+                let image = image.clone();
+                commands.queue(move |world: &mut World| {
+                    world
+                        .resource_mut::<Assets<Image>>()
+                        .insert(&image, captured);
+                    if let Ok(camera) = world.get_entity_mut(camera_entity) {
+                        camera.despawn();
+                    }
+                    world.send_event(TilemapSnapshotReady { tilemap, image });
+                });
+            },
+        );
+    }
+}
+
+/// Returns the world space rectangle covering every tile of a tilemap.
+fn tilemap_world_bounds(
+    map_size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    map_type: &TilemapType,
+    map_transform: &GlobalTransform,
+) -> Rect {
+    let last = TilePos::new(map_size.x.saturating_sub(1), map_size.y.saturating_sub(1));
+    let half_tile = Vec2::from(grid_size).max(tile_size.into()) / 2.0;
+    [
+        TilePos::new(0, 0),
+        TilePos::new(last.x, 0),
+        TilePos::new(0, last.y),
+        last,
+    ]
+    .into_iter()
+    .flat_map(|corner| {
+        let center = corner.center_in_world(grid_size, map_type);
+        // Every corner of the tile, in case the tilemap is rotated.
+        [
+            Vec2::new(-1.0, -1.0),
+            Vec2::new(1.0, -1.0),
+            Vec2::new(-1.0, 1.0),
+            Vec2::new(1.0, 1.0),
+        ]
+        .map(|direction| center + direction * half_tile)
+    })
+    .map(|point| map_transform.transform_point(point.extend(0.0)).truncate())
+    .fold(Rect::EMPTY, |bounds, point| bounds.union_point(point))
+}
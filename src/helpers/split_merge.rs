@@ -0,0 +1,91 @@
+use crate::map::TilemapId;
+use crate::tiles::{TilePos, TileRect, TileStorage};
+use bevy::hierarchy::BuildChildren;
+use bevy::prelude::{Commands, Entity};
+
+/// Moves the tiles inside `region` of one tilemap into another tilemap.
+///
+/// Tile positions are rebased so that the region's origin ends up at `(0, 0)` in the target
+/// tilemap, which is usually a freshly spawned tilemap with a [`TileStorage`] the size of the
+/// region.
+///
+/// Tile entities are reused, so all of their other components are preserved. Tiles that would
+/// land outside of `target_storage`, or on a position that is already occupied, are left where
+/// they are.
+pub fn split_tilemap(
+    region: TileRect,
+    source_storage: &mut TileStorage,
+    target_id: TilemapId,
+    target_storage: &mut TileStorage,
+    commands: &mut Commands,
+) {
+    for source_pos in region.clamp_to_map(&source_storage.size).iter() {
+        let target_pos = TilePos::new(
+            source_pos.x - region.origin.x,
+            source_pos.y - region.origin.y,
+        );
+        move_tile(
+            source_pos,
+            target_pos,
+            source_storage,
+            target_id,
+            target_storage,
+            commands,
+        );
+    }
+}
+
+/// Moves all tiles of one tilemap into another tilemap, placing the source's `(0, 0)` at
+/// `offset` in the target.
+///
+/// Tile entities are reused, so all of their other components are preserved. Tiles that would
+/// land outside of `target_storage`, or on a position that is already occupied, are left in the
+/// source tilemap. Once every tile has been moved, the source tilemap entity can be despawned.
+pub fn merge_tilemaps(
+    offset: TilePos,
+    source_storage: &mut TileStorage,
+    target_id: TilemapId,
+    target_storage: &mut TileStorage,
+    commands: &mut Commands,
+) {
+    for source_pos in TileRect::from_map_size(source_storage.size).iter() {
+        let Some(target_pos) = offset.checked_add(source_pos.into()) else {
+            continue;
+        };
+        move_tile(
+            source_pos,
+            target_pos,
+            source_storage,
+            target_id,
+            target_storage,
+            commands,
+        );
+    }
+}
+
+/// Moves the tile at `source_pos` to `target_pos` in the target tilemap, returning the moved
+/// tile entity.
+fn move_tile(
+    source_pos: TilePos,
+    target_pos: TilePos,
+    source_storage: &mut TileStorage,
+    target_id: TilemapId,
+    target_storage: &mut TileStorage,
+    commands: &mut Commands,
+) -> Option<Entity> {
+    if !target_pos.within_map_bounds(&target_storage.size)
+        || target_storage.get(&target_pos).is_some()
+    {
+        return None;
+    }
+
+    let tile_entity = source_storage.checked_get(&source_pos)?;
+    source_storage.remove(&source_pos);
+    target_storage.set(&target_pos, tile_entity);
+    commands
+        .entity(tile_entity)
+        .insert((target_pos, target_id))
+        .set_parent(target_id.0);
+
+    Some(tile_entity)
+}
@@ -0,0 +1,413 @@
+//! Edges between square cells, e.g. for thin walls between the rooms of a dungeon.
+
+use crate::helpers::square_grid::neighbors::{Neighbors, SquareDirection};
+use crate::helpers::square_grid::SquarePos;
+use crate::map::{TilemapAxes, TilemapId, TilemapYAxis};
+use crate::tiles::{TilePos, TilePosOffset, TileRotation, TileStorage};
+use crate::{TilemapGridSize, TilemapSize, TilemapSystemSet};
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::math::Vec2;
+use bevy::prelude::{
+    Component, DetectChanges, DetectChangesMut, Entity, IntoSystemConfigs, Local, Query, Ref,
+    Reflect, ReflectComponent,
+};
+use bevy::utils::HashMap;
+use std::f32::consts::FRAC_PI_2;
+
+/// The edge between two neighboring square cells.
+///
+/// Every edge has a single representation: the cell it belongs to, and a `side` of
+/// [`SquareDirection::West`] or [`SquareDirection::South`]. Edges built with the east or north
+/// side belong to the neighbor on that side instead, so that the edges of a map all belong to
+/// cells with non-negative coordinates.
+///
+/// ```
+/// # use bevy_ecs_tilemap::helpers::square_grid::SquarePos;
+/// # use bevy_ecs_tilemap::helpers::square_grid::edges::SquareEdge;
+/// # use bevy_ecs_tilemap::helpers::square_grid::neighbors::SquareDirection;
+/// let cell = SquarePos::new(2, 3);
+/// let edge = SquareEdge::new(cell, SquareDirection::East);
+/// assert_eq!(edge, SquareEdge::new(SquarePos::new(3, 3), SquareDirection::West));
+/// assert_eq!(SquareEdge::between(cell, SquarePos::new(3, 3)), Some(edge));
+/// ```
+#[derive(Component, Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SquareEdge {
+    pub cell: SquarePos,
+    pub side: SquareDirection,
+}
+
+impl SquareEdge {
+    /// Returns the edge of `cell` on the side `side`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `side` is a diagonal direction.
+    pub fn new(cell: SquarePos, side: SquareDirection) -> Self {
+        match side {
+            SquareDirection::West | SquareDirection::South => Self { cell, side },
+            SquareDirection::East | SquareDirection::North => Self {
+                cell: cell.offset(&side),
+                side: side + 4usize,
+            },
+            _ => panic!("Square cells have no edge in the diagonal direction {side:?}."),
+        }
+    }
+
+    /// Returns the edge between two cells, or `None` if they aren't neighbors across an edge.
+    pub fn between(a: SquarePos, b: SquarePos) -> Option<Self> {
+        let side = match (b.x - a.x, b.y - a.y) {
+            (1, 0) => SquareDirection::East,
+            (-1, 0) => SquareDirection::West,
+            (0, 1) => SquareDirection::North,
+            (0, -1) => SquareDirection::South,
+            _ => return None,
+        };
+        Some(Self::new(a, side))
+    }
+
+    /// Returns the two cells on either side of the edge.
+    pub fn cells(&self) -> [SquarePos; 2] {
+        [self.cell, self.cell.offset(&self.side)]
+    }
+
+    /// Returns true if the edge runs along the y axis, between a cell and its west neighbor.
+    pub fn is_vertical(&self) -> bool {
+        self.side == SquareDirection::West
+    }
+
+    /// Returns the center of the edge in world space, with the center of the cell `(0, 0)` at
+    /// `[0.0, 0.0]`.
+    pub fn center_in_world(&self, grid_size: &TilemapGridSize) -> Vec2 {
+        let offset = SquarePos::from(self.side);
+        let center = Vec2::new(self.cell.x as f32, self.cell.y as f32)
+            + 0.5 * Vec2::new(offset.x as f32, offset.y as f32);
+        SquarePos::project(center, grid_size)
+    }
+}
+
+impl SquarePos {
+    /// Returns the four edges of the cell, in the order east, north, west, south.
+    pub fn edges(&self) -> [SquareEdge; 4] {
+        [
+            SquareDirection::East,
+            SquareDirection::North,
+            SquareDirection::West,
+            SquareDirection::South,
+        ]
+        .map(|side| SquareEdge::new(*self, side))
+    }
+}
+
+/// Stores the entities on the edges of a square tilemap, e.g. its walls, the way a
+/// [`TileStorage`](crate::tiles::TileStorage) stores the entities on its cells.
+///
+/// It holds a slot for every edge of the cells of a map of the given size, including the edges
+/// along the border of the map.
+///
+/// ```
+/// # use bevy::prelude::Entity;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::square_grid::SquarePos;
+/// # use bevy_ecs_tilemap::helpers::square_grid::edges::{SquareEdge, SquareEdgeStorage};
+/// # use bevy_ecs_tilemap::helpers::square_grid::neighbors::SquareDirection;
+/// let mut walls = SquareEdgeStorage::empty(TilemapSize { x: 4, y: 4 });
+/// let wall = Entity::from_raw(7);
+/// walls.set(&SquareEdge::new(SquarePos::new(1, 1), SquareDirection::North), wall);
+///
+/// let (room, corridor) = (TilePos { x: 1, y: 1 }, TilePos { x: 1, y: 2 });
+/// assert!(walls.is_blocked(&room, &corridor));
+/// assert_eq!(walls.around(&room).north, Some(wall));
+/// assert!(!walls.is_blocked(&room, &TilePos { x: 2, y: 1 }));
+/// ```
+#[derive(Component, Reflect, Default, Debug, Clone)]
+#[reflect(Component)]
+pub struct SquareEdgeStorage {
+    edges: Vec<Option<Entity>>,
+    pub size: TilemapSize,
+}
+
+impl SquareEdgeStorage {
+    /// Creates a new edge storage for a tilemap of the given size.
+    pub fn empty(size: TilemapSize) -> Self {
+        Self {
+            edges: vec![None; ((size.x + 1) * (size.y + 1) * 2) as usize],
+            size,
+        }
+    }
+
+    /// Returns the index of the slot of `edge`, or `None` if the edge isn't one of the map.
+    fn index(&self, edge: &SquareEdge) -> Option<usize> {
+        let SquarePos { x, y } = edge.cell;
+        let in_range = x >= 0 && y >= 0 && x <= self.size.x as i32 && y <= self.size.y as i32;
+        // The edges of the corner cells past the map don't border it.
+        let in_map = match edge.side {
+            SquareDirection::West => y < self.size.y as i32,
+            _ => x < self.size.x as i32,
+        };
+        (in_range && in_map).then(|| {
+            let slot = (y as usize * (self.size.x as usize + 1) + x as usize) * 2;
+            slot + usize::from(edge.side == SquareDirection::South)
+        })
+    }
+
+    /// Gets the entity on `edge`, if there is one.
+    ///
+    /// Returns `None` for edges that aren't edges of the cells of the map.
+    pub fn get(&self, edge: &SquareEdge) -> Option<Entity> {
+        self.edges[self.index(edge)?]
+    }
+
+    /// Sets the entity on `edge`, and returns the entity it replaces.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `edge` isn't an edge of the cells of the map.
+    pub fn set(&mut self, edge: &SquareEdge, entity: Entity) -> Option<Entity> {
+        let index = self
+            .index(edge)
+            .unwrap_or_else(|| panic!("{edge:?} is not an edge of a map of size {:?}", self.size));
+        self.edges[index].replace(entity)
+    }
+
+    /// Removes the entity on `edge`, and returns it.
+    ///
+    /// Returns `None` for edges that aren't edges of the cells of the map.
+    pub fn remove(&mut self, edge: &SquareEdge) -> Option<Entity> {
+        let index = self.index(edge)?;
+        self.edges[index].take()
+    }
+
+    /// Returns the entity on the edge between two neighboring tiles.
+    pub fn between(&self, a: &TilePos, b: &TilePos) -> Option<Entity> {
+        self.get(&SquareEdge::between(a.into(), b.into())?)
+    }
+
+    /// Returns true if there is an entity on the edge between two neighboring tiles, e.g. a wall
+    /// blocking the way from one to the other.
+    pub fn is_blocked(&self, a: &TilePos, b: &TilePos) -> bool {
+        self.between(a, b).is_some()
+    }
+
+    /// Returns the entities on the four edges of a tile, in its cardinal directions.
+    pub fn around(&self, tile_pos: &TilePos) -> Neighbors<Entity> {
+        let cell = SquarePos::from(tile_pos);
+        Neighbors::from_directional_closure(|direction| {
+            if direction.is_cardinal() {
+                self.get(&SquareEdge::new(cell, direction))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns an iterator over the edges holding an entity, and their entities.
+    pub fn iter(&self) -> impl Iterator<Item = (SquareEdge, Entity)> + '_ {
+        let columns = self.size.x as usize + 1;
+        self.edges
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, entity)| {
+                let cell = index / 2;
+                let side = if index % 2 == 0 {
+                    SquareDirection::West
+                } else {
+                    SquareDirection::South
+                };
+                let cell = SquarePos::new((cell % columns) as i32, (cell / columns) as i32);
+                entity.map(|entity| (SquareEdge { cell, side }, entity))
+            })
+    }
+}
+
+/// Draws the tiles of a square tilemap with a [`SquareEdge`] along that edge, e.g. thin walls
+/// between cells, by setting their [`TilePos`], [`TilePosOffset`] and [`TileRotation`].
+///
+/// Keep wall tiles in their own tilemap, with the same size, grid size, axes and transform as
+/// the floor map, and a tile size as thin as the walls, e.g. `16x4` for a `16x16` grid. Wall
+/// textures run along the x axis and are rotated a quarter turn for vertical edges. Several walls
+/// can share a cell, so store them in a [`SquareEdgeStorage`] rather than in the tile storage of
+/// the wall map, which is left empty and only gives the map its axes.
+///
+/// Tiles are placed again when their edge changes, or when the size, grid size or axes of their
+/// tilemap change.
+pub struct SquareEdgePlugin;
+
+impl Plugin for SquareEdgePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SquareEdgeStorage>().add_systems(
+            PostUpdate,
+            place_edge_tiles.before(TilemapSystemSet::ExtractionPrep),
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn place_edge_tiles(
+    mut cached_axes: Local<HashMap<Entity, TilemapAxes>>,
+    tilemaps: Query<(
+        Entity,
+        Ref<TilemapSize>,
+        Ref<TilemapGridSize>,
+        Option<&TileStorage>,
+    )>,
+    mut tiles: Query<(
+        Ref<SquareEdge>,
+        &TilemapId,
+        &mut TilePos,
+        &mut TilePosOffset,
+        &mut TileRotation,
+    )>,
+) {
+    // Storages change with every tile, so only a change of their axes counts.
+    cached_axes.retain(|tilemap, _| tilemaps.contains(*tilemap));
+    let mut changed_tilemaps = Vec::new();
+    for (tilemap, map_size, grid_size, storage) in tilemaps.iter() {
+        let axes = storage.map(|storage| storage.axes).unwrap_or_default();
+        let axes_changed = cached_axes.insert(tilemap, axes) != Some(axes);
+        if axes_changed || map_size.is_changed() || grid_size.is_changed() {
+            changed_tilemaps.push(tilemap);
+        }
+    }
+
+    for (edge, tilemap_id, mut tile_pos, mut offset, mut rotation) in tiles.iter_mut() {
+        if !edge.is_changed() && !changed_tilemaps.contains(&tilemap_id.0) {
+            continue;
+        }
+        let Ok((_, map_size, grid_size, storage)) = tilemaps.get(tilemap_id.0) else {
+            continue;
+        };
+        // Edges along the border of the map are drawn from the cell inside it.
+        let [cell, neighbor] = edge.cells();
+        let (pos, side) = match cell.as_tile_pos(&map_size) {
+            Some(pos) => (pos, edge.side),
+            None => match neighbor.as_tile_pos(&map_size) {
+                Some(pos) => (pos, edge.side + 4usize),
+                None => continue,
+            },
+        };
+        let mut side_offset = SquarePos::corner_offset_in_world(side, &grid_size);
+        if storage.is_some_and(|storage| storage.axes.y_axis == TilemapYAxis::Down) {
+            side_offset.y = -side_offset.y;
+        }
+        tile_pos.set_if_neq(pos);
+        offset.set_if_neq(TilePosOffset(side_offset));
+        rotation.set_if_neq(TileRotation(if edge.is_vertical() {
+            FRAC_PI_2
+        } else {
+            0.0
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::App;
+
+    use super::*;
+    use crate::map::TilemapType;
+    use crate::test_utils::{spawn_empty_test_map, MinimalTilemapPlugins, StepApp};
+
+    #[test]
+    fn storage_holds_the_edges_along_the_border() {
+        let size = TilemapSize { x: 3, y: 2 };
+        let mut storage = SquareEdgeStorage::empty(size);
+        let east = SquareEdge::new(SquarePos::new(2, 1), SquareDirection::East);
+        let north = SquareEdge::new(SquarePos::new(2, 1), SquareDirection::North);
+        let west = SquareEdge::new(SquarePos::new(0, 0), SquareDirection::West);
+        let south = SquareEdge::new(SquarePos::new(0, 0), SquareDirection::South);
+        for (index, edge) in [east, north, west, south].iter().enumerate() {
+            assert_eq!(storage.set(edge, Entity::from_raw(index as u32)), None);
+        }
+        assert_eq!(storage.get(&east), Some(Entity::from_raw(0)));
+        assert_eq!(storage.get(&north), Some(Entity::from_raw(1)));
+
+        // The corner past the map, and cells outside of the map, have no edges of the map.
+        let corner = SquarePos::new(3, 2);
+        assert_eq!(
+            storage.index(&SquareEdge::new(corner, SquareDirection::West)),
+            None
+        );
+        assert_eq!(
+            storage.index(&SquareEdge::new(corner, SquareDirection::South)),
+            None
+        );
+        let outside = SquareEdge::new(SquarePos::new(-1, 0), SquareDirection::West);
+        assert_eq!(storage.get(&outside), None);
+        let beyond = SquareEdge::new(SquarePos::new(4, 0), SquareDirection::West);
+        assert_eq!(storage.remove(&beyond), None);
+
+        let mut edges: Vec<_> = storage.iter().collect();
+        edges.sort();
+        let mut expected = vec![
+            (east, Entity::from_raw(0)),
+            (north, Entity::from_raw(1)),
+            (west, Entity::from_raw(2)),
+            (south, Entity::from_raw(3)),
+        ];
+        expected.sort();
+        assert_eq!(edges, expected);
+    }
+
+    #[test]
+    fn edge_tiles_follow_their_tilemap() {
+        let mut app = App::new();
+        app.add_plugins((MinimalTilemapPlugins, SquareEdgePlugin));
+        let size = TilemapSize { x: 3, y: 2 };
+        let map = spawn_empty_test_map(app.world_mut(), size, TilemapType::Square);
+        // An edge along the north border, drawn from the cell below it.
+        let edge = SquareEdge::new(SquarePos::new(1, 1), SquareDirection::North);
+        let wall = app
+            .world_mut()
+            .spawn((
+                edge,
+                TilemapId(map),
+                TilePos::default(),
+                TilePosOffset::default(),
+                TileRotation::default(),
+            ))
+            .id();
+        app.step_frames(1);
+
+        let placement = |app: &App| {
+            let wall = app.world().entity(wall);
+            (
+                *wall.get::<TilePos>().unwrap(),
+                wall.get::<TilePosOffset>().unwrap().0,
+                wall.get::<TileRotation>().unwrap().0,
+            )
+        };
+        assert_eq!(
+            placement(&app),
+            (TilePos::new(1, 1), Vec2::new(0.0, 8.0), 0.0)
+        );
+
+        app.world_mut()
+            .entity_mut(map)
+            .insert(TileStorage::empty_with_axes(size, TilemapAxes::Y_DOWN));
+        app.step_frames(1);
+        assert_eq!(
+            placement(&app),
+            (TilePos::new(1, 1), Vec2::new(0.0, -8.0), 0.0)
+        );
+
+        app.world_mut()
+            .entity_mut(map)
+            .insert(TilemapGridSize { x: 32.0, y: 32.0 });
+        app.step_frames(1);
+        assert_eq!(
+            placement(&app),
+            (TilePos::new(1, 1), Vec2::new(0.0, -16.0), 0.0)
+        );
+
+        // The edge is now between two cells of the map, and drawn from its own cell.
+        app.world_mut()
+            .entity_mut(map)
+            .insert(TilemapSize { x: 3, y: 3 });
+        app.step_frames(1);
+        assert_eq!(
+            placement(&app),
+            (TilePos::new(1, 2), Vec2::new(0.0, 16.0), 0.0)
+        );
+    }
+}
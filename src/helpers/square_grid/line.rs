@@ -0,0 +1,110 @@
+//! Code for walking the tiles crossed by a line on a square grid.
+
+use crate::helpers::square_grid::SquarePos;
+use crate::TilemapGridSize;
+use bevy::math::{IVec2, Vec2};
+
+/// Iterates over the tiles crossed by the segment from `start` to `end`, in order, both given in
+/// the tilemap's local space. See [`SegmentTiles`].
+///
+/// ```
+/// # use bevy::math::Vec2;
+/// # use bevy_ecs_tilemap::helpers::square_grid::line::segment_tiles;
+/// # use bevy_ecs_tilemap::helpers::square_grid::SquarePos;
+/// # use bevy_ecs_tilemap::map::TilemapGridSize;
+/// let grid_size = TilemapGridSize { x: 16.0, y: 16.0 };
+/// let tiles: Vec<SquarePos> =
+///     segment_tiles(Vec2::new(0.0, 0.0), Vec2::new(48.0, 12.0), &grid_size).collect();
+///
+/// assert_eq!(tiles.first(), Some(&SquarePos { x: 0, y: 0 }));
+/// assert_eq!(tiles.last(), Some(&SquarePos { x: 3, y: 1 }));
+/// assert_eq!(tiles.len(), 5);
+/// ```
+pub fn segment_tiles(start: Vec2, end: Vec2, grid_size: &TilemapGridSize) -> SegmentTiles {
+    SegmentTiles::new(start, end, grid_size)
+}
+
+/// An iterator over the tiles crossed by a segment, from the tile containing its start to the
+/// tile containing its end.
+///
+/// Every tile the segment passes through is visited once, and consecutive tiles share an edge. A
+/// segment passing exactly through a corner visits one of the two tiles beside the corner.
+#[derive(Clone, Debug)]
+pub struct SegmentTiles {
+    current: IVec2,
+    end: IVec2,
+    step: IVec2,
+    /// How far along the segment, as a fraction of its length, the next x and y edges are.
+    t_max: Vec2,
+    /// How much of the segment's length crossing a whole tile along x and y takes.
+    t_delta: Vec2,
+    done: bool,
+}
+
+impl SegmentTiles {
+    /// Creates the iterator for the segment from `start` to `end`, in the tilemap's local space.
+    pub fn new(start: Vec2, end: Vec2, grid_size: &TilemapGridSize) -> Self {
+        // Tile centers lie at `grid_size * tile_pos`, so tile edges are at half steps.
+        let grid_size = Vec2::from(grid_size);
+        let start = start / grid_size + 0.5;
+        let end = end / grid_size + 0.5;
+        let delta = end - start;
+
+        let axis = |start: f32, delta: f32| -> (i32, f32, f32) {
+            if delta > 0.0 {
+                (1, (start.floor() + 1.0 - start) / delta, 1.0 / delta)
+            } else if delta < 0.0 {
+                (-1, (start - start.floor()) / -delta, 1.0 / -delta)
+            } else {
+                (0, f32::INFINITY, f32::INFINITY)
+            }
+        };
+        let (step_x, t_max_x, t_delta_x) = axis(start.x, delta.x);
+        let (step_y, t_max_y, t_delta_y) = axis(start.y, delta.y);
+
+        Self {
+            current: start.floor().as_ivec2(),
+            end: end.floor().as_ivec2(),
+            step: IVec2::new(step_x, step_y),
+            t_max: Vec2::new(t_max_x, t_max_y),
+            t_delta: Vec2::new(t_delta_x, t_delta_y),
+            done: false,
+        }
+    }
+}
+
+impl Iterator for SegmentTiles {
+    type Item = SquarePos;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let tile = SquarePos {
+            x: self.current.x,
+            y: self.current.y,
+        };
+        if self.current == self.end {
+            self.done = true;
+            return Some(tile);
+        }
+
+        // Once an axis reached the end tile, only the other one moves, so that rounding errors
+        // can't step past the end.
+        let step_x = if self.current.x == self.end.x {
+            false
+        } else if self.current.y == self.end.y {
+            true
+        } else {
+            self.t_max.x < self.t_max.y
+        };
+        if step_x {
+            self.current.x += self.step.x;
+            self.t_max.x += self.t_delta.x;
+        } else {
+            self.current.y += self.step.y;
+            self.t_max.y += self.t_delta.y;
+        }
+        Some(tile)
+    }
+}
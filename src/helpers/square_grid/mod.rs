@@ -1,4 +1,6 @@
 pub mod diamond;
+pub mod edges;
+pub mod line;
 pub mod neighbors;
 pub mod staggered;
 
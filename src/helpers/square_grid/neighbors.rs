@@ -10,6 +10,7 @@ use std::ops::{Add, Sub};
 /// Note that isometric grids are also square-like grids. In particular, there is no
 /// difference between the grid system for square and diamond-isometric grids.
 #[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SquareDirection {
     East,
     NorthEast,
@@ -340,6 +341,25 @@ impl Neighbors<TilePos> {
     ///
     /// A tile position will be `None` for a particular direction, if that neighbor would not lie
     /// on the map.
+    ///
+    /// Example:
+    /// ```
+    /// # use bevy::prelude::World;
+    /// # use bevy_ecs_tilemap::prelude::*;
+    /// # use bevy_ecs_tilemap::helpers::square_grid::neighbors::{Neighbors, SquareDirection};
+    /// # use bevy_ecs_tilemap::test_utils::spawn_test_map;
+    /// # let mut world = World::new();
+    /// let map_size = TilemapSize { x: 4, y: 4 };
+    /// let map = spawn_test_map(&mut world, map_size, TilemapType::Square);
+    ///
+    /// let neighbors =
+    ///     Neighbors::get_square_neighboring_positions(&TilePos { x: 0, y: 0 }, &map_size, false);
+    /// assert_eq!(neighbors.north, Some(TilePos { x: 0, y: 1 }));
+    /// assert_eq!(neighbors.south, None);
+    ///
+    /// let tile_storage = world.get::<TileStorage>(map).unwrap();
+    /// assert_eq!(neighbors.entities(tile_storage).iter().count(), 2);
+    /// ```
     pub fn get_square_neighboring_positions(
         tile_pos: &TilePos,
         map_size: &TilemapSize,
@@ -0,0 +1,109 @@
+use crate::tiles::{TilePos, TileStorage};
+use crate::{TilemapFirstSet, TilemapSystemSet};
+use bevy::app::{App, First, Plugin};
+use bevy::ecs::entity::Entities;
+use bevy::prelude::{Entity, Event, EventWriter, IntoSystemConfigs, Query, ResMut, Resource};
+
+/// Sent by the [`TileStorageRepairPlugin`] for every slot of a [`TileStorage`] that pointed at a
+/// despawned tile entity, after clearing the slot.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileStorageDesync {
+    pub tilemap: Entity,
+    pub tile_pos: TilePos,
+    /// The despawned tile entity the slot pointed at.
+    pub tile_entity: Entity,
+}
+
+/// Decides when the [`TileStorageRepairPlugin`] scans tile storages.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileStorageRepair {
+    /// Scans every frame, rather than only when requested.
+    pub every_frame: bool,
+    requested: bool,
+}
+
+impl Default for TileStorageRepair {
+    /// By default, storages are scanned every frame.
+    fn default() -> Self {
+        Self {
+            every_frame: true,
+            requested: false,
+        }
+    }
+}
+
+impl TileStorageRepair {
+    /// Scans only when requested, with [`Self::request`].
+    pub fn on_demand() -> Self {
+        Self {
+            every_frame: false,
+            requested: false,
+        }
+    }
+
+    /// Scans the tile storages at the start of the next frame.
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+}
+
+/// Finds the slots of [`TileStorage`]s pointing at despawned tile entities, clears them, and
+/// sends a [`TileStorageDesync`] event for each of them, along with a warning.
+///
+/// Despawned tiles only clear their own slot in the storage of the tilemap their
+/// [`TilemapId`](crate::map::TilemapId) points at, so slots can go stale when tiles are stored in
+/// another tilemap, or when storages are loaded with entities that no longer exist.
+///
+/// Storages are scanned in `First`, after [`TilemapSystemSet::StorageMaintenance`]. Scanning
+/// visits every slot of every storage, so in shipping builds with large maps, prefer inserting a
+/// [`TileStorageRepair::on_demand`] resource before adding the plugin.
+pub struct TileStorageRepairPlugin;
+
+impl Plugin for TileStorageRepairPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileStorageRepair>()
+            .add_event::<TileStorageDesync>()
+            .add_systems(
+                First,
+                repair_tile_storages
+                    .in_set(TilemapFirstSet)
+                    .after(TilemapSystemSet::StorageMaintenance),
+            );
+    }
+}
+
+fn repair_tile_storages(
+    mut repair: ResMut<TileStorageRepair>,
+    mut desyncs: EventWriter<TileStorageDesync>,
+    entities: &Entities,
+    mut storages: Query<(Entity, &mut TileStorage)>,
+) {
+    if !repair.every_frame && !repair.requested {
+        return;
+    }
+    repair.requested = false;
+
+    for (tilemap, mut storage) in storages.iter_mut() {
+        // Reading through `Mut` leaves healthy storages unchanged.
+        let stale: Vec<(TilePos, Entity)> = storage
+            .iter_some()
+            .filter(|(_, tile_entity)| !entities.contains(*tile_entity))
+            .collect();
+        if stale.is_empty() {
+            continue;
+        }
+        bevy::log::warn!(
+            "Cleared {} slots of the tile storage of tilemap {tilemap} that pointed at despawned \
+             tiles.",
+            stale.len()
+        );
+        for (tile_pos, tile_entity) in stale {
+            storage.remove(&tile_pos);
+            desyncs.send(TileStorageDesync {
+                tilemap,
+                tile_pos,
+                tile_entity,
+            });
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use crate::map::{
+    IsoCoordSystem, TilemapAxes, TilemapGridSize, TilemapInvalidate, TilemapSize, TilemapTexture,
+    TilemapTileSize, TilemapYAxis,
+};
+use crate::tiles::{TilePos, TileRect, TileStorage};
+use crate::TilemapType;
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::hierarchy::{BuildChildren, Parent};
+use bevy::log::warn;
+use bevy::math::{UVec2, Vec2};
+use bevy::prelude::{
+    Commands, Component, DetectChanges, Entity, IntoSystemConfigs, Query, Ref, Reflect,
+    ReflectComponent, Transform, TransformSystem, With, Without,
+};
+
+#[cfg(feature = "render")]
+type SubTilemapBundle = crate::TilemapBundle;
+#[cfg(not(feature = "render"))]
+type SubTilemapBundle = crate::StandardTilemapBundle;
+
+/// Makes a tilemap a higher resolution detail layer over a region of cells of its parent
+/// tilemap, e.g. damage decals with 2x2 tiles per terrain cell.
+///
+/// The sub tilemap must be a child of the parent tilemap. The [`SubTilemapPlugin`] derives its
+/// [`TilemapSize`], [`TilemapGridSize`], [`TilemapType`] and [`Transform`] from the parent, so that
+/// every parent cell of the region is covered by exactly `subdivisions` sub tiles. The z of the
+/// transform is left alone, so the detail layer can be placed above or below the parent.
+///
+/// `cells` are tile positions of the parent, under the parent's [`TilemapAxes`]. The storage of
+/// the sub tilemap is given the same axes, so that on a y-down parent the sub tiles of each cell
+/// are y-down too.
+///
+/// Only square tilemaps, and diamond isometric tilemaps with the same subdivisions along both
+/// axes, can be subdivided.
+///
+/// ```
+/// # use bevy::math::UVec2;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::subgrid::SubTilemap;
+/// let decals = SubTilemap::new(
+///     TileRect::new(TilePos::new(4, 4), TilemapSize { x: 8, y: 8 }),
+///     UVec2::new(2, 2),
+/// );
+/// assert_eq!(decals.size(), TilemapSize { x: 16, y: 16 });
+/// assert_eq!(decals.parent_cell(&TilePos::new(3, 0)), TilePos::new(5, 4));
+/// assert_eq!(decals.sub_tiles(&TilePos::new(5, 4)).unwrap().origin, TilePos::new(2, 0));
+/// assert!(decals.sub_tiles(&TilePos::new(0, 0)).is_none());
+/// ```
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct SubTilemap {
+    /// The cells of the parent tilemap covered by the sub tilemap.
+    pub cells: TileRect,
+    /// The number of sub tiles per parent cell along each axis.
+    pub subdivisions: UVec2,
+}
+
+impl SubTilemap {
+    pub fn new(cells: TileRect, subdivisions: UVec2) -> Self {
+        Self {
+            cells,
+            subdivisions: subdivisions.max(UVec2::ONE),
+        }
+    }
+
+    /// Returns the size of the sub tilemap, in sub tiles.
+    pub fn size(&self) -> TilemapSize {
+        TilemapSize {
+            x: self.cells.size.x * self.subdivisions.x,
+            y: self.cells.size.y * self.subdivisions.y,
+        }
+    }
+
+    /// Returns the grid size of the sub tilemap, given the grid size of its parent.
+    pub fn grid_size(&self, parent_grid_size: &TilemapGridSize) -> TilemapGridSize {
+        TilemapGridSize {
+            x: parent_grid_size.x / self.subdivisions.x as f32,
+            y: parent_grid_size.y / self.subdivisions.y as f32,
+        }
+    }
+
+    /// Returns the cell of the parent tilemap that contains the sub tile at `sub_pos`.
+    pub fn parent_cell(&self, sub_pos: &TilePos) -> TilePos {
+        TilePos::new(
+            self.cells.origin.x + sub_pos.x / self.subdivisions.x,
+            self.cells.origin.y + sub_pos.y / self.subdivisions.y,
+        )
+    }
+
+    /// Returns the sub tiles covering the cell of the parent tilemap at `parent_pos`, or `None`
+    /// if the cell is outside of the region of the sub tilemap.
+    pub fn sub_tiles(&self, parent_pos: &TilePos) -> Option<TileRect> {
+        if !self.cells.contains(parent_pos) {
+            return None;
+        }
+        let origin = TilePos::new(
+            (parent_pos.x - self.cells.origin.x) * self.subdivisions.x,
+            (parent_pos.y - self.cells.origin.y) * self.subdivisions.y,
+        );
+        Some(TileRect::new(
+            origin,
+            TilemapSize {
+                x: self.subdivisions.x,
+                y: self.subdivisions.y,
+            },
+        ))
+    }
+
+    /// Returns the position of the sub tilemap relative to its parent, or `None` if the parent's
+    /// map type can't be subdivided this way.
+    ///
+    /// `parent_size` and `parent_axes` are the size and axes of the parent tilemap, which place
+    /// the region on the parent's grid.
+    pub fn offset(
+        &self,
+        parent_size: &TilemapSize,
+        parent_grid_size: &TilemapGridSize,
+        map_type: &TilemapType,
+        parent_axes: &TilemapAxes,
+    ) -> Option<Vec2> {
+        // Diamond cells only split into smaller diamonds along both of their axes at once.
+        let supported = match map_type {
+            TilemapType::Square => true,
+            TilemapType::Isometric(IsoCoordSystem::Diamond) => {
+                self.subdivisions.x == self.subdivisions.y
+            }
+            _ => false,
+        };
+        if !supported {
+            return None;
+        }
+        // Both supported projections are linear, so the sub tiles of a cell are centered on it
+        // once the center of the first cell's block of sub tiles lines up with the cell center.
+        let project = |position: Vec2, grid_size: &TilemapGridSize| {
+            let x_axis = TilePos::new(1, 0).center_in_world(grid_size, map_type);
+            let y_axis = TilePos::new(0, 1).center_in_world(grid_size, map_type);
+            x_axis * position.x + y_axis * position.y
+        };
+        // On y-down parents the last row of the region is the one drawn lowest.
+        let origin = self.cells.origin;
+        let grid_origin = match parent_axes.y_axis {
+            TilemapYAxis::Up => UVec2::from(origin).as_vec2(),
+            TilemapYAxis::Down => Vec2::new(
+                origin.x as f32,
+                parent_size.y as f32 - (origin.y + self.cells.size.y) as f32,
+            ),
+        };
+        let block_center = (self.subdivisions.as_vec2() - Vec2::ONE) / 2.0;
+        Some(
+            project(grid_origin, parent_grid_size)
+                - project(block_center, &self.grid_size(parent_grid_size)),
+        )
+    }
+
+    /// Spawns a sub tilemap as a child of `parent`, with an empty storage of the right size, and
+    /// returns it. The grid, map type, axes and transform are filled in by the
+    /// [`SubTilemapPlugin`].
+    ///
+    /// `tile_size` is the size of the sub tiles in `texture`, which is usually the grid size of
+    /// the parent divided by the subdivisions.
+    pub fn spawn(
+        self,
+        parent: Entity,
+        texture: TilemapTexture,
+        tile_size: TilemapTileSize,
+        z_offset: f32,
+        commands: &mut Commands,
+    ) -> Entity {
+        let size = self.size();
+        commands
+            .spawn((
+                SubTilemapBundle {
+                    size,
+                    storage: TileStorage::empty(size),
+                    texture,
+                    tile_size,
+                    transform: Transform::from_xyz(0.0, 0.0, z_offset),
+                    ..Default::default()
+                },
+                self,
+            ))
+            .set_parent(parent)
+            .id()
+    }
+}
+
+/// Keeps [`SubTilemap`]s aligned with their parent tilemaps.
+pub struct SubTilemapPlugin;
+
+impl Plugin for SubTilemapPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SubTilemap>().add_systems(
+            PostUpdate,
+            sync_sub_tilemaps.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn sync_sub_tilemaps(
+    mut commands: Commands,
+    mut sub_tilemaps: Query<
+        (
+            Entity,
+            Ref<SubTilemap>,
+            Ref<Parent>,
+            &mut TilemapSize,
+            &mut TilemapGridSize,
+            &mut TilemapType,
+            &mut Transform,
+            Option<&mut TileStorage>,
+        ),
+        With<SubTilemap>,
+    >,
+    parents: Query<
+        (
+            Ref<TilemapSize>,
+            Ref<TilemapGridSize>,
+            Ref<TilemapType>,
+            Option<&TileStorage>,
+        ),
+        Without<SubTilemap>,
+    >,
+) {
+    for (
+        entity,
+        sub_tilemap,
+        parent,
+        mut size,
+        mut grid_size,
+        mut map_type,
+        mut transform,
+        storage,
+    ) in sub_tilemaps.iter_mut()
+    {
+        let Ok((parent_size, parent_grid_size, parent_map_type, parent_storage)) =
+            parents.get(parent.get())
+        else {
+            continue;
+        };
+        // Storages change with every tile, so changes of the axes are found by comparing them.
+        let parent_axes = parent_storage
+            .map(|storage| storage.axes)
+            .unwrap_or_default();
+        let axes_changed = storage
+            .as_ref()
+            .is_some_and(|storage| storage.axes != parent_axes);
+        let changed = sub_tilemap.is_changed()
+            || parent.is_changed()
+            || parent_size.is_changed()
+            || parent_grid_size.is_changed()
+            || parent_map_type.is_changed()
+            || axes_changed;
+        if !changed {
+            continue;
+        }
+        let Some(offset) = sub_tilemap.offset(
+            &parent_size,
+            &parent_grid_size,
+            &parent_map_type,
+            &parent_axes,
+        ) else {
+            warn!("Sub tilemap {entity} can't subdivide a {parent_map_type:?} tilemap.");
+            continue;
+        };
+
+        // Only touch components that differ, to avoid needless re-extraction.
+        let new_size = sub_tilemap.size();
+        if *size != new_size {
+            *size = new_size;
+        }
+        let new_grid_size = sub_tilemap.grid_size(&parent_grid_size);
+        if *grid_size != new_grid_size {
+            *grid_size = new_grid_size;
+        }
+        if *map_type != *parent_map_type {
+            *map_type = *parent_map_type;
+        }
+        if transform.translation.truncate() != offset {
+            transform.translation = offset.extend(transform.translation.z);
+        }
+        if let Some(mut storage) = storage.filter(|storage| storage.axes != parent_axes) {
+            let mut new_storage = TileStorage::empty_with_axes(storage.size, parent_axes);
+            for (tile_pos, tile_entity) in storage.drain_with_pos() {
+                new_storage.checked_set(&tile_pos, tile_entity);
+            }
+            *storage = new_storage;
+            commands.entity(entity).insert(TilemapInvalidate::All);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sub_tiles_are_centered_on_their_parent_cell() {
+        let parent_size = TilemapSize { x: 12, y: 12 };
+        let parent_grid_size = TilemapGridSize { x: 32.0, y: 16.0 };
+        for (map_type, subdivisions) in [
+            (TilemapType::Square, UVec2::new(2, 3)),
+            (
+                TilemapType::Isometric(IsoCoordSystem::Diamond),
+                UVec2::new(3, 3),
+            ),
+        ] {
+            for axes in [TilemapAxes::default(), TilemapAxes::Y_DOWN] {
+                let sub_tilemap = SubTilemap::new(
+                    TileRect::new(TilePos::new(3, 5), TilemapSize { x: 4, y: 4 }),
+                    subdivisions,
+                );
+                let size = sub_tilemap.size();
+                let grid_size = sub_tilemap.grid_size(&parent_grid_size);
+                let offset = sub_tilemap
+                    .offset(&parent_size, &parent_grid_size, &map_type, &axes)
+                    .unwrap();
+                let cell = TilePos::new(4, 7);
+                let sub_tiles = sub_tilemap.sub_tiles(&cell).unwrap();
+                let center = sub_tiles
+                    .iter()
+                    .map(|sub_pos| {
+                        offset
+                            + sub_pos.center_in_world_with_axes(&size, &grid_size, &map_type, &axes)
+                    })
+                    .sum::<Vec2>()
+                    / sub_tiles.iter().count() as f32;
+                let expected = cell.center_in_world_with_axes(
+                    &parent_size,
+                    &parent_grid_size,
+                    &map_type,
+                    &axes,
+                );
+                assert!(
+                    center.distance(expected) < 1e-3,
+                    "{map_type:?} {axes:?}: {center} != {expected}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,411 @@
+use crate::helpers::hex_grid::axial::{AxialPos, COL_BASIS, ROW_BASIS};
+use crate::helpers::square_grid::diamond::{DiamondPos, DIAMOND_BASIS};
+use crate::helpers::square_grid::staggered::StaggeredPos;
+use crate::map::{
+    ensure_unlocked, HexCoordSystem, IsoCoordSystem, TilemapId, TilemapLockedError, TilemapSize,
+    TilemapType,
+};
+use crate::tiles::{
+    TileBundle, TileColor, TileFlip, TilePos, TileRect, TileStorage, TileTextureIndex, TileVisible,
+};
+use bevy::hierarchy::{BuildChildren, DespawnRecursiveExt};
+use bevy::math::{IVec2, Mat2, Vec2};
+use bevy::prelude::{Entity, World};
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_3};
+
+/// A mirroring or rotation of the contents of a whole tilemap, e.g. to generate the other half of
+/// a symmetric multiplayer map from the authored half.
+///
+/// Mirrors are as seen on screen, and rotations turn counterclockwise around the center of the
+/// map, by steps of a quarter turn on square and isometric maps, and of a sixth of a turn on
+/// hexagonal maps. On isometric maps, rotations turn the grid of tiles rather than the screen,
+/// like rotating an isometric camera.
+///
+/// [`TileFlip`]s are updated so that the tile art is mirrored and rotated along with the map,
+/// except for the turns of hexagonal maps that flips can't express.
+///
+/// ```
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::symmetry::MapSymmetry;
+/// let map_size = TilemapSize { x: 4, y: 4 };
+/// let rotate = MapSymmetry::Rotate(1);
+/// assert_eq!(
+///     rotate.apply(&TilePos::new(0, 0), &map_size, &TilemapType::Square),
+///     Some(TilePos::new(3, 0))
+/// );
+/// assert_eq!(
+///     MapSymmetry::MirrorX.apply_to_flip(&TileFlip::default(), &TilemapType::Square),
+///     TileFlip { x: true, ..Default::default() }
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MapSymmetry {
+    /// Mirrors the map left to right.
+    MirrorX,
+    /// Mirrors the map top to bottom.
+    MirrorY,
+    /// Rotates the map counterclockwise by the given number of steps.
+    Rotate(u32),
+}
+
+impl MapSymmetry {
+    /// Returns the transformation of the world, in a frame where the tiles of `map_type` are
+    /// regular.
+    fn world_matrix(&self, map_type: &TilemapType) -> Mat2 {
+        match self {
+            MapSymmetry::MirrorX => Mat2::from_diagonal(Vec2::new(-1.0, 1.0)),
+            MapSymmetry::MirrorY => Mat2::from_diagonal(Vec2::new(1.0, -1.0)),
+            MapSymmetry::Rotate(steps) => {
+                let step = match map_type {
+                    TilemapType::Hexagon(_) => FRAC_PI_3,
+                    _ => FRAC_PI_2,
+                };
+                Mat2::from_angle((steps % 12) as f32 * step)
+            }
+        }
+    }
+
+    /// Returns the columns of the integer matrix transforming lattice positions, see
+    /// [`to_lattice`].
+    fn lattice_matrix(&self, map_type: &TilemapType) -> [IVec2; 2] {
+        let basis = match map_type {
+            TilemapType::Square => Mat2::IDENTITY,
+            TilemapType::Isometric(_) => DIAMOND_BASIS,
+            TilemapType::Hexagon(HexCoordSystem::Row)
+            | TilemapType::Hexagon(HexCoordSystem::RowEven)
+            | TilemapType::Hexagon(HexCoordSystem::RowOdd) => ROW_BASIS,
+            TilemapType::Hexagon(_) => COL_BASIS,
+        };
+        // Every symmetry maps the lattice of tile centers onto itself, so this is exact up to
+        // rounding.
+        let lattice = basis.inverse() * self.world_matrix(map_type) * basis;
+        [
+            lattice.x_axis.round().as_ivec2(),
+            lattice.y_axis.round().as_ivec2(),
+        ]
+    }
+
+    /// Returns where the tile at `tile_pos` ends up, or `None` if it lands outside of the map.
+    ///
+    /// Positions are y-up grid positions, see
+    /// [`TilemapAxes::to_grid_pos`](crate::map::TilemapAxes::to_grid_pos). Mirrors keep every
+    /// tile on square and isometric maps, and so do half turns. Quarter turns only keep every
+    /// tile on maps as wide as they are tall, and hexagonal maps lose the corners that stick out.
+    pub fn apply(
+        &self,
+        tile_pos: &TilePos,
+        map_size: &TilemapSize,
+        map_type: &TilemapType,
+    ) -> Option<TilePos> {
+        if !tile_pos.within_map_bounds(map_size) {
+            return None;
+        }
+        let [x_axis, y_axis] = self.lattice_matrix(map_type);
+        // Twice the center, so that maps with an even size keep integer coordinates.
+        let last = TilePos::new(map_size.x.saturating_sub(1), map_size.y.saturating_sub(1));
+        let center = to_lattice(&TilePos::new(0, 0), map_type) + to_lattice(&last, map_type);
+        let relative = 2 * to_lattice(tile_pos, map_type) - center;
+        let doubled = x_axis * relative.x + y_axis * relative.y + center;
+        if doubled % 2 != IVec2::ZERO {
+            return None;
+        }
+        from_lattice(doubled / 2, map_size, map_type)
+    }
+
+    /// Returns the flip that keeps the art of a tile with `flip` mirrored and rotated along with
+    /// the map. Flips can't express the sixth turns of hexagonal maps, which keep `flip` as is.
+    pub fn apply_to_flip(&self, flip: &TileFlip, map_type: &TilemapType) -> TileFlip {
+        let world = self.world_matrix(map_type);
+        let rounded = Mat2::from_cols(world.x_axis.round(), world.y_axis.round());
+        if !world.abs_diff_eq(rounded, 1e-4) {
+            return *flip;
+        }
+        // Flips are applied to texture coordinates, whose y axis points down.
+        let y_down = Mat2::from_diagonal(Vec2::new(1.0, -1.0));
+        let transform = y_down * rounded * y_down;
+        // The diagonal flip is applied first, then the x and y flips.
+        let diagonal = Mat2::from_cols(Vec2::Y, Vec2::X);
+        let mirror = Mat2::from_diagonal(Vec2::new(
+            if flip.x { -1.0 } else { 1.0 },
+            if flip.y { -1.0 } else { 1.0 },
+        ));
+        let orientation = if flip.d { mirror * diagonal } else { mirror };
+
+        let orientation = transform * orientation;
+        let d = orientation.x_axis.y != 0.0;
+        let mirror = if d {
+            orientation * diagonal
+        } else {
+            orientation
+        };
+        TileFlip {
+            x: mirror.x_axis.x < 0.0,
+            y: mirror.y_axis.y < 0.0,
+            d,
+        }
+    }
+}
+
+/// Returns the position of a tile on a lattice where every symmetry of the map type is linear.
+fn to_lattice(tile_pos: &TilePos, map_type: &TilemapType) -> IVec2 {
+    match map_type {
+        TilemapType::Square | TilemapType::Isometric(IsoCoordSystem::Diamond) => {
+            IVec2::new(tile_pos.x as i32, tile_pos.y as i32)
+        }
+        TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+            let DiamondPos { x, y } = DiamondPos::from(StaggeredPos::from(tile_pos));
+            IVec2::new(x, y)
+        }
+        TilemapType::Hexagon(hex_coord_sys) => {
+            let AxialPos { q, r } =
+                AxialPos::from_tile_pos_given_coord_system(tile_pos, *hex_coord_sys);
+            IVec2::new(q, r)
+        }
+    }
+}
+
+/// The inverse of [`to_lattice`], for positions that lie on the map.
+fn from_lattice(
+    lattice_pos: IVec2,
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+) -> Option<TilePos> {
+    match map_type {
+        TilemapType::Square | TilemapType::Isometric(IsoCoordSystem::Diamond) => {
+            TilePos::from_i32_pair(lattice_pos.x, lattice_pos.y, map_size)
+        }
+        TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+            let diamond_pos = DiamondPos {
+                x: lattice_pos.x,
+                y: lattice_pos.y,
+            };
+            StaggeredPos::from(diamond_pos).as_tile_pos(map_size)
+        }
+        TilemapType::Hexagon(hex_coord_sys) => AxialPos {
+            q: lattice_pos.x,
+            r: lattice_pos.y,
+        }
+        .as_tile_pos_given_coord_system_and_map_size(*hex_coord_sys, map_size),
+    }
+}
+
+/// Returns the tiles of the storage with their grid position, and where they end up.
+fn transformed_tiles(
+    storage: &TileStorage,
+    map_type: &TilemapType,
+    symmetry: MapSymmetry,
+) -> Vec<(Entity, Option<TilePos>)> {
+    let size = storage.size;
+    TileRect::from_map_size(size)
+        .iter()
+        .filter_map(|tile_pos| {
+            let tile_entity = storage.get(&tile_pos)?;
+            let grid_pos = storage.axes.to_grid_pos(&tile_pos, &size);
+            let target = symmetry
+                .apply(&grid_pos, &size, map_type)
+                .map(|grid_pos| storage.axes.to_grid_pos(&grid_pos, &size));
+            Some((tile_entity, target))
+        })
+        .collect()
+}
+
+/// Mirrors or rotates every tile of `tilemap` in place, updating its [`TilePos`], its
+/// [`TileFlip`] and the [`TileStorage`]. Tiles that land outside of the map are despawned.
+///
+/// The tilemap must have a [`TileStorage`] and a [`TilemapType`], or nothing happens. Fails if
+/// the tilemap is [`TilemapLocked`](crate::map::TilemapLocked).
+pub fn transform_tilemap(
+    world: &mut World,
+    tilemap: Entity,
+    symmetry: MapSymmetry,
+) -> Result<(), TilemapLockedError> {
+    ensure_unlocked(world, tilemap)?;
+    let Ok(mut tilemap) = world.get_entity_mut(tilemap) else {
+        return Ok(());
+    };
+    let Some(map_type) = tilemap.get::<TilemapType>().copied() else {
+        return Ok(());
+    };
+    let Some(mut storage) = tilemap.get_mut::<TileStorage>() else {
+        return Ok(());
+    };
+    let tiles = transformed_tiles(&storage, &map_type, symmetry);
+    *storage = TileStorage::empty_with_axes(storage.size, storage.axes);
+    for (tile_entity, target) in tiles.iter() {
+        if let Some(target) = target {
+            storage.set(target, *tile_entity);
+        }
+    }
+
+    for (tile_entity, target) in tiles {
+        let Ok(mut tile) = world.get_entity_mut(tile_entity) else {
+            continue;
+        };
+        let Some(target) = target else {
+            tile.despawn_recursive();
+            continue;
+        };
+        tile.insert(target);
+        if let Some(mut flip) = tile.get_mut::<TileFlip>() {
+            *flip = symmetry.apply_to_flip(&flip, &map_type);
+        }
+    }
+    Ok(())
+}
+
+/// Completes a symmetric map from its authored part: every tile of `tilemap` is copied to where
+/// `symmetry` takes it, unless that position already has a tile.
+///
+/// Copies are spawned with the [`TileBundle`] of their source, i.e. its texture index, color,
+/// visibility and transformed flip. Returns each source tile with its copy, to copy other
+/// components of the tiles.
+///
+/// The tilemap must have a [`TileStorage`] and a [`TilemapType`], or nothing happens. Fails if
+/// the tilemap is [`TilemapLocked`](crate::map::TilemapLocked).
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::symmetry::{symmetrize_tilemap, MapSymmetry};
+/// let mut world = World::new();
+/// let tilemap = world.spawn_empty().id();
+/// let mut storage = TileStorage::empty(TilemapSize { x: 4, y: 4 });
+/// let tile = world
+///     .spawn(TileBundle {
+///         position: TilePos::new(0, 1),
+///         tilemap_id: TilemapId(tilemap),
+///         ..Default::default()
+///     })
+///     .id();
+/// storage.set(&TilePos::new(0, 1), tile);
+/// world.entity_mut(tilemap).insert((storage, TilemapType::Square));
+///
+/// let copies = symmetrize_tilemap(&mut world, tilemap, MapSymmetry::MirrorX).unwrap();
+/// let storage = world.get::<TileStorage>(tilemap).unwrap();
+/// assert_eq!(copies.len(), 1);
+/// assert_eq!(storage.get(&TilePos::new(3, 1)), Some(copies[0].1));
+/// ```
+pub fn symmetrize_tilemap(
+    world: &mut World,
+    tilemap: Entity,
+    symmetry: MapSymmetry,
+) -> Result<Vec<(Entity, Entity)>, TilemapLockedError> {
+    ensure_unlocked(world, tilemap)?;
+    let Ok(tilemap_ref) = world.get_entity(tilemap) else {
+        return Ok(Vec::new());
+    };
+    let (Some(storage), Some(map_type)) = (
+        tilemap_ref.get::<TileStorage>(),
+        tilemap_ref.get::<TilemapType>().copied(),
+    ) else {
+        return Ok(Vec::new());
+    };
+    let mut occupied = storage.clone();
+    let tiles = transformed_tiles(storage, &map_type, symmetry);
+
+    let mut copies = Vec::new();
+    for (source, target) in tiles {
+        let Some(target) = target else {
+            continue;
+        };
+        if occupied.get(&target).is_some() {
+            continue;
+        }
+        let Ok(source_ref) = world.get_entity(source) else {
+            continue;
+        };
+        let flip = source_ref.get::<TileFlip>().copied().unwrap_or_default();
+        let bundle = TileBundle {
+            position: target,
+            tilemap_id: TilemapId(tilemap),
+            texture_index: source_ref
+                .get::<TileTextureIndex>()
+                .copied()
+                .unwrap_or_default(),
+            color: source_ref.get::<TileColor>().copied().unwrap_or_default(),
+            visible: source_ref.get::<TileVisible>().copied().unwrap_or_default(),
+            flip: symmetry.apply_to_flip(&flip, &map_type),
+            ..Default::default()
+        };
+        let copy = world.spawn(bundle).set_parent(tilemap).id();
+        occupied.set(&target, copy);
+        copies.push((source, copy));
+    }
+
+    if let Some(mut storage) = world.get_mut::<TileStorage>(tilemap) {
+        *storage = occupied;
+    }
+    Ok(copies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symmetries_compose_to_identity() {
+        let map_size = TilemapSize { x: 6, y: 6 };
+        for map_type in [
+            TilemapType::Square,
+            TilemapType::Isometric(IsoCoordSystem::Diamond),
+            TilemapType::Hexagon(HexCoordSystem::Row),
+            TilemapType::Hexagon(HexCoordSystem::Column),
+        ] {
+            let full_turn = match map_type {
+                TilemapType::Hexagon(_) => 6,
+                _ => 4,
+            };
+            for (symmetry, order) in [
+                (MapSymmetry::MirrorX, 2),
+                (MapSymmetry::MirrorY, 2),
+                (MapSymmetry::Rotate(1), full_turn),
+            ] {
+                let [x_axis, y_axis] = symmetry.lattice_matrix(&map_type);
+                let mut lattice_pos = IVec2::new(2, 3);
+                for _ in 0..order {
+                    lattice_pos = x_axis * lattice_pos.x + y_axis * lattice_pos.y;
+                }
+                assert_eq!(lattice_pos, IVec2::new(2, 3), "{map_type:?} {symmetry:?}");
+            }
+
+            for bits in 0..8 {
+                let flip = TileFlip {
+                    x: bits & 1 != 0,
+                    y: bits & 2 != 0,
+                    d: bits & 4 != 0,
+                };
+                let mut transformed = flip;
+                for _ in 0..4 {
+                    transformed = MapSymmetry::Rotate(1).apply_to_flip(&transformed, &map_type);
+                }
+                assert_eq!(transformed, flip);
+            }
+
+            let tile_pos = TilePos::new(1, 2);
+            let mirrored = MapSymmetry::MirrorY.apply(&tile_pos, &map_size, &map_type);
+            let back =
+                mirrored.and_then(|pos| MapSymmetry::MirrorY.apply(&pos, &map_size, &map_type));
+            if map_type == TilemapType::Square {
+                assert_eq!(mirrored, Some(TilePos::new(1, 3)));
+            }
+            if let Some(back) = back {
+                assert_eq!(back, tile_pos, "{map_type:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn half_turns_flip_both_axes() {
+        let flip = MapSymmetry::Rotate(2).apply_to_flip(&TileFlip::default(), &TilemapType::Square);
+        assert_eq!(
+            flip,
+            TileFlip {
+                x: true,
+                y: true,
+                d: false
+            }
+        );
+        let flip = MapSymmetry::Rotate(1).apply_to_flip(&TileFlip::default(), &TilemapType::Square);
+        assert!(flip.d);
+    }
+}
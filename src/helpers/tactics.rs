@@ -0,0 +1,129 @@
+use crate::helpers::hex_grid::neighbors::{HexNeighbors, HEX_DIRECTIONS};
+use crate::helpers::square_grid::neighbors::{Neighbors, SQUARE_DIRECTIONS};
+use crate::map::{IsoCoordSystem, TilemapSize, TilemapType};
+use crate::tiles::TilePos;
+use bevy::utils::HashSet;
+
+/// Returns the tiles adjacent to `tile_pos` that lie on the map, along with the bit of the
+/// direction they lie in: the [`SquareDirection`] on square and isometric maps, or the
+/// [`HexDirection`] on hexagonal maps, as a number.
+///
+/// Diagonal tiles are only included on square and isometric maps, if `diagonals` is true.
+///
+/// [`SquareDirection`]: crate::helpers::square_grid::neighbors::SquareDirection
+/// [`HexDirection`]: crate::helpers::hex_grid::neighbors::HexDirection
+pub fn adjacent_tiles(
+    tile_pos: &TilePos,
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+    diagonals: bool,
+) -> Vec<(u8, TilePos)> {
+    match map_type {
+        TilemapType::Square | TilemapType::Isometric(IsoCoordSystem::Diamond) => {
+            let neighbors =
+                Neighbors::get_square_neighboring_positions(tile_pos, map_size, diagonals);
+            square_adjacent_tiles(&neighbors)
+        }
+        TilemapType::Isometric(IsoCoordSystem::Staggered) => {
+            let neighbors =
+                Neighbors::get_staggered_neighboring_positions(tile_pos, map_size, diagonals);
+            square_adjacent_tiles(&neighbors)
+        }
+        TilemapType::Hexagon(hex_coord_sys) => {
+            let neighbors =
+                HexNeighbors::get_neighboring_positions(tile_pos, map_size, hex_coord_sys);
+            HEX_DIRECTIONS
+                .iter()
+                .filter_map(|direction| Some((*direction as u8, *neighbors.get(*direction)?)))
+                .collect()
+        }
+    }
+}
+
+fn square_adjacent_tiles(neighbors: &Neighbors<TilePos>) -> Vec<(u8, TilePos)> {
+    SQUARE_DIRECTIONS
+        .iter()
+        .filter_map(|direction| Some((*direction as u8, *neighbors.get(*direction)?)))
+        .collect()
+}
+
+/// Returns a bitmask of the directions around `tile_pos` whose adjacent tile matches, e.g. the
+/// sides of a unit flanked by enemies, or the walls around a tile for autotiling.
+///
+/// Bit `n` is set if the tile in the direction numbered `n` by [`adjacent_tiles`] lies on the map
+/// and `matches` returns true for it.
+///
+/// ```
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::square_grid::neighbors::SquareDirection;
+/// # use bevy_ecs_tilemap::helpers::tactics::adjacency_mask;
+/// let map_size = TilemapSize { x: 8, y: 8 };
+/// let enemies = [TilePos::new(3, 4), TilePos::new(4, 3)];
+/// let mask = adjacency_mask(&TilePos::new(3, 3), &map_size, &TilemapType::Square, true, |tile| {
+///     enemies.contains(tile)
+/// });
+///
+/// assert_eq!(
+///     mask,
+///     1 << SquareDirection::North as u8 | 1 << SquareDirection::East as u8
+/// );
+/// ```
+pub fn adjacency_mask(
+    tile_pos: &TilePos,
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+    diagonals: bool,
+    matches: impl Fn(&TilePos) -> bool,
+) -> u8 {
+    adjacent_tiles(tile_pos, map_size, map_type, diagonals)
+        .into_iter()
+        .filter(|(_, adjacent)| matches(adjacent))
+        .fold(0, |mask, (direction, _)| mask | 1 << direction)
+}
+
+/// Returns the zone of control of `units`: the tiles adjacent to any of the units' tiles, where
+/// enemy units usually have to stop moving in tactics games.
+///
+/// Tiles occupied by the units themselves are only part of the zone if they are adjacent to
+/// another unit. Unit positions can come from an
+/// [`OccupantsIndex`](crate::helpers::occupants::OccupantsIndex).
+///
+/// ```
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::tactics::zone_of_control;
+/// let map_size = TilemapSize { x: 8, y: 8 };
+/// let units = [TilePos::new(0, 0), TilePos::new(5, 5)];
+/// let zone = zone_of_control(units, &map_size, &TilemapType::Square, false);
+///
+/// // Two neighbors in the corner, four in the middle of the map.
+/// assert_eq!(zone.len(), 6);
+/// assert!(zone.contains(&TilePos::new(5, 6)));
+/// assert!(!zone.contains(&TilePos::new(6, 6)));
+/// ```
+pub fn zone_of_control(
+    units: impl IntoIterator<Item = TilePos>,
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+    diagonals: bool,
+) -> HashSet<TilePos> {
+    units
+        .into_iter()
+        .flat_map(|unit| adjacent_tiles(&unit, map_size, map_type, diagonals))
+        .map(|(_, tile_pos)| tile_pos)
+        .collect()
+}
+
+/// Returns true if `tile_pos` lies in the zone of control of any of `units`, without building the
+/// whole zone. See [`zone_of_control`].
+pub fn in_zone_of_control(
+    tile_pos: &TilePos,
+    units: impl IntoIterator<Item = TilePos>,
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+    diagonals: bool,
+) -> bool {
+    let units: HashSet<TilePos> = units.into_iter().collect();
+    adjacent_tiles(tile_pos, map_size, map_type, diagonals)
+        .iter()
+        .any(|(_, adjacent)| units.contains(adjacent))
+}
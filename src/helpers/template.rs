@@ -0,0 +1,394 @@
+use crate::map::TilemapId;
+use crate::tiles::{TileBundle, TilePos, TileStorage};
+use bevy::app::{App, Plugin, Update};
+use bevy::asset::{Asset, AssetApp, AssetEvent, AssetId, Assets, Handle};
+use bevy::ecs::reflect::ReflectCommandExt;
+use bevy::hierarchy::BuildChildren;
+use bevy::prelude::{
+    Commands, Component, DetectChanges, Entity, EventReader, PartialReflect, Query, Ref, Reflect,
+    ReflectComponent, Res,
+};
+use bevy::reflect::TypePath;
+use bevy::utils::HashSet;
+
+/// A kind of tile defined as a set of reflected components, e.g. a texture index, a color and
+/// game specific marker components, so that designers can define tiles in data files.
+///
+/// Components are applied to tiles with [`set_tile_from_template`], once the template is loaded.
+/// Every component type must be registered in the app's type registry and reflect `Component`.
+///
+/// With the `serde` feature, templates can be loaded from `.tile.ron` files, mapping the type
+/// paths of components to their values:
+/// ```ron
+/// (
+///     components: {
+///         "bevy_ecs_tilemap::tiles::TileTextureIndex": (5),
+///         "bevy_ecs_tilemap::tiles::TileFlip": (x: true, y: false, d: false),
+///         "my_game::Lava": (damage: 3),
+///     },
+/// )
+/// ```
+///
+/// Templates can also be built in code:
+/// ```
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::helpers::template::TileTemplate;
+/// let lava = TileTemplate::default()
+///     .with(TileTextureIndex(5))
+///     .with(TileFlip { x: true, ..Default::default() });
+/// assert_eq!(lava.components().len(), 2);
+/// ```
+#[derive(Asset, TypePath, Default)]
+pub struct TileTemplate {
+    components: Vec<Box<dyn PartialReflect>>,
+}
+
+impl TileTemplate {
+    /// Adds a component to the template, replacing any component of the same type.
+    pub fn with(mut self, component: impl Reflect) -> Self {
+        self.insert(Box::new(component));
+        self
+    }
+
+    /// Adds a reflected component to the template, replacing any component of the same type.
+    pub fn insert(&mut self, component: Box<dyn PartialReflect>) {
+        let type_path = component.reflect_type_path();
+        self.components
+            .retain(|other| other.reflect_type_path() != type_path);
+        self.components.push(component);
+    }
+
+    /// Returns the components of the template.
+    pub fn components(&self) -> &[Box<dyn PartialReflect>] {
+        &self.components
+    }
+
+    /// Returns `true` if the template has a component with the given type path.
+    pub fn contains(&self, type_path: &str) -> bool {
+        self.components
+            .iter()
+            .any(|component| component.reflect_type_path() == type_path)
+    }
+
+    /// Inserts copies of the template's components into `entity`.
+    pub fn apply(&self, entity: Entity, commands: &mut Commands) {
+        let mut entity_commands = commands.entity(entity);
+        for component in self.components.iter() {
+            entity_commands.insert_reflect(component.clone_value());
+        }
+    }
+}
+
+/// The [`TileTemplate`] a tile was created from. Its components are inserted into the tile when
+/// the template is loaded, and again whenever the template changes. Components which an earlier
+/// version of the template, or the tile's previous template, inserted and the template no longer
+/// has are removed.
+#[derive(Component, Reflect, Clone, Debug, Default, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct TileTemplateHandle(pub Handle<TileTemplate>);
+
+/// The type paths of the components a template inserted into a tile, so that they can be removed
+/// once the template no longer has them.
+#[derive(Component, Clone, Debug, Default)]
+struct AppliedTileTemplate(Vec<&'static str>);
+
+/// Makes the tile at `tile_pos` use `template`, spawning the tile if there is none yet, and
+/// returns the tile entity.
+///
+/// The template's components are inserted by the [`TileTemplatePlugin`] once it is loaded, so
+/// they may arrive a few frames later.
+pub fn set_tile_from_template(
+    tile_pos: TilePos,
+    template: Handle<TileTemplate>,
+    tilemap_id: TilemapId,
+    commands: &mut Commands,
+    tile_storage: &mut TileStorage,
+) -> Entity {
+    if let Some(tile_entity) = tile_storage.checked_get(&tile_pos) {
+        commands
+            .entity(tile_entity)
+            .insert(TileTemplateHandle(template));
+        return tile_entity;
+    }
+    let tile_entity = commands
+        .spawn((
+            TileBundle {
+                position: tile_pos,
+                tilemap_id,
+                ..Default::default()
+            },
+            TileTemplateHandle(template),
+        ))
+        .set_parent(tilemap_id.0)
+        .id();
+    tile_storage.set(&tile_pos, tile_entity);
+    tile_entity
+}
+
+/// Adds the [`TileTemplate`] asset, and with the `serde` feature, its `.tile.ron` loader, and
+/// applies templates to the tiles using them.
+pub struct TileTemplatePlugin;
+
+impl Plugin for TileTemplatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<TileTemplate>()
+            .register_type::<TileTemplateHandle>()
+            .add_systems(Update, apply_tile_templates);
+        #[cfg(feature = "serde")]
+        app.init_asset_loader::<loader::TileTemplateLoader>();
+    }
+}
+
+fn apply_tile_templates(
+    mut commands: Commands,
+    mut template_events: EventReader<AssetEvent<TileTemplate>>,
+    templates: Res<Assets<TileTemplate>>,
+    tiles: Query<(
+        Entity,
+        Ref<TileTemplateHandle>,
+        Option<&AppliedTileTemplate>,
+    )>,
+) {
+    let changed_templates: HashSet<AssetId<TileTemplate>> = template_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (tile_entity, handle, applied) in tiles.iter() {
+        if !handle.is_changed() && !changed_templates.contains(&handle.0.id()) {
+            continue;
+        }
+        let Some(template) = templates.get(&handle.0) else {
+            continue;
+        };
+        let mut tile_commands = commands.entity(tile_entity);
+        for type_path in applied.iter().flat_map(|applied| applied.0.iter()) {
+            if !template.contains(type_path) {
+                tile_commands.remove_reflect(*type_path);
+            }
+        }
+        template.apply(tile_entity, &mut commands);
+        let type_paths = template
+            .components
+            .iter()
+            .filter_map(|component| component.get_represented_type_info())
+            .map(|type_info| type_info.type_path())
+            .collect();
+        commands
+            .entity(tile_entity)
+            .insert(AppliedTileTemplate(type_paths));
+    }
+}
+
+#[cfg(feature = "serde")]
+pub use loader::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::{TileFlip, TileTextureIndex};
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::{AppTypeRegistry, Events, World};
+
+    #[test]
+    fn reapplied_templates_remove_components_they_no_longer_have() {
+        let mut world = World::new();
+        world.init_resource::<AppTypeRegistry>();
+        {
+            let mut registry = world.resource::<AppTypeRegistry>().write();
+            registry.register::<TileTextureIndex>();
+            registry.register::<TileFlip>();
+        }
+        world.init_resource::<Assets<TileTemplate>>();
+        world.init_resource::<Events<AssetEvent<TileTemplate>>>();
+        let flipped = TileFlip {
+            x: true,
+            ..Default::default()
+        };
+        let handle = world.resource_mut::<Assets<TileTemplate>>().add(
+            TileTemplate::default()
+                .with(TileTextureIndex(5))
+                .with(flipped),
+        );
+        let tile = world.spawn(TileTemplateHandle(handle.clone())).id();
+        world.run_system_once(apply_tile_templates).unwrap();
+        assert_eq!(world.get::<TileFlip>(tile), Some(&flipped));
+
+        world
+            .resource_mut::<Assets<TileTemplate>>()
+            .insert(&handle, TileTemplate::default().with(TileTextureIndex(6)));
+        world.send_event(AssetEvent::Modified { id: handle.id() });
+        world.run_system_once(apply_tile_templates).unwrap();
+        assert_eq!(
+            world.get::<TileTextureIndex>(tile),
+            Some(&TileTextureIndex(6))
+        );
+        assert_eq!(world.get::<TileFlip>(tile), None);
+    }
+}
+
+#[cfg(feature = "serde")]
+mod loader {
+    use std::fmt;
+
+    use super::TileTemplate;
+    use bevy::asset::{io::Reader, AssetLoader, LoadContext};
+    use bevy::prelude::{AppTypeRegistry, FromWorld, World};
+    use bevy::reflect::serde::TypedReflectDeserializer;
+    use bevy::reflect::{TypeRegistry, TypeRegistryArc};
+    use serde::de::{DeserializeSeed, Error, MapAccess, Visitor};
+
+    /// Loads a [`TileTemplate`] from a `.tile.ron` file, using the app's type registry.
+    pub struct TileTemplateLoader {
+        type_registry: TypeRegistryArc,
+    }
+
+    impl FromWorld for TileTemplateLoader {
+        fn from_world(world: &mut World) -> Self {
+            Self {
+                type_registry: world.resource::<AppTypeRegistry>().0.clone(),
+            }
+        }
+    }
+
+    /// The error returned when a [`TileTemplate`] could not be loaded.
+    #[derive(Debug)]
+    pub enum TileTemplateLoaderError {
+        Io(std::io::Error),
+        Ron(ron::error::SpannedError),
+    }
+
+    impl fmt::Display for TileTemplateLoaderError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                TileTemplateLoaderError::Io(error) => {
+                    write!(f, "could not read tile template: {error}")
+                }
+                TileTemplateLoaderError::Ron(error) => {
+                    write!(f, "could not parse tile template: {error}")
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for TileTemplateLoaderError {}
+
+    impl From<std::io::Error> for TileTemplateLoaderError {
+        fn from(error: std::io::Error) -> Self {
+            TileTemplateLoaderError::Io(error)
+        }
+    }
+
+    impl From<ron::error::SpannedError> for TileTemplateLoaderError {
+        fn from(error: ron::error::SpannedError) -> Self {
+            TileTemplateLoaderError::Ron(error)
+        }
+    }
+
+    impl AssetLoader for TileTemplateLoader {
+        type Asset = TileTemplate;
+        type Settings = ();
+        type Error = TileTemplateLoaderError;
+
+        async fn load(
+            &self,
+            reader: &mut dyn Reader,
+            _settings: &Self::Settings,
+            _load_context: &mut LoadContext<'_>,
+        ) -> Result<Self::Asset, Self::Error> {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let mut deserializer = ron::de::Deserializer::from_bytes(&bytes)?;
+            let registry = self.type_registry.read();
+            let template = TemplateDeserializer(&registry)
+                .deserialize(&mut deserializer)
+                .map_err(|error| deserializer.span_error(error))?;
+            deserializer
+                .end()
+                .map_err(|error| deserializer.span_error(error))?;
+            Ok(template)
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["tile.ron"]
+        }
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(field_identifier, rename_all = "snake_case")]
+    enum TemplateField {
+        Components,
+    }
+
+    /// Deserializes the `(components: { ... })` struct of a template file.
+    struct TemplateDeserializer<'a>(&'a TypeRegistry);
+
+    impl<'de> DeserializeSeed<'de> for TemplateDeserializer<'_> {
+        type Value = TileTemplate;
+
+        fn deserialize<D: serde::Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_struct("TileTemplate", &["components"], self)
+        }
+    }
+
+    impl<'de> Visitor<'de> for TemplateDeserializer<'_> {
+        type Value = TileTemplate;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a tile template")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut template = TileTemplate::default();
+            while let Some(field) = map.next_key::<TemplateField>()? {
+                match field {
+                    TemplateField::Components => {
+                        template = map.next_value_seed(ComponentsDeserializer(self.0))?;
+                    }
+                }
+            }
+            Ok(template)
+        }
+    }
+
+    /// Deserializes the map from component type paths to component values.
+    struct ComponentsDeserializer<'a>(&'a TypeRegistry);
+
+    impl<'de> DeserializeSeed<'de> for ComponentsDeserializer<'_> {
+        type Value = TileTemplate;
+
+        fn deserialize<D: serde::Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_map(self)
+        }
+    }
+
+    impl<'de> Visitor<'de> for ComponentsDeserializer<'_> {
+        type Value = TileTemplate;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map of component type paths to components")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut template = TileTemplate::default();
+            while let Some(type_path) = map.next_key::<String>()? {
+                let registration = self.0.get_with_type_path(&type_path).ok_or_else(|| {
+                    A::Error::custom(format!("no type registered for `{type_path}`"))
+                })?;
+                let component =
+                    map.next_value_seed(TypedReflectDeserializer::new(registration, self.0))?;
+                template.insert(component);
+            }
+            Ok(template)
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use std::fmt;
+
+use crate::map::{TilemapId, TilemapSize};
+use crate::tiles::{
+    AnimatedTile, AnimatedTileFrames, AnimationState, TileBundle, TilePos, TileRect, TileStorage,
+    TileTextureIndex,
+};
+use bevy::ecs::system::EntityCommands;
+use bevy::hierarchy::{BuildChildren, DespawnRecursiveExt};
+use bevy::prelude::{ChildBuild, Commands, Component, Entity, Reflect, ReflectComponent};
+
+/// Associates several tiles with one logical structure, e.g. a 3x3 building.
+///
+/// The component lives on its own entity, and the member tiles are spawned as children of the
+/// tilemap as usual. Use [`place_tile_group`], [`move_tile_group`] and [`remove_tile_group`] to
+/// keep the group and the [`TileStorage`] in sync.
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+pub struct TileGroup {
+    /// The tilemap the group belongs to.
+    pub tilemap_id: TilemapId,
+    /// The position the cell offsets are relative to.
+    pub origin: TilePos,
+    /// The offset of each member tile from `origin`.
+    pub cells: Vec<TilePos>,
+    /// The member tile entities, in the same order as `cells`.
+    pub tiles: Vec<Entity>,
+}
+
+impl TileGroup {
+    /// Returns the current position of each member tile.
+    pub fn positions(&self) -> impl Iterator<Item = TilePos> + '_ {
+        self.cells.iter().map(|cell| offset(self.origin, *cell))
+    }
+}
+
+/// Creates the cell offsets of a rectangular group of `width` by `height` tiles.
+pub fn rect_cells(width: u32, height: u32) -> Vec<TilePos> {
+    TileRect::from_map_size(TilemapSize {
+        x: width,
+        y: height,
+    })
+    .iter()
+    .collect()
+}
+
+/// The reason a tile group could not be placed or moved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileGroupError {
+    /// A cell would lie outside of the tilemap.
+    OutOfBounds(TilePos),
+    /// A cell is already occupied by another tile.
+    Occupied(TilePos),
+    /// An [`AnimatedTileStamp`] has no frames, or frames that aren't shown for any time.
+    InvalidAnimation,
+}
+
+impl fmt::Display for TileGroupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TileGroupError::OutOfBounds(pos) => {
+                write!(f, "tile position ({}, {}) is out of bounds", pos.x, pos.y)
+            }
+            TileGroupError::Occupied(pos) => {
+                write!(
+                    f,
+                    "tile position ({}, {}) is already occupied",
+                    pos.x, pos.y
+                )
+            }
+            TileGroupError::InvalidAnimation => {
+                write!(f, "the animation has no frames or a frame duration of zero")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TileGroupError {}
+
+/// Checks that every cell of a group placed at `origin` is on the map and free.
+///
+/// Tiles listed in `ignore` count as free, which allows a group to be checked against its own
+/// current position.
+pub fn validate_tile_group(
+    origin: TilePos,
+    cells: &[TilePos],
+    tile_storage: &TileStorage,
+    ignore: &[Entity],
+) -> Result<(), TileGroupError> {
+    for cell in cells {
+        let tile_pos = offset(origin, *cell);
+        if !tile_pos.within_map_bounds(&tile_storage.size) {
+            return Err(TileGroupError::OutOfBounds(tile_pos));
+        }
+        if let Some(tile_entity) = tile_storage.get(&tile_pos) {
+            if !ignore.contains(&tile_entity) {
+                return Err(TileGroupError::Occupied(tile_pos));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Spawns a tile for every cell of a group at `origin`, along with the entity holding the
+/// [`TileGroup`], which is returned.
+///
+/// Each cell is given as an offset from `origin` and the texture of its tile. Nothing is spawned
+/// unless every cell is on the map and free.
+pub fn place_tile_group(
+    origin: TilePos,
+    cells: &[(TilePos, TileTextureIndex)],
+    tilemap_id: TilemapId,
+    commands: &mut Commands,
+    tile_storage: &mut TileStorage,
+) -> Result<Entity, TileGroupError> {
+    spawn_tile_group(origin, cells, tilemap_id, commands, tile_storage, |_, _| {})
+}
+
+/// A multi-tile object whose tiles are animated together, e.g. a 2x3 waterfall.
+///
+/// Frame `k` of a cell shows the texture index of its first frame plus `k * frame_stride`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimatedTileStamp {
+    /// The offset of each cell from the origin, and its texture in the first frame.
+    pub cells: Vec<(TilePos, TileTextureIndex)>,
+    /// The number of frames of the animation.
+    pub frame_count: u32,
+    /// How far the texture indices of a frame are from those of the previous frame.
+    pub frame_stride: u32,
+    /// How long each frame is shown, in seconds.
+    pub frame_duration: f32,
+}
+
+impl AnimatedTileStamp {
+    /// Creates a `width` by `height` stamp from a tileset `columns` tiles wide, with the frames
+    /// of the object side by side. `first` is the texture index of the top left tile of the
+    /// first frame.
+    ///
+    /// ```
+    /// # use bevy_ecs_tilemap::prelude::*;
+    /// # use bevy_ecs_tilemap::helpers::tile_group::AnimatedTileStamp;
+    /// // A 2x3 waterfall with 4 frames, in a tileset 16 tiles wide.
+    /// let waterfall = AnimatedTileStamp::from_tileset(32, 2, 3, 16, 4, 0.15);
+    /// // The bottom left tile of the first frame is two rows below the top left one.
+    /// assert_eq!(waterfall.cells[0], (TilePos::new(0, 0), TileTextureIndex(64)));
+    /// assert_eq!(waterfall.frame_stride, 2);
+    /// ```
+    pub fn from_tileset(
+        first: u32,
+        width: u32,
+        height: u32,
+        columns: u32,
+        frame_count: u32,
+        frame_duration: f32,
+    ) -> Self {
+        let cells = rect_cells(width, height)
+            .into_iter()
+            .map(|cell| {
+                // Tileset rows go down, tile positions go up.
+                let row = height - 1 - cell.y;
+                (cell, TileTextureIndex(first + row * columns + cell.x))
+            })
+            .collect();
+        Self {
+            cells,
+            frame_count,
+            frame_stride: width,
+            frame_duration,
+        }
+    }
+}
+
+/// Spawns the tiles of an [`AnimatedTileStamp`] at `origin` as a [`TileGroup`], which is
+/// returned, so that the object can be moved and removed as a unit with [`move_tile_group`] and
+/// [`remove_tile_group`].
+///
+/// The tiles are given an [`AnimatedTile`], or an [`AnimatedTileFrames`] for strides other than
+/// 1, along with an [`AnimationState`] so that they play in lockstep from their first frame.
+/// Nothing is spawned unless every cell is on the map and free, and the stamp has at least one
+/// frame with a positive duration.
+pub fn place_animated_tile_group(
+    origin: TilePos,
+    stamp: &AnimatedTileStamp,
+    tilemap_id: TilemapId,
+    commands: &mut Commands,
+    tile_storage: &mut TileStorage,
+) -> Result<Entity, TileGroupError> {
+    if stamp.frame_count == 0 || stamp.frame_duration.is_nan() || stamp.frame_duration <= 0.0 {
+        return Err(TileGroupError::InvalidAnimation);
+    }
+    spawn_tile_group(
+        origin,
+        &stamp.cells,
+        tilemap_id,
+        commands,
+        tile_storage,
+        |tile, texture_index| {
+            let start = texture_index.0;
+            if stamp.frame_stride == 1 {
+                tile.insert(AnimatedTile {
+                    start,
+                    end: start + stamp.frame_count,
+                    speed: 1.0 / (stamp.frame_count as f32 * stamp.frame_duration),
+                });
+            } else {
+                tile.insert(AnimatedTileFrames::uniform(
+                    (0..stamp.frame_count).map(|frame| start + frame * stamp.frame_stride),
+                    stamp.frame_duration,
+                ));
+            }
+            tile.insert(AnimationState::default());
+        },
+    )
+}
+
+fn spawn_tile_group(
+    origin: TilePos,
+    cells: &[(TilePos, TileTextureIndex)],
+    tilemap_id: TilemapId,
+    commands: &mut Commands,
+    tile_storage: &mut TileStorage,
+    mut insert: impl FnMut(&mut EntityCommands, TileTextureIndex),
+) -> Result<Entity, TileGroupError> {
+    let offsets: Vec<TilePos> = cells.iter().map(|(cell, _)| *cell).collect();
+    validate_tile_group(origin, &offsets, tile_storage, &[])?;
+
+    let mut tiles = Vec::with_capacity(cells.len());
+    commands.entity(tilemap_id.0).with_children(|parent| {
+        for (cell, texture_index) in cells {
+            let tile_pos = offset(origin, *cell);
+            let mut tile = parent.spawn(TileBundle {
+                position: tile_pos,
+                tilemap_id,
+                texture_index: *texture_index,
+                ..Default::default()
+            });
+            insert(&mut tile, *texture_index);
+            let tile_entity = tile.id();
+            tile_storage.set(&tile_pos, tile_entity);
+            tiles.push(tile_entity);
+        }
+    });
+
+    Ok(commands
+        .spawn(TileGroup {
+            tilemap_id,
+            origin,
+            cells: offsets,
+            tiles,
+        })
+        .id())
+}
+
+/// Moves every member tile of a group so that the group's origin ends up at `new_origin`.
+///
+/// Nothing is moved unless every new cell is on the map and either free or already part of the
+/// group.
+pub fn move_tile_group(
+    group: &mut TileGroup,
+    new_origin: TilePos,
+    commands: &mut Commands,
+    tile_storage: &mut TileStorage,
+) -> Result<(), TileGroupError> {
+    validate_tile_group(new_origin, &group.cells, tile_storage, &group.tiles)?;
+
+    for tile_pos in group.positions() {
+        tile_storage.remove(&tile_pos);
+    }
+    group.origin = new_origin;
+    for (tile_pos, tile_entity) in group.positions().zip(group.tiles.iter()) {
+        tile_storage.set(&tile_pos, *tile_entity);
+        commands.entity(*tile_entity).insert(tile_pos);
+    }
+
+    Ok(())
+}
+
+/// Despawns a group entity along with all of its member tiles.
+pub fn remove_tile_group(
+    group_entity: Entity,
+    group: &TileGroup,
+    commands: &mut Commands,
+    tile_storage: &mut TileStorage,
+) {
+    for (tile_pos, tile_entity) in group.positions().zip(group.tiles.iter()) {
+        if tile_storage.checked_get(&tile_pos) == Some(*tile_entity) {
+            tile_storage.remove(&tile_pos);
+        }
+        commands.entity(*tile_entity).despawn_recursive();
+    }
+    commands.entity(group_entity).despawn();
+}
+
+fn offset(origin: TilePos, cell: TilePos) -> TilePos {
+    TilePos {
+        x: origin.x + cell.x,
+        y: origin.y + cell.y,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::world::CommandQueue;
+    use bevy::prelude::World;
+
+    use super::*;
+
+    #[test]
+    fn animated_stamps_without_duration_are_rejected() {
+        let mut world = World::new();
+        let tilemap = world.spawn_empty().id();
+        let mut tile_storage = TileStorage::empty(TilemapSize { x: 8, y: 8 });
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+
+        for (frame_count, frame_duration) in [(0, 0.2), (4, 0.0), (4, -1.0), (4, f32::NAN)] {
+            let stamp = AnimatedTileStamp::from_tileset(0, 2, 2, 8, frame_count, frame_duration);
+            let result = place_animated_tile_group(
+                TilePos::new(1, 1),
+                &stamp,
+                TilemapId(tilemap),
+                &mut commands,
+                &mut tile_storage,
+            );
+            assert_eq!(result, Err(TileGroupError::InvalidAnimation));
+        }
+        assert!(tile_storage.iter().all(Option::is_none));
+
+        let stamp = AnimatedTileStamp::from_tileset(0, 1, 2, 8, 4, 0.2);
+        place_animated_tile_group(
+            TilePos::new(1, 1),
+            &stamp,
+            TilemapId(tilemap),
+            &mut commands,
+            &mut tile_storage,
+        )
+        .unwrap();
+        queue.apply(&mut world);
+        let tile = tile_storage.get(&TilePos::new(1, 1)).unwrap();
+        let animation = world.get::<AnimatedTile>(tile).unwrap();
+        assert_eq!(animation.speed, 1.0 / 0.8);
+    }
+}
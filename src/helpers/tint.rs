@@ -0,0 +1,132 @@
+use crate::map::TilemapColor;
+use bevy::app::{App, Plugin, Update};
+use bevy::asset::{Asset, AssetApp, Assets, Handle};
+use bevy::color::{Color, LinearRgba, Mix};
+use bevy::prelude::{Commands, Component, Entity, IntoSystemConfigs, Query, Res, ResMut, Resource};
+use bevy::reflect::TypePath;
+use bevy::time::Time;
+
+/// Keyframed colors over a day cycle, used to tint tilemaps with [`TilemapTintCurveHandle`].
+///
+/// Keyframe times are in the range `0.0..1.0`. The curve wraps around, so the last keyframe
+/// blends into the first one at the end of the day.
+#[derive(Asset, TypePath, Clone, Debug, Default)]
+pub struct TilemapTintCurve {
+    keyframes: Vec<(f32, Color)>,
+}
+
+impl TilemapTintCurve {
+    /// Creates a curve from `(time_of_day, color)` keyframes, in any order.
+    pub fn new(mut keyframes: Vec<(f32, Color)>) -> Self {
+        keyframes.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Self { keyframes }
+    }
+
+    /// Returns the keyframes, sorted by time.
+    pub fn keyframes(&self) -> &[(f32, Color)] {
+        &self.keyframes
+    }
+
+    /// Returns the color at `time_of_day`, blending linearly between keyframes.
+    ///
+    /// An empty curve is always white.
+    ///
+    /// Example:
+    /// ```
+    /// # use bevy::color::{Color, LinearRgba};
+    /// # use bevy_ecs_tilemap::helpers::tint::TilemapTintCurve;
+    /// let curve = TilemapTintCurve::new(vec![
+    ///     (0.25, Color::WHITE),
+    ///     (0.75, Color::BLACK),
+    /// ]);
+    /// assert_eq!(curve.sample(0.25), Color::WHITE);
+    /// // Halfway between the evening and the next morning.
+    /// assert_eq!(LinearRgba::from(curve.sample(0.0)), LinearRgba::rgb(0.5, 0.5, 0.5));
+    /// ```
+    pub fn sample(&self, time_of_day: f32) -> Color {
+        let (Some(first), Some(last)) = (self.keyframes.first(), self.keyframes.last()) else {
+            return Color::WHITE;
+        };
+        let time_of_day = time_of_day.rem_euclid(1.0);
+
+        // Find the keyframes on either side, wrapping around the end of the day.
+        let next_index = self
+            .keyframes
+            .iter()
+            .position(|(time, _)| *time > time_of_day);
+        let (previous, next) = match next_index {
+            Some(0) => ((last.0 - 1.0, last.1), *first),
+            None => (*last, (first.0 + 1.0, first.1)),
+            Some(index) => (self.keyframes[index - 1], self.keyframes[index]),
+        };
+
+        let span = next.0 - previous.0;
+        let factor = if span > 0.0 {
+            (time_of_day - previous.0) / span
+        } else {
+            0.0
+        };
+        LinearRgba::from(previous.1)
+            .mix(&LinearRgba::from(next.1), factor)
+            .into()
+    }
+}
+
+/// Drives the [`TilemapColor`] of a tilemap from a [`TilemapTintCurve`] and the [`DayClock`].
+///
+/// Requires the [`TilemapTintPlugin`].
+#[derive(Component, Clone, Debug, Default)]
+pub struct TilemapTintCurveHandle(pub Handle<TilemapTintCurve>);
+
+/// The global time of day used to sample [`TilemapTintCurve`]s.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct DayClock {
+    /// The current time of day, in the range `0.0..1.0`.
+    pub time_of_day: f32,
+    /// If set, the clock advances on its own and completes a day in this many seconds. Otherwise
+    /// `time_of_day` is left to be set by the user.
+    pub seconds_per_day: Option<f32>,
+}
+
+/// Adds [`TilemapTintCurve`] assets, the [`DayClock`], and the systems tinting tilemaps.
+pub struct TilemapTintPlugin;
+
+impl Plugin for TilemapTintPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<TilemapTintCurve>()
+            .init_resource::<DayClock>()
+            .add_systems(Update, (advance_day_clock, apply_tint_curves).chain());
+    }
+}
+
+fn advance_day_clock(time: Res<Time>, mut clock: ResMut<DayClock>) {
+    if let Some(seconds_per_day) = clock.seconds_per_day.filter(|seconds| *seconds > 0.0) {
+        clock.time_of_day =
+            (clock.time_of_day + time.delta_secs() / seconds_per_day).rem_euclid(1.0);
+    }
+}
+
+fn apply_tint_curves(
+    mut commands: Commands,
+    clock: Res<DayClock>,
+    curves: Res<Assets<TilemapTintCurve>>,
+    mut tilemap_query: Query<(Entity, &TilemapTintCurveHandle, Option<&mut TilemapColor>)>,
+) {
+    for (entity, handle, color) in tilemap_query.iter_mut() {
+        let Some(curve) = curves.get(&handle.0) else {
+            continue;
+        };
+        let tint = curve.sample(clock.time_of_day);
+        match color {
+            // Only touch the component when the color changes, to avoid needless re-extraction.
+            Some(mut color) => {
+                if color.0 != tint {
+                    color.0 = tint;
+                }
+            }
+            None => {
+                commands.entity(entity).insert(TilemapColor(tint));
+            }
+        }
+    }
+}
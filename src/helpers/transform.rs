@@ -3,7 +3,66 @@ use crate::{TilemapGridSize, TilemapTileSize, TilemapType};
 use bevy::math::{UVec2, Vec2, Vec3};
 use bevy::render::primitives::Aabb;
 
+/// Calculates the index of the render chunk of `chunk_size` tiles that `tile_pos` is drawn in.
+///
+/// The chunk size is the `render_chunk_size` of the tilemap's
+/// [`TilemapRenderSettings`](crate::map::TilemapRenderSettings). Chunks are laid out over y-up
+/// grid positions, so tilemaps with other [`TilemapAxes`](crate::map::TilemapAxes) must convert
+/// their tile positions with [`TilemapAxes::to_grid_pos`](crate::map::TilemapAxes::to_grid_pos)
+/// first.
+///
+/// ```
+/// # use bevy::math::UVec2;
+/// # use bevy_ecs_tilemap::helpers::transform::map_tile_to_chunk;
+/// # use bevy_ecs_tilemap::tiles::TilePos;
+/// let chunk_size = UVec2::new(32, 16);
+/// assert_eq!(map_tile_to_chunk(&TilePos::new(40, 10), chunk_size), UVec2::new(1, 0));
+/// assert_eq!(map_tile_to_chunk(&TilePos::new(31, 16), chunk_size), UVec2::new(0, 1));
+/// ```
+#[inline]
+pub fn map_tile_to_chunk(tile_pos: &TilePos, chunk_size: UVec2) -> UVec2 {
+    UVec2::from(tile_pos) / chunk_size
+}
+
+/// Calculates the position of `tile_pos` within the render chunk of `chunk_size` tiles at
+/// `chunk_index`, as returned by [`map_tile_to_chunk`].
+///
+/// ```
+/// # use bevy::math::UVec2;
+/// # use bevy_ecs_tilemap::helpers::transform::{map_tile_to_chunk, map_tile_to_chunk_tile};
+/// # use bevy_ecs_tilemap::tiles::TilePos;
+/// let chunk_size = UVec2::new(32, 16);
+/// let tile_pos = TilePos::new(40, 10);
+/// let chunk_index = map_tile_to_chunk(&tile_pos, chunk_size);
+/// assert_eq!(
+///     map_tile_to_chunk_tile(&tile_pos, chunk_index, chunk_size),
+///     UVec2::new(8, 10)
+/// );
+/// ```
+#[inline]
+pub fn map_tile_to_chunk_tile(tile_pos: &TilePos, chunk_index: UVec2, chunk_size: UVec2) -> UVec2 {
+    UVec2::from(tile_pos) - chunk_index * chunk_size
+}
+
 /// Calculates the world-space position of the bottom-left of the specified chunk.
+///
+/// This is the center of the chunk's bottom-left tile, relative to the tilemap's transform. It is
+/// the translation render chunks are drawn at, so effects can be aligned to them by adding the
+/// tilemap's transform.
+///
+/// ```
+/// # use bevy::math::{UVec2, Vec2};
+/// # use bevy_ecs_tilemap::helpers::transform::chunk_index_to_world_space;
+/// # use bevy_ecs_tilemap::map::{TilemapGridSize, TilemapType};
+/// let grid_size = TilemapGridSize { x: 16.0, y: 16.0 };
+/// let position = chunk_index_to_world_space(
+///     UVec2::new(1, 2),
+///     UVec2::new(32, 32),
+///     &grid_size,
+///     &TilemapType::Square,
+/// );
+/// assert_eq!(position, Vec2::new(512.0, 1024.0));
+/// ```
 pub fn chunk_index_to_world_space(
     chunk_index: UVec2,
     chunk_size: UVec2,
@@ -25,7 +84,24 @@ pub fn chunk_index_to_world_space(
 /// `(chunk_x_size_in_world_space, chunk_y_size_in_world_space, 1.0)`.
 ///
 /// Note that the AABB must be transformed by a chunk's actual position in order for it to be
-/// useful.
+/// useful, i.e. offset by [`chunk_index_to_world_space`] and then by the tilemap's transform.
+///
+/// ```
+/// # use bevy::math::{UVec2, Vec3, Vec3A};
+/// # use bevy_ecs_tilemap::helpers::transform::{chunk_aabb, chunk_index_to_world_space};
+/// # use bevy_ecs_tilemap::map::{TilemapGridSize, TilemapTileSize, TilemapType};
+/// let chunk_size = UVec2::new(32, 32);
+/// let grid_size = TilemapGridSize { x: 16.0, y: 16.0 };
+/// let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
+/// let map_type = TilemapType::Square;
+/// let aabb = chunk_aabb(chunk_size, &grid_size, &tile_size, &map_type);
+///
+/// // The AABB covers the tiles of the chunk, with a border of one tile.
+/// let chunk_origin = chunk_index_to_world_space(UVec2::new(1, 0), chunk_size, &grid_size, &map_type);
+/// let min = Vec3A::from(chunk_origin.extend(0.0)) + aabb.min();
+/// assert_eq!(min, Vec3A::new(496.0, -16.0, 0.0));
+/// assert_eq!(aabb.max(), Vec3A::new(528.0, 528.0, 1.0));
+/// ```
 pub fn chunk_aabb(
     chunk_size: UVec2,
     grid_size: &TilemapGridSize,
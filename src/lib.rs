@@ -16,27 +16,50 @@
 
 use bevy::{
     prelude::{
-        Bundle, Changed, Component, Deref, First, GlobalTransform, InheritedVisibility,
-        IntoSystemConfigs, IntoSystemSetConfigs, Plugin, Query, Reflect, ReflectComponent,
-        SystemSet, Transform, ViewVisibility, Visibility,
+        resource_exists, Bundle, Changed, Component, Deref, First, GlobalTransform,
+        InheritedVisibility, IntoSystemConfigs, IntoSystemSetConfigs, Plugin, PostUpdate, Query,
+        Reflect, ReflectComponent, Res, Resource, SystemSet, Transform, TransformSystem, Update,
+        ViewVisibility, Visibility,
     },
     render::sync_world::SyncToRenderWorld,
     time::TimeSystem,
 };
 
+use helpers::layer_stack::{update_layer_occlusion, LayerStack, TileOccluded, TileOpaque};
+use helpers::layers::{
+    invalidate_composed_tilemaps, invalidate_removed_composition, LayerIndex, TilemapComposition,
+    TilemapLayers,
+};
+use helpers::path::TilePathConnections;
+use helpers::platform::{
+    carry_tilemap_riders, update_tilemap_transform_deltas, TilemapRider, TilemapTransformDelta,
+};
+use helpers::tile_group::TileGroup;
+
 #[cfg(feature = "render")]
 use render::material::MaterialTilemapHandle;
 
 use map::{
-    TilemapGridSize, TilemapSize, TilemapSpacing, TilemapTexture, TilemapTextureSize,
-    TilemapTileSize, TilemapType,
+    ChunkZPolicy, TilemapAnimationPhase, TilemapAxes, TilemapBlendMode, TilemapClipRect,
+    TilemapColor, TilemapGridSize, TilemapLocked, TilemapRenderMode, TilemapSize, TilemapSpacing,
+    TilemapStatic, TilemapTexture, TilemapTextureSize, TilemapTileSize, TilemapType,
+    TilemapUvInset,
 };
 use prelude::{TilemapId, TilemapRenderSettings};
 #[cfg(feature = "render")]
 use render::material::{MaterialTilemap, StandardTilemapMaterial};
 use tiles::{
-    AnimatedTile, TileColor, TileFlip, TilePos, TilePosOld, TileStorage, TileTextureIndex,
-    TileVisible,
+    assign_tile_stable_ids, check_tile_texture_indices, clear_removed_tile_slots,
+    record_dirty_chunks, record_placed_tiles, record_removed_dirty_tile, record_removed_tile,
+    refresh_removed_animation_states, refresh_removed_color_animations,
+    refresh_removed_sort_biases, refresh_removed_tile_motions, refresh_removed_tile_transforms,
+    refresh_removed_tile_vertex_data, refresh_tile_render_sizes, sync_chunked_tilemaps,
+    texture_index_check_enabled, trigger_tile_hooks_on_insert, trigger_tile_hooks_on_replace,
+    update_animation_states, update_tile_motions, AnimatedTile, AnimatedTileFrames, AnimationState,
+    ChunkedTilemapChunk, DirtyTileChunks, RecentTileChanges, TileCollisionShape, TileColor,
+    TileColorAnimation, TileFlip, TileFlow, TileMotion, TilePos, TilePosOffset, TilePosOld,
+    TileRenderSize, TileRotation, TileSortBias, TileStableId, TileStableIdAllocator, TileStorage,
+    TileTextureIndex, TileVertexData, TileVisible,
 };
 
 #[cfg(all(not(feature = "atlas"), feature = "render"))]
@@ -51,9 +74,15 @@ pub mod helpers;
 pub mod map;
 #[cfg(feature = "render")]
 pub(crate) mod render;
+/// A module for building small tilemaps in a bare `World` or a headless `App`, useful in tests
+/// and doctests.
+pub mod test_utils;
 /// A module which contains tile components.
 pub mod tiles;
 
+#[cfg(feature = "render")]
+pub use render::culling_debug::{ChunkAabb, TilemapCullingDebug, TilemapCullingDebugPlugin};
+
 /// A bevy tilemap plugin. This must be included in order for everything to be rendered.
 /// But is not necessary if you are running without a renderer.
 pub struct TilemapPlugin;
@@ -61,9 +90,16 @@ pub struct TilemapPlugin;
 impl Plugin for TilemapPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         #[cfg(feature = "render")]
-        app.add_plugins(render::TilemapRenderingPlugin);
+        app.add_plugins(render::TilemapRenderingPlugin)
+            .add_event::<helpers::snapshot::TilemapSnapshotReady>()
+            .add_systems(
+                PostUpdate,
+                helpers::snapshot::frame_snapshot_cameras
+                    .after(TransformSystem::TransformPropagate)
+                    .before(bevy::render::camera::CameraUpdateSystem),
+            );
 
-        app.add_systems(First, update_changed_tile_positions.in_set(TilemapFirstSet));
+        app.add_plugins(TilemapCorePlugin);
 
         #[cfg(all(not(feature = "atlas"), feature = "render"))]
         {
@@ -71,6 +107,85 @@ impl Plugin for TilemapPlugin {
             let render_app = app.sub_app_mut(RenderApp);
             render_app.add_systems(ExtractSchedule, array_texture_preload::extract);
         }
+    }
+}
+
+/// The part of [`TilemapPlugin`] that doesn't render anything: the storage, position and
+/// transform tracking systems, and the type registrations.
+///
+/// It is added by [`TilemapPlugin`], so only add it on its own in apps without a renderer, e.g.
+/// servers or tests. See [`MinimalTilemapPlugins`](test_utils::MinimalTilemapPlugins).
+pub struct TilemapCorePlugin;
+
+impl Plugin for TilemapCorePlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<TilemapPluginConfig>();
+
+        app.add_systems(
+            First,
+            update_changed_tile_positions.in_set(TilemapSystemSet::PositionSync),
+        );
+        app.add_systems(
+            PostUpdate,
+            (
+                assign_tile_stable_ids.run_if(resource_exists::<TileStableIdAllocator>),
+                record_placed_tiles.run_if(resource_exists::<RecentTileChanges>),
+                sync_chunked_tilemaps.before(TransformSystem::TransformPropagate),
+            )
+                .in_set(TilemapSystemSet::StorageMaintenance),
+        );
+        app.add_observer(record_removed_tile)
+            .add_observer(record_removed_dirty_tile)
+            .add_observer(clear_removed_tile_slots)
+            .add_observer(invalidate_removed_composition)
+            .add_observer(map::thaw_static_tilemaps);
+        app.add_observer(trigger_tile_hooks_on_insert)
+            .add_observer(trigger_tile_hooks_on_replace);
+        app.add_systems(
+            PostUpdate,
+            (
+                update_layer_occlusion,
+                refresh_removed_color_animations,
+                update_animation_states,
+                refresh_removed_animation_states,
+                refresh_removed_sort_biases,
+                update_tile_motions,
+                refresh_removed_tile_motions,
+                refresh_removed_tile_vertex_data,
+                refresh_removed_tile_transforms,
+                refresh_tile_render_sizes,
+                invalidate_composed_tilemaps,
+            )
+                .in_set(TilemapSystemSet::ExtractionPrep),
+        );
+        #[cfg(debug_assertions)]
+        app.add_systems(
+            PostUpdate,
+            (
+                map::warn_irregular_hex_grids,
+                map::warn_locked_tilemap_changes,
+            )
+                .in_set(TilemapSystemSet::ExtractionPrep),
+        );
+        app.add_systems(
+            PostUpdate,
+            check_tile_texture_indices
+                .run_if(texture_index_check_enabled)
+                .in_set(TilemapSystemSet::ExtractionPrep),
+        );
+        app.add_systems(
+            PostUpdate,
+            record_dirty_chunks
+                .run_if(resource_exists::<DirtyTileChunks>)
+                .after(TransformSystem::TransformPropagate)
+                .in_set(TilemapSystemSet::ExtractionPrep),
+        );
+        app.add_systems(
+            PostUpdate,
+            (update_tilemap_transform_deltas, carry_tilemap_riders)
+                .chain()
+                .in_set(TilemapSystemSet::TransformTracking),
+        );
 
         app.register_type::<FrustumCulling>()
             .register_type::<TilemapId>()
@@ -81,21 +196,148 @@ impl Plugin for TilemapPlugin {
             .register_type::<TilemapSpacing>()
             .register_type::<TilemapTextureSize>()
             .register_type::<TilemapType>()
+            .register_type::<TilemapColor>()
+            .register_type::<TilemapAnimationPhase>()
+            .register_type::<TilemapUvInset>()
+            .register_type::<TilemapLocked>()
+            .register_type::<TilemapStatic>()
+            .register_type::<TilemapBlendMode>()
+            .register_type::<TilemapRenderMode>()
+            .register_type::<ChunkZPolicy>()
+            .register_type::<TilemapClipRect>()
+            .register_type::<TilemapAxes>()
             .register_type::<TilePos>()
             .register_type::<TileTextureIndex>()
             .register_type::<TileColor>()
             .register_type::<TileVisible>()
             .register_type::<TileFlip>()
             .register_type::<TileStorage>()
+            .register_type::<ChunkedTilemapChunk>()
             .register_type::<TilePosOld>()
             .register_type::<AnimatedTile>()
-            .configure_sets(First, TilemapFirstSet.after(TimeSystem));
+            .register_type::<AnimatedTileFrames>()
+            .register_type::<AnimationState>()
+            .register_type::<TileColorAnimation>()
+            .register_type::<TileSortBias>()
+            .register_type::<TileMotion>()
+            .register_type::<TileVertexData>()
+            .register_type::<TilePosOffset>()
+            .register_type::<TileRotation>()
+            .register_type::<TileRenderSize>()
+            .register_type::<TileCollisionShape>()
+            .register_type::<TilemapTransformDelta>()
+            .register_type::<TilemapRider>()
+            .register_type::<TileGroup>()
+            .register_type::<TilePathConnections>()
+            .register_type::<LayerStack>()
+            .register_type::<TileOpaque>()
+            .register_type::<TileOccluded>()
+            .register_type::<TilemapLayers>()
+            .register_type::<LayerIndex>()
+            .register_type::<TilemapComposition>()
+            .register_type::<TileFlow>()
+            .register_type::<TileStableId>()
+            .register_type::<TileStableIdAllocator>()
+            .configure_sets(First, TilemapFirstSet.after(TimeSystem))
+            .configure_sets(
+                First,
+                (
+                    TilemapSystemSet::PositionSync
+                        .run_if(config_enabled(|config| config.position_sync)),
+                    TilemapSystemSet::StorageMaintenance
+                        .run_if(config_enabled(|config| config.storage_maintenance)),
+                )
+                    .in_set(TilemapFirstSet),
+            )
+            .configure_sets(
+                Update,
+                TilemapSystemSet::ExtractionPrep
+                    .run_if(config_enabled(|config| config.extraction_prep)),
+            )
+            .configure_sets(
+                PostUpdate,
+                (
+                    TilemapSystemSet::StorageMaintenance
+                        .run_if(config_enabled(|config| config.storage_maintenance)),
+                    TilemapSystemSet::ExtractionPrep
+                        .run_if(config_enabled(|config| config.extraction_prep)),
+                    TilemapSystemSet::TransformTracking
+                        .run_if(config_enabled(|config| config.transform_tracking))
+                        .after(TransformSystem::TransformPropagate),
+                ),
+            );
     }
 }
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TilemapFirstSet;
 
+/// The system sets used by [`TilemapPlugin`], so that user systems can be ordered relative to
+/// them.
+///
+/// Sets running in `First` are also part of [`TilemapFirstSet`].
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TilemapSystemSet {
+    /// Keeps [`TilePosOld`] in sync with [`TilePos`]. Runs in `First`.
+    PositionSync,
+    /// Cleans up after removed tiles and tilemaps, assigns stable tile ids and records recent
+    /// tile changes. Runs in `First` and `PostUpdate`.
+    StorageMaintenance,
+    /// Prepares main world data for extraction, e.g. textures and invalidated tilemaps. Runs in
+    /// `Update` and `PostUpdate`.
+    ExtractionPrep,
+    /// Tracks tilemap transform changes and carries riders along. Runs in `PostUpdate`, after
+    /// transform propagation.
+    TransformTracking,
+    /// Extracts tilemaps into the render world. Runs in the render app's `ExtractSchedule`.
+    Extract,
+    /// Builds chunk meshes, uniforms and materials. Runs in the render app's `Render` schedule.
+    Prepare,
+    /// Creates bind groups and queues chunks for drawing. Runs in the render app's `Render`
+    /// schedule.
+    Queue,
+}
+
+/// Enables or disables groups of the main world systems added by [`TilemapPlugin`].
+///
+/// Each flag controls the [`TilemapSystemSet`] of the same name, and can be changed at any time.
+/// To reschedule the systems instead, configure the sets themselves, e.g. with
+/// `app.configure_sets(Update, TilemapSystemSet::ExtractionPrep.after(MySystems))`.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilemapPluginConfig {
+    /// Runs [`TilemapSystemSet::PositionSync`]. Without it, [`TilePosOld`] stops following
+    /// [`TilePos`], so moved tiles aren't removed from their previous render chunk.
+    pub position_sync: bool,
+    /// Runs [`TilemapSystemSet::StorageMaintenance`]. Without it, the markers left by removed
+    /// tiles and tilemaps pile up, [`TileStableId`]s aren't assigned, placed tiles aren't
+    /// recorded and chunked tilemaps aren't kept in sync.
+    pub storage_maintenance: bool,
+    /// Runs [`TilemapSystemSet::ExtractionPrep`]. Without it, animations, motions and layer
+    /// occlusion stop updating, and some component changes don't reach the render world.
+    pub extraction_prep: bool,
+    /// Runs [`TilemapSystemSet::TransformTracking`]. Without it, [`TilemapTransformDelta`] isn't
+    /// updated and [`TilemapRider`]s aren't carried along.
+    pub transform_tracking: bool,
+}
+
+impl Default for TilemapPluginConfig {
+    /// By default, all systems are enabled.
+    fn default() -> Self {
+        Self {
+            position_sync: true,
+            storage_maintenance: true,
+            extraction_prep: true,
+            transform_tracking: true,
+        }
+    }
+}
+
+fn config_enabled(
+    enabled: fn(&TilemapPluginConfig) -> bool,
+) -> impl FnMut(Res<TilemapPluginConfig>) -> bool + Clone {
+    move |config: Res<TilemapPluginConfig>| enabled(&config)
+}
+
 #[derive(Component, Reflect, Debug, Clone, Copy, Deref)]
 #[reflect(Component)]
 pub struct FrustumCulling(pub bool);
@@ -168,9 +410,18 @@ pub mod prelude {
     pub use crate::helpers;
     pub use crate::helpers::filling::*;
     pub use crate::helpers::geometry::*;
+    pub use crate::helpers::rng::TilemapRng;
     pub use crate::helpers::transform::*;
     pub use crate::map::*;
     #[cfg(feature = "render")]
+    pub use crate::render::capabilities::TilemapRenderCapabilities;
+    #[cfg(feature = "render")]
+    pub use crate::render::culling_debug::{
+        ChunkAabb, TilemapCullingDebug, TilemapCullingDebugPlugin,
+    };
+    #[cfg(feature = "render")]
+    pub use crate::render::inspect::{TilemapRenderInspector, TilemapRenderInspectorPlugin};
+    #[cfg(feature = "render")]
     pub use crate::render::material::MaterialTilemap;
     #[cfg(feature = "render")]
     pub use crate::render::material::MaterialTilemapHandle;
@@ -180,12 +431,27 @@ pub mod prelude {
     pub use crate::render::material::MaterialTilemapPlugin;
     #[cfg(feature = "render")]
     pub use crate::render::material::StandardTilemapMaterial;
+    #[cfg(feature = "render")]
+    pub use crate::render::material::TileVertexDataEnabled;
+    #[cfg(feature = "render")]
+    pub use crate::render::memory::{TilemapMemoryStats, TilemapMemoryStatsPlugin};
+    #[cfg(feature = "render")]
+    pub use crate::render::shader::{TilemapShader, TilemapShaderOverrides};
+    #[cfg(feature = "render")]
+    pub use crate::render::stats::{TilemapRenderStats, TilemapRenderStatsPlugin};
+    #[cfg(feature = "stats_overlay")]
+    pub use crate::render::stats::{TilemapStatsOverlay, TilemapStatsOverlayPlugin};
+    #[cfg(all(not(feature = "atlas"), feature = "render"))]
+    pub use crate::render::{TilemapTextureStrategies, TilemapTextureStrategy};
     pub use crate::tiles::*;
     #[cfg(feature = "render")]
     pub use crate::MaterialTilemapBundle;
     #[cfg(feature = "render")]
     pub use crate::TilemapBundle;
+    pub use crate::TilemapCorePlugin;
     pub use crate::TilemapPlugin;
+    pub use crate::TilemapPluginConfig;
+    pub use crate::TilemapSystemSet;
 }
 
 /// Updates old tile positions with the new values from the last frame.
@@ -1,13 +1,23 @@
 use bevy::asset::Assets;
 use bevy::ecs::entity::{EntityMapper, MapEntities};
 use bevy::ecs::reflect::ReflectMapEntities;
-use bevy::prelude::{ReflectComponent, Res, ResMut};
+use bevy::prelude::Resource;
+use bevy::prelude::{
+    Changed, Commands, DetectChanges, OnRemove, Or, Query, Ref, ReflectComponent, Res, ResMut,
+    Trigger, World,
+};
+use bevy::render::extract_resource::ExtractResource;
 use bevy::render::render_resource::TextureUsages;
 use bevy::{
-    math::{UVec2, Vec2},
-    prelude::{Component, Deref, DerefMut, Entity, Handle, Image, Reflect},
+    math::{Rect, UVec2, UVec3, Vec2},
+    prelude::{Color, Component, Deref, DerefMut, Entity, Handle, Image, Reflect, Transform},
 };
+use std::fmt;
 use std::ops::Add;
+use std::sync::Arc;
+
+use crate::helpers::hex_grid::consts::{DOUBLE_INV_SQRT_3, HALF_SQRT_3};
+use crate::tiles::{TilePos, TileRect};
 
 /// The default chunk_size (in tiles) used per mesh.
 pub const CHUNK_SIZE_2D: UVec2 = UVec2::from_array([64, 64]);
@@ -26,11 +36,22 @@ pub struct TilemapRenderSettings {
     pub render_chunk_size: UVec2,
     /// If true, uses the chunk's `z` and `y` values when sorting during rendering.
     ///
-    /// When using this option with layered tilemaps, `z` values for layers should be separated by
-    /// at least `1.0` units.
+    /// Each chunk is sorted at the map's `z` plus a value between `0.0` and `1.0` that grows as
+    /// the chunk's world `y` decreases, so lower chunks are drawn on top. When using this option
+    /// with layered tilemaps, `z` values for layers should be separated by at least `1.0` units,
+    /// or the chunks of one layer can end up drawn above the other layer depending on their `y`.
     ///
     /// `render_chunk_size`'s `z` value should be `1` when using this for 3d isometric tilemaps.
+    ///
+    /// This is the same as [`ChunkZPolicy::WorldY`], and is ignored if the tilemap has a
+    /// [`ChunkZPolicy`].
     pub y_sort: bool,
+    /// Added to the sort key of every chunk when `y_sort` is enabled.
+    ///
+    /// Use this to order y-sorted maps that share the same `z`, e.g. a bias of `0.5` draws a
+    /// map's chunks above the chunks of a map at the same `z` and `y`. Chunks with equal sort
+    /// keys are always drawn in the same order, so this only changes which one comes first.
+    pub y_sort_bias: f32,
 }
 
 impl Default for TilemapRenderSettings {
@@ -38,6 +59,71 @@ impl Default for TilemapRenderSettings {
         Self {
             render_chunk_size: CHUNK_SIZE_2D,
             y_sort: false,
+            y_sort_bias: 0.0,
+        }
+    }
+}
+
+/// Controls which dirty render chunks get their meshes rebuilt each frame.
+///
+/// Chunks that are frustum culled are never rebuilt until they become visible again, regardless of
+/// the policy. Chunks whose rebuild is deferred keep rendering their previous mesh. Changes that
+/// only touch tile colors are always applied immediately, since they don't require a rebuild.
+///
+/// Insert it as a resource to change the default, which is [`RemeshPolicy::AllImmediately`].
+#[derive(Resource, ExtractResource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RemeshPolicy {
+    /// Every visible dirty chunk is rebuilt in the frame it changed.
+    #[default]
+    AllImmediately,
+    /// At most `chunks_per_frame` dirty chunks are rebuilt per frame, in no particular order.
+    Budgeted { chunks_per_frame: usize },
+    /// At most `chunks_per_frame` dirty chunks are rebuilt per frame, starting with the chunks
+    /// closest to any camera.
+    NearestCameraFirst { chunks_per_frame: usize },
+}
+
+/// Forces the render world to discard what it knows about a tilemap (or part of it) and rebuild
+/// it from the tiles currently in the main world.
+///
+/// This is useful after manipulating the `World` directly, loading scenes, or when debugging stale
+/// chunks. Insert it on the tilemap entity; it is removed automatically once it has been handled.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub enum TilemapInvalidate {
+    /// Invalidates the whole tilemap.
+    All,
+    /// Invalidates the tiles inside the rectangle, given in tile positions.
+    Region(TileRect),
+}
+
+impl TilemapInvalidate {
+    /// Converts a region given in tile positions into one given in grid positions, see
+    /// [`TilemapAxes::to_grid_pos`].
+    pub fn to_grid(&self, axes: &TilemapAxes, map_size: &TilemapSize) -> TilemapInvalidate {
+        match (self, axes.y_axis) {
+            (TilemapInvalidate::Region(rect), TilemapYAxis::Down) => {
+                let top = rect.end().y.min(map_size.y);
+                let bottom = rect.origin.y.min(top);
+                TilemapInvalidate::Region(TileRect::new(
+                    TilePos {
+                        x: rect.origin.x,
+                        y: map_size.y - top,
+                    },
+                    TilemapSize {
+                        x: rect.size.x,
+                        y: top - bottom,
+                    },
+                ))
+            }
+            _ => *self,
+        }
+    }
+
+    /// Returns true if `tile_pos` is covered by this invalidation.
+    pub fn contains(&self, tile_pos: &TilePos) -> bool {
+        match self {
+            TilemapInvalidate::All => true,
+            TilemapInvalidate::Region(rect) => rect.contains(tile_pos),
         }
     }
 }
@@ -63,6 +149,7 @@ impl Default for TilemapId {
 /// Size of the tilemap in tiles.
 #[derive(Component, Reflect, Default, Clone, Copy, Debug, Hash, PartialEq)]
 #[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TilemapSize {
     pub x: u32,
     pub y: u32,
@@ -182,6 +269,69 @@ impl TilemapTexture {
         })
     }
 
+    /// Returns the number of tiles in the texture, once its images are loaded.
+    ///
+    /// A single image is split into tiles the same way the shaders do it, rounding to the nearest
+    /// tile.
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+    /// # use bevy::render::render_asset::RenderAssetUsages;
+    /// # use bevy_ecs_tilemap::prelude::*;
+    /// let mut images = Assets::<Image>::default();
+    /// let image = Image::new_fill(
+    ///     Extent3d { width: 64, height: 32, depth_or_array_layers: 1 },
+    ///     TextureDimension::D2,
+    ///     &[0; 4],
+    ///     TextureFormat::Rgba8Unorm,
+    ///     RenderAssetUsages::default(),
+    /// );
+    /// let texture = TilemapTexture::Single(images.add(image));
+    /// let tile_size = TilemapTileSize::new(16.0, 16.0);
+    /// assert_eq!(texture.tile_count(&images, &tile_size, &TilemapSpacing::zero()), Some(8));
+    ///
+    /// // Four columns of tiles with two pixels between them.
+    /// let image = Image::new_fill(
+    ///     Extent3d { width: 70, height: 16, depth_or_array_layers: 1 },
+    ///     TextureDimension::D2,
+    ///     &[0; 4],
+    ///     TextureFormat::Rgba8Unorm,
+    ///     RenderAssetUsages::default(),
+    /// );
+    /// let texture = TilemapTexture::Single(images.add(image));
+    /// let spacing = TilemapSpacing::new(2.0, 0.0);
+    /// assert_eq!(texture.tile_count(&images, &tile_size, &spacing), Some(4));
+    /// ```
+    pub fn tile_count(
+        &self,
+        images: &Assets<Image>,
+        tile_size: &TilemapTileSize,
+        spacing: &TilemapSpacing,
+    ) -> Option<u32> {
+        match self {
+            TilemapTexture::Single(handle) => {
+                let texture_size = images.get(handle)?.size_f32();
+                // Matches the number of columns computed by the vertex shader.
+                let count = |size: f32, tile_size: f32, spacing: f32| {
+                    ((size - spacing) / (tile_size + spacing)).round().max(0.0) as u32
+                };
+                let columns = count(texture_size.x, tile_size.x, spacing.x);
+                let rows = count(texture_size.y, tile_size.y, spacing.y);
+                Some(columns * rows)
+            }
+            #[cfg(not(feature = "atlas"))]
+            TilemapTexture::Vector(handles) => handles
+                .iter()
+                .all(|handle| images.contains(handle))
+                .then_some(handles.len() as u32),
+            #[cfg(not(feature = "atlas"))]
+            TilemapTexture::TextureContainer(handle) => {
+                Some(images.get(handle)?.texture_descriptor.array_layer_count())
+            }
+        }
+    }
+
     /// Sets images with the `COPY_SRC` flag.
     pub fn set_images_to_copy_src(&self, images: &mut ResMut<Assets<Image>>) {
         for handle in self.image_handles() {
@@ -254,6 +404,15 @@ impl Add<Vec2> for TilemapTileSize {
     }
 }
 
+impl From<TilemapGridSize> for TilemapTileSize {
+    fn from(grid_size: TilemapGridSize) -> Self {
+        TilemapTileSize {
+            x: grid_size.x,
+            y: grid_size.y,
+        }
+    }
+}
+
 impl From<TilemapTileSize> for TilemapGridSize {
     fn from(tile_size: TilemapTileSize) -> Self {
         TilemapGridSize {
@@ -297,6 +456,68 @@ impl TilemapGridSize {
     pub const fn new(x: f32, y: f32) -> Self {
         Self { x, y }
     }
+
+    /// Returns the grid size of regular "pointy top" hexagons `width` pixels wide, for
+    /// [`HexCoordSystem::Row`], [`HexCoordSystem::RowEven`] and [`HexCoordSystem::RowOdd`] maps.
+    ///
+    /// Hexagon tilesets are often rounded to whole pixels, which is fine: the grid size of the
+    /// matching tile size is close enough.
+    ///
+    /// ```
+    /// # use bevy_ecs_tilemap::prelude::*;
+    /// let grid_size = TilemapGridSize::hex_row_from_tile_width(50.0);
+    /// assert_eq!(grid_size.y.round(), 58.0);
+    /// // Tiles usually have the size of the grid.
+    /// let tile_size: TilemapTileSize = grid_size.into();
+    /// ```
+    pub fn hex_row_from_tile_width(width: f32) -> Self {
+        Self::new(width, width * DOUBLE_INV_SQRT_3)
+    }
+
+    /// Returns the grid size of regular "pointy top" hexagons `height` pixels tall, for row
+    /// hexagon maps.
+    pub fn hex_row_from_tile_height(height: f32) -> Self {
+        Self::new(height * HALF_SQRT_3, height)
+    }
+
+    /// Returns the grid size of regular "flat top" hexagons `width` pixels wide, for
+    /// [`HexCoordSystem::Column`], [`HexCoordSystem::ColumnEven`] and
+    /// [`HexCoordSystem::ColumnOdd`] maps.
+    pub fn hex_column_from_tile_width(width: f32) -> Self {
+        Self::new(width, width * HALF_SQRT_3)
+    }
+
+    /// Returns the grid size of regular "flat top" hexagons `height` pixels tall, for column
+    /// hexagon maps.
+    pub fn hex_column_from_tile_height(height: f32) -> Self {
+        Self::new(height * DOUBLE_INV_SQRT_3, height)
+    }
+
+    /// Returns how far the proportions of the grid are from those of a regular hexagon in the
+    /// given coordinate system, as a fraction: `0.0` for a regular hexagon, `0.1` for a grid 10%
+    /// too wide or too tall.
+    ///
+    /// ```
+    /// # use bevy_ecs_tilemap::prelude::*;
+    /// let grid_size = TilemapGridSize::new(50.0, 58.0);
+    /// assert!(grid_size.hex_ratio_error(HexCoordSystem::Row) < 0.01);
+    /// // A flat top grid used for pointy top hexagons.
+    /// assert!(grid_size.hex_ratio_error(HexCoordSystem::Column) > 0.2);
+    /// ```
+    pub fn hex_ratio_error(&self, hex_coord_system: HexCoordSystem) -> f32 {
+        if self.x <= 0.0 || self.y <= 0.0 {
+            return f32::INFINITY;
+        }
+        let ratio = match hex_coord_system {
+            HexCoordSystem::Row | HexCoordSystem::RowEven | HexCoordSystem::RowOdd => {
+                self.y / self.x
+            }
+            HexCoordSystem::Column | HexCoordSystem::ColumnEven | HexCoordSystem::ColumnOdd => {
+                self.x / self.y
+            }
+        };
+        (ratio / DOUBLE_INV_SQRT_3 - 1.0).abs()
+    }
 }
 
 impl Add<TilemapGridSize> for TilemapGridSize {
@@ -345,6 +566,538 @@ impl From<&Vec2> for TilemapGridSize {
     }
 }
 
+/// The direction tile rows are counted in.
+#[derive(Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TilemapYAxis {
+    /// Row `0` is at the bottom of the map.
+    #[default]
+    Up,
+    /// Row `0` is at the top of the map, like in most map editors.
+    Down,
+}
+
+/// The order tiles are laid out in by [`TileStorage`](crate::tiles::TileStorage).
+#[derive(Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TilemapIndexing {
+    /// Tiles of the same row are next to each other.
+    #[default]
+    RowMajor,
+    /// Tiles of the same column are next to each other.
+    ColumnMajor,
+}
+
+/// The coordinate conventions of a tilemap.
+///
+/// [`TilePos`] is always interpreted using these axes; the tilemap is drawn, and world positions
+/// are converted, using the "grid" position returned by [`TilemapAxes::to_grid_pos`]. The axes of
+/// a tilemap are those of its [`TileStorage`](crate::tiles::TileStorage), see
+/// [`TileStorage::empty_with_axes`](crate::tiles::TileStorage::empty_with_axes), which everything
+/// else, including the renderer, reads them from.
+///
+/// Changing the axes of an existing tilemap means replacing its storage, and requires a
+/// [`TilemapInvalidate::All`].
+#[derive(Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilemapAxes {
+    pub y_axis: TilemapYAxis,
+    pub indexing: TilemapIndexing,
+}
+
+impl TilemapAxes {
+    /// Row `0` at the top of the map, with row-major indexing, as used by most map editors.
+    pub const Y_DOWN: TilemapAxes = TilemapAxes {
+        y_axis: TilemapYAxis::Down,
+        indexing: TilemapIndexing::RowMajor,
+    };
+
+    /// Converts a tile position into the y-up position the tile is drawn at.
+    ///
+    /// Converting twice returns the original position, so this also converts grid positions back
+    /// into tile positions.
+    pub fn to_grid_pos(&self, tile_pos: &TilePos, map_size: &TilemapSize) -> TilePos {
+        match self.y_axis {
+            TilemapYAxis::Up => *tile_pos,
+            TilemapYAxis::Down => TilePos {
+                x: tile_pos.x,
+                y: map_size.y.saturating_sub(1).saturating_sub(tile_pos.y),
+            },
+        }
+    }
+
+    /// Converts a tile position into its index in a tile storage of the given size.
+    pub fn to_index(&self, tile_pos: &TilePos, map_size: &TilemapSize) -> usize {
+        match self.indexing {
+            TilemapIndexing::RowMajor => tile_pos.to_index(map_size),
+            TilemapIndexing::ColumnMajor => ((tile_pos.x * map_size.y) + tile_pos.y) as usize,
+        }
+    }
+
+    /// Converts an index in a tile storage of the given size back into a tile position.
+    pub fn from_index(&self, index: usize, map_size: &TilemapSize) -> TilePos {
+        let index = index as u32;
+        match self.indexing {
+            TilemapIndexing::RowMajor => TilePos::new(index % map_size.x, index / map_size.x),
+            TilemapIndexing::ColumnMajor => TilePos::new(index / map_size.y, index % map_size.y),
+        }
+    }
+}
+
+/// A color that every tile of the tilemap is multiplied by.
+///
+/// This is optional, tilemaps without it are drawn as if it was white.
+///
+/// The color is part of the tilemap's uniform rather than of its meshes, so changing it every
+/// frame, e.g. to fade a layer in or out, tint it for the time of day or flash it on damage,
+/// neither touches the [`TileColor`](crate::tiles::TileColor) of its tiles nor re-meshes its
+/// chunks. Its alpha has no effect on tilemaps drawn with [`TilemapRenderMode::Opaque`].
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// /// Fades the tilemaps with a `Fade` out over a second.
+/// #[derive(Component)]
+/// struct Fade;
+///
+/// fn fade_out(time: Res<Time>, mut tilemaps: Query<&mut TilemapColor, With<Fade>>) {
+///     for mut color in tilemaps.iter_mut() {
+///         let alpha = (color.0.alpha() - time.delta_secs()).max(0.0);
+///         color.0.set_alpha(alpha);
+///     }
+/// }
+/// ```
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct TilemapColor(pub Color);
+
+impl Default for TilemapColor {
+    /// By default, `TilemapColor` is white, which leaves tile colors unchanged.
+    fn default() -> Self {
+        TilemapColor(Color::WHITE)
+    }
+}
+
+/// Offsets the animations of a tilemap's tiles by a pseudo-random part of their cycle, derived
+/// from a hash of each tile's position, so that fields of animated tiles like grass or water don't
+/// pulse in lockstep.
+///
+/// The value is the largest offset, as a fraction of a cycle: `0.0` plays every animation in
+/// lockstep, and `1.0` spreads them over a whole cycle. This is optional, tilemaps without it play
+/// in lockstep. Custom material shaders can offset their own effects, like scrolling textures, by
+/// the same amount with `tile_phase` from `bevy_ecs_tilemap::common`.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct TilemapAnimationPhase(pub f32);
+
+impl Default for TilemapAnimationPhase {
+    /// By default, animations play in lockstep.
+    fn default() -> Self {
+        TilemapAnimationPhase(0.0)
+    }
+}
+
+/// How far, in texels, texture coordinates are pulled in from the edges of tiles in an atlas, so
+/// that the sampler doesn't bleed onto neighboring tiles of the atlas.
+///
+/// The default half texel is enough in most cases, but some GPUs and scales need more. Larger
+/// insets crop the edges of tiles. This is optional, and only applies to tilemaps drawn from an
+/// atlas: tiles of texture arrays can't bleed onto each other.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct TilemapUvInset(pub f32);
+
+impl Default for TilemapUvInset {
+    /// By default, texture coordinates are pulled in by half a texel.
+    fn default() -> Self {
+        TilemapUvInset(0.5)
+    }
+}
+
+/// Marks a tilemap as read-only, e.g. a background map shared between several systems, to catch
+/// accidental writes to it.
+///
+/// Only the helpers that take the [`World`](bevy::prelude::World) and the tilemap entity honor
+/// the lock, and return a [`TilemapLockedError`] instead of changing a locked tilemap:
+/// [`try_fill_tilemap`](crate::helpers::filling::try_fill_tilemap),
+/// [`apply_to_radius`](crate::helpers::area_effect::apply_to_radius),
+/// [`transform_tilemap`](crate::helpers::symmetry::transform_tilemap) and
+/// [`symmetrize_tilemap`](crate::helpers::symmetry::symmetrize_tilemap). The helpers that take
+/// [`Commands`](bevy::prelude::Commands) and a [`TileStorage`](crate::tiles::TileStorage), like
+/// [`fill_tilemap`](crate::helpers::filling::fill_tilemap), the tile group, split, merge, path
+/// and template helpers, as well as `TileStorage::set` and `TileStorage::remove`, can't see the
+/// lock and change the tilemap anyway.
+///
+/// In debug builds, a warning is logged when the tiles or the
+/// [`TileStorage`](crate::tiles::TileStorage) of a locked tilemap change, starting from the frame
+/// after the lock was added, so that a tilemap can be spawned locked. This catches writes from
+/// the helpers that don't honor the lock. Remove the component to unlock the tilemap.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct TilemapLocked;
+
+/// The error returned when trying to change a tilemap marked with [`TilemapLocked`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TilemapLockedError(pub Entity);
+
+impl fmt::Display for TilemapLockedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tilemap {} is locked", self.0)
+    }
+}
+
+impl std::error::Error for TilemapLockedError {}
+
+/// Returns an error if `tilemap` is marked with [`TilemapLocked`], for mutation helpers to bail
+/// out early.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// let mut world = World::new();
+/// let tilemap = world.spawn(TilemapLocked).id();
+/// assert_eq!(ensure_unlocked(&world, tilemap), Err(TilemapLockedError(tilemap)));
+/// world.entity_mut(tilemap).remove::<TilemapLocked>();
+/// assert_eq!(ensure_unlocked(&world, tilemap), Ok(()));
+/// ```
+pub fn ensure_unlocked(world: &World, tilemap: Entity) -> Result<(), TilemapLockedError> {
+    if world.get::<TilemapLocked>(tilemap).is_some() {
+        Err(TilemapLockedError(tilemap))
+    } else {
+        Ok(())
+    }
+}
+
+/// Warns about tiles and tile storages that changed on tilemaps marked with [`TilemapLocked`].
+/// Only runs in debug builds.
+#[cfg(debug_assertions)]
+#[allow(clippy::type_complexity)]
+pub(crate) fn warn_locked_tilemap_changes(
+    locked_tilemaps: Query<(Entity, Ref<TilemapLocked>, Ref<crate::tiles::TileStorage>)>,
+    changed_tiles: Query<
+        &TilemapId,
+        Or<(
+            Changed<TilePos>,
+            Changed<crate::tiles::TileTextureIndex>,
+            Changed<crate::tiles::TileColor>,
+            Changed<crate::tiles::TileVisible>,
+            Changed<crate::tiles::TileFlip>,
+        )>,
+    >,
+) {
+    if locked_tilemaps.is_empty() {
+        return;
+    }
+    let mut warned = Vec::new();
+    let mut warn = |tilemap: Entity, what: &str| {
+        if !warned.contains(&tilemap) {
+            warned.push(tilemap);
+            bevy::log::warn!("The {what} of locked tilemap {tilemap} changed.");
+        }
+    };
+    for (tilemap, locked, storage) in locked_tilemaps.iter() {
+        if !locked.is_added() && storage.is_changed() {
+            warn(tilemap, "tile storage");
+        }
+    }
+    for tilemap_id in changed_tiles.iter() {
+        if let Ok((tilemap, locked, _)) = locked_tilemaps.get(tilemap_id.0) {
+            if !locked.is_added() {
+                warn(tilemap, "tiles");
+            }
+        }
+    }
+}
+
+/// Freezes a tilemap that never changes, e.g. a large background, to minimize what it costs per
+/// frame.
+///
+/// Once the render chunks of a static tilemap are built, the render world drops the tile data and
+/// meshes it keeps in main memory, and only keeps drawing the GPU buffers. Changes to its tiles
+/// are not extracted anymore, and changes to the tilemap that require remeshing, like its
+/// [`ChunkZPolicy`], are ignored. Chunks that were never visible are built the first time they
+/// are.
+///
+/// Insert it along with the tiles, or once they were spawned: tiles changed from the next frame on
+/// are ignored. Removing the component rebuilds the whole tilemap from its current tiles.
+///
+/// From the next frame on, changes to the tiles of a static tilemap are also skipped by
+/// [`DirtyTileChunks`](crate::tiles::DirtyTileChunks),
+/// [`RecentTileChanges`](crate::tiles::RecentTileChanges), the occlusion of a
+/// [`LayerStack`](crate::helpers::layer_stack::LayerStack), the
+/// [`TileTextureIndexCheck`](crate::tiles::TileTextureIndexCheck) and
+/// [`TileMotion`](crate::tiles::TileMotion)s. Tile hooks and the debug warning of
+/// [`TilemapLocked`] still see them.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct TilemapStatic;
+
+/// Returns true if `tilemap` was already [`TilemapStatic`] before the current run of the system,
+/// so that changes to its tiles are ignored.
+pub(crate) fn is_frozen(static_tilemaps: &Query<Ref<TilemapStatic>>, tilemap: Entity) -> bool {
+    static_tilemaps
+        .get(tilemap)
+        .is_ok_and(|marker| !marker.is_added())
+}
+
+/// Rebuilds the render chunks of tilemaps that are no longer [`TilemapStatic`].
+pub(crate) fn thaw_static_tilemaps(
+    trigger: Trigger<OnRemove, TilemapStatic>,
+    mut commands: Commands,
+) {
+    // The tilemap may be despawning.
+    if let Some(mut tilemap) = commands.get_entity(trigger.entity()) {
+        tilemap.try_insert(TilemapInvalidate::All);
+    }
+}
+
+/// How the tiles of a tilemap are blended with what is drawn behind them.
+///
+/// This is optional, tilemaps without it use [`TilemapBlendMode::Alpha`]. Each blend mode is
+/// compiled into its own pipeline, and also applies to tilemaps with custom materials.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TilemapBlendMode {
+    /// Regular alpha blending.
+    #[default]
+    Alpha,
+    /// Adds the tile colors, scaled by their alpha, to what is behind them, e.g. for light
+    /// overlays.
+    Additive,
+    /// Multiplies what is behind the tiles by the tile colors, e.g. for shadow maps. Alpha is
+    /// ignored, so parts of a tile that should leave the scene unchanged must be white.
+    Multiply,
+    /// Alpha blending for textures whose colors are already multiplied by their alpha.
+    Premultiplied,
+}
+
+/// The render phase the chunks of a tilemap are drawn in.
+///
+/// This is optional, tilemaps without it use [`TilemapRenderMode::Transparent`].
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TilemapRenderMode {
+    /// The chunks are sorted and blended in the 2d transparent phase.
+    #[default]
+    Transparent,
+    /// The chunks are drawn in the 2d opaque phase, before everything transparent, writing the
+    /// depth of the tilemap's z so that what is drawn behind them later is skipped, e.g. for
+    /// fully opaque base layers of large maps with many overlapping layers.
+    ///
+    /// Pixels with an alpha below `0.5` are discarded, and the others are drawn fully opaque:
+    /// the [`TilemapBlendMode`] and the [`TilemapSortKey`] are ignored. Custom fragment shaders
+    /// have to discard transparent pixels themselves.
+    Opaque,
+}
+
+/// Restricts the rendering of a tilemap to a rectangle, e.g. for minimaps in split-screen games
+/// or editor viewports. Tiles outside of the rectangle are cut off.
+///
+/// This is optional, tilemaps without it are rendered everywhere on screen.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub enum TilemapClipRect {
+    /// A rectangle in physical pixels, relative to the top-left corner of each camera's viewport.
+    Viewport(Rect),
+    /// A rectangle in world space. Cameras that rotate the view clip to the screen-space bounds
+    /// of the rotated rectangle.
+    World(Rect),
+}
+
+/// What a [`TilemapSortKey`] is given to compute the sort key of one chunk.
+#[derive(Clone, Copy, Debug)]
+pub struct ChunkSortInfo {
+    /// The index of the chunk, in chunks.
+    pub chunk_index: UVec3,
+    /// The world space transform of the chunk, which sits at the center of its bottom left tile.
+    pub transform: Transform,
+    /// The size of the chunk, in tiles.
+    pub chunk_size: UVec2,
+    pub map_size: TilemapSize,
+    pub grid_size: TilemapGridSize,
+    pub tile_size: TilemapTileSize,
+    pub map_type: TilemapType,
+    /// The sort key the chunk gets without a [`TilemapSortKey`], from the tilemap's
+    /// [`ChunkZPolicy`].
+    pub default_key: f32,
+}
+
+/// How the sort key of each chunk of a tilemap is derived from the tilemap's z in the 2d
+/// transparent phase. Chunks with larger keys are drawn on top.
+///
+/// Tilemaps without it use [`ChunkZPolicy::WorldY`] with the
+/// [`y_sort_bias`](TilemapRenderSettings::y_sort_bias) if
+/// [`y_sort`](TilemapRenderSettings::y_sort) is enabled, and [`ChunkZPolicy::MapZ`] otherwise. A
+/// [`TilemapSortKey`] takes precedence, and is given the key of the policy as its `default_key`.
+///
+/// ```
+/// # use bevy::math::{UVec2, UVec3};
+/// # use bevy::prelude::Transform;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// let chunk = |row: u32, z: f32| ChunkSortInfo {
+///     chunk_index: UVec3::new(0, row, 0),
+///     transform: Transform::from_xyz(0.0, row as f32 * 512.0 + 10_000.0, z),
+///     chunk_size: UVec2::new(32, 32),
+///     map_size: TilemapSize { x: 32, y: 128 },
+///     grid_size: TilemapGridSize { x: 16.0, y: 16.0 },
+///     tile_size: TilemapTileSize { x: 16.0, y: 16.0 },
+///     map_type: TilemapType::Square,
+///     default_key: 0.0,
+/// };
+///
+/// // Layers at fractional z values keep exactly their z.
+/// assert_eq!(ChunkZPolicy::MapZ.key(&chunk(3, 0.25)), 0.25);
+///
+/// // Rows further down are drawn on top, but never reach the next layer, wherever the map is.
+/// let policy = ChunkZPolicy::ChunkRow { range: 0.5 };
+/// assert!(policy.key(&chunk(0, 1.0)) > policy.key(&chunk(3, 1.0)));
+/// assert!((0..4).all(|row| (1.0..1.5).contains(&policy.key(&chunk(row, 1.0)))));
+/// ```
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq)]
+#[reflect(Component)]
+pub enum ChunkZPolicy {
+    /// Every chunk is sorted at exactly the tilemap's z, so tilemaps can be layered at any z,
+    /// including fractional values like `0.1` and `0.2`.
+    #[default]
+    MapZ,
+    /// The sorting of [`TilemapRenderSettings::y_sort`]: the tilemap's z, plus `bias`, plus
+    /// `1.0 - y / map_height` for the chunk's world `y`, so lower chunks are drawn on top.
+    ///
+    /// The offset is only between `0.0` and `1.0` while the chunk's world `y` lies between `0.0`
+    /// and the map's height. Tilemaps placed elsewhere get larger or negative offsets.
+    WorldY { bias: f32 },
+    /// The tilemap's z, plus an offset from `0.0` up to, but excluding, `range`, which grows for
+    /// chunk rows further down the map, so lower chunks are drawn on top.
+    ///
+    /// The offset only depends on the chunk's row, not on where the tilemap is, so tilemaps
+    /// whose z values are at least `range` apart never interleave.
+    ChunkRow { range: f32 },
+    /// Every chunk is sorted at exactly the tilemap's z, and each tile is drawn at its own depth,
+    /// the tilemap's z plus an offset between `0.0` and `1.0` that grows for tile centers further
+    /// down the map, as projected by its [`TilemapType`] and [`TilemapGridSize`]. Tiles write that
+    /// depth, so sprites drawn after the tilemap are hidden behind the tiles further down the map,
+    /// even tiles of the same chunk.
+    ///
+    /// Give sprites sorted among the tiles the z of [`ChunkZPolicy::tile_depth_z`]. Sprites
+    /// and tilemaps with a z below the tilemap's are drawn before it, and always end up behind
+    /// its tiles. Pixels with an alpha below `0.5` are discarded rather than hiding what is
+    /// behind them, except with custom fragment shaders, which have to discard them
+    /// themselves.
+    TileDepth,
+}
+
+impl ChunkZPolicy {
+    /// Returns the policy tilemaps without a [`ChunkZPolicy`] use, given their render settings.
+    pub fn from_settings(render_settings: &TilemapRenderSettings) -> Self {
+        if render_settings.y_sort {
+            ChunkZPolicy::WorldY {
+                bias: render_settings.y_sort_bias,
+            }
+        } else {
+            ChunkZPolicy::MapZ
+        }
+    }
+
+    /// Returns the z at which a sprite standing at `y`, in the tilemap's local space, sorts
+    /// among the tiles of a tilemap with [`ChunkZPolicy::TileDepth`] at `map_z`.
+    ///
+    /// ```
+    /// # use bevy_ecs_tilemap::prelude::*;
+    /// let map_size = TilemapSize { x: 16, y: 16 };
+    /// let grid_size = TilemapGridSize { x: 32.0, y: 32.0 };
+    /// // A sprite in the middle of the map, in front of the tiles of the upper half.
+    /// let z = ChunkZPolicy::tile_depth_z(2.0, 240.0, &map_size, &grid_size, &TilemapType::Square);
+    /// assert_eq!(z, 2.5);
+    /// ```
+    pub fn tile_depth_z(
+        map_z: f32,
+        y: f32,
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        map_type: &TilemapType,
+    ) -> f32 {
+        let range = Self::tile_depth_range(map_size, grid_size, map_type);
+        map_z + 1.0 - (y - range.x) / range.y
+    }
+
+    /// Returns the bottom and the height of the local `y` range that [`ChunkZPolicy::TileDepth`]
+    /// spreads over depths from `1.0` down to `0.0`: the projected tile centers of the map, with
+    /// half a grid cell of margin on both ends, so that every tile gets a depth in `(0.0, 1.0)`.
+    pub(crate) fn tile_depth_range(
+        map_size: &TilemapSize,
+        grid_size: &TilemapGridSize,
+        map_type: &TilemapType,
+    ) -> Vec2 {
+        // Projections are linear apart from offsets alternating between rows or columns, so the
+        // extremes are at the corners of the map, or next to them.
+        let edges = |len: u32| {
+            let last = len.saturating_sub(1);
+            [0, 1.min(last), last.saturating_sub(1), last]
+        };
+        let (bottom, top) = edges(map_size.x)
+            .into_iter()
+            .flat_map(|x| edges(map_size.y).map(|y| TilePos::new(x, y)))
+            .map(|tile_pos| tile_pos.center_in_world(grid_size, map_type).y)
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(bottom, top), y| {
+                (bottom.min(y), top.max(y))
+            });
+        Vec2::new(bottom - grid_size.y / 2.0, top - bottom + grid_size.y)
+    }
+
+    /// Returns the sort key of a chunk. The `default_key` of `chunk` is ignored.
+    pub fn key(&self, chunk: &ChunkSortInfo) -> f32 {
+        let z = chunk.transform.translation.z;
+        match *self {
+            ChunkZPolicy::MapZ | ChunkZPolicy::TileDepth => z,
+            ChunkZPolicy::WorldY { bias } => {
+                let map_height = chunk.map_size.y as f32 * chunk.tile_size.y;
+                z + bias + (1.0 - chunk.transform.translation.y / map_height)
+            }
+            ChunkZPolicy::ChunkRow { range } => {
+                let rows = chunk.map_size.y.div_ceil(chunk.chunk_size.y.max(1)).max(1);
+                let rows_below = rows - 1 - chunk.chunk_index.y.min(rows - 1);
+                z + range * rows_below as f32 / rows as f32
+            }
+        }
+    }
+}
+
+/// Computes the sort key of each chunk of a tilemap in the 2d transparent phase, for layering
+/// schemes that the z coordinate and [`TilemapRenderSettings::y_sort`] can't express, e.g.
+/// sorting by blocks of map rows. Chunks with larger keys are drawn on top.
+///
+/// This is optional, tilemaps without it use the default key of [`ChunkSortInfo`]. The function
+/// runs in the render world, once per visible chunk and view, every frame.
+///
+/// ```
+/// # use bevy_ecs_tilemap::prelude::*;
+/// // Draw every block of 4 chunk rows above the blocks further up the map.
+/// let sort_key = TilemapSortKey::new(|chunk: &ChunkSortInfo| {
+///     chunk.transform.translation.z - (chunk.chunk_index.y / 4) as f32
+/// });
+/// ```
+#[derive(Component, Clone)]
+pub struct TilemapSortKey(pub Arc<dyn Fn(&ChunkSortInfo) -> f32 + Send + Sync>);
+
+impl TilemapSortKey {
+    pub fn new(sort_key: impl Fn(&ChunkSortInfo) -> f32 + Send + Sync + 'static) -> Self {
+        TilemapSortKey(Arc::new(sort_key))
+    }
+
+    /// Returns the sort key of a chunk.
+    pub fn key(&self, chunk: &ChunkSortInfo) -> f32 {
+        (self.0)(chunk)
+    }
+}
+
+impl fmt::Debug for TilemapSortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TilemapSortKey").finish_non_exhaustive()
+    }
+}
+
 /// Spacing between tiles in pixels inside of the texture atlas.
 /// Defaults to 0.0
 #[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
@@ -461,6 +1214,37 @@ pub enum HexCoordSystem {
     Column,
 }
 
+/// How far the grid of a hexagonal tilemap can be from the proportions of a regular hexagon
+/// before [`warn_irregular_hex_grids`] complains.
+#[cfg(debug_assertions)]
+const HEX_RATIO_TOLERANCE: f32 = 0.1;
+
+/// Warns about hexagonal tilemaps whose grid size is far from the proportions of a regular
+/// hexagon, the usual cause of hexes overlapping or leaving gaps. Only runs in debug builds.
+#[cfg(debug_assertions)]
+#[allow(clippy::type_complexity)]
+pub(crate) fn warn_irregular_hex_grids(
+    tilemaps: Query<
+        (Entity, &TilemapGridSize, &TilemapType),
+        Or<(Changed<TilemapGridSize>, Changed<TilemapType>)>,
+    >,
+) {
+    for (entity, grid_size, map_type) in tilemaps.iter() {
+        let TilemapType::Hexagon(hex_coord_system) = map_type else {
+            continue;
+        };
+        let error = grid_size.hex_ratio_error(*hex_coord_system);
+        if error > HEX_RATIO_TOLERANCE {
+            bevy::log::warn!(
+                "The grid size {grid_size:?} of hexagonal tilemap {entity} is {:.0}% off from \
+                 regular {hex_coord_system:?} hexagons, so tiles may overlap or leave gaps. See \
+                 the `TilemapGridSize::hex_*` constructors.",
+                error * 100.0
+            );
+        }
+    }
+}
+
 /// Different isometric coordinate systems.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
 pub enum IsoCoordSystem {
@@ -494,6 +1278,80 @@ impl Default for TilemapType {
 mod tests {
     use super::*;
 
+    fn chunk_sort_info(row: u32, y: f32, z: f32) -> ChunkSortInfo {
+        ChunkSortInfo {
+            chunk_index: UVec3::new(0, row, 0),
+            transform: Transform::from_xyz(0.0, y, z),
+            chunk_size: UVec2::new(64, 64),
+            map_size: TilemapSize { x: 64, y: 200 },
+            grid_size: TilemapGridSize { x: 16.0, y: 16.0 },
+            tile_size: TilemapTileSize { x: 16.0, y: 16.0 },
+            map_type: TilemapType::Square,
+            default_key: 0.0,
+        }
+    }
+
+    #[test]
+    fn chunk_row_policy_stays_below_range() {
+        let policy = ChunkZPolicy::ChunkRow { range: 0.1 };
+        // The last row of the map is only partially filled, and the map is far from the origin.
+        for row in 0..4 {
+            let key = policy.key(&chunk_sort_info(row, -50_000.0, 0.3));
+            assert!((0.3..0.4).contains(&key), "row {row} has key {key}");
+        }
+        let keys: Vec<f32> = (0..4)
+            .map(|row| policy.key(&chunk_sort_info(row, 0.0, 0.3)))
+            .collect();
+        assert!(keys.windows(2).all(|pair| pair[0] > pair[1]));
+    }
+
+    #[test]
+    fn default_chunk_z_policy_follows_y_sort() {
+        let mut settings = TilemapRenderSettings::default();
+        assert_eq!(ChunkZPolicy::from_settings(&settings), ChunkZPolicy::MapZ);
+        settings.y_sort = true;
+        settings.y_sort_bias = 0.5;
+        let policy = ChunkZPolicy::from_settings(&settings);
+        assert_eq!(policy, ChunkZPolicy::WorldY { bias: 0.5 });
+        // A chunk at the bottom of a map placed at the origin gets the largest offset.
+        assert_eq!(policy.key(&chunk_sort_info(0, 0.0, 2.0)), 3.5);
+    }
+
+    #[test]
+    fn tile_depths_stay_in_range_and_follow_the_projection() {
+        let map_size = TilemapSize { x: 5, y: 4 };
+        // The depth only depends on the projected grid, not on the tile size, which is often
+        // taller than the grid cells, e.g. for the walls of isometric maps.
+        let grid_size = TilemapGridSize { x: 32.0, y: 16.0 };
+        for map_type in [
+            TilemapType::Square,
+            TilemapType::Isometric(IsoCoordSystem::Diamond),
+            TilemapType::Isometric(IsoCoordSystem::Staggered),
+            TilemapType::Hexagon(HexCoordSystem::Row),
+            TilemapType::Hexagon(HexCoordSystem::ColumnOdd),
+            TilemapType::Hexagon(HexCoordSystem::ColumnEven),
+        ] {
+            let mut depths = Vec::new();
+            for x in 0..map_size.x {
+                for y in 0..map_size.y {
+                    let center = TilePos::new(x, y).center_in_world(&grid_size, &map_type);
+                    let depth =
+                        ChunkZPolicy::tile_depth_z(0.0, center.y, &map_size, &grid_size, &map_type);
+                    assert!(
+                        depth > 0.0 && depth < 1.0,
+                        "{map_type:?} tile {x},{y} has depth {depth}"
+                    );
+                    depths.push((center.y, depth));
+                }
+            }
+            depths.sort_by(|a, b| a.0.total_cmp(&b.0));
+            assert!(
+                depths.windows(2).all(|pair| pair[0].1 >= pair[1].1),
+                "{map_type:?} depths don't decrease up the map"
+            );
+        }
+    }
+
     #[test]
     fn add_tilemap_size() {
         let a = TilemapSize { x: 2, y: 2 };
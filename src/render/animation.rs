@@ -0,0 +1,289 @@
+use bevy::{
+    prelude::{Entity, FromWorld, Res, ResMut, Resource, World},
+    render::{
+        render_resource::{
+            Extent3d, ImageDataLayout, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureUsages, TextureView, TextureViewDescriptor,
+        },
+        renderer::{RenderDevice, RenderQueue},
+    },
+    utils::HashMap,
+};
+
+use crate::tiles::{AnimatedTile, TileAnimationFrame};
+
+/// The animation of an extracted tile.
+#[derive(Clone, Debug)]
+pub enum ExtractedAnimation {
+    /// A range of frames, from an [`AnimatedTile`].
+    Range(AnimatedTile),
+    /// A list of frames, from an [`AnimatedTileFrames`](crate::tiles::AnimatedTileFrames).
+    Frames(Vec<TileAnimationFrame>),
+}
+
+/// Identifies equal animations, to share their ids.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum AnimationKey {
+    Range(u32, u32, u32),
+    Frames(Vec<(u32, u32)>),
+}
+
+/// The animations of one tilemap, stored in consecutive rows of the lookup texture.
+struct MapAnimations {
+    /// The first row of the animations.
+    row: u32,
+    /// The number of rows reserved for the animations.
+    rows: u32,
+    ids: HashMap<AnimationKey, u32>,
+    /// `[start, end, speed, 0.0]` per range animation id, and
+    /// `[first frame, frame count, duration, 1.0]` per frame list animation id, followed by
+    /// `[texture index, end time, 0.0, 0.0]` per frame. Id `0` means "not animated".
+    entries: Vec<[f32; 4]>,
+}
+
+/// Gives every distinct [`AnimatedTile`] and
+/// [`AnimatedTileFrames`](crate::tiles::AnimatedTileFrames) of a tilemap a compact id, and keeps
+/// the frames and timing of each animation in a lookup texture, starting a new row per tilemap.
+///
+/// Tiles only carry the id of their animation in their vertex data, and the vertex shader looks
+/// the animation up. Ids are kept until their tilemap is removed. The texture is at most
+/// `max_texture_dimension_2d` wide, and the animations of a tilemap that don't fit in one row
+/// wrap into the next ones.
+#[derive(Resource)]
+pub struct AnimationLookup {
+    maps: HashMap<Entity, MapAnimations>,
+    free_rows: Vec<u32>,
+    row_count: u32,
+    /// The widest the lookup texture can be.
+    max_width: u32,
+    dirty: bool,
+    texture: Option<(Texture, TextureView)>,
+}
+
+impl FromWorld for AnimationLookup {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        Self::with_max_width(render_device.limits().max_texture_dimension_2d)
+    }
+}
+
+impl AnimationLookup {
+    fn with_max_width(max_width: u32) -> Self {
+        Self {
+            maps: HashMap::default(),
+            free_rows: Vec::new(),
+            row_count: 0,
+            max_width: max_width.max(1),
+            dirty: true,
+            texture: None,
+        }
+    }
+
+    /// Returns the id of `animation` on the given tilemap, adding it if it is new.
+    pub fn animation_id(&mut self, tilemap: Entity, animation: &ExtractedAnimation) -> u32 {
+        if let ExtractedAnimation::Frames(frames) = animation {
+            if frames.iter().all(|frame| frame.duration <= 0.0) {
+                return 0;
+            }
+        }
+
+        let map = self.maps.entry(tilemap).or_insert_with(|| {
+            let row = self.free_rows.pop().unwrap_or_else(|| {
+                self.row_count += 1;
+                self.row_count - 1
+            });
+            MapAnimations {
+                row,
+                rows: 1,
+                ids: HashMap::default(),
+                entries: vec![[0.0; 4]],
+            }
+        });
+
+        let key = match animation {
+            ExtractedAnimation::Range(range) => {
+                AnimationKey::Range(range.start, range.end, range.speed.to_bits())
+            }
+            ExtractedAnimation::Frames(frames) => AnimationKey::Frames(
+                frames
+                    .iter()
+                    .map(|frame| (frame.texture_index, frame.duration.to_bits()))
+                    .collect(),
+            ),
+        };
+        let id = *map.ids.entry(key).or_insert_with(|| {
+            let id = map.entries.len() as u32;
+            match animation {
+                ExtractedAnimation::Range(range) => {
+                    map.entries
+                        .push([range.start as f32, range.end as f32, range.speed, 0.0]);
+                }
+                ExtractedAnimation::Frames(frames) => {
+                    let mut end_time = 0.0;
+                    let frame_entries: Vec<[f32; 4]> = frames
+                        .iter()
+                        .map(|frame| {
+                            end_time += frame.duration.max(0.0);
+                            [frame.texture_index as f32, end_time, 0.0, 0.0]
+                        })
+                        .collect();
+                    map.entries
+                        .push([(id + 1) as f32, frames.len() as f32, end_time, 1.0]);
+                    map.entries.extend(frame_entries);
+                }
+            }
+            self.dirty = true;
+            id
+        });
+        let rows = (map.entries.len() as u32).div_ceil(self.max_width);
+        if rows > map.rows {
+            if map.row + map.rows == self.row_count {
+                self.row_count += rows - map.rows;
+            } else {
+                // Moves the animations to the end of the texture, where there is room for them.
+                self.free_rows.extend(map.row..map.row + map.rows);
+                map.row = self.row_count;
+                self.row_count += rows;
+            }
+            map.rows = rows;
+        }
+        id
+    }
+
+    /// Returns the first row of the lookup texture holding the animations of `tilemap`.
+    pub fn row(&self, tilemap: Entity) -> u32 {
+        self.maps.get(&tilemap).map_or(0, |map| map.row)
+    }
+
+    /// Forgets the animations of a removed tilemap.
+    pub fn remove_map(&mut self, tilemap: Entity) {
+        if let Some(map) = self.maps.remove(&tilemap) {
+            self.free_rows.extend(map.row..map.row + map.rows);
+        }
+    }
+
+    /// Returns the width, the height and the texels of the lookup texture. Entry `i` of a tilemap
+    /// is at `(i % width, row + i / width)`.
+    fn texture_data(&self) -> (u32, u32, Vec<[f32; 4]>) {
+        let width = self
+            .maps
+            .values()
+            .map(|map| map.entries.len() as u32)
+            .max()
+            .unwrap_or(1)
+            .min(self.max_width);
+        let height = self.row_count.max(1);
+        let mut data = vec![[0.0f32; 4]; (width * height) as usize];
+        for map in self.maps.values() {
+            let start = (map.row * width) as usize;
+            data[start..start + map.entries.len()].copy_from_slice(&map.entries);
+        }
+        (width, height, data)
+    }
+
+    /// The view of the lookup texture, once it has been prepared.
+    pub fn texture_view(&self) -> Option<&TextureView> {
+        self.texture.as_ref().map(|(_, view)| view)
+    }
+}
+
+/// Uploads the animation lookup texture whenever animations were added.
+pub fn prepare_animation_lookup(
+    mut lookup: ResMut<AnimationLookup>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    if !lookup.dirty {
+        return;
+    }
+    lookup.dirty = false;
+
+    let (width, height, data) = lookup.texture_data();
+
+    let size = Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = render_device.create_texture(&TextureDescriptor {
+        label: Some("tilemap_animation_lookup"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba32Float,
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    let bytes: Vec<u8> = data
+        .iter()
+        .flatten()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    render_queue.write_texture(
+        texture.as_image_copy(),
+        &bytes,
+        ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(width * 16),
+            rows_per_image: None,
+        },
+        size,
+    );
+    let view = texture.create_view(&TextureViewDescriptor::default());
+    lookup.texture = Some((texture, view));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::TileAnimationFrame;
+
+    fn frames(texture_indices: &[u32]) -> ExtractedAnimation {
+        ExtractedAnimation::Frames(
+            texture_indices
+                .iter()
+                .map(|&texture_index| TileAnimationFrame {
+                    texture_index,
+                    duration: 0.5,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn frame_lists_are_packed_after_their_header() {
+        let mut lookup = AnimationLookup::with_max_width(64);
+        let tilemap = Entity::from_raw(1);
+        assert_eq!(lookup.animation_id(tilemap, &frames(&[4, 7])), 1);
+        assert_eq!(lookup.animation_id(tilemap, &frames(&[9])), 4);
+        assert_eq!(lookup.animation_id(tilemap, &frames(&[4, 7])), 1);
+
+        let (width, height, data) = lookup.texture_data();
+        assert_eq!((width, height), (6, 1));
+        assert_eq!(data[1], [2.0, 2.0, 1.0, 1.0]);
+        assert_eq!(data[2], [4.0, 0.5, 0.0, 0.0]);
+        assert_eq!(data[3], [7.0, 1.0, 0.0, 0.0]);
+        assert_eq!(data[4], [5.0, 1.0, 0.5, 1.0]);
+        assert_eq!(data[5], [9.0, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn animations_wrap_into_extra_rows() {
+        let mut lookup = AnimationLookup::with_max_width(4);
+        let (first, second) = (Entity::from_raw(1), Entity::from_raw(2));
+        lookup.animation_id(first, &frames(&[1]));
+        lookup.animation_id(second, &frames(&[2, 3, 4, 5, 6]));
+        lookup.animation_id(first, &frames(&[7]));
+
+        // The second tilemap grew in place, the first one moved after it.
+        assert_eq!(lookup.row(second), 1);
+        assert_eq!(lookup.row(first), 3);
+        assert_eq!(lookup.free_rows, [0]);
+        let (width, height, data) = lookup.texture_data();
+        assert_eq!((width, height), (4, 5));
+        assert_eq!(data[4 + 1], [2.0, 5.0, 2.5, 1.0]);
+        assert_eq!(data[2 * 4 + 2], [6.0, 2.5, 0.0, 0.0]);
+        assert_eq!(data[4 * 4], [7.0, 0.5, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,181 @@
+use bevy::{
+    log::warn,
+    prelude::*,
+    render::{
+        render_resource::ShaderDefVal,
+        renderer::{RenderAdapterInfo, RenderDevice},
+        settings::Backends,
+        RenderApp,
+    },
+    utils::HashSet,
+};
+
+use crate::map::{TilemapSpacing, TilemapTexture, TilemapTileSize};
+
+/// The limits of the render device that decide how tilemaps can be drawn, read once at startup.
+///
+/// The plugin uses these to pick paths the device supports, e.g. sampling tilesets with more
+/// tiles than `max_texture_array_layers` as atlases, and to warn as soon as a tilemap texture is
+/// loaded that won't draw as expected, instead of failing in the middle of a frame. WebGL2 and
+/// many mobile GPUs only support 256 array layers, and no storage buffers.
+///
+/// Available in the main world and the render world, once the [`TilemapPlugin`] is finished.
+///
+/// [`TilemapPlugin`]: crate::TilemapPlugin
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TilemapRenderCapabilities {
+    /// The largest width and height of a 2D texture, in pixels.
+    pub max_texture_dimension_2d: u32,
+    /// The largest number of layers of a texture array, i.e. of tiles in a tileset without the
+    /// `atlas` feature.
+    pub max_texture_array_layers: u32,
+    /// Whether shaders can read storage buffers, which WebGL2 doesn't support.
+    pub storage_buffers: bool,
+    /// Whether the renderer runs on OpenGL ES, which is WebGL2 in browsers.
+    pub gl_backend: bool,
+}
+
+impl TilemapRenderCapabilities {
+    /// Reads the capabilities of `render_device`, running on the adapter of `adapter_info`.
+    pub fn new(render_device: &RenderDevice, adapter_info: &RenderAdapterInfo) -> Self {
+        let limits = render_device.limits();
+        Self {
+            max_texture_dimension_2d: limits.max_texture_dimension_2d,
+            max_texture_array_layers: limits.max_texture_array_layers,
+            storage_buffers: limits.max_storage_buffers_per_shader_stage > 0,
+            gl_backend: Backends::from(adapter_info.backend) == Backends::GL,
+        }
+    }
+
+    /// The shader defs describing these capabilities: `TILEMAP_GL_BACKEND` when running on
+    /// OpenGL ES or WebGL2, and `TILEMAP_STORAGE_BUFFERS` when storage buffers are supported.
+    ///
+    /// The crate's own shaders don't depend on them. They are passed to the shaders of
+    /// [`MaterialTilemap`](crate::render::material::MaterialTilemap)s, so that custom material
+    /// shaders can pick a path for the device, e.g. with `#ifdef TILEMAP_STORAGE_BUFFERS`. Unlike
+    /// bevy's own `WEBGL2` def, they are decided at runtime, so the same build can pick the right
+    /// path on WebGL2 and WebGPU.
+    pub fn shader_defs(&self) -> Vec<ShaderDefVal> {
+        let mut shader_defs = Vec::new();
+        if self.gl_backend {
+            shader_defs.push("TILEMAP_GL_BACKEND".into());
+        }
+        if self.storage_buffers {
+            shader_defs.push("TILEMAP_STORAGE_BUFFERS".into());
+        }
+        shader_defs
+    }
+}
+
+impl Default for TilemapRenderCapabilities {
+    /// By default, capabilities are the guaranteed minimum of WebGL2.
+    fn default() -> Self {
+        Self {
+            max_texture_dimension_2d: 2048,
+            max_texture_array_layers: 256,
+            storage_buffers: false,
+            gl_backend: true,
+        }
+    }
+}
+
+/// Inserts the [`TilemapRenderCapabilities`] of the render device in both worlds.
+pub(crate) fn insert_render_capabilities(app: &mut App) {
+    let world = app.world();
+    let capabilities = match (
+        world.get_resource::<RenderDevice>(),
+        world.get_resource::<RenderAdapterInfo>(),
+    ) {
+        (Some(render_device), Some(adapter_info)) => {
+            TilemapRenderCapabilities::new(render_device, adapter_info)
+        }
+        _ => TilemapRenderCapabilities::default(),
+    };
+    app.insert_resource(capabilities);
+    if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+        render_app.insert_resource(capabilities);
+    }
+}
+
+/// Warns once about every loaded tilemap texture the device can't draw as is.
+#[cfg_attr(feature = "atlas", allow(unused_variables))]
+pub(crate) fn check_tilemap_textures(
+    capabilities: Res<TilemapRenderCapabilities>,
+    images: Res<Assets<Image>>,
+    tilemaps: Query<(&TilemapTexture, &TilemapTileSize, &TilemapSpacing)>,
+    mut checked: Local<HashSet<TilemapTexture>>,
+) {
+    for (texture, tile_size, spacing) in tilemaps.iter() {
+        if checked.contains(texture) {
+            continue;
+        }
+        let Some(sizes) = texture
+            .image_handles()
+            .into_iter()
+            .map(|handle| images.get(handle).map(|image| image.size()))
+            .collect::<Option<Vec<UVec2>>>()
+        else {
+            continue;
+        };
+        checked.insert(texture.clone_weak());
+
+        let max_size = capabilities.max_texture_dimension_2d;
+        if sizes.iter().any(|size| size.max_element() > max_size) {
+            warn!(
+                "Tilemap texture {texture:?} has images larger than the {max_size} pixels this \
+                device supports, and may fail to load."
+            );
+        }
+
+        #[cfg(not(feature = "atlas"))]
+        {
+            let max_layers = capabilities.max_texture_array_layers;
+            let tile_count = match texture {
+                TilemapTexture::Single(_) => {
+                    let tiles = (sizes[0].as_vec2()
+                        / (Vec2::from(*tile_size) + Vec2::from(*spacing)))
+                    .floor();
+                    (tiles.x * tiles.y) as u32
+                }
+                TilemapTexture::Vector(handles) => handles.len() as u32,
+                TilemapTexture::TextureContainer(_) => continue,
+            };
+            if tile_count > max_layers {
+                warn!(
+                    "Tilemap texture {texture:?} has {tile_count} tiles, more than the \
+                    {max_layers} texture array layers this device supports. See \
+                    `TilemapTextureStrategies` for how it is drawn instead."
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shader_defs_follow_the_capabilities() {
+        let names = |capabilities: TilemapRenderCapabilities| -> Vec<String> {
+            capabilities
+                .shader_defs()
+                .into_iter()
+                .map(|shader_def| match shader_def {
+                    ShaderDefVal::Bool(name, true) => name,
+                    other => panic!("unexpected shader def {other:?}"),
+                })
+                .collect()
+        };
+        assert_eq!(
+            names(TilemapRenderCapabilities::default()),
+            ["TILEMAP_GL_BACKEND"]
+        );
+        let webgpu = TilemapRenderCapabilities {
+            storage_buffers: true,
+            gl_backend: false,
+            ..Default::default()
+        };
+        assert_eq!(names(webgpu), ["TILEMAP_STORAGE_BUFFERS"]);
+    }
+}
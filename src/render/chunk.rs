@@ -2,10 +2,11 @@ use std::hash::{Hash, Hasher};
 
 use bevy::render::render_asset::RenderAssetUsages;
 use bevy::render::render_resource::Buffer;
+use bevy::render::renderer::RenderQueue;
 use bevy::render::{mesh::BaseMeshPipelineKey, primitives::Aabb};
 use bevy::{math::Mat4, render::mesh::PrimitiveTopology};
 use bevy::{
-    math::{UVec2, UVec3, UVec4, Vec2, Vec3Swizzles, Vec4, Vec4Swizzles},
+    math::{UVec2, UVec3, Vec2, Vec3, Vec3Swizzles, Vec4},
     prelude::{Component, Entity, GlobalTransform, Mesh},
     render::{
         mesh::{Indices, RenderMesh, RenderMeshBufferInfo, VertexAttributeValues},
@@ -22,17 +23,24 @@ use bevy::{
 use crate::prelude::helpers::transform::{chunk_aabb, chunk_index_to_world_space};
 use crate::render::extract::ExtractedFrustum;
 use crate::{
-    map::{TilemapSize, TilemapTexture, TilemapType},
+    map::{
+        ChunkZPolicy, TilemapBlendMode, TilemapClipRect, TilemapInvalidate, TilemapRenderMode,
+        TilemapSize, TilemapSortKey, TilemapTexture, TilemapType,
+    },
     tiles::TilePos,
     FrustumCulling, TilemapGridSize, TilemapTileSize,
 };
 
 use super::RenderChunkSize;
 
+/// The render chunks of every tilemap, by tilemap and chunk index.
+///
+/// Tilemaps are keyed by their whole render entity rather than its index, so that a tilemap
+/// spawned in the slot of a despawned one doesn't pick up its chunks.
 #[derive(Resource, Default, Clone, Debug)]
 pub struct RenderChunk2dStorage {
-    chunks: HashMap<u32, HashMap<UVec3, RenderChunk2d>>,
-    entity_to_chunk_tile: HashMap<Entity, (u32, UVec3, UVec2)>,
+    chunks: HashMap<Entity, HashMap<UVec3, RenderChunk2d>>,
+    entity_to_chunk_tile: HashMap<Entity, (Entity, UVec3, UVec2)>,
     entity_to_chunk: HashMap<Entity, UVec3>,
 }
 
@@ -46,7 +54,7 @@ impl RenderChunk2dStorage {
         tile_entity: Entity,
         tile_pos: UVec2,
         chunk_entity: Entity,
-        position: &UVec4,
+        index: &UVec3,
         chunk_size: UVec2,
         mesh_type: TilemapType,
         tile_size: TilemapTileSize,
@@ -59,23 +67,16 @@ impl RenderChunk2dStorage {
         visibility: &InheritedVisibility,
         frustum_culling: &FrustumCulling,
         render_size: RenderChunkSize,
-        y_sort: bool,
     ) -> &mut RenderChunk2d {
-        let pos = position.xyz();
+        let pos = *index;
 
         self.entity_to_chunk_tile
-            .insert(tile_entity, (position.w, pos, tile_pos));
+            .insert(tile_entity, (chunk_entity, pos, tile_pos));
 
-        let chunk_storage = if self.chunks.contains_key(&position.w) {
-            self.chunks.get_mut(&position.w).unwrap()
-        } else {
-            let hash_map = HashMap::default();
-            self.chunks.insert(position.w, hash_map);
-            self.chunks.get_mut(&position.w).unwrap()
-        };
+        let chunk_storage = self.chunks.entry(chunk_entity).or_default();
 
         let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        position.hash(&mut hasher);
+        (chunk_entity, pos).hash(&mut hasher);
 
         if chunk_storage.contains_key(&pos) {
             chunk_storage.get_mut(&pos).unwrap()
@@ -96,7 +97,6 @@ impl RenderChunk2dStorage {
                 visibility.get(),
                 **frustum_culling,
                 render_size,
-                y_sort,
             );
             self.entity_to_chunk.insert(chunk_entity, pos);
             chunk_storage.insert(pos, chunk);
@@ -104,16 +104,13 @@ impl RenderChunk2dStorage {
         }
     }
 
-    pub fn get(&self, position: &UVec4) -> Option<&RenderChunk2d> {
-        if let Some(chunk_storage) = self.chunks.get(&position.w) {
-            return chunk_storage.get(&position.xyz());
-        }
-        None
+    pub fn get(&self, tilemap: Entity, index: &UVec3) -> Option<&RenderChunk2d> {
+        self.chunks.get(&tilemap)?.get(index)
     }
 
-    pub fn get_mut(&mut self, position: &UVec4) -> &mut RenderChunk2d {
-        let chunk_storage = self.chunks.get_mut(&position.w).unwrap();
-        chunk_storage.get_mut(&position.xyz()).unwrap()
+    pub fn get_mut(&mut self, tilemap: Entity, index: &UVec3) -> &mut RenderChunk2d {
+        let chunk_storage = self.chunks.get_mut(&tilemap).unwrap();
+        chunk_storage.get_mut(index).unwrap()
     }
 
     pub fn remove_tile_with_entity(&mut self, entity: Entity) {
@@ -126,32 +123,25 @@ impl RenderChunk2dStorage {
     }
 
     pub fn get_mut_from_entity(&mut self, entity: Entity) -> Option<(&mut RenderChunk2d, UVec2)> {
-        if !self.entity_to_chunk_tile.contains_key(&entity) {
-            return None;
-        }
-
-        let (tilemap_id, chunk_pos, tile_pos) = self.entity_to_chunk_tile.get(&entity).unwrap();
+        let (tilemap, chunk_pos, tile_pos) = self.entity_to_chunk_tile.get(&entity)?;
 
-        let chunk_storage = self.chunks.get_mut(tilemap_id).unwrap();
-        Some((chunk_storage.get_mut(&chunk_pos.xyz()).unwrap(), *tile_pos))
+        let chunk_storage = self.chunks.get_mut(tilemap)?;
+        Some((chunk_storage.get_mut(chunk_pos)?, *tile_pos))
     }
 
-    pub fn get_chunk_storage(&mut self, position: &UVec4) -> &mut HashMap<UVec3, RenderChunk2d> {
-        if self.chunks.contains_key(&position.w) {
-            self.chunks.get_mut(&position.w).unwrap()
-        } else {
-            let hash_map = HashMap::default();
-            self.chunks.insert(position.w, hash_map);
-            self.chunks.get_mut(&position.w).unwrap()
-        }
+    /// Returns the tilemap the tile `entity` was last stored in, if any.
+    pub fn tilemap_of(&self, entity: Entity) -> Option<Entity> {
+        self.entity_to_chunk_tile
+            .get(&entity)
+            .map(|(tilemap, _, _)| *tilemap)
     }
 
-    pub fn remove(&mut self, position: &UVec4) {
-        let chunk_storage = self.get_chunk_storage(position);
-
-        let pos = position.xyz();
+    pub fn get_chunk_storage(&mut self, tilemap: Entity) -> &mut HashMap<UVec3, RenderChunk2d> {
+        self.chunks.entry(tilemap).or_default()
+    }
 
-        chunk_storage.remove(&pos);
+    pub fn remove(&mut self, tilemap: Entity, index: &UVec3) {
+        self.get_chunk_storage(tilemap).remove(index);
     }
 
     pub fn count(&self) -> usize {
@@ -169,16 +159,118 @@ impl RenderChunk2dStorage {
     }
 
     pub fn remove_map(&mut self, entity: Entity) {
-        self.chunks.remove(&entity.index());
+        self.chunks.remove(&entity);
+    }
+
+    /// Drops the chunks that have no tiles left, and frees the capacity the lookup tables kept
+    /// after tiles were removed. Dropped chunks are recreated when tiles are added to them again.
+    pub fn compact(&mut self) {
+        for chunks in self.chunks.values_mut() {
+            chunks.retain(|_, chunk| chunk.frozen || chunk.tiles.iter().any(Option::is_some));
+            chunks.shrink_to_fit();
+        }
+        self.chunks.retain(|_, chunks| !chunks.is_empty());
+        self.chunks.shrink_to_fit();
+        self.entity_to_chunk_tile.shrink_to_fit();
+        self.entity_to_chunk.shrink_to_fit();
+    }
+
+    /// Clears the tiles of the given map that are covered by `invalidate`, so that they can be
+    /// rebuilt from freshly extracted data.
+    pub fn invalidate(&mut self, entity: Entity, invalidate: &TilemapInvalidate) {
+        self.entity_to_chunk_tile
+            .retain(|_, (id, chunk_index, tile_pos)| {
+                if *id != entity {
+                    return true;
+                }
+                let chunk_size = self
+                    .chunks
+                    .get(id)
+                    .and_then(|chunks| chunks.get(chunk_index))
+                    .map(|chunk| chunk.size_in_tiles)
+                    .unwrap_or_default();
+                // Tiles of composed layers are stored in slots above the chunk.
+                let in_chunk = UVec2::new(tile_pos.x, tile_pos.y % chunk_size.y.max(1));
+                let map_pos = chunk_index.xy() * chunk_size + in_chunk;
+                !invalidate.contains(&map_pos.into())
+            });
+
+        match invalidate {
+            TilemapInvalidate::All => self.remove_map(entity),
+            TilemapInvalidate::Region(_) => {
+                let Some(chunks) = self.chunks.get_mut(&entity) else {
+                    return;
+                };
+                for chunk in chunks.values_mut() {
+                    let chunk_origin = chunk.index.xy() * chunk.size_in_tiles;
+                    let rows = chunk.tiles.len() as u32 / chunk.size_in_tiles.x.max(1);
+                    for y in 0..rows {
+                        for x in 0..chunk.size_in_tiles.x {
+                            let tile_pos = TilePos { x, y };
+                            let index = tile_pos.to_index(&chunk.size_in_tiles.into());
+                            let in_chunk = UVec2::new(x, y % chunk.size_in_tiles.y);
+                            if chunk.tiles[index].is_some()
+                                && invalidate.contains(&(chunk_origin + in_chunk).into())
+                            {
+                                chunk.set(&tile_pos, None);
+                            }
+                        }
+                    }
+                }
+            }
+        }
     }
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct PackedTileData {
     pub visible: bool,
-    pub position: Vec4,
-    pub texture: Vec4,
+    /// The position of the tile inside of its chunk.
+    pub position: Vec2,
+    /// The texture index, the flip bits, and the animation id of the tile.
+    pub texture: Vec3,
     pub color: [f32; 4],
+    /// The color the tile pulses towards, if `color_period` is positive.
+    pub color_to: [f32; 4],
+    /// The seconds of a color pulse, or zero for a static color.
+    pub color_period: f32,
+    /// The `TILE_FLAG_ANIMATION_*` flags of the tile.
+    pub animation_flags: u32,
+    /// The time the animation started at, or the seconds into the animation it is held at.
+    pub animation_time: f32,
+    /// The [`TileSortBias`](crate::tiles::TileSortBias) of the tile.
+    pub sort_bias: f32,
+    /// The [`TileVertexData`](crate::tiles::TileVertexData) of the tile.
+    pub vertex_data: Vec4,
+    /// The [`TilePosOffset`](crate::tiles::TilePosOffset) of the tile, followed by its
+    /// [`TileRotation`](crate::tiles::TileRotation).
+    pub transform: Vec3,
+    /// The [`TileRenderSize`](crate::tiles::TileRenderSize) of the tile, or zero to draw it at
+    /// the tile size of its tilemap.
+    pub render_size: Vec2,
+}
+
+impl PackedTileData {
+    /// Returns true if `other` would produce the same geometry as `self`, so that only the state
+    /// vertex stream needs to be rewritten when switching between them.
+    ///
+    /// Visibility is part of the state stream, so that blinking tiles don't cause a remesh.
+    #[inline]
+    pub fn same_geometry(&self, other: &PackedTileData) -> bool {
+        self.position == other.position
+            && self.texture == other.texture
+            && self.sort_bias == other.sort_bias
+            && self.vertex_data == other.vertex_data
+            && self.transform == other.transform
+            && self.render_size == other.render_size
+    }
+
+    /// Returns the flags of the tile in the state vertex stream.
+    #[inline]
+    fn flags(&self) -> u32 {
+        let hidden = if self.visible { 0 } else { TILE_FLAG_HIDDEN };
+        hidden | self.animation_flags
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -212,17 +304,47 @@ pub struct RenderChunk2d {
     transform_matrix: Mat4,
     pub spacing: Vec2,
     pub tiles: Vec<Option<PackedTileData>>,
+    /// The indices of the tiles in `tiles`, in the order they were last meshed in.
+    draw_order: Vec<usize>,
     pub texture: TilemapTexture,
     pub texture_size: Vec2,
     pub mesh: Mesh,
     pub render_mesh: Option<RenderMesh>,
     pub vertex_buffer: Option<Buffer>,
+    /// Per-vertex colors live in their own buffer so they can be rewritten without rebuilding
+    /// the rest of the mesh.
+    pub color_buffer: Option<Buffer>,
     pub index_buffer: Option<Buffer>,
+    /// The [`TileVertexData`](crate::tiles::TileVertexData) of every vertex, if `vertex_data` is
+    /// set.
+    pub data_buffer: Option<Buffer>,
+    /// The offset and rotation of every vertex, if any tile of the chunk is offset or rotated.
+    pub transform_buffer: Option<Buffer>,
+    /// The render size of every vertex, if any tile of the chunk has one.
+    pub size_buffer: Option<Buffer>,
     pub dirty_mesh: bool,
+    /// Set once the tiles and mesh of a chunk of a [`TilemapStatic`](crate::map::TilemapStatic)
+    /// map were dropped, after uploading them.
+    pub frozen: bool,
+    /// Set when tiles changed in a way that only affects their color.
+    pub dirty_colors: bool,
     pub visible: bool,
     pub frustum_culling: bool,
+    /// The [`TilemapColor`](crate::map::TilemapColor) of the map, in linear space.
+    pub color: Vec4,
+    /// The [`TilemapAnimationPhase`](crate::map::TilemapAnimationPhase) of the map.
+    pub animation_phase: f32,
+    /// The [`TilemapUvInset`](crate::map::TilemapUvInset) of the map, in texels.
+    pub uv_inset: f32,
+    pub blend_mode: TilemapBlendMode,
+    pub render_mode: TilemapRenderMode,
+    pub clip_rect: Option<TilemapClipRect>,
+    pub sort_key: Option<TilemapSortKey>,
     pub render_size: RenderChunkSize,
-    pub y_sort: bool,
+    pub z_policy: ChunkZPolicy,
+    /// Set for tilemaps with a [`TileVertexDataEnabled`](super::material::TileVertexDataEnabled),
+    /// whose chunks upload the vertex data of their tiles.
+    pub vertex_data: bool,
 }
 
 impl RenderChunk2d {
@@ -243,7 +365,6 @@ impl RenderChunk2d {
         visible: bool,
         frustum_culling: bool,
         render_size: RenderChunkSize,
-        y_sort: bool,
     ) -> Self {
         let position = chunk_index_to_world_space(index.xy(), size_in_tiles, &grid_size, &map_type);
         let local_transform = Transform::from_translation(position.extend(0.0));
@@ -253,6 +374,8 @@ impl RenderChunk2d {
         let aabb = chunk_aabb(size_in_tiles, &grid_size, &tile_size, &map_type);
         Self {
             dirty_mesh: true,
+            frozen: false,
+            dirty_colors: false,
             render_mesh: None,
             id,
             index: *index,
@@ -272,16 +395,29 @@ impl RenderChunk2d {
                 RenderAssetUsages::default(),
             ),
             vertex_buffer: None,
+            color_buffer: None,
             index_buffer: None,
+            data_buffer: None,
+            transform_buffer: None,
+            size_buffer: None,
             spacing,
             texture_size,
             texture,
             tilemap_id,
             tiles: vec![None; (size_in_tiles.x * size_in_tiles.y) as usize],
+            draw_order: Vec::new(),
             visible,
             frustum_culling,
+            color: Vec4::ONE,
+            animation_phase: 0.0,
+            uv_inset: 0.5,
+            blend_mode: TilemapBlendMode::default(),
+            render_mode: TilemapRenderMode::default(),
+            clip_rect: None,
+            sort_key: None,
             render_size,
-            y_sort,
+            z_policy: ChunkZPolicy::default(),
+            vertex_data: false,
         }
     }
 
@@ -294,9 +430,62 @@ impl RenderChunk2d {
         &mut self.tiles[tile_pos.to_index(&self.size_in_tiles.into())]
     }
 
+    /// Stores `tile` in the slot at `tile_pos`. Slots above the chunk hold the tiles of the
+    /// layers composed into its tilemap, and are allocated as they are first set.
     pub fn set(&mut self, tile_pos: &TilePos, tile: Option<PackedTileData>) {
-        self.dirty_mesh = true;
-        self.tiles[tile_pos.to_index(&self.size_in_tiles.into())] = tile;
+        let index = tile_pos.to_index(&self.size_in_tiles.into());
+        if index >= self.tiles.len() {
+            if tile.is_none() {
+                return;
+            }
+            let layer_size = (self.size_in_tiles.x * self.size_in_tiles.y) as usize;
+            self.tiles
+                .resize((index / layer_size + 1) * layer_size, None);
+        }
+        match (&self.tiles[index], &tile) {
+            (Some(old), Some(new)) if old.same_geometry(new) => self.dirty_colors = true,
+            _ => self.dirty_mesh = true,
+        }
+        self.tiles[index] = tile;
+    }
+
+    /// Drops the tiles and the mesh kept in main memory, if they were uploaded. The chunk keeps
+    /// drawing its GPU buffers, and is never remeshed again.
+    pub fn freeze(&mut self) {
+        if self.frozen || self.dirty_mesh || self.dirty_colors || self.render_mesh.is_none() {
+            return;
+        }
+        self.frozen = true;
+        self.tiles = Vec::new();
+        self.draw_order = Vec::new();
+        self.mesh = Mesh::new(
+            bevy::render::render_resource::PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        );
+    }
+
+    /// Returns the number of bytes the chunk occupies in main memory, including its mesh.
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self.tiles.capacity() * size_of::<Option<PackedTileData>>()
+            + self.mesh.get_vertex_buffer_size()
+            + self.mesh.get_index_buffer_bytes().map_or(0, <[u8]>::len)
+    }
+
+    /// Returns the number of bytes of the chunk's GPU buffers.
+    pub fn gpu_memory_usage(&self) -> u64 {
+        [
+            &self.vertex_buffer,
+            &self.color_buffer,
+            &self.index_buffer,
+            &self.data_buffer,
+            &self.transform_buffer,
+            &self.size_buffer,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|buffer| buffer.size())
+        .sum()
     }
 
     pub fn get_index(&self) -> UVec3 {
@@ -311,10 +500,20 @@ impl RenderChunk2d {
         self.transform
     }
 
+    pub fn get_aabb(&self) -> Aabb {
+        self.aabb
+    }
+
     pub fn get_transform_matrix(&self) -> Mat4 {
         self.transform_matrix
     }
 
+    /// Returns the center of this chunk's [`Aabb`], in world space.
+    pub fn world_center(&self) -> Vec3 {
+        self.transform_matrix
+            .transform_point3(Vec3::from(self.aabb.center))
+    }
+
     pub fn intersects_frustum(&self, frustum: &ExtractedFrustum) -> bool {
         frustum.intersects_obb(&self.aabb, &self.transform_matrix)
     }
@@ -363,114 +562,326 @@ impl RenderChunk2d {
         }
     }
 
-    pub fn prepare(
-        &mut self,
-        device: &RenderDevice,
-        mesh_vertex_buffer_layouts: &mut MeshVertexBufferLayouts,
-    ) {
-        if self.dirty_mesh {
-            let size = ((self.size_in_tiles.x * self.size_in_tiles.y) * 4) as usize;
-            let mut positions: Vec<[f32; 4]> = Vec::with_capacity(size);
-            let mut textures: Vec<[f32; 4]> = Vec::with_capacity(size);
-            let mut colors: Vec<[f32; 4]> = Vec::with_capacity(size);
-            let mut indices: Vec<u32> =
-                Vec::with_capacity(((self.size_in_tiles.x * self.size_in_tiles.y) * 6) as usize);
-
-            let mut i = 0;
-
-            // Convert tile into mesh data.
-            for tile in self.tiles.iter().filter_map(|x| x.as_ref()) {
-                if !tile.visible {
-                    continue;
+    /// Packs the colors and flags of every tile, in the same order used to build the mesh, into
+    /// the byte layout expected by the state vertex buffer.
+    fn state_buffer_data(&self) -> Vec<u8> {
+        self.draw_order
+            .iter()
+            .filter_map(|index| self.tiles[*index].as_ref())
+            .flat_map(|tile| {
+                let mut vertex = [0; STATE_VERTEX_SIZE];
+                let words = tile
+                    .color
+                    .map(f32::to_bits)
+                    .into_iter()
+                    .chain([tile.flags()])
+                    .chain(tile.color_to.map(f32::to_bits))
+                    .chain([tile.color_period.to_bits()])
+                    .chain([tile.animation_time.to_bits()]);
+                for (bytes, word) in vertex.chunks_exact_mut(4).zip(words) {
+                    bytes.copy_from_slice(&word.to_le_bytes());
+                }
+                std::iter::repeat(vertex).take(4)
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Packs the vertex data of every tile, in the same order used to build the mesh, into the
+    /// byte layout expected by the data vertex buffer.
+    fn vertex_data_buffer_data(&self) -> Vec<u8> {
+        self.draw_order
+            .iter()
+            .filter_map(|index| self.tiles[*index].as_ref())
+            .flat_map(|tile| {
+                let mut vertex = [0; DATA_VERTEX_SIZE];
+                let words = tile.vertex_data.to_array().map(f32::to_bits);
+                for (bytes, word) in vertex.chunks_exact_mut(4).zip(words) {
+                    bytes.copy_from_slice(&word.to_le_bytes());
                 }
+                std::iter::repeat_n(vertex, 4)
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Packs the offset and rotation of every tile, in the same order used to build the mesh, into
+    /// the byte layout expected by the transform vertex buffer.
+    fn transform_buffer_data(&self) -> Vec<u8> {
+        self.draw_order
+            .iter()
+            .filter_map(|index| self.tiles[*index].as_ref())
+            .flat_map(|tile| {
+                let mut vertex = [0; TRANSFORM_VERTEX_SIZE];
+                let words = tile.transform.to_array().map(f32::to_bits);
+                for (bytes, word) in vertex.chunks_exact_mut(4).zip(words) {
+                    bytes.copy_from_slice(&word.to_le_bytes());
+                }
+                std::iter::repeat_n(vertex, 4)
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Packs the render size of every tile, in the same order used to build the mesh, into the
+    /// byte layout expected by the size vertex buffer.
+    fn size_buffer_data(&self) -> Vec<u8> {
+        self.draw_order
+            .iter()
+            .filter_map(|index| self.tiles[*index].as_ref())
+            .flat_map(|tile| {
+                let mut vertex = [0; SIZE_VERTEX_SIZE];
+                let words = tile.render_size.to_array().map(f32::to_bits);
+                for (bytes, word) in vertex.chunks_exact_mut(4).zip(words) {
+                    bytes.copy_from_slice(&word.to_le_bytes());
+                }
+                std::iter::repeat_n(vertex, 4)
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Orders the tiles for drawing: in storage order, or from the top of the chunk down on
+    /// y-sorted maps, with the [`TileSortBias`](crate::tiles::TileSortBias) of each tile applied.
+    fn update_draw_order(&mut self) {
+        let y_sort = self.z_policy != ChunkZPolicy::MapZ;
+        let biased = self
+            .tiles
+            .iter()
+            .flatten()
+            .any(|tile| tile.sort_bias != 0.0);
+        let mut keyed: Vec<(f32, usize)> = self
+            .tiles
+            .iter()
+            .enumerate()
+            .filter_map(|(index, tile)| {
+                let tile = tile.as_ref()?;
+                if !y_sort {
+                    return Some((tile.sort_bias, index));
+                }
+                let grid_pos =
+                    TilePos::from(self.index.xy() * self.size_in_tiles + tile.position.as_uvec2());
+                let y = grid_pos.center_in_world(&self.grid_size, &self.map_type).y;
+                Some((tile.sort_bias * self.grid_size.y - y, index))
+            })
+            .collect();
+        if y_sort || biased {
+            // The sort is stable, so tiles with equal keys stay in storage order.
+            keyed.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        }
+        self.draw_order.clear();
+        self.draw_order
+            .extend(keyed.into_iter().map(|(_, index)| index));
+    }
+
+    /// Rebuilds the mesh of a dirty chunk and uploads it to new GPU buffers, or only rewrites the
+    /// colors and visibility if nothing else changed. This doesn't touch any shared state, so it
+    /// can run for many chunks in parallel.
+    ///
+    /// Returns `true` if the mesh was rebuilt, in which case
+    /// [`prepare_render_mesh`](Self::prepare_render_mesh) must be called afterwards.
+    pub fn prepare_buffers(&mut self, device: &RenderDevice, queue: &RenderQueue) -> bool {
+        if self.frozen {
+            // There is nothing left to build a mesh from.
+            self.dirty_mesh = false;
+            self.dirty_colors = false;
+            return false;
+        }
 
-                let position: [f32; 4] = tile.position.to_array();
-                positions.extend(
-                    [
-                        // X, Y
-                        position,
-                        // X, Y + 1
-                        //[tile_pos.x, tile_pos.y + 1.0, animation_speed],
-                        position,
-                        // X + 1, Y + 1
-                        //[tile_pos.x + 1.0, tile_pos.y + 1.0, animation_speed],
-                        position,
-                        // X + 1, Y
-                        //[tile_pos.x + 1.0, tile_pos.y, animation_speed],
-                        position,
-                    ]
-                    .into_iter(),
-                );
-
-                colors.extend(std::iter::repeat(tile.color).take(4));
-
-                // flipping and rotation packed in bits
-                // bit 0 : flip_x
-                // bit 1 : flip_y
-                // bit 2 : flip_d (anti diagonal)
-
-                // let tile_flip_bits =
-                //     tile.flip_x as i32 | (tile.flip_y as i32) << 1 | (tile.flip_d as i32) << 2;
-
-                //let texture: [f32; 4] = tile.texture.xyxx().into();
-                let texture: [f32; 4] = tile.texture.to_array();
-                textures.extend([texture, texture, texture, texture].into_iter());
-
-                indices.extend_from_slice(&[i, i + 2, i + 1, i, i + 3, i + 2]);
-                i += 4;
+        if !self.dirty_mesh && self.dirty_colors {
+            // Only colors or visibility changed, so the vertex count is unchanged and the
+            // existing state buffer can be overwritten in place.
+            if let Some(color_buffer) = &self.color_buffer {
+                queue.write_buffer(color_buffer, 0, &self.state_buffer_data());
+                self.dirty_colors = false;
+                return false;
             }
+            self.dirty_mesh = true;
+        }
 
-            self.mesh.insert_attribute(
-                crate::render::ATTRIBUTE_POSITION,
-                VertexAttributeValues::Float32x4(positions),
-            );
-            self.mesh.insert_attribute(
-                crate::render::ATTRIBUTE_TEXTURE,
-                VertexAttributeValues::Float32x4(textures),
-            );
-            self.mesh.insert_attribute(
-                crate::render::ATTRIBUTE_COLOR,
-                VertexAttributeValues::Float32x4(colors),
+        if !self.dirty_mesh {
+            return false;
+        }
+
+        let size = ((self.size_in_tiles.x * self.size_in_tiles.y) * 4) as usize;
+        let mut positions: Vec<[f32; 2]> = Vec::with_capacity(size);
+        let mut textures: Vec<[f32; 3]> = Vec::with_capacity(size);
+        let mut indices: Vec<u32> =
+            Vec::with_capacity(((self.size_in_tiles.x * self.size_in_tiles.y) * 6) as usize);
+
+        let mut i = 0;
+
+        // Convert tile into mesh data. Hidden tiles are kept, and collapsed by the vertex shader.
+        self.update_draw_order();
+        for tile in self
+            .draw_order
+            .iter()
+            .filter_map(|index| self.tiles[*index].as_ref())
+        {
+            let position: [f32; 2] = tile.position.to_array();
+            positions.extend(
+                [
+                    // X, Y
+                    position,
+                    // X, Y + 1
+                    //[tile_pos.x, tile_pos.y + 1.0, animation_speed],
+                    position,
+                    // X + 1, Y + 1
+                    //[tile_pos.x + 1.0, tile_pos.y + 1.0, animation_speed],
+                    position,
+                    // X + 1, Y
+                    //[tile_pos.x + 1.0, tile_pos.y, animation_speed],
+                    position,
+                ]
+                .into_iter(),
             );
-            self.mesh.insert_indices(Indices::U32(indices));
 
-            let vertex_buffer_data = self.mesh.create_packed_vertex_buffer_data();
-            let vertex_buffer = device.create_buffer_with_data(&BufferInitDescriptor {
-                usage: BufferUsages::VERTEX,
-                label: Some("Mesh Vertex Buffer"),
-                contents: &vertex_buffer_data,
-            });
+            // flipping and rotation packed in bits
+            // bit 0 : flip_x
+            // bit 1 : flip_y
+            // bit 2 : flip_d (anti diagonal)
 
-            let index_buffer = device.create_buffer_with_data(&BufferInitDescriptor {
-                usage: BufferUsages::INDEX,
-                contents: self.mesh.get_index_buffer_bytes().unwrap(),
-                label: Some("Mesh Index Buffer"),
-            });
+            // let tile_flip_bits =
+            //     tile.flip_x as i32 | (tile.flip_y as i32) << 1 | (tile.flip_d as i32) << 2;
 
-            let buffer_info = RenderMeshBufferInfo::Indexed {
-                count: self.mesh.indices().unwrap().len() as u32,
-                index_format: self.mesh.indices().unwrap().into(),
-            };
-
-            let mesh_vertex_buffer_layout = self
-                .mesh
-                .get_mesh_vertex_buffer_layout(mesh_vertex_buffer_layouts);
-            self.render_mesh = Some(RenderMesh {
-                vertex_count: self.mesh.count_vertices() as u32,
-                buffer_info,
-                morph_targets: None,
-                layout: mesh_vertex_buffer_layout,
-                key_bits: BaseMeshPipelineKey::from_primitive_topology(
-                    PrimitiveTopology::TriangleList,
-                ),
-            });
-            self.vertex_buffer = Some(vertex_buffer);
-            self.index_buffer = Some(index_buffer);
-            self.dirty_mesh = false;
+            //let texture: [f32; 4] = tile.texture.xyxx().into();
+            let texture: [f32; 3] = tile.texture.to_array();
+            textures.extend([texture, texture, texture, texture].into_iter());
+
+            indices.extend_from_slice(&[i, i + 2, i + 1, i, i + 3, i + 2]);
+            i += 4;
         }
+
+        self.mesh.insert_attribute(
+            crate::render::ATTRIBUTE_POSITION,
+            VertexAttributeValues::Float32x2(positions),
+        );
+        self.mesh.insert_attribute(
+            crate::render::ATTRIBUTE_TEXTURE,
+            VertexAttributeValues::Float32x3(textures),
+        );
+        self.mesh.insert_indices(Indices::U32(indices));
+
+        let vertex_buffer_data = self.mesh.create_packed_vertex_buffer_data();
+        let vertex_buffer = device.create_buffer_with_data(&BufferInitDescriptor {
+            usage: BufferUsages::VERTEX,
+            label: Some("Mesh Vertex Buffer"),
+            contents: &vertex_buffer_data,
+        });
+
+        let color_buffer = device.create_buffer_with_data(&BufferInitDescriptor {
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            label: Some("Mesh State Buffer"),
+            contents: &self.state_buffer_data(),
+        });
+
+        let index_buffer = device.create_buffer_with_data(&BufferInitDescriptor {
+            usage: BufferUsages::INDEX,
+            contents: self.mesh.get_index_buffer_bytes().unwrap(),
+            label: Some("Mesh Index Buffer"),
+        });
+
+        self.data_buffer = self.vertex_data.then(|| {
+            device.create_buffer_with_data(&BufferInitDescriptor {
+                usage: BufferUsages::VERTEX,
+                label: Some("Mesh Data Buffer"),
+                contents: &self.vertex_data_buffer_data(),
+            })
+        });
+
+        // Most chunks only hold tiles sitting on the grid, which don't need a transform buffer.
+        let transformed = self
+            .tiles
+            .iter()
+            .flatten()
+            .any(|tile| tile.transform != Vec3::ZERO);
+        self.transform_buffer = transformed.then(|| {
+            device.create_buffer_with_data(&BufferInitDescriptor {
+                usage: BufferUsages::VERTEX,
+                label: Some("Mesh Transform Buffer"),
+                contents: &self.transform_buffer_data(),
+            })
+        });
+
+        // Oversized tiles can stick out of the chunk, so its bounds grow to keep them in view.
+        let render_size = self
+            .tiles
+            .iter()
+            .flatten()
+            .fold(Vec2::ZERO, |size, tile| size.max(tile.render_size));
+        self.size_buffer = (render_size != Vec2::ZERO).then(|| {
+            device.create_buffer_with_data(&BufferInitDescriptor {
+                usage: BufferUsages::VERTEX,
+                label: Some("Mesh Size Buffer"),
+                contents: &self.size_buffer_data(),
+            })
+        });
+        let bounds_size = Vec2::from(self.tile_size).max(render_size);
+        self.aabb = chunk_aabb(
+            self.size_in_tiles,
+            &self.grid_size,
+            &TilemapTileSize {
+                x: bounds_size.x,
+                y: bounds_size.y,
+            },
+            &self.map_type,
+        );
+
+        self.vertex_buffer = Some(vertex_buffer);
+        self.color_buffer = Some(color_buffer);
+        self.index_buffer = Some(index_buffer);
+        self.dirty_mesh = false;
+        self.dirty_colors = false;
+        true
+    }
+
+    /// Describes the mesh rebuilt by [`prepare_buffers`](Self::prepare_buffers) to the render
+    /// pipeline.
+    pub fn prepare_render_mesh(
+        &mut self,
+        mesh_vertex_buffer_layouts: &mut MeshVertexBufferLayouts,
+    ) {
+        let buffer_info = RenderMeshBufferInfo::Indexed {
+            count: self.mesh.indices().unwrap().len() as u32,
+            index_format: self.mesh.indices().unwrap().into(),
+        };
+
+        let mesh_vertex_buffer_layout = self
+            .mesh
+            .get_mesh_vertex_buffer_layout(mesh_vertex_buffer_layouts);
+        self.render_mesh = Some(RenderMesh {
+            vertex_count: self.mesh.count_vertices() as u32,
+            buffer_info,
+            morph_targets: None,
+            layout: mesh_vertex_buffer_layout,
+            key_bits: BaseMeshPipelineKey::from_primitive_topology(PrimitiveTopology::TriangleList),
+        });
     }
 }
 
+/// Set in the flags of the state vertex stream for tiles that are hidden.
+pub(crate) const TILE_FLAG_HIDDEN: u32 = 1;
+/// Set for animations holding the frame at their `animation_time`.
+pub(crate) const TILE_FLAG_ANIMATION_HOLD: u32 = 1 << 1;
+pub(crate) const TILE_FLAG_ANIMATION_REVERSE: u32 = 1 << 2;
+pub(crate) const TILE_FLAG_ANIMATION_PING_PONG: u32 = 1 << 3;
+pub(crate) const TILE_FLAG_ANIMATION_ONCE: u32 = 1 << 4;
+/// Set for animations with a state, which don't get the tilemap's animation phase.
+pub(crate) const TILE_FLAG_ANIMATION_STATE: u32 = 1 << 5;
+
+/// The bytes of a vertex in the state buffer: the color, the flags, the color to pulse towards
+/// and the period of the pulse, then the animation time.
+pub(crate) const STATE_VERTEX_SIZE: usize = 44;
+
+/// The bytes of a vertex in the data buffer: the vertex data of the tile.
+pub(crate) const DATA_VERTEX_SIZE: usize = 16;
+
+/// The bytes of a vertex in the transform buffer: the offset and the rotation of the tile.
+pub(crate) const TRANSFORM_VERTEX_SIZE: usize = 12;
+
+/// The bytes of a vertex in the size buffer: the render size of the tile.
+pub(crate) const SIZE_VERTEX_SIZE: usize = 8;
+
 // Used to transfer info to the GPU for tile building.
 #[derive(Debug, Default, Copy, Component, Clone, ShaderType)]
 pub struct TilemapUniformData {
@@ -480,6 +891,16 @@ pub struct TilemapUniformData {
     pub spacing: Vec2,
     pub chunk_pos: Vec2,
     pub map_size: Vec2,
+    pub color: Vec4,
+    /// The row of the animation lookup texture holding the animations of the map.
+    pub animation_row: u32,
+    /// The largest offset of tile animations, as a fraction of a cycle.
+    pub animation_phase: f32,
+    /// How far texture coordinates are pulled in from the edges of atlas tiles, in texels.
+    pub uv_inset: f32,
+    /// The bottom and height of the local `y` range that [`ChunkZPolicy::TileDepth`] spreads over
+    /// depths from `1.0` down to `0.0`.
+    pub depth_range: Vec2,
 }
 
 impl From<&RenderChunk2d> for TilemapUniformData {
@@ -495,6 +916,15 @@ impl From<&RenderChunk2d> for TilemapUniformData {
             spacing: chunk.spacing,
             chunk_pos: chunk_ix * chunk_size,
             map_size: map_size * tile_size,
+            color: chunk.color,
+            animation_row: 0,
+            animation_phase: chunk.animation_phase,
+            uv_inset: chunk.uv_inset,
+            depth_range: ChunkZPolicy::tile_depth_range(
+                &chunk.map_size,
+                &chunk.grid_size,
+                &chunk.map_type,
+            ),
         }
     }
 }
@@ -512,6 +942,62 @@ impl From<&mut RenderChunk2d> for TilemapUniformData {
             spacing: chunk.spacing,
             chunk_pos: chunk_pos * chunk_size,
             map_size: map_size * tile_size,
+            color: chunk.color,
+            animation_row: 0,
+            animation_phase: chunk.animation_phase,
+            uv_inset: chunk.uv_inset,
+            depth_range: ChunkZPolicy::tile_depth_range(
+                &chunk.map_size,
+                &chunk.grid_size,
+                &chunk.map_type,
+            ),
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use bevy::prelude::World;
+
+    pub(crate) fn add_tile(storage: &mut RenderChunk2dStorage, tile: Entity, tilemap: Entity) {
+        storage.get_or_add(
+            tile,
+            UVec2::ZERO,
+            tilemap,
+            &UVec3::ZERO,
+            UVec2::splat(4),
+            TilemapType::Square,
+            TilemapTileSize { x: 16.0, y: 16.0 },
+            Vec2::splat(16.0),
+            Vec2::ZERO,
+            TilemapGridSize { x: 16.0, y: 16.0 },
+            TilemapTexture::default(),
+            TilemapSize { x: 4, y: 4 },
+            GlobalTransform::default(),
+            &InheritedVisibility::VISIBLE,
+            &FrustumCulling::default(),
+            RenderChunkSize::new(UVec2::splat(4)),
+        );
+    }
+
+    #[test]
+    fn respawned_tilemaps_do_not_share_chunks() {
+        let mut world = World::new();
+        let despawned = world.spawn_empty().id();
+        world.despawn(despawned);
+        let respawned = world.spawn_empty().id();
+        assert_eq!(despawned.index(), respawned.index());
+
+        let mut storage = RenderChunk2dStorage::default();
+        let tile = world.spawn_empty().id();
+        add_tile(&mut storage, tile, despawned);
+
+        assert!(storage.get(despawned, &UVec3::ZERO).is_some());
+        assert!(storage.get(respawned, &UVec3::ZERO).is_none());
+        assert_eq!(storage.tilemap_of(tile), Some(despawned));
+
+        storage.remove_map(respawned);
+        assert!(storage.get(despawned, &UVec3::ZERO).is_some());
+    }
+}
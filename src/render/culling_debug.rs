@@ -0,0 +1,156 @@
+use std::sync::{Arc, RwLock};
+
+use bevy::{
+    prelude::*,
+    render::{primitives::Aabb, sync_world::RenderEntity, Extract, ExtractSchedule, RenderApp},
+    utils::{HashMap, HashSet},
+};
+
+#[cfg(feature = "culling_gizmos")]
+use crate::TilemapSystemSet;
+
+/// Runtime toggles to debug tilemap chunks that disappear while they should be in view.
+///
+/// Flip its fields at runtime, e.g. from a key binding. Requires the
+/// [`TilemapCullingDebugPlugin`].
+#[derive(Resource, Clone, Default, Debug)]
+pub struct TilemapCullingDebug {
+    /// Draws the bounding box of every render chunk with gizmos: green for the chunks that are
+    /// drawn, red for the frustum culled ones. Requires the `culling_gizmos` feature.
+    pub draw_aabbs: bool,
+    /// Tilemaps whose chunks are never frustum culled, whatever their [`FrustumCulling`](crate::FrustumCulling).
+    pub unculled_tilemaps: HashSet<Entity>,
+    /// Logs every chunk that is frustum culled in a frame where some of its tiles changed.
+    pub log_culled_changes: bool,
+    // Arc and RwLock let the render world write the chunk bounds back to the main world.
+    aabbs: Arc<RwLock<Vec<ChunkAabb>>>,
+}
+
+impl TilemapCullingDebug {
+    /// Returns the bounds of the render chunks during the last rendered frame, if
+    /// [`Self::draw_aabbs`] was set.
+    pub fn chunk_aabbs(&self) -> Vec<ChunkAabb> {
+        self.aabbs
+            .read()
+            .map_or_else(|_| Vec::new(), |aabbs| aabbs.clone())
+    }
+}
+
+/// The bounds of a render chunk, as reported by [`TilemapCullingDebug::chunk_aabbs`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChunkAabb {
+    /// The bounding box of the chunk, in the space of `transform`.
+    pub aabb: Aabb,
+    /// The transform of the chunk, which the frustum is tested against.
+    pub transform: Mat4,
+    /// True if the chunk was frustum culled.
+    pub culled: bool,
+}
+
+/// Adds the [`TilemapCullingDebug`] resource, and draws the chunk bounds it asks for when the
+/// `culling_gizmos` feature is enabled.
+///
+/// ```no_run
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::{TilemapCullingDebug, TilemapCullingDebugPlugin};
+/// fn toggle_aabbs(keys: Res<ButtonInput<KeyCode>>, mut debug: ResMut<TilemapCullingDebug>) {
+///     if keys.just_pressed(KeyCode::F3) {
+///         debug.draw_aabbs = !debug.draw_aabbs;
+///     }
+/// }
+///
+/// App::new()
+///     .add_plugins(TilemapCullingDebugPlugin)
+///     .add_systems(Update, toggle_aabbs);
+/// ```
+pub struct TilemapCullingDebugPlugin;
+
+impl Plugin for TilemapCullingDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TilemapCullingDebug>();
+
+        #[cfg(feature = "culling_gizmos")]
+        app.add_systems(
+            PostUpdate,
+            draw_chunk_aabbs.after(TilemapSystemSet::ExtractionPrep),
+        );
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_systems(ExtractSchedule, extract_culling_debug);
+        }
+    }
+}
+
+/// The [`TilemapCullingDebug`] of the main world, with tilemaps as render entities.
+#[derive(Resource, Clone, Default, Debug)]
+pub(crate) struct ExtractedCullingDebug {
+    pub draw_aabbs: bool,
+    pub unculled_tilemaps: HashSet<Entity>,
+    pub log_culled_changes: bool,
+    /// The main world entities of the tilemaps, by render entity, to log culled changes.
+    pub main_entities: HashMap<Entity, Entity>,
+    aabbs: Arc<RwLock<Vec<ChunkAabb>>>,
+}
+
+impl ExtractedCullingDebug {
+    /// Replaces the chunk bounds read by [`TilemapCullingDebug::chunk_aabbs`].
+    pub fn write_aabbs(&self, aabbs: Vec<ChunkAabb>) {
+        if let Ok(mut written) = self.aabbs.write() {
+            *written = aabbs;
+        }
+    }
+}
+
+fn extract_culling_debug(
+    mut commands: Commands,
+    debug: Extract<Res<TilemapCullingDebug>>,
+    tilemaps: Extract<Query<(Entity, &RenderEntity), With<crate::map::TilemapTexture>>>,
+) {
+    let mut unculled_tilemaps = HashSet::default();
+    let mut main_entities = HashMap::default();
+    for (entity, render_entity) in tilemaps.iter() {
+        if debug.unculled_tilemaps.contains(&entity) {
+            unculled_tilemaps.insert(render_entity.id());
+        }
+        if debug.log_culled_changes {
+            main_entities.insert(render_entity.id(), entity);
+        }
+    }
+    commands.insert_resource(ExtractedCullingDebug {
+        draw_aabbs: debug.draw_aabbs,
+        unculled_tilemaps,
+        log_culled_changes: debug.log_culled_changes,
+        main_entities,
+        aabbs: debug.aabbs.clone(),
+    });
+}
+
+#[cfg(feature = "culling_gizmos")]
+fn draw_chunk_aabbs(debug: Res<TilemapCullingDebug>, mut gizmos: Gizmos) {
+    if !debug.draw_aabbs {
+        return;
+    }
+    for chunk in debug.chunk_aabbs() {
+        let transform = Transform::from_matrix(
+            chunk.transform
+                * Mat4::from_scale_rotation_translation(
+                    Vec3::from(chunk.aabb.half_extents) * 2.0,
+                    Quat::IDENTITY,
+                    chunk.aabb.center.into(),
+                ),
+        );
+        let color = if chunk.culled {
+            Color::srgb(1.0, 0.0, 0.0)
+        } else {
+            Color::srgb(0.0, 1.0, 0.0)
+        };
+        gizmos.rect_2d(
+            Isometry2d::new(
+                transform.translation.truncate(),
+                Rot2::radians(transform.rotation.to_euler(EulerRot::XYZ).2),
+            ),
+            transform.scale.truncate(),
+            color,
+        );
+    }
+}
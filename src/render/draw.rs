@@ -1,21 +1,23 @@
 use std::marker::PhantomData;
 
 use bevy::{
-    core_pipeline::core_2d::Transparent2d,
     ecs::system::{
         lifetimeless::{Read, SQuery, SRes},
         SystemParamItem,
     },
-    math::UVec4,
+    math::{Rect, URect, Vec2, Vec4Swizzles},
     render::{
         mesh::RenderMeshBufferInfo,
-        render_phase::{RenderCommand, RenderCommandResult, TrackedRenderPass},
+        render_phase::{
+            CachedRenderPipelinePhaseItem, PhaseItem, RenderCommand, RenderCommandResult,
+            TrackedRenderPass,
+        },
         render_resource::PipelineCache,
-        view::ViewUniformOffset,
+        view::{ExtractedView, ViewUniformOffset},
     },
 };
 
-use crate::map::TilemapId;
+use crate::map::{TilemapClipRect, TilemapId};
 use crate::TilemapTexture;
 
 use super::{
@@ -27,13 +29,13 @@ use super::{
 };
 
 pub struct SetMeshViewBindGroup<const I: usize>;
-impl<const I: usize> RenderCommand<Transparent2d> for SetMeshViewBindGroup<I> {
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetMeshViewBindGroup<I> {
     type Param = ();
     type ViewQuery = (Read<ViewUniformOffset>, Read<TilemapViewBindGroup>);
     type ItemQuery = ();
     #[inline]
     fn render<'w>(
-        _item: &Transparent2d,
+        _item: &P,
         (view_uniform, pbr_view_bind_group): (&'w ViewUniformOffset, &'w TilemapViewBindGroup),
         _entity: Option<()>,
         _param: (),
@@ -46,7 +48,7 @@ impl<const I: usize> RenderCommand<Transparent2d> for SetMeshViewBindGroup<I> {
 }
 
 pub struct SetTransformBindGroup<const I: usize>;
-impl<const I: usize> RenderCommand<Transparent2d> for SetTransformBindGroup<I> {
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetTransformBindGroup<I> {
     type Param = SRes<TransformBindGroup>;
     type ViewQuery = ();
     type ItemQuery = (
@@ -55,7 +57,7 @@ impl<const I: usize> RenderCommand<Transparent2d> for SetTransformBindGroup<I> {
     );
     #[inline]
     fn render<'w>(
-        _item: &Transparent2d,
+        _item: &P,
         _view: (),
         uniform_indices: Option<(
             &'w DynamicUniformIndex<MeshUniform>,
@@ -79,13 +81,13 @@ impl<const I: usize> RenderCommand<Transparent2d> for SetTransformBindGroup<I> {
 }
 
 pub struct SetTextureBindGroup<const I: usize>;
-impl<const I: usize> RenderCommand<Transparent2d> for SetTextureBindGroup<I> {
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetTextureBindGroup<I> {
     type Param = SRes<ImageBindGroups>;
     type ViewQuery = ();
     type ItemQuery = Read<TilemapTexture>;
     #[inline]
     fn render<'w>(
-        _item: &Transparent2d,
+        _item: &P,
         _view: (),
         texture: Option<&'w TilemapTexture>,
         image_bind_groups: SystemParamItem<'w, '_, Self::Param>,
@@ -103,13 +105,13 @@ impl<const I: usize> RenderCommand<Transparent2d> for SetTextureBindGroup<I> {
 }
 
 pub struct SetItemPipeline;
-impl RenderCommand<Transparent2d> for SetItemPipeline {
+impl<P: CachedRenderPipelinePhaseItem> RenderCommand<P> for SetItemPipeline {
     type Param = SRes<PipelineCache>;
     type ViewQuery = ();
     type ItemQuery = ();
     #[inline]
     fn render<'w>(
-        item: &Transparent2d,
+        item: &P,
         _view: (),
         _entity: Option<()>,
         pipeline_cache: SystemParamItem<'w, '_, Self::Param>,
@@ -117,7 +119,7 @@ impl RenderCommand<Transparent2d> for SetItemPipeline {
     ) -> RenderCommandResult {
         if let Some(pipeline) = pipeline_cache
             .into_inner()
-            .get_render_pipeline(item.pipeline)
+            .get_render_pipeline(item.cached_pipeline())
         {
             pass.set_render_pipeline(pipeline);
             RenderCommandResult::Success
@@ -127,12 +129,109 @@ impl RenderCommand<Transparent2d> for SetItemPipeline {
     }
 }
 
+/// Restricts drawing to the chunk's [`TilemapClipRect`], if it has one. Chunks whose clip rect
+/// is entirely outside of the view are skipped.
+pub struct SetClipRect;
+impl<P: PhaseItem> RenderCommand<P> for SetClipRect {
+    type Param = ();
+    type ViewQuery = Read<ExtractedView>;
+    type ItemQuery = Read<TilemapClipRect>;
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        view: &'w ExtractedView,
+        clip_rect: Option<&'w TilemapClipRect>,
+        _param: (),
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(clip_rect) = clip_rect else {
+            return RenderCommandResult::Success;
+        };
+
+        let scissor = clip_rect_to_scissor(clip_rect, view);
+        if scissor.is_empty() {
+            return RenderCommandResult::Skip;
+        }
+        pass.set_scissor_rect(
+            scissor.min.x,
+            scissor.min.y,
+            scissor.width(),
+            scissor.height(),
+        );
+
+        RenderCommandResult::Success
+    }
+}
+
+/// Resets the scissor rect set by [`SetClipRect`] to the whole view, so that it doesn't apply to
+/// whatever is drawn next.
+pub struct ResetClipRect;
+impl<P: PhaseItem> RenderCommand<P> for ResetClipRect {
+    type Param = ();
+    type ViewQuery = Read<ExtractedView>;
+    type ItemQuery = Read<TilemapClipRect>;
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        view: &'w ExtractedView,
+        clip_rect: Option<&'w TilemapClipRect>,
+        _param: (),
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        if clip_rect.is_some() {
+            let viewport = view.viewport;
+            pass.set_scissor_rect(viewport.x, viewport.y, viewport.z, viewport.w);
+        }
+
+        RenderCommandResult::Success
+    }
+}
+
+/// Converts a clip rect into a scissor rect in render target pixels, clamped to the view's
+/// viewport.
+fn clip_rect_to_scissor(clip_rect: &TilemapClipRect, view: &ExtractedView) -> URect {
+    let origin = view.viewport.xy().as_vec2();
+    let size = view.viewport.zw().as_vec2();
+    let rect = match clip_rect {
+        TilemapClipRect::Viewport(rect) => rect.intersect(Rect::from_corners(Vec2::ZERO, size)),
+        TilemapClipRect::World(rect) => {
+            let clip_from_world = view.clip_from_world.unwrap_or_else(|| {
+                view.clip_from_view * view.world_from_view.compute_matrix().inverse()
+            });
+            [
+                rect.min,
+                Vec2::new(rect.max.x, rect.min.y),
+                rect.max,
+                Vec2::new(rect.min.x, rect.max.y),
+            ]
+            .into_iter()
+            .map(|corner| {
+                let ndc = clip_from_world.project_point3(corner.extend(0.0));
+                // Normalized device coordinates have y pointing up, pixels have y pointing down.
+                (ndc.truncate() * Vec2::new(0.5, -0.5) + Vec2::splat(0.5)) * size
+            })
+            .fold(Rect::EMPTY, |bounds, corner| bounds.union_point(corner))
+            .intersect(Rect::from_corners(Vec2::ZERO, size))
+        }
+    };
+
+    if rect.is_empty() {
+        return URect::default();
+    }
+    URect::from_corners(
+        (origin + rect.min).round().as_uvec2(),
+        (origin + rect.max).round().as_uvec2(),
+    )
+}
+
 pub type DrawTilemap = (
     SetItemPipeline,
     SetMeshViewBindGroup<0>,
     SetTransformBindGroup<1>,
     SetTextureBindGroup<2>,
+    SetClipRect,
     DrawMesh,
+    ResetClipRect,
 );
 
 pub type DrawTilemapMaterial<M> = (
@@ -141,11 +240,13 @@ pub type DrawTilemapMaterial<M> = (
     SetTransformBindGroup<1>,
     SetTextureBindGroup<2>,
     SetMaterialBindGroup<M, 3>,
+    SetClipRect,
     DrawMesh,
+    ResetClipRect,
 );
 
 pub struct SetMaterialBindGroup<M: MaterialTilemap, const I: usize>(PhantomData<M>);
-impl<M: MaterialTilemap, const I: usize> RenderCommand<Transparent2d>
+impl<P: PhaseItem, M: MaterialTilemap, const I: usize> RenderCommand<P>
     for SetMaterialBindGroup<M, I>
 {
     type Param = (
@@ -156,7 +257,7 @@ impl<M: MaterialTilemap, const I: usize> RenderCommand<Transparent2d>
     type ItemQuery = Read<TilemapId>;
     #[inline]
     fn render<'w>(
-        _item: &Transparent2d,
+        _item: &P,
         _view: (),
         id: Option<&'w TilemapId>,
         (material_bind_groups, material_handles): SystemParamItem<'w, '_, Self::Param>,
@@ -179,13 +280,13 @@ impl<M: MaterialTilemap, const I: usize> RenderCommand<Transparent2d>
 }
 
 pub struct DrawMesh;
-impl RenderCommand<Transparent2d> for DrawMesh {
+impl<P: PhaseItem> RenderCommand<P> for DrawMesh {
     type Param = SRes<RenderChunk2dStorage>;
     type ViewQuery = ();
     type ItemQuery = (Read<ChunkId>, Read<TilemapId>);
     #[inline]
     fn render<'w>(
-        _item: &Transparent2d,
+        _item: &P,
         _view: (),
         ids: Option<(&'w ChunkId, &'w TilemapId)>,
         chunk_storage: SystemParamItem<'w, '_, Self::Param>,
@@ -195,15 +296,16 @@ impl RenderCommand<Transparent2d> for DrawMesh {
             return RenderCommandResult::Skip;
         };
 
-        if let Some(chunk) = chunk_storage.into_inner().get(&UVec4::new(
-            chunk_id.0.x,
-            chunk_id.0.y,
-            chunk_id.0.z,
-            tilemap_id.0.index(),
-        )) {
-            if let (Some(render_mesh), Some(vertex_buffer), Some(index_buffer)) = (
+        if let Some(chunk) = chunk_storage.into_inner().get(tilemap_id.0, &chunk_id.0) {
+            if let (
+                Some(render_mesh),
+                Some(vertex_buffer),
+                Some(color_buffer),
+                Some(index_buffer),
+            ) = (
                 &chunk.render_mesh,
                 &chunk.vertex_buffer,
+                &chunk.color_buffer,
                 &chunk.index_buffer,
             ) {
                 if render_mesh.vertex_count == 0 {
@@ -211,6 +313,19 @@ impl RenderCommand<Transparent2d> for DrawMesh {
                 }
 
                 pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                pass.set_vertex_buffer(1, color_buffer.slice(..));
+                // The optional buffers follow each other, in the order of the pipeline layout.
+                for (slot, buffer) in [
+                    &chunk.data_buffer,
+                    &chunk.transform_buffer,
+                    &chunk.size_buffer,
+                ]
+                .into_iter()
+                .flatten()
+                .enumerate()
+                {
+                    pass.set_vertex_buffer(2 + slot, buffer.slice(..));
+                }
                 match &render_mesh.buffer_info {
                     RenderMeshBufferInfo::Indexed {
                         index_format,
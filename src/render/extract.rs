@@ -3,23 +3,43 @@ use bevy::render::primitives::{Aabb, Frustum};
 use bevy::render::render_resource::FilterMode;
 use bevy::render::render_resource::TextureFormat;
 use bevy::render::sync_world::RenderEntity;
-use bevy::{prelude::*, render::Extract, utils::HashMap};
+use bevy::{
+    prelude::*,
+    render::Extract,
+    utils::{HashMap, HashSet, Parallel},
+};
 
+use crate::helpers::layer_stack::TileOccluded;
+use crate::helpers::layers::TilemapComposition;
 use crate::prelude::TilemapGridSize;
 use crate::prelude::TilemapRenderSettings;
 use crate::render::DefaultSampler;
-use crate::tiles::AnimatedTile;
 use crate::tiles::TilePosOld;
+use crate::tiles::{
+    AnimatedTile, AnimatedTileFrames, AnimationMode, AnimationState, TileMotion, TilePosOffset,
+    TileRenderSize, TileRotation, TileSortBias, TileVertexData,
+};
 use crate::{
     map::{
-        TilemapId, TilemapSize, TilemapSpacing, TilemapTexture, TilemapTextureSize,
-        TilemapTileSize, TilemapType,
+        ChunkZPolicy, TilemapAnimationPhase, TilemapBlendMode, TilemapClipRect, TilemapColor,
+        TilemapId, TilemapRenderMode, TilemapSize, TilemapSortKey, TilemapSpacing, TilemapStatic,
+        TilemapTexture, TilemapTextureSize, TilemapTileSize, TilemapType, TilemapUvInset,
+    },
+    tiles::{
+        TileColor, TileColorAnimation, TileFlip, TilePos, TileStorage, TileTextureIndex,
+        TileVisible,
     },
-    tiles::{TileColor, TileFlip, TilePos, TileTextureIndex, TileVisible},
     FrustumCulling,
 };
 
-use super::chunk::PackedTileData;
+use super::{
+    animation::ExtractedAnimation,
+    chunk::{
+        PackedTileData, TILE_FLAG_ANIMATION_HOLD, TILE_FLAG_ANIMATION_ONCE,
+        TILE_FLAG_ANIMATION_PING_PONG, TILE_FLAG_ANIMATION_REVERSE, TILE_FLAG_ANIMATION_STATE,
+    },
+    material::TileVertexDataEnabled,
+};
 
 #[derive(Component)]
 pub struct ChangedInMainWorld;
@@ -29,8 +49,14 @@ pub struct ExtractedTile {
     pub entity: Entity,
     pub position: TilePos,
     pub old_position: TilePosOld,
+    /// The offset, in tiles, of a tile gliding to its position.
+    pub motion_offset: Vec2,
     pub tile: PackedTileData,
+    pub animation: Option<ExtractedAnimation>,
     pub tilemap_id: TilemapId,
+    /// The layer of the chunk the tile is drawn in: 0 for tiles of the tilemap of the chunk, and
+    /// from 1 for tiles of the tilemaps of its [`TilemapComposition`].
+    pub layer: u32,
 }
 
 #[derive(Bundle)]
@@ -52,9 +78,34 @@ pub struct ExtractedTilemapBundle {
     visibility: InheritedVisibility,
     frustum_culling: FrustumCulling,
     render_settings: TilemapRenderSettings,
+    color: TilemapColor,
+    animation_phase: TilemapAnimationPhase,
+    uv_inset: TilemapUvInset,
+    blend_mode: TilemapBlendMode,
+    render_mode: TilemapRenderMode,
+    clip_rect: ExtractedClipRect,
+    sort_key: ExtractedSortKey,
+    z_policy: ChunkZPolicy,
+    vertex_data: ExtractedVertexData,
     changed: ChangedInMainWorld,
 }
 
+/// The render entities of the tilemaps marked with [`TilemapStatic`], extracted every frame.
+#[derive(Resource, Default, Debug)]
+pub struct ExtractedStaticTilemaps(pub HashSet<Entity>);
+
+/// Whether an extracted tilemap has a [`TileVertexDataEnabled`].
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct ExtractedVertexData(pub bool);
+
+/// The [`TilemapClipRect`] of an extracted tilemap, if it has one.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct ExtractedClipRect(pub Option<TilemapClipRect>);
+
+/// The [`TilemapSortKey`] of an extracted tilemap, if it has one.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ExtractedSortKey(pub Option<TilemapSortKey>);
+
 #[derive(Component)]
 pub(crate) struct ExtractedTilemapTexture {
     pub tilemap_id: TilemapId,
@@ -167,6 +218,23 @@ impl ExtractedFrustum {
     }
 }
 
+/// Returns the flags telling the shader how to play the animation of a tile.
+fn animation_flags(state: &AnimationState) -> u32 {
+    let mut flags = TILE_FLAG_ANIMATION_STATE
+        | match state.mode {
+            AnimationMode::Loop => 0,
+            AnimationMode::PingPong => TILE_FLAG_ANIMATION_PING_PONG,
+            AnimationMode::Once => TILE_FLAG_ANIMATION_ONCE,
+        };
+    if state.reverse {
+        flags |= TILE_FLAG_ANIMATION_REVERSE;
+    }
+    if state.is_holding() {
+        flags |= TILE_FLAG_ANIMATION_HOLD;
+    }
+    flags
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn extract(
     mut commands: Commands,
@@ -183,14 +251,39 @@ pub fn extract(
                 &TileFlip,
                 &TileColor,
                 Option<&AnimatedTile>,
+                Option<&TileOccluded>,
+                Option<&TileColorAnimation>,
+                Option<&AnimatedTileFrames>,
+                Option<&AnimationState>,
+                (
+                    Option<&TileSortBias>,
+                    Option<&TileMotion>,
+                    Option<&TileVertexData>,
+                    Option<&TilePosOffset>,
+                    Option<&TileRotation>,
+                    Option<&TileRenderSize>,
+                ),
             ),
             Or<(
                 Changed<TilePos>,
+                Changed<TilemapId>,
                 Changed<TileVisible>,
                 Changed<TileTextureIndex>,
                 Changed<TileFlip>,
                 Changed<TileColor>,
                 Changed<AnimatedTile>,
+                Changed<TileOccluded>,
+                Changed<TileColorAnimation>,
+                Changed<AnimatedTileFrames>,
+                Changed<AnimationState>,
+                Changed<TileSortBias>,
+                Changed<TileMotion>,
+                Or<(
+                    Changed<TileVertexData>,
+                    Changed<TilePosOffset>,
+                    Changed<TileRotation>,
+                    Changed<TileRenderSize>,
+                )>,
             )>,
         >,
     >,
@@ -207,6 +300,19 @@ pub fn extract(
             &InheritedVisibility,
             &FrustumCulling,
             &TilemapRenderSettings,
+            Option<&TilemapColor>,
+            Option<&TileStorage>,
+            (
+                Option<&TilemapBlendMode>,
+                Option<&TilemapClipRect>,
+                Option<&TilemapSortKey>,
+                Option<&TilemapAnimationPhase>,
+                Option<&ChunkZPolicy>,
+                Option<&TilemapUvInset>,
+                Has<TileVertexDataEnabled>,
+                Option<&TileRenderSize>,
+                Option<&TilemapRenderMode>,
+            ),
         )>,
     >,
     changed_tilemap_query: Extract<
@@ -224,91 +330,145 @@ pub fn extract(
                 Changed<InheritedVisibility>,
                 Changed<FrustumCulling>,
                 Changed<TilemapRenderSettings>,
+                Changed<TilemapColor>,
+                Changed<TilemapBlendMode>,
+                Changed<TilemapClipRect>,
+                Or<(
+                    Changed<TilemapSortKey>,
+                    Changed<TilemapAnimationPhase>,
+                    Changed<ChunkZPolicy>,
+                    Changed<TilemapUvInset>,
+                    Changed<TileVertexDataEnabled>,
+                    Changed<TilemapRenderMode>,
+                )>,
             )>,
         >,
     >,
+    composition_query: Extract<Query<(Entity, &RenderEntity, &TilemapComposition)>>,
+    static_tilemap_query: Extract<Query<(Entity, &RenderEntity, Ref<TilemapStatic>)>>,
     camera_query: Extract<Query<(&RenderEntity, &Frustum), With<Camera>>>,
     images: Extract<Res<Assets<Image>>>,
+    mut tiles_buffer: Local<Parallel<Vec<(Entity, Entity, ExtractedTileBundle)>>>,
 ) {
-    let mut extracted_tiles = Vec::new();
     let mut extracted_tilemaps = HashMap::default();
     let mut extracted_tilemap_textures = Vec::new();
-    // Process all tiles
-    for (
-        render_entity,
-        tile_pos,
-        tile_pos_old,
-        tilemap_id,
-        tile_texture,
-        visible,
-        flip,
-        color,
-        animated,
-    ) in changed_tiles_query.iter()
-    {
-        // flipping and rotation packed in bits
-        // bit 0 : flip_x
-        // bit 1 : flip_y
-        // bit 2 : flip_d (anti diagonal)
-        let tile_flip_bits = flip.x as i32 | (flip.y as i32) << 1 | (flip.d as i32) << 2;
+    // Tiles of static tilemaps are still extracted in the frame they became static, so that
+    // tilemaps can be spawned static.
+    let frozen_tilemaps: HashSet<Entity> = static_tilemap_query
+        .iter()
+        .filter(|(_, _, tilemap_static)| !tilemap_static.is_added())
+        .map(|(tilemap, _, _)| tilemap)
+        .collect();
+    // Tiles of composed tilemaps are extracted into the tilemap they are composed into.
+    let composed_into: HashMap<Entity, (Entity, Entity, u32)> = composition_query
+        .iter()
+        .flat_map(|(tilemap, render_entity, composition)| {
+            composition
+                .layers
+                .iter()
+                .zip(1..)
+                .map(move |(layer, index)| (*layer, (tilemap, render_entity.id(), index)))
+        })
+        .collect();
+    // Process all tiles. Large maps can change many tiles at once, so tiles are packed in
+    // parallel, and sorted afterwards to keep the output independent of thread scheduling.
+    changed_tiles_query.par_iter().for_each(
+        |(
+            render_entity,
+            tile_pos,
+            tile_pos_old,
+            tilemap_id,
+            tile_texture,
+            visible,
+            flip,
+            color,
+            animated,
+            occluded,
+            color_animation,
+            animated_frames,
+            animation_state,
+            (sort_bias, motion, vertex_data, offset, rotation, render_size),
+        )| {
+            // flipping and rotation packed in bits
+            // bit 0 : flip_x
+            // bit 1 : flip_y
+            // bit 2 : flip_d (anti diagonal)
+            let tile_flip_bits = flip.x as i32 | (flip.y as i32) << 1 | (flip.d as i32) << 2;
 
-        let mut position = Vec4::new(tile_pos.x as f32, tile_pos.y as f32, 0.0, 0.0);
-        let mut texture = Vec4::new(tile_texture.0 as f32, tile_flip_bits as f32, 0.0, 0.0);
-        if let Some(animation_data) = animated {
-            position.z = animation_data.speed;
-            texture.z = animation_data.start as f32;
-            texture.w = animation_data.end as f32;
-        } else {
-            texture.z = tile_texture.0 as f32;
-            texture.w = tile_texture.0 as f32;
-        }
+            let data = tilemap_query.get(tilemap_id.0).unwrap();
+            let (tilemap_entity, tilemap_render_entity, layer) = composed_into
+                .get(&tilemap_id.0)
+                .copied()
+                .unwrap_or((tilemap_id.0, data.0.id(), 0));
+            if frozen_tilemaps.contains(&tilemap_entity) {
+                return;
+            }
 
-        let tile = PackedTileData {
-            visible: visible.0,
-            position,
-            texture,
-            color: color.0.to_linear().to_f32_array(),
-        };
+            // The render world only deals with y-up grid positions.
+            let axes = data.12.map(|storage| storage.axes).unwrap_or_default();
+            let tile_pos = axes.to_grid_pos(tile_pos, data.7);
+            let tile_pos_old = TilePosOld(axes.to_grid_pos(&tile_pos_old.0, data.7));
 
-        let data = tilemap_query.get(tilemap_id.0).unwrap();
+            // The animation id is assigned when the tile is added to its chunk.
+            let tile = PackedTileData {
+                visible: visible.0 && !occluded.is_some_and(|occluded| occluded.0),
+                position: Vec2::new(tile_pos.x as f32, tile_pos.y as f32),
+                texture: Vec3::new(tile_texture.0 as f32, tile_flip_bits as f32, 0.0),
+                color: color_animation
+                    .map_or(color.0, |animation| animation.from)
+                    .to_linear()
+                    .to_f32_array(),
+                color_to: color_animation.map_or([0.0; 4], |animation| {
+                    animation.to.to_linear().to_f32_array()
+                }),
+                color_period: color_animation.map_or(0.0, |animation| animation.period),
+                animation_flags: animation_state.map_or(0, animation_flags),
+                animation_time: animation_state.map_or(0.0, AnimationState::gpu_time),
+                sort_bias: sort_bias.map_or(0.0, |sort_bias| sort_bias.0),
+                vertex_data: vertex_data.map_or(Vec4::ZERO, |vertex_data| vertex_data.0),
+                transform: offset
+                    .map_or(Vec2::ZERO, |offset| offset.0)
+                    .extend(rotation.map_or(0.0, |rotation| rotation.0)),
+                render_size: render_size
+                    .or(data.13 .7)
+                    .map_or(Vec2::ZERO, |render_size| render_size.0),
+            };
 
-        extracted_tilemaps.insert(
-            data.0.id(),
-            (
-                data.0.id(),
-                ExtractedTilemapBundle {
-                    transform: *data.1,
-                    tile_size: *data.2,
-                    texture_size: TilemapTextureSize::default(),
-                    spacing: *data.3,
-                    grid_size: *data.4,
-                    map_type: *data.5,
-                    texture: data.6.clone_weak(),
-                    map_size: *data.7,
-                    visibility: *data.8,
-                    frustum_culling: *data.9,
-                    render_settings: *data.10,
+            tiles_buffer.borrow_local_mut().push((
+                tilemap_entity,
+                render_entity.id(),
+                ExtractedTileBundle {
+                    tile: ExtractedTile {
+                        entity: render_entity.id(),
+                        position: tile_pos,
+                        old_position: tile_pos_old,
+                        motion_offset: motion
+                            .map_or(Vec2::ZERO, |motion| motion.grid_offset(&axes)),
+                        tile,
+                        animation: match animated_frames {
+                            Some(animated_frames) => {
+                                Some(ExtractedAnimation::Frames(animated_frames.frames.clone()))
+                            }
+                            None => animated.copied().map(ExtractedAnimation::Range),
+                        },
+                        tilemap_id: TilemapId(tilemap_render_entity),
+                        layer,
+                    },
                     changed: ChangedInMainWorld,
                 },
-            ),
-        );
+            ));
+        },
+    );
 
-        extracted_tiles.push((
-            render_entity.id(),
-            ExtractedTileBundle {
-                tile: ExtractedTile {
-                    entity: render_entity.id(),
-                    position: *tile_pos,
-                    old_position: *tile_pos_old,
-                    tile,
-                    tilemap_id: TilemapId(data.0.id()),
-                },
-                changed: ChangedInMainWorld,
-            },
-        ));
+    let mut changed_tilemaps: HashSet<Entity> = changed_tilemap_query.iter().collect();
+    let mut extracted_tiles = Vec::new();
+    for (tilemap_entity, render_entity, bundle) in tiles_buffer.drain() {
+        changed_tilemaps.insert(tilemap_entity);
+        extracted_tiles.push((render_entity, bundle));
     }
+    extracted_tiles.sort_unstable_by_key(|(render_entity, _)| *render_entity);
 
-    for tilemap_entity in changed_tilemap_query.iter() {
+    for tilemap_entity in changed_tilemaps {
         if let Ok(data) = tilemap_query.get(tilemap_entity) {
             extracted_tilemaps.insert(
                 data.0.id(),
@@ -326,6 +486,19 @@ pub fn extract(
                         visibility: *data.8,
                         frustum_culling: *data.9,
                         render_settings: *data.10,
+                        color: data.11.copied().unwrap_or_default(),
+                        blend_mode: data.13 .0.copied().unwrap_or_default(),
+                        render_mode: data.13 .8.copied().unwrap_or_default(),
+                        clip_rect: ExtractedClipRect(data.13 .1.copied()),
+                        sort_key: ExtractedSortKey(data.13 .2.cloned()),
+                        animation_phase: data.13 .3.copied().unwrap_or_default(),
+                        uv_inset: data.13 .5.copied().unwrap_or_default(),
+                        vertex_data: ExtractedVertexData(data.13 .6),
+                        z_policy: data
+                            .13
+                             .4
+                            .copied()
+                            .unwrap_or_else(|| ChunkZPolicy::from_settings(data.10)),
                         changed: ChangedInMainWorld,
                     },
                 ),
@@ -336,7 +509,7 @@ pub fn extract(
     let extracted_tilemaps: Vec<_> = extracted_tilemaps.drain().map(|(_, val)| val).collect();
 
     // Extracts tilemap textures.
-    for (render_entity, _, tile_size, tile_spacing, _, _, texture, _, _, _, _) in
+    for (render_entity, _, tile_size, tile_spacing, _, _, texture, _, _, _, _, _, _, _) in
         tilemap_query.iter()
     {
         if texture.verify_ready(&images) {
@@ -363,6 +536,12 @@ pub fn extract(
             .insert(ExtractedFrustum { frustum: *frustum });
     }
 
+    commands.insert_resource(ExtractedStaticTilemaps(
+        static_tilemap_query
+            .iter()
+            .map(|(_, render_entity, _)| render_entity.id())
+            .collect(),
+    ));
     commands.insert_batch(extracted_tiles);
     commands.insert_batch(extracted_tilemaps);
     commands.insert_batch(extracted_tilemap_textures);
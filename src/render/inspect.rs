@@ -0,0 +1,157 @@
+use std::sync::{Arc, RwLock};
+
+use bevy::{
+    prelude::*,
+    render::{sync_world::MainEntity, Render, RenderApp, RenderSet},
+    utils::HashMap,
+};
+
+use super::chunk::RenderChunk2dStorage;
+use crate::map::TilemapTexture;
+
+/// What the render world holds for a chunk of a tilemap, as reported by
+/// [`TilemapRenderInspector`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderChunkSnapshot {
+    /// The index of the chunk, in chunk coordinates.
+    pub index: UVec3,
+    /// The number of tiles stored in the chunk.
+    pub tiles: usize,
+    /// The number of tiles stored in the chunk that are visible.
+    pub visible_tiles: usize,
+    /// The texture the chunk is drawn with.
+    pub texture: TilemapTexture,
+    /// True if the chunk still has to be remeshed, e.g. because the
+    /// [`RemeshPolicy`](crate::prelude::RemeshPolicy) deferred it.
+    pub dirty_mesh: bool,
+    /// True if the colors of the chunk still have to be rewritten.
+    pub dirty_colors: bool,
+    /// True if the tilemap of the chunk is visible.
+    pub visible: bool,
+}
+
+/// Reads back the render chunks of every tilemap, so that tests can check what was extracted to
+/// the render world, e.g. that no tiles linger after removing them.
+///
+/// The chunks are read at the end of every rendered frame, so they lag a frame behind.
+///
+/// Requires the [`TilemapRenderInspectorPlugin`].
+#[derive(Resource, Clone, Default, Debug)]
+pub struct TilemapRenderInspector {
+    // Arc and RwLock let the render world write its chunks back to the main world.
+    chunks: Arc<RwLock<HashMap<Entity, Vec<RenderChunkSnapshot>>>>,
+}
+
+impl TilemapRenderInspector {
+    /// Returns the render chunks of `tilemap`, ordered by index.
+    pub fn chunks(&self, tilemap: Entity) -> Vec<RenderChunkSnapshot> {
+        self.chunks
+            .read()
+            .ok()
+            .and_then(|chunks| chunks.get(&tilemap).cloned())
+            .unwrap_or_default()
+    }
+
+    /// Returns the render chunk of `tilemap` at `index`.
+    pub fn chunk(&self, tilemap: Entity, index: UVec3) -> Option<RenderChunkSnapshot> {
+        self.chunks
+            .read()
+            .ok()?
+            .get(&tilemap)?
+            .iter()
+            .find(|chunk| chunk.index == index)
+            .cloned()
+    }
+
+    /// Returns the number of tiles stored in the render chunks of `tilemap`.
+    pub fn tile_count(&self, tilemap: Entity) -> usize {
+        self.chunks(tilemap).iter().map(|chunk| chunk.tiles).sum()
+    }
+
+    /// Returns the number of visible tiles stored in the render chunks of `tilemap`.
+    pub fn visible_tile_count(&self, tilemap: Entity) -> usize {
+        self.chunks(tilemap)
+            .iter()
+            .map(|chunk| chunk.visible_tiles)
+            .sum()
+    }
+}
+
+/// Adds the [`TilemapRenderInspector`] resource, and the system reading back the render chunks
+/// every frame.
+///
+/// Reading back visits every tile of the render chunks, so this is meant for tests rather than
+/// shipping builds.
+pub struct TilemapRenderInspectorPlugin;
+
+impl Plugin for TilemapRenderInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        let inspector = TilemapRenderInspector::default();
+        app.insert_resource(inspector.clone());
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(inspector)
+                .add_systems(Render, inspect_render_chunks.in_set(RenderSet::Cleanup));
+        }
+    }
+}
+
+fn inspect_render_chunks(
+    inspector: Res<TilemapRenderInspector>,
+    chunk_storage: Res<RenderChunk2dStorage>,
+    main_entities: Query<&MainEntity>,
+) {
+    let mut inspected: HashMap<Entity, Vec<RenderChunkSnapshot>> = HashMap::default();
+    for chunk in chunk_storage.iter() {
+        // Chunks know their tilemap by its render entity, tests by its main world entity.
+        let Ok(tilemap) = main_entities.get(Entity::from_bits(chunk.tilemap_id)) else {
+            continue;
+        };
+        let tiles = chunk.tiles.iter().flatten();
+        inspected
+            .entry(tilemap.id())
+            .or_default()
+            .push(RenderChunkSnapshot {
+                index: chunk.get_index(),
+                tiles: tiles.clone().count(),
+                visible_tiles: tiles.filter(|tile| tile.visible).count(),
+                texture: chunk.texture.clone(),
+                dirty_mesh: chunk.dirty_mesh,
+                dirty_colors: chunk.dirty_colors,
+                visible: chunk.visible,
+            });
+    }
+    for chunks in inspected.values_mut() {
+        chunks.sort_by_key(|chunk| chunk.index.to_array());
+    }
+
+    if let Ok(mut chunks) = inspector.chunks.write() {
+        *chunks = inspected;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::chunk::tests::add_tile;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn chunks_are_inspected_by_main_world_tilemap() {
+        let main_map = Entity::from_raw(0);
+        let inspector = TilemapRenderInspector::default();
+        let mut render_world = World::new();
+        render_world.spawn_empty();
+        let render_map = render_world.spawn(MainEntity::from(main_map)).id();
+
+        let mut chunk_storage = RenderChunk2dStorage::default();
+        add_tile(&mut chunk_storage, Entity::from_raw(7), render_map);
+        render_world.insert_resource(chunk_storage);
+        render_world.insert_resource(inspector.clone());
+        render_world.run_system_once(inspect_render_chunks).unwrap();
+
+        assert!(inspector.chunks(render_map).is_empty());
+        assert_eq!(inspector.chunks(main_map).len(), 1);
+    }
+}
@@ -1,9 +1,15 @@
-use crate::prelude::{TilemapId, TilemapRenderSettings};
+use crate::prelude::{
+    ChunkSortInfo, ChunkZPolicy, TilemapId, TilemapRenderMode, TilemapRenderSettings,
+};
+use crate::TilemapSystemSet;
 #[cfg(not(feature = "atlas"))]
 use bevy::render::renderer::RenderQueue;
 use bevy::{
-    core_pipeline::core_2d::Transparent2d,
-    ecs::system::{StaticSystemParam, SystemParamItem},
+    core_pipeline::core_2d::{Opaque2d, Opaque2dBinKey, Transparent2d},
+    ecs::{
+        entity::EntityHashSet,
+        system::{StaticSystemParam, SystemParamItem},
+    },
     log::error,
     math::FloatOrd,
     prelude::*,
@@ -13,7 +19,8 @@ use bevy::{
         globals::GlobalsBuffer,
         render_asset::RenderAssets,
         render_phase::{
-            AddRenderCommand, DrawFunctions, PhaseItemExtraIndex, ViewSortedRenderPhases,
+            AddRenderCommand, BinnedRenderPhaseType, DrawFunctions, PhaseItemExtraIndex,
+            ViewBinnedRenderPhases, ViewSortedRenderPhases,
         },
         render_resource::{
             AsBindGroup, AsBindGroupError, BindGroup, BindGroupEntry, BindGroupLayout,
@@ -34,7 +41,7 @@ use super::{
     draw::DrawTilemapMaterial,
     pipeline::{TilemapPipeline, TilemapPipelineKey},
     prepare,
-    queue::{ImageBindGroups, TilemapViewBindGroup},
+    queue::{ImageBindGroups, TilemapViewBindGroup, TilemapViewBindGroupCache},
     ModifiedImageIds,
 };
 
@@ -44,6 +51,11 @@ pub(crate) use super::TextureArrayCache;
 pub trait MaterialTilemap: AsBindGroup + Asset + Clone + Sized {
     /// Returns this material's vertex shader. If [`ShaderRef::Default`] is returned, the default mesh vertex shader
     /// will be used.
+    ///
+    /// Custom vertex shaders can `#import bevy_ecs_tilemap::projection::tile_pos_to_world_pos`
+    /// to place tiles exactly like the default one, and like [`TilePos::center_in_world`] does.
+    ///
+    /// [`TilePos::center_in_world`]: crate::tiles::TilePos::center_in_world
     fn vertex_shader() -> ShaderRef {
         ShaderRef::Default
     }
@@ -54,6 +66,17 @@ pub trait MaterialTilemap: AsBindGroup + Asset + Clone + Sized {
         ShaderRef::Default
     }
 
+    /// Returns true if this material's shaders read the [`TileVertexData`] of tiles, which is
+    /// then uploaded for the tilemaps using this material.
+    ///
+    /// The data is at `@location(7)` of the vertex input, and `TILE_VERTEX_DATA` is defined in
+    /// the shaders whenever it is bound, so guard custom inputs with `#ifdef TILE_VERTEX_DATA`.
+    ///
+    /// [`TileVertexData`]: crate::tiles::TileVertexData
+    fn tile_vertex_data() -> bool {
+        false
+    }
+
     /// Customizes the default [`RenderPipelineDescriptor`].
     #[allow(unused_variables)]
     #[inline]
@@ -127,6 +150,14 @@ impl<M: MaterialTilemap> From<&MaterialTilemapHandle<M>> for AssetId<M> {
     }
 }
 
+/// Marks tilemaps whose render chunks upload the [`TileVertexData`] of their tiles. It is added
+/// to the tilemaps using a material that returns true from
+/// [`MaterialTilemap::tile_vertex_data`].
+///
+/// [`TileVertexData`]: crate::tiles::TileVertexData
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct TileVertexDataEnabled;
+
 pub struct MaterialTilemapPlugin<M: MaterialTilemap>(PhantomData<M>);
 
 impl<M: MaterialTilemap> Default for MaterialTilemapPlugin<M> {
@@ -142,20 +173,29 @@ where
     fn build(&self, app: &mut App) {
         app.init_asset::<M>()
             .add_plugins(ExtractComponentPlugin::<MaterialTilemapHandle<M>>::extract_visible());
+        if M::tile_vertex_data() {
+            app.register_required_components::<MaterialTilemapHandle<M>, TileVertexDataEnabled>();
+        }
     }
 
     fn finish(&self, app: &mut App) {
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app
                 .add_render_command::<Transparent2d, DrawTilemapMaterial<M>>()
+                .add_render_command::<Opaque2d, DrawTilemapMaterial<M>>()
                 .init_resource::<MaterialTilemapPipeline<M>>()
                 .init_resource::<ExtractedMaterialsTilemap<M>>()
                 .init_resource::<RenderMaterialsTilemap<M>>()
                 .init_resource::<SpecializedRenderPipelines<MaterialTilemapPipeline<M>>>()
-                .add_systems(ExtractSchedule, extract_materials_tilemap::<M>)
+                .add_systems(
+                    ExtractSchedule,
+                    extract_materials_tilemap::<M>.in_set(TilemapSystemSet::Extract),
+                )
                 .add_systems(
                     Render,
-                    prepare_materials_tilemap::<M>.in_set(RenderSet::PrepareAssets),
+                    prepare_materials_tilemap::<M>
+                        .in_set(RenderSet::PrepareAssets)
+                        .in_set(TilemapSystemSet::Prepare),
                 )
                 .add_systems(
                     Render,
@@ -167,8 +207,11 @@ where
                         // on the scheduler.
                         queue_material_tilemap_meshes::<M>
                             .in_set(RenderSet::Queue)
+                            .in_set(TilemapSystemSet::Queue)
                             .after(prepare::prepare),
-                        bind_material_tilemap_meshes::<M>.in_set(RenderSet::PrepareBindGroups),
+                        bind_material_tilemap_meshes::<M>
+                            .in_set(RenderSet::PrepareBindGroups)
+                            .in_set(TilemapSystemSet::Queue),
                     ),
                 );
         }
@@ -235,7 +278,9 @@ where
         descriptor.layout = vec![
             self.tilemap_pipeline.view_layout.clone(),
             self.tilemap_pipeline.mesh_layout.clone(),
-            self.tilemap_pipeline.material_layout.clone(),
+            self.tilemap_pipeline
+                .material_layout(key.tilemap_pipeline_key.atlas)
+                .clone(),
             self.material_tilemap_layout.clone(),
         ];
 
@@ -391,6 +436,7 @@ fn prepare_material_tilemap<M: MaterialTilemap>(
 pub fn queue_material_tilemap_meshes<M: MaterialTilemap>(
     chunk_storage: Res<RenderChunk2dStorage>,
     transparent_2d_draw_functions: Res<DrawFunctions<Transparent2d>>,
+    opaque_2d_draw_functions: Res<DrawFunctions<Opaque2d>>,
     _render_device: Res<RenderDevice>,
     (material_tilemap_pipeline, mut material_pipelines): (
         Res<MaterialTilemapPipeline<M>>,
@@ -411,6 +457,7 @@ pub fn queue_material_tilemap_meshes<M: MaterialTilemap>(
         Res<RenderQueue>,
     ),
     mut transparent_render_phases: ResMut<ViewSortedRenderPhases<Transparent2d>>,
+    mut opaque_render_phases: ResMut<ViewBinnedRenderPhases<Opaque2d>>,
 ) where
     M::Data: PartialEq + Eq + Hash + Clone,
 {
@@ -429,17 +476,23 @@ pub fn queue_material_tilemap_meshes<M: MaterialTilemap>(
         let Some(transparent_phase) = transparent_render_phases.get_mut(&view_entity) else {
             continue;
         };
+        let mut opaque_phase = opaque_render_phases.get_mut(&view_entity);
+        let visible_tilemaps = visible_tilemaps(visible_entities);
 
         let draw_tilemap = transparent_2d_draw_functions
             .read()
             .get_id::<DrawTilemapMaterial<M>>()
             .unwrap();
+        let draw_opaque_tilemap = opaque_2d_draw_functions
+            .read()
+            .get_id::<DrawTilemapMaterial<M>>()
+            .unwrap();
 
+        // Query order is not stable across frames, so chunks are queued in a fixed order to keep
+        // chunks with equal sort keys from swapping places (the phase sort is stable).
+        let mut queued = Vec::new();
         for (entity, chunk_id, transform, tilemap_id) in standard_tilemap_meshes.iter() {
-            if !visible_entities
-                .iter::<With<TilemapRenderSettings>>()
-                .any(|(entity, _main_entity)| entity.index() == tilemap_id.0.index())
-            {
+            if !visible_tilemaps.contains(&tilemap_id.0) {
                 continue;
             }
 
@@ -450,12 +503,7 @@ pub fn queue_material_tilemap_meshes<M: MaterialTilemap>(
                 continue;
             };
 
-            if let Some(chunk) = chunk_storage.get(&UVec4::new(
-                chunk_id.0.x,
-                chunk_id.0.y,
-                chunk_id.0.z,
-                tilemap_id.0.index(),
-            )) {
+            if let Some(chunk) = chunk_storage.get(tilemap_id.0, &chunk_id.0) {
                 #[cfg(not(feature = "atlas"))]
                 if !texture_array_cache.contains(&chunk.texture) {
                     continue;
@@ -466,10 +514,22 @@ pub fn queue_material_tilemap_meshes<M: MaterialTilemap>(
                     continue;
                 }
 
+                #[cfg(not(feature = "atlas"))]
+                let atlas = texture_array_cache.is_atlas(&chunk.texture);
+                #[cfg(feature = "atlas")]
+                let atlas = true;
+
                 let key = TilemapPipelineKey {
                     msaa: msaa.samples(),
                     map_type: chunk.get_map_type(),
                     hdr: view.hdr,
+                    blend_mode: chunk.blend_mode,
+                    atlas,
+                    vertex_data: chunk.data_buffer.is_some(),
+                    tile_transforms: chunk.transform_buffer.is_some(),
+                    tile_render_sizes: chunk.size_buffer.is_some(),
+                    tile_depth: chunk.z_policy == ChunkZPolicy::TileDepth,
+                    opaque: chunk.render_mode == TilemapRenderMode::Opaque,
                 };
 
                 let pipeline_id = material_pipelines.specialize(
@@ -480,24 +540,57 @@ pub fn queue_material_tilemap_meshes<M: MaterialTilemap>(
                         bind_group_data: material.key.clone(),
                     },
                 );
-                let z = if chunk.y_sort {
-                    transform.translation.z
-                        + (1.0
-                            - (transform.translation.y
-                                / (chunk.map_size.y as f32 * chunk.tile_size.y)))
-                } else {
-                    transform.translation.z
+
+                // Opaque chunks are drawn in any order, the depth buffer sorts them.
+                if key.opaque {
+                    if let Some(opaque_phase) = opaque_phase.as_mut() {
+                        opaque_phase.add(
+                            Opaque2dBinKey {
+                                pipeline: pipeline_id,
+                                draw_function: draw_opaque_tilemap,
+                                asset_id: material_handle.id().untyped(),
+                                material_bind_group_id: None,
+                            },
+                            (entity, tilemap_id.0.into()),
+                            BinnedRenderPhaseType::NonMesh,
+                        );
+                    }
+                    continue;
+                }
+
+                let mut sort_info = ChunkSortInfo {
+                    chunk_index: chunk.get_index(),
+                    transform: *transform,
+                    chunk_size: chunk.size_in_tiles,
+                    map_size: chunk.map_size,
+                    grid_size: chunk.grid_size,
+                    tile_size: chunk.tile_size,
+                    map_type: chunk.get_map_type(),
+                    default_key: 0.0,
+                };
+                sort_info.default_key = chunk.z_policy.key(&sort_info);
+                let z = match &chunk.sort_key {
+                    Some(sort_key) => sort_key.key(&sort_info),
+                    None => sort_info.default_key,
                 };
-                transparent_phase.add(Transparent2d {
-                    entity: (entity, tilemap_id.0.into()),
-                    draw_function: draw_tilemap,
-                    pipeline: pipeline_id,
-                    sort_key: FloatOrd(z),
-                    batch_range: 0..1,
-                    extra_index: PhaseItemExtraIndex::NONE,
-                });
+                queued.push((
+                    (tilemap_id.0, chunk_id.0.to_array()),
+                    Transparent2d {
+                        entity: (entity, tilemap_id.0.into()),
+                        draw_function: draw_tilemap,
+                        pipeline: pipeline_id,
+                        sort_key: FloatOrd(z),
+                        batch_range: 0..1,
+                        extra_index: PhaseItemExtraIndex::NONE,
+                    },
+                ));
             }
         }
+
+        queued.sort_unstable_by_key(|(order, _)| *order);
+        for (_, item) in queued {
+            transparent_phase.add(item);
+        }
     }
 }
 
@@ -510,12 +603,19 @@ pub fn bind_material_tilemap_meshes<M: MaterialTilemap>(
     view_uniforms: Res<ViewUniforms>,
     gpu_images: Res<RenderAssets<GpuImage>>,
     globals_buffer: Res<GlobalsBuffer>,
-    mut image_bind_groups: ResMut<ImageBindGroups>,
+    (mut image_bind_groups, mut view_bind_group_cache): (
+        ResMut<ImageBindGroups>,
+        ResMut<TilemapViewBindGroupCache>,
+    ),
     (standard_tilemap_meshes, materials): (
         Query<(&ChunkId, &TilemapId)>,
         Query<&MaterialTilemapHandle<M>>,
     ),
-    mut views: Query<(Entity, &RenderVisibleEntities)>,
+    mut views: Query<(
+        Entity,
+        &RenderVisibleEntities,
+        Option<&TilemapViewBindGroup>,
+    )>,
     render_materials: Res<RenderMaterialsTilemap<M>>,
     modified_image_ids: Res<ModifiedImageIds>,
     #[cfg(not(feature = "atlas"))] (mut texture_array_cache, render_queue): (
@@ -532,98 +632,140 @@ pub fn bind_material_tilemap_meshes<M: MaterialTilemap>(
         return;
     }
 
-    if let (Some(view_binding), Some(globals)) = (
+    let (Some(view_binding), Some(globals), Some(view_buffer), Some(globals_buffer)) = (
         view_uniforms.uniforms.binding(),
         globals_buffer.buffer.binding(),
-    ) {
-        for (entity, visible_entities) in views.iter_mut() {
-            let view_bind_group = render_device.create_bind_group(
+        view_uniforms.uniforms.buffer(),
+        globals_buffer.buffer.buffer(),
+    ) else {
+        return;
+    };
+
+    let view_bind_group = view_bind_group_cache
+        .get_or_create(view_buffer.id(), globals_buffer.id(), || {
+            render_device.create_bind_group(
                 Some("tilemap_view_bind_group"),
                 &tilemap_pipeline.view_layout,
                 &[
                     BindGroupEntry {
                         binding: 0,
-                        resource: view_binding.clone(),
+                        resource: view_binding,
                     },
                     BindGroupEntry {
                         binding: 1,
-                        resource: globals.clone(),
+                        resource: globals,
                     },
                 ],
-            );
+            )
+        })
+        .clone();
 
+    for (entity, visible_entities, current_view_bind_group) in views.iter_mut() {
+        if current_view_bind_group.is_none_or(|current| current.value.id() != view_bind_group.id())
+        {
             commands.entity(entity).insert(TilemapViewBindGroup {
-                value: view_bind_group,
+                value: view_bind_group.clone(),
             });
+        }
+        let visible_tilemaps = visible_tilemaps(visible_entities);
 
-            for (chunk_id, tilemap_id) in standard_tilemap_meshes.iter() {
-                if !visible_entities
-                    .iter::<With<TilemapRenderSettings>>()
-                    .any(|(entity, _main_entity)| entity.index() == tilemap_id.0.index())
-                {
-                    continue;
-                }
+        for (chunk_id, tilemap_id) in standard_tilemap_meshes.iter() {
+            if !visible_tilemaps.contains(&tilemap_id.0) {
+                continue;
+            }
 
-                let Ok(material_handle) = materials.get(tilemap_id.0) else {
-                    continue;
-                };
-                if render_materials.get(&material_handle.id()).is_none() {
-                    continue;
-                };
+            let Ok(material_handle) = materials.get(tilemap_id.0) else {
+                continue;
+            };
+            if render_materials.get(&material_handle.id()).is_none() {
+                continue;
+            };
 
-                if let Some(chunk) = chunk_storage.get(&UVec4::new(
-                    chunk_id.0.x,
-                    chunk_id.0.y,
-                    chunk_id.0.z,
-                    tilemap_id.0.index(),
-                )) {
-                    #[cfg(not(feature = "atlas"))]
-                    if !texture_array_cache.contains(&chunk.texture) {
-                        continue;
-                    }
+            let Some(chunk) = chunk_storage.get(tilemap_id.0, &chunk_id.0) else {
+                continue;
+            };
 
-                    #[cfg(feature = "atlas")]
-                    if gpu_images.get(chunk.texture.image_handle()).is_none() {
-                        continue;
-                    }
+            #[cfg(not(feature = "atlas"))]
+            let Some((gpu_image, atlas)) =
+                texture_array_cache.contains(&chunk.texture).then(|| {
+                    (
+                        texture_array_cache.get(&chunk.texture),
+                        texture_array_cache.is_atlas(&chunk.texture),
+                    )
+                })
+            else {
+                continue;
+            };
+            #[cfg(feature = "atlas")]
+            let Some((gpu_image, atlas)) = gpu_images
+                .get(chunk.texture.image_handle())
+                .map(|gpu_image| (gpu_image, true))
+            else {
+                continue;
+            };
 
-                    let create_bind_group = || {
-                        #[cfg(not(feature = "atlas"))]
-                        let gpu_image = texture_array_cache.get(&chunk.texture);
-                        #[cfg(feature = "atlas")]
-                        let gpu_image = gpu_images.get(chunk.texture.image_handle()).unwrap();
-                        render_device.create_bind_group(
-                            Some("sprite_material_bind_group"),
-                            &tilemap_pipeline.material_layout,
-                            &[
-                                BindGroupEntry {
-                                    binding: 0,
-                                    resource: BindingResource::TextureView(&gpu_image.texture_view),
-                                },
-                                BindGroupEntry {
-                                    binding: 1,
-                                    resource: BindingResource::Sampler(&gpu_image.sampler),
-                                },
-                            ],
-                        )
-                    };
-                    if modified_image_ids.is_texture_modified(&chunk.texture) {
-                        image_bind_groups
-                            .values
-                            .insert(chunk.texture.clone_weak(), create_bind_group());
-                    } else {
-                        image_bind_groups
-                            .values
-                            .entry(chunk.texture.clone_weak())
-                            .or_insert_with(create_bind_group);
-                    }
-                }
+            if modified_image_ids.is_texture_modified(&chunk.texture) {
+                image_bind_groups.invalidate(&chunk.texture);
             }
+            // Only creates a bind group if the texture is new, or its view or sampler changed.
+            image_bind_groups.get_or_create(&chunk.texture, gpu_image, || {
+                render_device.create_bind_group(
+                    Some("sprite_material_bind_group"),
+                    tilemap_pipeline.material_layout(atlas),
+                    &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(&gpu_image.texture_view),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::Sampler(&gpu_image.sampler),
+                        },
+                    ],
+                )
+            });
         }
     }
 }
 
+/// Returns the render entities of the tilemaps visible from a view.
+///
+/// Entities are compared whole, generation included, so that a tilemap reusing the index of a
+/// despawned one is never drawn in its place.
+pub(crate) fn visible_tilemaps(visible_entities: &RenderVisibleEntities) -> EntityHashSet {
+    visible_entities
+        .iter::<With<TilemapRenderSettings>>()
+        .map(|(entity, _main_entity)| *entity)
+        .collect()
+}
+
 #[derive(AsBindGroup, Debug, Clone, Default, TypePath, Asset)]
 pub struct StandardTilemapMaterial {}
 
 impl MaterialTilemap for StandardTilemapMaterial {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{render::sync_world::MainEntity, utils::TypeIdMap};
+    use std::any::TypeId;
+
+    #[test]
+    fn visible_tilemaps_ignore_recycled_entities() {
+        let mut world = World::new();
+        let despawned = world.spawn_empty().id();
+        world.despawn(despawned);
+        let recycled = world.spawn_empty().id();
+        assert_eq!(despawned.index(), recycled.index());
+
+        let mut entities = TypeIdMap::default();
+        entities.insert(
+            TypeId::of::<With<TilemapRenderSettings>>(),
+            vec![(recycled, MainEntity::from(recycled))],
+        );
+        let visible_tilemaps = visible_tilemaps(&RenderVisibleEntities { entities });
+
+        assert!(visible_tilemaps.contains(&recycled));
+        assert!(!visible_tilemaps.contains(&despawned));
+    }
+}
@@ -0,0 +1,210 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, RwLock,
+};
+
+use bevy::{
+    prelude::*,
+    render::{sync_world::MainEntity, Render, RenderApp, RenderSet},
+    utils::HashMap,
+};
+
+use super::chunk::RenderChunk2dStorage;
+use crate::tiles::TileStorage;
+
+/// The memory used by a tilemap, as reported by [`TilemapMemoryStats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TilemapMemoryUsage {
+    /// The bytes of the tilemap's [`TileStorage`].
+    pub storage_bytes: usize,
+    /// The number of render chunks of the tilemap.
+    pub render_chunks: usize,
+    /// The number of tiles stored in the render chunks.
+    pub render_tiles: usize,
+    /// The bytes of the render chunks in main memory, including their meshes.
+    pub render_chunk_bytes: usize,
+    /// The bytes of the vertex and index buffers of the render chunks.
+    pub gpu_bytes: u64,
+}
+
+impl TilemapMemoryUsage {
+    /// Returns the bytes used in main memory and on the GPU together.
+    pub fn total_bytes(&self) -> u64 {
+        (self.storage_bytes + self.render_chunk_bytes) as u64 + self.gpu_bytes
+    }
+
+    fn add(&mut self, other: &TilemapMemoryUsage) {
+        self.storage_bytes += other.storage_bytes;
+        self.render_chunks += other.render_chunks;
+        self.render_tiles += other.render_tiles;
+        self.render_chunk_bytes += other.render_chunk_bytes;
+        self.gpu_bytes += other.gpu_bytes;
+    }
+}
+
+/// Reports how much memory the storages and render chunks of every tilemap use, to help track
+/// down memory that keeps growing in long-running worlds.
+///
+/// Render chunks are measured in the render world, so their numbers lag a frame behind. Chunks
+/// whose tiles were all removed are kept around until [`compact_render_chunks`] is called.
+///
+/// Requires the [`TilemapMemoryStatsPlugin`].
+///
+/// [`compact_render_chunks`]: Self::compact_render_chunks
+#[derive(Resource, Clone, Default, Debug)]
+pub struct TilemapMemoryStats {
+    // Arc and RwLock let the render world write its numbers back to the main world.
+    usage: Arc<RwLock<HashMap<Entity, TilemapMemoryUsage>>>,
+    compact: Arc<AtomicBool>,
+}
+
+impl TilemapMemoryStats {
+    /// Returns the memory used by `tilemap`, if it was measured yet.
+    pub fn get(&self, tilemap: Entity) -> Option<TilemapMemoryUsage> {
+        self.usage.read().ok()?.get(&tilemap).copied()
+    }
+
+    /// Returns the memory used by every tilemap.
+    pub fn all(&self) -> Vec<(Entity, TilemapMemoryUsage)> {
+        self.usage.read().map_or_else(
+            |_| Vec::new(),
+            |usage| {
+                usage
+                    .iter()
+                    .map(|(tilemap, usage)| (*tilemap, *usage))
+                    .collect()
+            },
+        )
+    }
+
+    /// Returns the memory used by all tilemaps together.
+    pub fn total(&self) -> TilemapMemoryUsage {
+        let mut total = TilemapMemoryUsage::default();
+        for (_, usage) in self.all() {
+            total.add(&usage);
+        }
+        total
+    }
+
+    /// Drops the render chunks without tiles during the next frame, and shrinks the render
+    /// world's lookup tables, e.g. after despawning most of a large map. See also
+    /// [`TileStorage::compact`].
+    pub fn compact_render_chunks(&self) {
+        self.compact.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Adds the [`TilemapMemoryStats`] resource, and the systems measuring tilemaps every frame.
+///
+/// Measuring visits every tile of the render chunks, so this is meant for diagnostics rather
+/// than shipping builds.
+pub struct TilemapMemoryStatsPlugin;
+
+impl Plugin for TilemapMemoryStatsPlugin {
+    fn build(&self, app: &mut App) {
+        let stats = TilemapMemoryStats::default();
+        app.insert_resource(stats.clone())
+            .add_systems(Last, update_storage_memory_stats);
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.insert_resource(stats).add_systems(
+                Render,
+                update_render_memory_stats.in_set(RenderSet::Cleanup),
+            );
+        }
+    }
+}
+
+fn update_storage_memory_stats(
+    stats: Res<TilemapMemoryStats>,
+    storages: Query<(Entity, Ref<TileStorage>)>,
+) {
+    let Ok(mut usage) = stats.usage.write() else {
+        return;
+    };
+    usage.retain(|tilemap, _| storages.contains(*tilemap));
+    for (tilemap, storage) in storages.iter() {
+        if storage.is_changed() || !usage.contains_key(&tilemap) {
+            usage.entry(tilemap).or_default().storage_bytes = storage.memory_usage();
+        }
+    }
+}
+
+fn update_render_memory_stats(
+    stats: Res<TilemapMemoryStats>,
+    mut chunk_storage: ResMut<RenderChunk2dStorage>,
+    main_entities: Query<&MainEntity>,
+) {
+    if stats.compact.swap(false, Ordering::Relaxed) {
+        chunk_storage.compact();
+    }
+
+    let mut measured: HashMap<Entity, TilemapMemoryUsage> = HashMap::default();
+    for chunk in chunk_storage.iter() {
+        // Chunks know their tilemap by its render entity, the stats by its main world entity.
+        let Ok(tilemap) = main_entities.get(Entity::from_bits(chunk.tilemap_id)) else {
+            continue;
+        };
+        let usage = measured.entry(tilemap.id()).or_default();
+        usage.render_chunks += 1;
+        usage.render_tiles += chunk.tiles.iter().flatten().count();
+        usage.render_chunk_bytes += chunk.memory_usage();
+        usage.gpu_bytes += chunk.gpu_memory_usage();
+    }
+
+    let Ok(mut usage) = stats.usage.write() else {
+        return;
+    };
+    // Only tilemaps the main world knows about are kept, so that despawned ones disappear.
+    for (tilemap, usage) in usage.iter_mut() {
+        let render = measured.remove(tilemap).unwrap_or_default();
+        usage.render_chunks = render.render_chunks;
+        usage.render_tiles = render.render_tiles;
+        usage.render_chunk_bytes = render.render_chunk_bytes;
+        usage.gpu_bytes = render.gpu_bytes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{TilemapSize, TilemapType};
+    use crate::render::chunk::tests::add_tile;
+    use crate::test_utils::{spawn_test_map, tile_at};
+    use crate::tiles::TilePos;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn render_bytes_are_reported_for_main_world_tilemaps() {
+        let stats = TilemapMemoryStats::default();
+        let mut main_world = World::new();
+        main_world.insert_resource(stats.clone());
+        let map = spawn_test_map(
+            &mut main_world,
+            TilemapSize { x: 4, y: 4 },
+            TilemapType::Square,
+        );
+        let tile = tile_at(&main_world, map, TilePos::new(0, 0)).unwrap();
+        main_world
+            .run_system_once(update_storage_memory_stats)
+            .unwrap();
+
+        // The render world knows the tilemap by another entity.
+        let mut render_world = World::new();
+        render_world.spawn_empty();
+        let render_map = render_world.spawn(MainEntity::from(map)).id();
+        assert_ne!(render_map, map);
+        let mut chunk_storage = RenderChunk2dStorage::default();
+        add_tile(&mut chunk_storage, tile, render_map);
+        render_world.insert_resource(chunk_storage);
+        render_world.insert_resource(stats.clone());
+        render_world
+            .run_system_once(update_render_memory_stats)
+            .unwrap();
+
+        let usage = stats.get(map).unwrap();
+        assert!(usage.storage_bytes > 0);
+        assert_eq!(usage.render_chunks, 1);
+        assert!(usage.render_chunk_bytes > 0);
+    }
+}
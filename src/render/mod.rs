@@ -1,13 +1,12 @@
 use std::marker::PhantomData;
 
 use bevy::{
-    asset::load_internal_asset,
-    core_pipeline::core_2d::Transparent2d,
+    core_pipeline::core_2d::{Opaque2d, Transparent2d},
     image::ImageSamplerDescriptor,
     prelude::*,
     render::{
         extract_component::{ExtractComponent, ExtractComponentPlugin},
-        extract_resource::{extract_resource, ExtractResource},
+        extract_resource::{extract_resource, ExtractResource, ExtractResourcePlugin},
         mesh::MeshVertexAttribute,
         render_phase::AddRenderCommand,
         render_resource::{FilterMode, SpecializedRenderPipelines, VertexFormat},
@@ -25,9 +24,10 @@ use bevy::render::texture::GpuImage;
 use extract::remove_changed;
 
 use crate::{
-    prelude::TilemapRenderSettings,
-    tiles::{TilePos, TileStorage},
-    TilemapFirstSet,
+    helpers::transform,
+    prelude::{RemeshPolicy, TilemapInvalidate, TilemapRenderSettings, TilemapSize},
+    tiles::{DirtyTileChunks, TilePos, TileStorage, TileTextureIndex},
+    TilemapSystemSet,
 };
 use crate::{
     prelude::TilemapTexture,
@@ -38,19 +38,27 @@ use crate::{
 };
 
 use self::{
+    animation::AnimationLookup,
     chunk::RenderChunk2dStorage,
     draw::DrawTilemap,
-    pipeline::{TilemapPipeline, TILEMAP_SHADER_FRAGMENT, TILEMAP_SHADER_VERTEX},
-    queue::ImageBindGroups,
+    pipeline::TilemapPipeline,
+    queue::{ImageBindGroups, TilemapViewBindGroupCache},
 };
 
+mod animation;
+pub mod capabilities;
 mod chunk;
+pub mod culling_debug;
 mod draw;
 mod extract;
+pub mod inspect;
 pub mod material;
+pub mod memory;
 mod pipeline;
 pub(crate) mod prepare;
 mod queue;
+pub mod shader;
+pub mod stats;
 
 #[cfg(not(feature = "atlas"))]
 mod texture_array_cache;
@@ -59,6 +67,8 @@ mod texture_array_cache;
 use self::extract::ExtractedTilemapTexture;
 #[cfg(not(feature = "atlas"))]
 pub(crate) use self::texture_array_cache::TextureArrayCache;
+#[cfg(not(feature = "atlas"))]
+pub use self::texture_array_cache::{TilemapTextureStrategies, TilemapTextureStrategy};
 
 #[derive(Copy, Clone, Debug, Component)]
 pub(crate) struct ExtractedFilterMode(FilterMode);
@@ -81,45 +91,41 @@ impl RenderChunkSize {
     /// Calculates the index of the chunk this tile is in.
     #[inline]
     pub fn map_tile_to_chunk(&self, tile_position: &TilePos) -> UVec2 {
-        let tile_pos: UVec2 = tile_position.into();
-        tile_pos / self.0
+        transform::map_tile_to_chunk(tile_position, self.0)
     }
 
     /// Calculates the index of this tile within the chunk.
     #[inline]
     pub fn map_tile_to_chunk_tile(&self, tile_position: &TilePos, chunk_position: &UVec2) -> UVec2 {
-        let tile_pos: UVec2 = tile_position.into();
-        tile_pos - (*chunk_position * self.0)
+        transform::map_tile_to_chunk_tile(tile_position, *chunk_position, self.0)
     }
 }
 
 pub struct TilemapRenderingPlugin;
 
-pub const COLUMN_EVEN_HEX: Handle<Shader> = Handle::weak_from_u128(7704924705970804993);
-pub const COLUMN_HEX: Handle<Shader> = Handle::weak_from_u128(11710877199891728627);
-pub const COLUMN_ODD_HEX: Handle<Shader> = Handle::weak_from_u128(6706359414982022142);
-pub const COMMON: Handle<Shader> = Handle::weak_from_u128(15420881977837458322);
-pub const DIAMOND_ISO: Handle<Shader> = Handle::weak_from_u128(6710251300621614118);
-pub const MESH_OUTPUT: Handle<Shader> = Handle::weak_from_u128(2707251459590872179);
-pub const ROW_EVEN_HEX: Handle<Shader> = Handle::weak_from_u128(7149718726759672633);
-pub const ROW_HEX: Handle<Shader> = Handle::weak_from_u128(5506589682629967569);
-pub const ROW_ODD_HEX: Handle<Shader> = Handle::weak_from_u128(13608302855194400936);
-pub const STAGGERED_ISO: Handle<Shader> = Handle::weak_from_u128(9802843761568314416);
-pub const SQUARE: Handle<Shader> = Handle::weak_from_u128(7333720254399106799);
-pub const TILEMAP_VERTEX_OUTPUT: Handle<Shader> = Handle::weak_from_u128(6104533649830094529);
-
 impl Plugin for TilemapRenderingPlugin {
     fn build(&self, app: &mut App) {
         #[cfg(not(feature = "atlas"))]
-        app.add_systems(Update, set_texture_to_copy_src);
+        app.add_systems(
+            Update,
+            set_texture_to_copy_src.in_set(TilemapSystemSet::ExtractionPrep),
+        );
 
-        app.add_systems(First, clear_removed.in_set(TilemapFirstSet));
+        app.add_systems(
+            First,
+            clear_removed.in_set(TilemapSystemSet::StorageMaintenance),
+        );
+        app.add_systems(
+            PostUpdate,
+            invalidate_tilemaps.in_set(TilemapSystemSet::ExtractionPrep),
+        );
 
         app.add_observer(on_remove_tile);
         app.add_observer(on_remove_tilemap);
 
         app.add_plugins(ExtractComponentPlugin::<RemovedTileEntity>::default());
         app.add_plugins(ExtractComponentPlugin::<RemovedMapEntity>::default());
+        app.add_plugins(ExtractComponentPlugin::<InvalidatedMapEntity>::default());
 
         app.add_plugins(MaterialTilemapPlugin::<StandardTilemapMaterial>::default());
 
@@ -130,8 +136,14 @@ impl Plugin for TilemapRenderingPlugin {
                 StandardTilemapMaterial::default(),
             );
 
-        app.init_resource::<ModifiedImageIds>()
-            .add_systems(Update, collect_modified_image_asset_events);
+        app.init_resource::<ModifiedImageIds>().add_systems(
+            Update,
+            collect_modified_image_asset_events.in_set(TilemapSystemSet::ExtractionPrep),
+        );
+
+        app.init_resource::<RemeshPolicy>()
+            .add_plugins(ExtractResourcePlugin::<RemeshPolicy>::default())
+            .add_plugins(ExtractResourcePlugin::<DirtyTileChunks>::default());
     }
 
     fn finish(&self, app: &mut App) {
@@ -140,90 +152,14 @@ impl Plugin for TilemapRenderingPlugin {
             |plugin| plugin.default_sampler.clone(),
         );
 
-        load_internal_asset!(
-            app,
-            COLUMN_EVEN_HEX,
-            "shaders/column_even_hex.wgsl",
-            Shader::from_wgsl
-        );
-
-        load_internal_asset!(
-            app,
-            COLUMN_HEX,
-            "shaders/column_hex.wgsl",
-            Shader::from_wgsl
-        );
-
-        load_internal_asset!(
-            app,
-            COLUMN_ODD_HEX,
-            "shaders/column_odd_hex.wgsl",
-            Shader::from_wgsl
-        );
-
-        load_internal_asset!(app, COMMON, "shaders/common.wgsl", Shader::from_wgsl);
-
-        load_internal_asset!(
-            app,
-            DIAMOND_ISO,
-            "shaders/diamond_iso.wgsl",
-            Shader::from_wgsl
-        );
-
-        load_internal_asset!(
-            app,
-            ROW_EVEN_HEX,
-            "shaders/row_even_hex.wgsl",
-            Shader::from_wgsl
-        );
-
-        load_internal_asset!(app, ROW_HEX, "shaders/row_hex.wgsl", Shader::from_wgsl);
-
-        load_internal_asset!(
-            app,
-            ROW_ODD_HEX,
-            "shaders/row_odd_hex.wgsl",
-            Shader::from_wgsl
-        );
-
-        load_internal_asset!(app, ROW_HEX, "shaders/row_hex.wgsl", Shader::from_wgsl);
-
-        load_internal_asset!(
-            app,
-            MESH_OUTPUT,
-            "shaders/mesh_output.wgsl",
-            Shader::from_wgsl
-        );
-
-        load_internal_asset!(app, SQUARE, "shaders/square.wgsl", Shader::from_wgsl);
-
-        load_internal_asset!(
-            app,
-            STAGGERED_ISO,
-            "shaders/staggered_iso.wgsl",
-            Shader::from_wgsl
-        );
-
-        load_internal_asset!(
-            app,
-            TILEMAP_VERTEX_OUTPUT,
-            "shaders/tilemap_vertex_output.wgsl",
-            Shader::from_wgsl
+        capabilities::insert_render_capabilities(app);
+        app.add_systems(
+            Update,
+            capabilities::check_tilemap_textures.in_set(TilemapSystemSet::ExtractionPrep),
         );
 
-        load_internal_asset!(
-            app,
-            TILEMAP_SHADER_VERTEX,
-            "shaders/tilemap_vertex.wgsl",
-            Shader::from_wgsl
-        );
-
-        load_internal_asset!(
-            app,
-            TILEMAP_SHADER_FRAGMENT,
-            "shaders/tilemap_fragment.wgsl",
-            Shader::from_wgsl
-        );
+        let shaders = shader::load_tilemap_shaders(app);
+        app.insert_resource(shaders.clone());
 
         app.add_systems(
             PostUpdate,
@@ -232,15 +168,23 @@ impl Plugin for TilemapRenderingPlugin {
                 .after(VisibilitySystems::CalculateBounds),
         );
 
+        #[cfg(not(feature = "atlas"))]
+        let strategies = TilemapTextureStrategies::default();
+        #[cfg(not(feature = "atlas"))]
+        app.insert_resource(strategies.clone());
+
         let render_app = match app.get_sub_app_mut(RenderApp) {
             Some(render_app) => render_app,
             None => return,
         };
 
-        render_app.init_resource::<TilemapPipeline>();
+        render_app
+            .insert_resource(shaders)
+            .init_resource::<TilemapPipeline>();
 
         #[cfg(not(feature = "atlas"))]
         render_app
+            .insert_resource(strategies)
             .init_resource::<TextureArrayCache>()
             .add_systems(Render, prepare_textures.in_set(RenderSet::PrepareAssets))
             .add_systems(Render, texture_array_cache::remove_modified_textures);
@@ -248,28 +192,44 @@ impl Plugin for TilemapRenderingPlugin {
         render_app
             .insert_resource(DefaultSampler(sampler))
             .insert_resource(RenderChunk2dStorage::default())
+            .init_resource::<AnimationLookup>()
+            .init_resource::<extract::ExtractedStaticTilemaps>()
             .add_systems(
                 ExtractSchedule,
-                (extract::extract, extract_resource::<ModifiedImageIds>),
+                (extract::extract, extract_resource::<ModifiedImageIds>)
+                    .in_set(TilemapSystemSet::Extract),
             )
             .add_systems(
                 Render,
-                (prepare::prepare_removal, prepare::prepare)
+                (
+                    prepare::prepare_removal,
+                    prepare::prepare,
+                    animation::prepare_animation_lookup,
+                )
                     .chain()
-                    .in_set(RenderSet::PrepareAssets),
+                    .in_set(RenderSet::PrepareAssets)
+                    .in_set(TilemapSystemSet::Prepare),
+            )
+            .add_systems(
+                Render,
+                queue::queue_transform_bind_group
+                    .in_set(RenderSet::PrepareBindGroups)
+                    .in_set(TilemapSystemSet::Queue),
             )
             .add_systems(
                 Render,
-                queue::queue_transform_bind_group.in_set(RenderSet::PrepareBindGroups),
+                (remove_changed, queue::remove_unused_image_bind_groups).in_set(RenderSet::Cleanup),
             )
-            .add_systems(Render, remove_changed.in_set(RenderSet::Cleanup))
             .init_resource::<ImageBindGroups>()
+            .init_resource::<TilemapViewBindGroupCache>()
             .init_resource::<SpecializedRenderPipelines<TilemapPipeline>>()
             .init_resource::<MeshUniformResource>()
             .init_resource::<TilemapUniformResource>()
             .init_resource::<ModifiedImageIds>();
 
-        render_app.add_render_command::<Transparent2d, DrawTilemap>();
+        render_app
+            .add_render_command::<Transparent2d, DrawTilemap>()
+            .add_render_command::<Opaque2d, DrawTilemap>();
     }
 }
 
@@ -298,9 +258,9 @@ impl<C: Component> DynamicUniformIndex<C> {
 }
 
 pub const ATTRIBUTE_POSITION: MeshVertexAttribute =
-    MeshVertexAttribute::new("Position", 229221259, VertexFormat::Float32x4);
+    MeshVertexAttribute::new("Position", 229221259, VertexFormat::Float32x2);
 pub const ATTRIBUTE_TEXTURE: MeshVertexAttribute =
-    MeshVertexAttribute::new("Texture", 222922753, VertexFormat::Float32x4);
+    MeshVertexAttribute::new("Texture", 222922753, VertexFormat::Float32x3);
 pub const ATTRIBUTE_COLOR: MeshVertexAttribute =
     MeshVertexAttribute::new("Color", 231497124, VertexFormat::Float32x4);
 
@@ -311,6 +271,9 @@ pub struct RemovedTileEntity(pub RenderEntity);
 #[derive(Component, ExtractComponent, Clone)]
 pub struct RemovedMapEntity(pub RenderEntity);
 
+#[derive(Component, ExtractComponent, Clone)]
+pub struct InvalidatedMapEntity(pub RenderEntity, pub TilemapInvalidate);
+
 fn on_remove_tile(
     trigger: Trigger<OnRemove, TilePos>,
     mut commands: Commands,
@@ -331,10 +294,43 @@ fn on_remove_tilemap(
     }
 }
 
+/// Marks the tiles covered by a [`TilemapInvalidate`] as changed so they get re-extracted, and
+/// tells the render world to drop its stale data for them.
+fn invalidate_tilemaps(
+    mut commands: Commands,
+    tilemap_query: Query<(
+        Entity,
+        &TilemapInvalidate,
+        &TileStorage,
+        &TilemapSize,
+        Option<&RenderEntity>,
+    )>,
+    mut tile_query: Query<(&TilePos, &mut TileTextureIndex)>,
+) {
+    for (entity, invalidate, tile_storage, map_size, render_entity) in tilemap_query.iter() {
+        for tile_entity in tile_storage.iter().flatten() {
+            if let Ok((tile_pos, mut texture_index)) = tile_query.get_mut(*tile_entity) {
+                if invalidate.contains(tile_pos) {
+                    texture_index.set_changed();
+                }
+            }
+        }
+
+        if let Some(render_entity) = render_entity {
+            commands.spawn(InvalidatedMapEntity(
+                *render_entity,
+                invalidate.to_grid(&tile_storage.axes, map_size),
+            ));
+        }
+        commands.entity(entity).remove::<TilemapInvalidate>();
+    }
+}
+
 fn clear_removed(
     mut commands: Commands,
     removed_query: Query<Entity, With<RemovedTileEntity>>,
     removed_map_query: Query<Entity, With<RemovedMapEntity>>,
+    invalidated_map_query: Query<Entity, With<InvalidatedMapEntity>>,
 ) {
     for entity in removed_query.iter() {
         commands.entity(entity).despawn();
@@ -343,6 +339,10 @@ fn clear_removed(
     for entity in removed_map_query.iter() {
         commands.entity(entity).despawn();
     }
+
+    for entity in invalidated_map_query.iter() {
+        commands.entity(entity).despawn();
+    }
 }
 
 #[cfg(not(feature = "atlas"))]
@@ -351,12 +351,14 @@ fn prepare_textures(
     mut texture_array_cache: ResMut<TextureArrayCache>,
     extracted_tilemap_textures: Query<&ExtractedTilemapTexture>,
     render_images: Res<bevy::render::render_asset::RenderAssets<GpuImage>>,
+    capabilities: Res<capabilities::TilemapRenderCapabilities>,
+    strategies: Res<TilemapTextureStrategies>,
 ) {
     for extracted_texture in extracted_tilemap_textures.iter() {
         texture_array_cache.add_extracted_texture(extracted_texture);
     }
 
-    texture_array_cache.prepare(&render_device, &render_images);
+    texture_array_cache.prepare(&render_device, &render_images, &capabilities, &strategies);
 }
 
 /// Resource to hold the ids of modified Image assets of a single frame.
@@ -11,26 +11,37 @@ use bevy::{
             MultisampleState, PolygonMode, PrimitiveState, PrimitiveTopology,
             RenderPipelineDescriptor, SamplerBindingType, ShaderStages, ShaderType,
             SpecializedRenderPipeline, StencilFaceState, StencilState, TextureFormat,
-            TextureSampleType, TextureViewDimension, VertexBufferLayout, VertexFormat, VertexState,
-            VertexStepMode,
+            TextureSampleType, TextureViewDimension, VertexAttribute, VertexBufferLayout,
+            VertexFormat, VertexState, VertexStepMode,
         },
         renderer::RenderDevice,
         view::{ViewTarget, ViewUniform},
     },
 };
 
-use crate::map::{HexCoordSystem, IsoCoordSystem, TilemapType};
+use crate::map::{HexCoordSystem, IsoCoordSystem, TilemapBlendMode, TilemapType};
 
-use super::{chunk::TilemapUniformData, prepare::MeshUniform};
-
-pub const TILEMAP_SHADER_VERTEX: Handle<Shader> = Handle::weak_from_u128(8094008129742001941);
-pub const TILEMAP_SHADER_FRAGMENT: Handle<Shader> = Handle::weak_from_u128(5716002228110903793);
+use super::{
+    capabilities::TilemapRenderCapabilities,
+    chunk::{
+        TilemapUniformData, DATA_VERTEX_SIZE, SIZE_VERTEX_SIZE, STATE_VERTEX_SIZE,
+        TRANSFORM_VERTEX_SIZE,
+    },
+    prepare::MeshUniform,
+    shader::{TilemapShader, TilemapShaders},
+};
 
 #[derive(Clone, Resource)]
 pub struct TilemapPipeline {
     pub view_layout: BindGroupLayout,
     pub material_layout: BindGroupLayout,
+    /// The material layout of textures sampled as atlases, which is the same as
+    /// `material_layout` with the `atlas` feature.
+    pub atlas_material_layout: BindGroupLayout,
     pub mesh_layout: BindGroupLayout,
+    pub vertex_shader: Handle<Shader>,
+    pub fragment_shader: Handle<Shader>,
+    pub capabilities: TilemapRenderCapabilities,
 }
 
 impl FromWorld for TilemapPipeline {
@@ -89,6 +100,17 @@ impl FromWorld for TilemapPipeline {
                     },
                     count: None,
                 },
+                // Animation lookup
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
             ],
         );
 
@@ -115,8 +137,7 @@ impl FromWorld for TilemapPipeline {
             ],
         );
 
-        #[cfg(feature = "atlas")]
-        let material_layout = render_device.create_bind_group_layout(
+        let atlas_material_layout = render_device.create_bind_group_layout(
             "tilemap_material_layout",
             &[
                 BindGroupLayoutEntry {
@@ -138,28 +159,92 @@ impl FromWorld for TilemapPipeline {
             ],
         );
 
+        #[cfg(feature = "atlas")]
+        let material_layout = atlas_material_layout.clone();
+
+        let shaders = world.resource::<TilemapShaders>();
+
         TilemapPipeline {
             view_layout,
             material_layout,
+            atlas_material_layout,
             mesh_layout,
+            vertex_shader: shaders.get(TilemapShader::Vertex),
+            fragment_shader: shaders.get(TilemapShader::Fragment),
+            capabilities: world
+                .get_resource::<TilemapRenderCapabilities>()
+                .copied()
+                .unwrap_or_default(),
+        }
+    }
+}
+impl TilemapPipeline {
+    /// Returns the material layout of textures sampled as atlases if `atlas` is true, or as
+    /// texture arrays otherwise.
+    pub fn material_layout(&self, atlas: bool) -> &BindGroupLayout {
+        if atlas {
+            &self.atlas_material_layout
+        } else {
+            &self.material_layout
         }
     }
 }
+
 #[derive(Debug, Component, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TilemapPipelineKey {
     pub msaa: u32,
     pub map_type: TilemapType,
     pub hdr: bool,
+    pub blend_mode: TilemapBlendMode,
+    /// Whether the texture is sampled as an atlas, which is always the case with the `atlas`
+    /// feature.
+    pub atlas: bool,
+    /// Whether the chunk has a buffer of [`TileVertexData`](crate::tiles::TileVertexData).
+    pub vertex_data: bool,
+    /// Whether the chunk has a buffer of tile offsets and rotations.
+    pub tile_transforms: bool,
+    /// Whether the chunk has a buffer of [`TileRenderSize`](crate::tiles::TileRenderSize)s.
+    pub tile_render_sizes: bool,
+    /// Whether each tile writes its own depth, for
+    /// [`ChunkZPolicy::TileDepth`](crate::map::ChunkZPolicy::TileDepth).
+    pub tile_depth: bool,
+    /// Whether the chunk is drawn in the opaque phase, for
+    /// [`TilemapRenderMode::Opaque`](crate::map::TilemapRenderMode::Opaque).
+    pub opaque: bool,
 }
 
 impl SpecializedRenderPipeline for TilemapPipeline {
     type Key = TilemapPipelineKey;
 
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
-        let mut shader_defs = Vec::new();
+        // Only read by custom material shaders, see `TilemapRenderCapabilities::shader_defs`.
+        let mut shader_defs = self.capabilities.shader_defs();
 
-        #[cfg(feature = "atlas")]
-        shader_defs.push("ATLAS".into());
+        if key.atlas {
+            shader_defs.push("ATLAS".into());
+        }
+
+        if key.vertex_data {
+            shader_defs.push("TILE_VERTEX_DATA".into());
+        }
+
+        if key.tile_transforms {
+            shader_defs.push("TILE_TRANSFORMS".into());
+        }
+
+        if key.tile_render_sizes {
+            shader_defs.push("TILE_RENDER_SIZES".into());
+        }
+
+        if key.tile_depth {
+            shader_defs.push("TILE_DEPTH".into());
+        }
+
+        // Pixels drawn with depth writes would hide what is drawn behind them later, even when
+        // they are transparent.
+        if key.tile_depth || key.opaque {
+            shader_defs.push("ALPHA_DISCARD".into());
+        }
 
         let mesh_string = match key.map_type {
             TilemapType::Square { .. } => "SQUARE",
@@ -179,26 +264,93 @@ impl SpecializedRenderPipeline for TilemapPipeline {
         shader_defs.push(mesh_string.into());
 
         let formats = vec![
+            // Texture index, flip bits and animation id
+            VertexFormat::Float32x3,
             // Position
-            VertexFormat::Float32x4,
-            // Uv
-            VertexFormat::Float32x4,
-            // Color
-            VertexFormat::Float32x4,
+            VertexFormat::Float32x2,
         ];
 
         let vertex_layout =
             VertexBufferLayout::from_vertex_formats(VertexStepMode::Vertex, formats);
 
+        // Colors, color animations and visibility flags are stored in a separate buffer, so that
+        // they can be updated without re-uploading the rest of the chunk mesh.
+        let color_layout = VertexBufferLayout {
+            array_stride: STATE_VERTEX_SIZE as u64,
+            step_mode: VertexStepMode::Vertex,
+            attributes: vec![
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: 0,
+                    shader_location: 2,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Uint32,
+                    offset: 16,
+                    shader_location: 3,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: 20,
+                    shader_location: 4,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32,
+                    offset: 36,
+                    shader_location: 5,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32,
+                    offset: 40,
+                    shader_location: 6,
+                },
+            ],
+        };
+
+        let mut buffers = vec![vertex_layout, color_layout];
+        if key.vertex_data {
+            buffers.push(VertexBufferLayout {
+                array_stride: DATA_VERTEX_SIZE as u64,
+                step_mode: VertexStepMode::Vertex,
+                attributes: vec![VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: 0,
+                    shader_location: 7,
+                }],
+            });
+        }
+        if key.tile_transforms {
+            buffers.push(VertexBufferLayout {
+                array_stride: TRANSFORM_VERTEX_SIZE as u64,
+                step_mode: VertexStepMode::Vertex,
+                attributes: vec![VertexAttribute {
+                    format: VertexFormat::Float32x3,
+                    offset: 0,
+                    shader_location: 8,
+                }],
+            });
+        }
+        if key.tile_render_sizes {
+            buffers.push(VertexBufferLayout {
+                array_stride: SIZE_VERTEX_SIZE as u64,
+                step_mode: VertexStepMode::Vertex,
+                attributes: vec![VertexAttribute {
+                    format: VertexFormat::Float32x2,
+                    offset: 0,
+                    shader_location: 9,
+                }],
+            });
+        }
+
         RenderPipelineDescriptor {
             vertex: VertexState {
-                shader: TILEMAP_SHADER_VERTEX,
+                shader: self.vertex_shader.clone(),
                 entry_point: "vertex".into(),
                 shader_defs: shader_defs.clone(),
-                buffers: vec![vertex_layout],
+                buffers,
             },
             fragment: Some(FragmentState {
-                shader: TILEMAP_SHADER_FRAGMENT,
+                shader: self.fragment_shader.clone(),
                 shader_defs,
                 entry_point: "fragment".into(),
                 targets: vec![Some(ColorTargetState {
@@ -207,25 +359,14 @@ impl SpecializedRenderPipeline for TilemapPipeline {
                     } else {
                         TextureFormat::bevy_default()
                     },
-                    blend: Some(BlendState {
-                        color: BlendComponent {
-                            src_factor: BlendFactor::SrcAlpha,
-                            dst_factor: BlendFactor::OneMinusSrcAlpha,
-                            operation: BlendOperation::Add,
-                        },
-                        alpha: BlendComponent {
-                            src_factor: BlendFactor::One,
-                            dst_factor: BlendFactor::One,
-                            operation: BlendOperation::Add,
-                        },
-                    }),
+                    blend: (!key.opaque).then(|| blend_state(key.blend_mode)),
                     write_mask: ColorWrites::ALL,
                 })],
             }),
             layout: vec![
                 self.view_layout.clone(),
                 self.mesh_layout.clone(),
-                self.material_layout.clone(),
+                self.material_layout(key.atlas).clone(),
             ],
             primitive: PrimitiveState {
                 conservative: false,
@@ -238,7 +379,7 @@ impl SpecializedRenderPipeline for TilemapPipeline {
             },
             depth_stencil: Some(DepthStencilState {
                 format: CORE_2D_DEPTH_FORMAT,
-                depth_write_enabled: false,
+                depth_write_enabled: key.tile_depth || key.opaque,
                 depth_compare: CompareFunction::GreaterEqual,
                 stencil: StencilState {
                     front: StencilFaceState::IGNORE,
@@ -263,3 +404,40 @@ impl SpecializedRenderPipeline for TilemapPipeline {
         }
     }
 }
+
+/// Returns the blend state of the color target for the given blend mode.
+fn blend_state(blend_mode: TilemapBlendMode) -> BlendState {
+    // Alpha is accumulated for the regular modes, and left untouched by the lighting modes.
+    let accumulate_alpha = BlendComponent {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    };
+    let keep_alpha = BlendComponent {
+        src_factor: BlendFactor::Zero,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    };
+    let (src_factor, dst_factor, alpha) = match blend_mode {
+        TilemapBlendMode::Alpha => (
+            BlendFactor::SrcAlpha,
+            BlendFactor::OneMinusSrcAlpha,
+            accumulate_alpha,
+        ),
+        TilemapBlendMode::Additive => (BlendFactor::SrcAlpha, BlendFactor::One, keep_alpha),
+        TilemapBlendMode::Multiply => (BlendFactor::Zero, BlendFactor::Src, keep_alpha),
+        TilemapBlendMode::Premultiplied => (
+            BlendFactor::One,
+            BlendFactor::OneMinusSrcAlpha,
+            accumulate_alpha,
+        ),
+    };
+    BlendState {
+        color: BlendComponent {
+            src_factor,
+            dst_factor,
+            operation: BlendOperation::Add,
+        },
+        alpha,
+    }
+}
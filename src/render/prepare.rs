@@ -1,18 +1,25 @@
 use std::marker::PhantomData;
 
 use crate::map::{
-    TilemapId, TilemapSize, TilemapSpacing, TilemapTexture, TilemapTextureSize, TilemapTileSize,
-    TilemapType,
+    ChunkZPolicy, TilemapAnimationPhase, TilemapBlendMode, TilemapColor, TilemapId,
+    TilemapRenderMode, TilemapSize, TilemapSpacing, TilemapTexture, TilemapTextureSize,
+    TilemapTileSize, TilemapType, TilemapUvInset,
+};
+use crate::prelude::{RemeshPolicy, TilemapRenderSettings};
+use crate::render::extract::{
+    ExtractedClipRect, ExtractedFrustum, ExtractedSortKey, ExtractedStaticTilemaps,
+    ExtractedVertexData,
 };
-use crate::prelude::TilemapRenderSettings;
-use crate::render::extract::ExtractedFrustum;
 use crate::{prelude::TilemapGridSize, render::RenderChunkSize, FrustumCulling};
-use bevy::log::trace;
+use bevy::color::ColorToComponents;
+use bevy::log::{info, trace};
 use bevy::prelude::{InheritedVisibility, Resource, With};
 use bevy::render::mesh::MeshVertexBufferLayouts;
 use bevy::render::sync_world::TemporaryRenderEntity;
+use bevy::render::view::ExtractedView;
+use bevy::tasks::ComputeTaskPool;
 use bevy::{
-    math::{Mat4, UVec4},
+    math::{Mat4, UVec2, UVec3},
     prelude::{Commands, Component, Entity, GlobalTransform, Query, Res, ResMut, Vec2},
     render::{
         render_resource::{DynamicUniformBuffer, ShaderType},
@@ -20,13 +27,18 @@ use bevy::{
     },
 };
 
+use bevy::utils::HashSet;
+
+use super::animation::AnimationLookup;
+use super::culling_debug::{ChunkAabb, ExtractedCullingDebug};
 use super::extract::ChangedInMainWorld;
+use super::stats::RemeshedChunks;
 use super::{
-    chunk::{ChunkId, PackedTileData, RenderChunk2dStorage, TilemapUniformData},
+    chunk::{ChunkId, PackedTileData, RenderChunk2d, RenderChunk2dStorage, TilemapUniformData},
     extract::{ExtractedTile, ExtractedTilemapTexture},
     DynamicUniformIndex,
 };
-use super::{RemovedMapEntity, RemovedTileEntity};
+use super::{InvalidatedMapEntity, RemovedMapEntity, RemovedTileEntity};
 
 #[derive(Resource, Default)]
 pub struct MeshUniformResource(pub DynamicUniformBuffer<MeshUniform>);
@@ -60,18 +72,46 @@ pub(crate) fn prepare(
             &InheritedVisibility,
             &FrustumCulling,
             &TilemapRenderSettings,
+            &TilemapColor,
+            (
+                &TilemapBlendMode,
+                &ExtractedClipRect,
+                &ExtractedSortKey,
+                &TilemapAnimationPhase,
+                &ChunkZPolicy,
+                &TilemapUvInset,
+                &ExtractedVertexData,
+                &TilemapRenderMode,
+            ),
         ),
         With<ChangedInMainWorld>,
     >,
     extracted_tilemap_textures: Query<&ExtractedTilemapTexture, With<ChangedInMainWorld>>,
     extracted_frustum_query: Query<&ExtractedFrustum>,
+    views: Query<&ExtractedView>,
+    remesh_policy: Res<RemeshPolicy>,
+    (static_tilemaps, culling_debug): (
+        Res<ExtractedStaticTilemaps>,
+        Option<Res<ExtractedCullingDebug>>,
+    ),
     render_device: Res<RenderDevice>,
     render_queue: Res<RenderQueue>,
     mut mesh_vertex_buffer_layouts: ResMut<MeshVertexBufferLayouts>,
+    mut animation_lookup: ResMut<AnimationLookup>,
+    mut remeshed: Option<ResMut<RemeshedChunks>>,
 ) {
+    let log_culled_changes = culling_debug
+        .as_ref()
+        .is_some_and(|debug| debug.log_culled_changes);
+    let mut changed_chunks: HashSet<(u64, UVec3)> = HashSet::default();
+
     for tile in extracted_tiles.iter() {
-        // First if the tile position has changed remove the tile from the old location.
-        if tile.position != tile.old_position.0 {
+        // First if the tile position or tilemap has changed remove the tile from the old location.
+        if tile.position != tile.old_position.0
+            || chunk_storage
+                .tilemap_of(tile.entity)
+                .is_some_and(|tilemap| tilemap != tile.tilemap_id.0)
+        {
             chunk_storage.remove_tile_with_entity(tile.entity);
         }
 
@@ -88,21 +128,24 @@ pub(crate) fn prepare(
             visibility,
             frustum_culling,
             tilemap_render_settings,
+            _,
+            _,
         ) = extracted_tilemaps.get(tile.tilemap_id.0).unwrap();
         let chunk_size = RenderChunkSize(tilemap_render_settings.render_chunk_size);
         let chunk_index = chunk_size.map_tile_to_chunk(&tile.position);
 
-        let chunk_data = UVec4::new(
+        let chunk_data = UVec3::new(
             chunk_index.x,
             chunk_index.y,
             transform.translation().z as u32,
-            tile.tilemap_id.0.index(),
         );
 
         let in_chunk_tile_index = chunk_size.map_tile_to_chunk_tile(&tile.position, &chunk_index);
+        // Each composed layer stores its tiles in its own chunk-sized block of slots.
+        let slot = in_chunk_tile_index + UVec2::new(0, tile.layer * chunk_size.y);
         let chunk = chunk_storage.get_or_add(
             tile.entity,
-            in_chunk_tile_index,
+            slot,
             tile.tilemap_id.0,
             &chunk_data,
             *chunk_size,
@@ -117,16 +160,18 @@ pub(crate) fn prepare(
             visibility,
             frustum_culling,
             chunk_size,
-            tilemap_render_settings.y_sort,
         );
+        if log_culled_changes {
+            changed_chunks.insert((chunk.tilemap_id, chunk.get_index()));
+        }
+        let animation_id = tile.animation.as_ref().map_or(0, |animation| {
+            animation_lookup.animation_id(tile.tilemap_id.0, animation)
+        });
         chunk.set(
-            &in_chunk_tile_index.into(),
+            &slot.into(),
             Some(PackedTileData {
-                position: chunk_size
-                    .map_tile_to_chunk_tile(&tile.position, &chunk_index)
-                    .as_vec2()
-                    .extend(tile.tile.position.z)
-                    .extend(tile.tile.position.w),
+                position: in_chunk_tile_index.as_vec2() + tile.motion_offset,
+                texture: tile.tile.texture.with_z(animation_id as f32),
                 ..tile.tile
             }),
         );
@@ -146,9 +191,20 @@ pub(crate) fn prepare(
         visibility,
         frustum_culling,
         _,
+        color,
+        (
+            blend_mode,
+            clip_rect,
+            sort_key,
+            animation_phase,
+            z_policy,
+            uv_inset,
+            vertex_data,
+            render_mode,
+        ),
     ) in extracted_tilemaps.iter()
     {
-        let chunks = chunk_storage.get_chunk_storage(&UVec4::new(0, 0, 0, entity.index()));
+        let chunks = chunk_storage.get_chunk_storage(entity);
         for chunk in chunks.values_mut() {
             chunk.texture = texture.clone();
             chunk.map_size = *map_size;
@@ -156,6 +212,23 @@ pub(crate) fn prepare(
             chunk.spacing = (*spacing).into();
             chunk.visible = visibility.get();
             chunk.frustum_culling = **frustum_culling;
+            chunk.color = color.0.to_linear().to_vec4();
+            chunk.animation_phase = animation_phase.0;
+            chunk.uv_inset = uv_inset.0;
+            chunk.blend_mode = *blend_mode;
+            chunk.render_mode = *render_mode;
+            chunk.clip_rect = clip_rect.0;
+            chunk.sort_key = sort_key.0.clone();
+            if chunk.z_policy != *z_policy {
+                // The drawing order of the tiles depends on the policy.
+                chunk.dirty_mesh = true;
+                chunk.z_policy = *z_policy;
+            }
+            if chunk.vertex_data != vertex_data.0 {
+                // The vertex data is uploaded along with the mesh.
+                chunk.dirty_mesh = true;
+                chunk.vertex_data = vertex_data.0;
+            }
             chunk.update_geometry(
                 (*global_transform).into(),
                 *grid_size,
@@ -167,8 +240,7 @@ pub(crate) fn prepare(
 
     for tilemap in extracted_tilemap_textures.iter() {
         let texture_size: Vec2 = tilemap.texture_size.into();
-        let chunks =
-            chunk_storage.get_chunk_storage(&UVec4::new(0, 0, 0, tilemap.tilemap_id.0.index()));
+        let chunks = chunk_storage.get_chunk_storage(tilemap.tilemap_id.0);
         for chunk in chunks.values_mut() {
             chunk.texture_size = texture_size;
         }
@@ -177,26 +249,117 @@ pub(crate) fn prepare(
     mesh_uniforms.0.clear();
     tilemap_uniforms.0.clear();
 
-    for chunk in chunk_storage.iter_mut() {
-        if !chunk.visible {
-            trace!("Visibility culled chunk: {:?}", chunk.get_index());
-            continue;
-        }
+    let mut chunk_aabbs = Vec::new();
+    let mut visible_chunks = chunk_storage
+        .iter_mut()
+        .filter(|chunk| {
+            if !chunk.visible {
+                trace!("Visibility culled chunk: {:?}", chunk.get_index());
+                return false;
+            }
 
-        if chunk.frustum_culling
-            && !extracted_frustum_query
+            let tilemap = Entity::from_bits(chunk.tilemap_id);
+            let culled = chunk.frustum_culling
+                && !culling_debug
+                    .as_ref()
+                    .is_some_and(|debug| debug.unculled_tilemaps.contains(&tilemap))
+                && !extracted_frustum_query
+                    .iter()
+                    .any(|frustum| chunk.intersects_frustum(frustum));
+            if let Some(culling_debug) = culling_debug.as_ref() {
+                if culling_debug.draw_aabbs {
+                    chunk_aabbs.push(ChunkAabb {
+                        aabb: chunk.get_aabb(),
+                        transform: chunk.get_transform_matrix(),
+                        culled,
+                    });
+                }
+                if culled && changed_chunks.contains(&(chunk.tilemap_id, chunk.get_index())) {
+                    let main_tilemap = culling_debug.main_entities.get(&tilemap);
+                    info!(
+                        "Chunk {:?} of tilemap {} was frustum culled in a frame where its tiles \
+                         changed.",
+                        chunk.get_index(),
+                        main_tilemap.unwrap_or(&tilemap)
+                    );
+                }
+            }
+            if culled {
+                trace!("Frustum culled chunk: {:?}", chunk.get_index());
+                return false;
+            }
+
+            true
+        })
+        .collect::<Vec<_>>();
+    if let Some(debug) = culling_debug.as_ref().filter(|debug| debug.draw_aabbs) {
+        debug.write_aabbs(chunk_aabbs);
+    }
+
+    let remesh_budget = match *remesh_policy {
+        RemeshPolicy::AllImmediately => usize::MAX,
+        RemeshPolicy::Budgeted { chunks_per_frame } => chunks_per_frame,
+        RemeshPolicy::NearestCameraFirst { chunks_per_frame } => {
+            let camera_positions = views
                 .iter()
-                .any(|frustum| chunk.intersects_frustum(frustum))
-        {
-            trace!("Frustum culled chunk: {:?}", chunk.get_index());
-            continue;
+                .map(|view| view.world_from_view.translation())
+                .collect::<Vec<_>>();
+            let distance_to_camera = |chunk: &RenderChunk2d| {
+                let center = chunk.world_center();
+                camera_positions
+                    .iter()
+                    .map(|camera| camera.truncate().distance_squared(center.truncate()))
+                    .fold(f32::INFINITY, f32::min)
+            };
+            visible_chunks.sort_by(|a, b| distance_to_camera(a).total_cmp(&distance_to_camera(b)));
+            chunks_per_frame
         }
+    };
 
-        chunk.prepare(&render_device, &mut mesh_vertex_buffer_layouts);
+    // Deferred chunks keep rendering their previous mesh until a later frame has budget left.
+    let mut remeshed_chunks = 0;
+    let mut pending: Vec<(&mut RenderChunk2d, bool)> = Vec::new();
+    for chunk in visible_chunks.iter_mut() {
+        let deferred = chunk.dirty_mesh && remeshed_chunks >= remesh_budget;
+        if !deferred && (chunk.dirty_mesh || chunk.dirty_colors) {
+            if chunk.dirty_mesh {
+                remeshed_chunks += 1;
+                if let Some(remeshed) = remeshed.as_mut() {
+                    *remeshed
+                        .0
+                        .entry(Entity::from_bits(chunk.tilemap_id))
+                        .or_default() += 1;
+                }
+            }
+            pending.push((&mut **chunk, false));
+        }
+    }
 
-        let chunk_uniform: TilemapUniformData = chunk.into();
+    // Building meshes is the expensive part, so it is spread over the compute task pool. Each
+    // chunk only writes to itself, so the result doesn't depend on how the work is split.
+    let task_pool = ComputeTaskPool::get();
+    let batch_size = pending.len().div_ceil(task_pool.thread_num().max(1)).max(1);
+    let (device, queue) = (&*render_device, &*render_queue);
+    task_pool.scope(|scope| {
+        for batch in pending.chunks_mut(batch_size) {
+            scope.spawn(async move {
+                for (chunk, rebuilt) in batch.iter_mut() {
+                    *rebuilt = chunk.prepare_buffers(device, queue);
+                }
+            });
+        }
+    });
+    for (chunk, rebuilt) in pending {
+        if rebuilt {
+            chunk.prepare_render_mesh(&mut mesh_vertex_buffer_layouts);
+        }
+    }
 
-        commands.spawn((
+    for chunk in visible_chunks {
+        let mut chunk_uniform: TilemapUniformData = chunk.into();
+        chunk_uniform.animation_row = animation_lookup.row(Entity::from_bits(chunk.tilemap_id));
+
+        let mut chunk_entity = commands.spawn((
             chunk.texture.clone_weak(),
             chunk.get_transform(),
             ChunkId(chunk.get_index()),
@@ -214,6 +377,18 @@ pub(crate) fn prepare(
             },
             TemporaryRenderEntity,
         ));
+        if let Some(clip_rect) = chunk.clip_rect {
+            chunk_entity.insert(clip_rect);
+        }
+    }
+
+    for chunk in chunk_storage.iter_mut() {
+        if static_tilemaps
+            .0
+            .contains(&Entity::from_bits(chunk.tilemap_id))
+        {
+            chunk.freeze();
+        }
     }
 
     mesh_uniforms.0.write_buffer(&render_device, &render_queue);
@@ -226,6 +401,8 @@ pub fn prepare_removal(
     mut chunk_storage: ResMut<RenderChunk2dStorage>,
     removed_tiles: Query<&RemovedTileEntity>,
     removed_maps: Query<&RemovedMapEntity>,
+    invalidated_maps: Query<&InvalidatedMapEntity>,
+    mut animation_lookup: ResMut<AnimationLookup>,
 ) {
     for removed_tile in removed_tiles.iter() {
         chunk_storage.remove_tile_with_entity(removed_tile.0.id())
@@ -233,5 +410,10 @@ pub fn prepare_removal(
 
     for removed_map in removed_maps.iter() {
         chunk_storage.remove_map(removed_map.0.id());
+        animation_lookup.remove_map(removed_map.0.id());
+    }
+
+    for invalidated_map in invalidated_maps.iter() {
+        chunk_storage.invalidate(invalidated_map.0.id(), &invalidated_map.1);
     }
 }
@@ -1,13 +1,18 @@
 use bevy::{
     prelude::*,
     render::{
-        render_resource::{BindGroup, BindGroupEntry},
+        render_resource::{
+            BindGroup, BindGroupEntry, BindingResource, BufferId, SamplerId, TextureViewId,
+        },
         renderer::RenderDevice,
+        texture::GpuImage,
     },
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
 
 use super::{
+    animation::AnimationLookup,
+    chunk::RenderChunk2dStorage,
     pipeline::TilemapPipeline,
     prepare::{MeshUniformResource, TilemapUniformResource},
 };
@@ -16,19 +21,34 @@ use crate::TilemapTexture;
 #[derive(Resource)]
 pub struct TransformBindGroup {
     pub value: BindGroup,
+    /// The buffers and animation lookup the bind group was created from.
+    source: (BufferId, BufferId, TextureViewId),
 }
 
+/// Creates the transform bind group, unless its buffers and animation lookup are the same as in
+/// the previous frame.
 pub fn queue_transform_bind_group(
     mut commands: Commands,
     tilemap_pipeline: Res<TilemapPipeline>,
     render_device: Res<RenderDevice>,
     transform_uniforms: Res<MeshUniformResource>,
     tilemap_uniforms: Res<TilemapUniformResource>,
+    animation_lookup: Res<AnimationLookup>,
+    transform_bind_group: Option<Res<TransformBindGroup>>,
 ) {
-    if let (Some(binding1), Some(binding2)) =
-        (transform_uniforms.0.binding(), tilemap_uniforms.0.binding())
-    {
+    if let (Some(binding1), Some(binding2), Some(buffer1), Some(buffer2), Some(animation_lookup)) = (
+        transform_uniforms.0.binding(),
+        tilemap_uniforms.0.binding(),
+        transform_uniforms.0.buffer(),
+        tilemap_uniforms.0.buffer(),
+        animation_lookup.texture_view(),
+    ) {
+        let source = (buffer1.id(), buffer2.id(), animation_lookup.id());
+        if transform_bind_group.is_some_and(|bind_group| bind_group.source == source) {
+            return;
+        }
         commands.insert_resource(TransformBindGroup {
+            source,
             value: render_device.create_bind_group(
                 Some("transform_bind_group"),
                 &tilemap_pipeline.mesh_layout,
@@ -41,6 +61,10 @@ pub fn queue_transform_bind_group(
                         binding: 1,
                         resource: binding2,
                     },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::TextureView(animation_lookup),
+                    },
                 ],
             ),
         });
@@ -52,7 +76,74 @@ pub struct TilemapViewBindGroup {
     pub value: BindGroup,
 }
 
+/// The view bind group shared by every view, as views only differ by their offset into the view
+/// uniform buffer. It is only created again when the view or globals buffer is reallocated.
+#[derive(Default, Resource)]
+pub struct TilemapViewBindGroupCache {
+    value: Option<((BufferId, BufferId), BindGroup)>,
+}
+
+impl TilemapViewBindGroupCache {
+    /// Returns the view bind group of the `view` and `globals` buffers, calling `create` if they
+    /// changed since it was last created.
+    pub fn get_or_create(
+        &mut self,
+        view: BufferId,
+        globals: BufferId,
+        create: impl FnOnce() -> BindGroup,
+    ) -> &BindGroup {
+        if self
+            .value
+            .as_ref()
+            .is_none_or(|(source, _)| *source != (view, globals))
+        {
+            self.value = Some(((view, globals), create()));
+        }
+        &self.value.as_ref().unwrap().1
+    }
+}
+
+/// The material bind groups of tilemap textures, kept across frames.
 #[derive(Default, Resource)]
 pub struct ImageBindGroups {
     pub values: HashMap<TilemapTexture, BindGroup>,
+    /// The texture view and sampler each bind group was created from.
+    sources: HashMap<TilemapTexture, (TextureViewId, SamplerId)>,
+}
+
+impl ImageBindGroups {
+    /// Makes sure `texture` has a bind group for `gpu_image`, calling `create` only if it has
+    /// none yet, or if the texture view or sampler of the image changed since it was created.
+    pub fn get_or_create(
+        &mut self,
+        texture: &TilemapTexture,
+        gpu_image: &GpuImage,
+        create: impl FnOnce() -> BindGroup,
+    ) {
+        let source = (gpu_image.texture_view.id(), gpu_image.sampler.id());
+        if self.sources.get(texture) != Some(&source) || !self.values.contains_key(texture) {
+            self.values.insert(texture.clone_weak(), create());
+            self.sources.insert(texture.clone_weak(), source);
+        }
+    }
+
+    /// Forgets the bind group of `texture`, so that it is created again.
+    pub fn invalidate(&mut self, texture: &TilemapTexture) {
+        self.values.remove(texture);
+        self.sources.remove(texture);
+    }
+}
+
+/// Drops the bind groups of textures no render chunk uses anymore.
+pub fn remove_unused_image_bind_groups(
+    chunk_storage: Res<RenderChunk2dStorage>,
+    mut image_bind_groups: ResMut<ImageBindGroups>,
+) {
+    if image_bind_groups.values.is_empty() {
+        return;
+    }
+    let used: HashSet<&TilemapTexture> = chunk_storage.iter().map(|chunk| &chunk.texture).collect();
+    let ImageBindGroups { values, sources } = image_bind_groups.as_mut();
+    values.retain(|texture, _| used.contains(texture));
+    sources.retain(|texture, _| used.contains(texture));
 }
@@ -0,0 +1,193 @@
+use bevy::{
+    asset::{embedded_asset, AssetServer, Handle},
+    prelude::{App, Resource, Shader},
+    utils::HashMap,
+};
+
+/// The root of the asset paths of the built-in tilemap shaders.
+const SHADER_ROOT: &str = "embedded://bevy_ecs_tilemap/render/shaders/";
+
+/// One of the shaders used to render tilemaps, which can be replaced with
+/// [`TilemapShaderOverrides`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TilemapShader {
+    /// The vertex shader entry point.
+    Vertex,
+    /// The fragment shader entry point.
+    Fragment,
+    /// `bevy_ecs_tilemap::common`: bindings and vertex input.
+    Common,
+    /// `bevy_ecs_tilemap::mesh_output`.
+    MeshOutput,
+    /// `bevy_ecs_tilemap::vertex_output`.
+    VertexOutput,
+    /// `bevy_ecs_tilemap::projection`: the tile-to-world math of every map type, which custom
+    /// material shaders can import too.
+    Projection,
+    /// The projection of [`TilemapType::Square`](crate::map::TilemapType::Square) maps.
+    Square,
+    /// The projection of diamond isometric maps.
+    DiamondIso,
+    /// The projection of staggered isometric maps.
+    StaggeredIso,
+    /// The projection of row hexagonal maps.
+    RowHex,
+    /// The projection of row even hexagonal maps.
+    RowEvenHex,
+    /// The projection of row odd hexagonal maps.
+    RowOddHex,
+    /// The projection of column hexagonal maps.
+    ColumnHex,
+    /// The projection of column even hexagonal maps.
+    ColumnEvenHex,
+    /// The projection of column odd hexagonal maps.
+    ColumnOddHex,
+}
+
+impl TilemapShader {
+    /// Every tilemap shader.
+    pub const ALL: [TilemapShader; 15] = [
+        TilemapShader::Vertex,
+        TilemapShader::Fragment,
+        TilemapShader::Common,
+        TilemapShader::MeshOutput,
+        TilemapShader::VertexOutput,
+        TilemapShader::Projection,
+        TilemapShader::Square,
+        TilemapShader::DiamondIso,
+        TilemapShader::StaggeredIso,
+        TilemapShader::RowHex,
+        TilemapShader::RowEvenHex,
+        TilemapShader::RowOddHex,
+        TilemapShader::ColumnHex,
+        TilemapShader::ColumnEvenHex,
+        TilemapShader::ColumnOddHex,
+    ];
+
+    /// The file name of the built-in shader, which is also its name in the source tree.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            TilemapShader::Vertex => "tilemap_vertex.wgsl",
+            TilemapShader::Fragment => "tilemap_fragment.wgsl",
+            TilemapShader::Common => "common.wgsl",
+            TilemapShader::MeshOutput => "mesh_output.wgsl",
+            TilemapShader::VertexOutput => "tilemap_vertex_output.wgsl",
+            TilemapShader::Projection => "projection.wgsl",
+            TilemapShader::Square => "square.wgsl",
+            TilemapShader::DiamondIso => "diamond_iso.wgsl",
+            TilemapShader::StaggeredIso => "staggered_iso.wgsl",
+            TilemapShader::RowHex => "row_hex.wgsl",
+            TilemapShader::RowEvenHex => "row_even_hex.wgsl",
+            TilemapShader::RowOddHex => "row_odd_hex.wgsl",
+            TilemapShader::ColumnHex => "column_hex.wgsl",
+            TilemapShader::ColumnEvenHex => "column_even_hex.wgsl",
+            TilemapShader::ColumnOddHex => "column_odd_hex.wgsl",
+        }
+    }
+
+    /// The asset path of the built-in shader, e.g. to load it as a starting point.
+    pub fn asset_path(self) -> String {
+        format!("{SHADER_ROOT}{}", self.file_name())
+    }
+}
+
+/// Replaces some of the built-in tilemap shaders, without forking the crate.
+///
+/// This must be inserted before the [`TilemapPlugin`](crate::TilemapPlugin) is finished, i.e.
+/// while building the app. Shaders loaded from files are hot-reloaded by the asset server like any
+/// other asset, when its file watcher is enabled.
+///
+/// Every shader except [`TilemapShader::Vertex`] and [`TilemapShader::Fragment`] is an import
+/// module, so its replacement must keep the same `#define_import_path` and the functions the
+/// other shaders use from it. The built-in shader isn't loaded at all when replaced, so the
+/// import path only resolves to the replacement.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// // After `DefaultPlugins` and before the app runs:
+/// fn override_square_projection(app: &mut App) {
+///     let square = app
+///         .world()
+///         .resource::<AssetServer>()
+///         .load("shaders/my_square.wgsl");
+///     app.insert_resource(TilemapShaderOverrides::default().with(TilemapShader::Square, square));
+/// }
+/// ```
+#[derive(Resource, Clone, Default, Debug)]
+pub struct TilemapShaderOverrides {
+    shaders: HashMap<TilemapShader, Handle<Shader>>,
+}
+
+impl TilemapShaderOverrides {
+    /// Replaces `shader` with `handle`.
+    pub fn with(mut self, shader: TilemapShader, handle: Handle<Shader>) -> Self {
+        self.insert(shader, handle);
+        self
+    }
+
+    /// Replaces `shader` with `handle`, returning the previous replacement, if any.
+    pub fn insert(
+        &mut self,
+        shader: TilemapShader,
+        handle: Handle<Shader>,
+    ) -> Option<Handle<Shader>> {
+        self.shaders.insert(shader, handle)
+    }
+
+    /// Returns the replacement of `shader`, if any.
+    pub fn get(&self, shader: TilemapShader) -> Option<&Handle<Shader>> {
+        self.shaders.get(&shader)
+    }
+}
+
+/// The handles of the shaders used to render tilemaps, which keep them loaded.
+#[derive(Resource, Clone, Debug)]
+pub(crate) struct TilemapShaders {
+    shaders: HashMap<TilemapShader, Handle<Shader>>,
+}
+
+impl TilemapShaders {
+    pub fn get(&self, shader: TilemapShader) -> Handle<Shader> {
+        self.shaders[&shader].clone()
+    }
+}
+
+/// Embeds the built-in shaders, and loads every shader which isn't overridden by the
+/// [`TilemapShaderOverrides`] resource.
+pub(crate) fn load_tilemap_shaders(app: &mut App) -> TilemapShaders {
+    embedded_asset!(app, "shaders/tilemap_vertex.wgsl");
+    embedded_asset!(app, "shaders/tilemap_fragment.wgsl");
+    embedded_asset!(app, "shaders/common.wgsl");
+    embedded_asset!(app, "shaders/mesh_output.wgsl");
+    embedded_asset!(app, "shaders/tilemap_vertex_output.wgsl");
+    embedded_asset!(app, "shaders/projection.wgsl");
+    embedded_asset!(app, "shaders/square.wgsl");
+    embedded_asset!(app, "shaders/diamond_iso.wgsl");
+    embedded_asset!(app, "shaders/staggered_iso.wgsl");
+    embedded_asset!(app, "shaders/row_hex.wgsl");
+    embedded_asset!(app, "shaders/row_even_hex.wgsl");
+    embedded_asset!(app, "shaders/row_odd_hex.wgsl");
+    embedded_asset!(app, "shaders/column_hex.wgsl");
+    embedded_asset!(app, "shaders/column_even_hex.wgsl");
+    embedded_asset!(app, "shaders/column_odd_hex.wgsl");
+
+    let overrides = app
+        .world()
+        .get_resource::<TilemapShaderOverrides>()
+        .cloned()
+        .unwrap_or_default();
+    let asset_server = app.world().resource::<AssetServer>();
+    let shaders = TilemapShader::ALL
+        .into_iter()
+        .map(|shader| {
+            let handle = overrides
+                .get(shader)
+                .cloned()
+                .unwrap_or_else(|| asset_server.load(shader.asset_path()));
+            (shader, handle)
+        })
+        .collect();
+
+    TilemapShaders { shaders }
+}
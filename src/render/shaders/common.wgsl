@@ -15,15 +15,57 @@ struct TilemapData {
     spacing: vec2<f32>,
     chunk_pos: vec2<f32>,
     map_size: vec2<f32>,
+    color: vec4<f32>,
+    animation_row: u32,
+    animation_phase: f32,
+    uv_inset: f32,
+    depth_range: vec2<f32>,
 };
 @group(1) @binding(1)
 var<uniform> tilemap_data: TilemapData;
 
+// A pseudo-random value in `[0, 1)` for a tile position in the map, used to offset the animations
+// of tiles so they don't play in lockstep.
+fn tile_phase(tile_pos: vec2<f32>) -> f32 {
+    var h: u32 = (u32(tile_pos.x) * 73856093u) ^ (u32(tile_pos.y) * 19349663u);
+    h = (h ^ (h >> 16u)) * 0x45d9f3bu;
+    h = (h ^ (h >> 16u)) * 0x45d9f3bu;
+    h = h ^ (h >> 16u);
+    return f32(h & 0xffffu) / 65536.0;
+}
+
+// One row per tilemap, one `vec4(start, end, speed, 0.0)` per range animation id, or one
+// `vec4(first frame column, frame count, duration, 1.0)` per frame list animation id, followed by
+// one `vec4(texture index, end time, 0.0, 0.0)` per frame.
+@group(1) @binding(2)
+var animation_lookup: texture_2d<f32>;
+
 struct VertexInput {
     @builtin(vertex_index) v_index: u32,
-    @location(0) uv: vec4<f32>,
-    @location(1) position: vec4<f32>,
+    // The texture index, the flip bits, and the animation id.
+    @location(0) uv: vec3<f32>,
+    @location(1) position: vec2<f32>,
     @location(2) color: vec4<f32>,
+    // Bit 0 is set for hidden tiles. Bits 1 to 5 control the animation: hold the frame at
+    // `animation_time`, reverse, ping-pong, play once, and ignore the tile phase.
+    @location(3) flags: u32,
+    // The color pulsed towards, and the seconds of a pulse, or zero for a static color.
+    @location(4) color_to: vec4<f32>,
+    @location(5) color_period: f32,
+    // The time the animation started at, or the seconds into the animation it is held at.
+    @location(6) animation_time: f32,
+#ifdef TILE_VERTEX_DATA
+    // The `TileVertexData` of the tile.
+    @location(7) vertex_data: vec4<f32>,
+#endif
+#ifdef TILE_TRANSFORMS
+    // The `TilePosOffset` of the tile, then its `TileRotation`.
+    @location(8) tile_transform: vec3<f32>,
+#endif
+#ifdef TILE_RENDER_SIZES
+    // The `TileRenderSize` of the tile, or zero.
+    @location(9) render_size: vec2<f32>,
+#endif
 }
 
 #ifdef ATLAS
@@ -41,13 +83,13 @@ var sprite_sampler: sampler;
 
 fn process_fragment(in: MeshVertexOutput) -> vec4<f32> {
     #ifdef ATLAS
-    let half_texture_pixel_size_u = 0.5 / tilemap_data.texture_size.x;
-    let half_texture_pixel_size_v = 0.5 / tilemap_data.texture_size.y;
-    let half_tile_pixel_size_u = 0.5 / tilemap_data.tile_size.x;
-    let half_tile_pixel_size_v = 0.5 / tilemap_data.tile_size.y;
+    let half_texture_pixel_size_u = tilemap_data.uv_inset / tilemap_data.texture_size.x;
+    let half_texture_pixel_size_v = tilemap_data.uv_inset / tilemap_data.texture_size.y;
+    let half_tile_pixel_size_u = tilemap_data.uv_inset / tilemap_data.tile_size.x;
+    let half_tile_pixel_size_v = tilemap_data.uv_inset / tilemap_data.tile_size.y;
 
-    // Offset the UV 1/2 pixel from the sides of the tile, so that the sampler doesn't bleed onto
-    // adjacent tiles at the edges.
+    // Offset the UV by the inset (1/2 pixel by default) from the sides of the tile, so that the
+    // sampler doesn't bleed onto adjacent tiles at the edges.
     var uv_offset: vec2<f32> = vec2<f32>(0.0, 0.0);
     if (in.uv.z < half_tile_pixel_size_u) {
         uv_offset.x = half_texture_pixel_size_u;
@@ -0,0 +1,119 @@
+#define_import_path bevy_ecs_tilemap::projection
+
+// The tile-to-world math of every map type, matching the CPU side `TilePos::center_in_world`.
+// Positions are in tiles, and results are in the tilemap's local space.
+
+const SQRT_3: f32 = 1.7320508;
+const HALF_SQRT_3: f32 = 0.8660254;
+
+const DIAMOND_BASIS_X: vec2<f32> = vec2<f32>(0.5, -0.5);
+const DIAMOND_BASIS_Y: vec2<f32> = vec2<f32>(0.5, 0.5);
+
+const ROW_BASIS_X: vec2<f32> = vec2<f32>(1.0, 0.0);
+const ROW_BASIS_Y: vec2<f32> = vec2<f32>(0.5, HALF_SQRT_3);
+
+const COL_BASIS_X: vec2<f32> = vec2<f32>(HALF_SQRT_3, 0.5);
+const COL_BASIS_Y: vec2<f32> = vec2<f32>(0.0, 1.0);
+
+fn square_tile_pos_to_world_pos(pos: vec2<f32>, grid_size: vec2<f32>) -> vec2<f32> {
+    return pos * grid_size;
+}
+
+fn diamond_tile_pos_to_world_pos(pos: vec2<f32>, grid_size: vec2<f32>) -> vec2<f32> {
+    let unscaled_pos = pos.x * DIAMOND_BASIS_X + pos.y * DIAMOND_BASIS_Y;
+    return grid_size * unscaled_pos;
+}
+
+fn staggered_tile_pos_to_world_pos(pos: vec2<f32>, grid_size: vec2<f32>) -> vec2<f32> {
+    return diamond_tile_pos_to_world_pos(vec2<f32>(pos.x, pos.y + pos.x), grid_size);
+}
+
+// Takes an axial position.
+fn hex_row_tile_pos_to_world_pos(pos: vec2<f32>, grid_size: vec2<f32>) -> vec2<f32> {
+    let unscaled_pos = pos.x * ROW_BASIS_X + pos.y * ROW_BASIS_Y;
+    return vec2<f32>(grid_size.x * unscaled_pos.x, ROW_BASIS_Y.y * grid_size.y * unscaled_pos.y);
+}
+
+// Takes an axial position.
+fn hex_col_tile_pos_to_world_pos(pos: vec2<f32>, grid_size: vec2<f32>) -> vec2<f32> {
+    let unscaled_pos = pos.x * COL_BASIS_X + pos.y * COL_BASIS_Y;
+    return vec2<f32>(COL_BASIS_X.x * grid_size.x * unscaled_pos.x, grid_size.y * unscaled_pos.y);
+}
+
+fn row_even_to_axial(offset_pos: vec2<f32>) -> vec2<f32> {
+    let delta: f32 = ceil(offset_pos.y / 2.0);
+    return vec2<f32>(offset_pos.x - delta, offset_pos.y);
+}
+
+fn row_odd_to_axial(offset_pos: vec2<f32>) -> vec2<f32> {
+    let delta: f32 = floor(offset_pos.y / 2.0);
+    return vec2<f32>(offset_pos.x - delta, offset_pos.y);
+}
+
+fn col_even_to_axial(offset_pos: vec2<f32>) -> vec2<f32> {
+    let delta: f32 = ceil(offset_pos.x / 2.0);
+    return vec2<f32>(offset_pos.x, offset_pos.y - delta);
+}
+
+fn col_odd_to_axial(offset_pos: vec2<f32>) -> vec2<f32> {
+    let delta: f32 = floor(offset_pos.x / 2.0);
+    return vec2<f32>(offset_pos.x, offset_pos.y - delta);
+}
+
+// The center of the tile at `pos`, using the map type of the pipeline's shader defs.
+fn tile_pos_to_world_pos(pos: vec2<f32>, grid_size: vec2<f32>) -> vec2<f32> {
+#ifdef ISO_DIAMOND
+    return diamond_tile_pos_to_world_pos(pos, grid_size);
+#else ifdef ISO_STAGGERED
+    return staggered_tile_pos_to_world_pos(pos, grid_size);
+#else ifdef ROW_HEX
+    return hex_row_tile_pos_to_world_pos(pos, grid_size);
+#else ifdef ROW_EVEN_HEX
+    return hex_row_tile_pos_to_world_pos(row_even_to_axial(pos), grid_size);
+#else ifdef ROW_ODD_HEX
+    return hex_row_tile_pos_to_world_pos(row_odd_to_axial(pos), grid_size);
+#else ifdef COLUMN_HEX
+    return hex_col_tile_pos_to_world_pos(pos, grid_size);
+#else ifdef COLUMN_EVEN_HEX
+    return hex_col_tile_pos_to_world_pos(col_even_to_axial(pos), grid_size);
+#else ifdef COLUMN_ODD_HEX
+    return hex_col_tile_pos_to_world_pos(col_odd_to_axial(pos), grid_size);
+#else
+    return square_tile_pos_to_world_pos(pos, grid_size);
+#endif
+}
+
+// The corner `v_index % 4` of a tile quad, in the order of the chunk mesh's vertices.
+fn tile_quad_corner(center: vec2<f32>, tile_size: vec2<f32>, v_index: u32) -> vec2<f32> {
+    let bot_left = center - 0.5 * tile_size;
+    let top_right = bot_left + tile_size;
+
+    var positions = array<vec2<f32>, 4>(
+        bot_left,
+        vec2<f32>(bot_left.x, top_right.y),
+        top_right,
+        vec2<f32>(top_right.x, bot_left.y)
+    );
+
+    return positions[v_index % 4u];
+}
+
+// The corner `v_index % 4` of the quad of a tile drawn at `render_size`, standing on the bottom
+// edge of the grid cell at `center`. Tiles without a render size are centered on the cell.
+fn tile_render_corner(center: vec2<f32>, grid_size: vec2<f32>, tile_size: vec2<f32>, render_size: vec2<f32>, v_index: u32) -> vec2<f32> {
+    if (render_size.x <= 0.0 || render_size.y <= 0.0) {
+        return tile_quad_corner(center, tile_size, v_index);
+    }
+    let bottom = center.y - 0.5 * grid_size.y;
+    return tile_quad_corner(vec2<f32>(center.x, bottom + 0.5 * render_size.y), render_size, v_index);
+}
+
+// Moves a corner of a tile quad by the offset of the tile, after rotating it counterclockwise
+// around the center of the tile. `tile_transform` holds the offset, then the angle in radians.
+fn transform_tile_corner(corner: vec2<f32>, center: vec2<f32>, tile_transform: vec3<f32>) -> vec2<f32> {
+    let c = cos(tile_transform.z);
+    let s = sin(tile_transform.z);
+    let from_center = corner - center;
+    let rotated = vec2<f32>(c * from_center.x - s * from_center.y, s * from_center.x + c * from_center.y);
+    return center + rotated + tile_transform.xy;
+}
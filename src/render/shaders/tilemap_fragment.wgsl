@@ -3,5 +3,12 @@
 
 @fragment
 fn fragment(in: MeshVertexOutput) -> @location(0) vec4<f32> {
-    return process_fragment(in);
+    let color = process_fragment(in);
+#ifdef ALPHA_DISCARD
+    // Tiles write their depth, so transparent pixels would hide what is drawn behind them later.
+    if (color.a < 0.5) {
+        discard;
+    }
+#endif
+    return color;
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
-#import bevy_ecs_tilemap::common::{VertexInput, tilemap_data}
+#import bevy_ecs_tilemap::common::{VertexInput, tilemap_data, animation_lookup, tile_phase, mesh}
+#import bevy_ecs_tilemap::projection::tile_pos_to_world_pos
 #import bevy_ecs_tilemap::mesh_output::MeshOutput
 #import bevy_sprite::mesh2d_view_bindings::{view, globals}
 #import bevy_ecs_tilemap::vertex_output::MeshVertexOutput
@@ -40,20 +41,97 @@
 #endif
 
 
+// The bits of `VertexInput::flags`, matching the `TILE_FLAG_*` constants in `chunk.rs`.
+const TILE_FLAG_HIDDEN: u32 = 1u;
+const TILE_FLAG_ANIMATION_HOLD: u32 = 2u;
+const TILE_FLAG_ANIMATION_REVERSE: u32 = 4u;
+const TILE_FLAG_ANIMATION_PING_PONG: u32 = 8u;
+const TILE_FLAG_ANIMATION_ONCE: u32 = 16u;
+const TILE_FLAG_ANIMATION_STATE: u32 = 32u;
+
+// Entry `index` of the animations of the tilemap, which wrap into the rows after its first one.
+fn animation_entry(index: u32) -> vec4<f32> {
+    let width = textureDimensions(animation_lookup).x;
+    return textureLoad(animation_lookup, vec2<u32>(index % width, tilemap_data.animation_row + index / width), 0);
+}
+
 @vertex
 fn vertex(vertex_input: VertexInput) -> MeshVertexOutput {
     var out: MeshVertexOutput;
-    let animation_speed = vertex_input.position.z;
-
-    let mesh_data: MeshOutput = get_mesh(vertex_input.v_index, vec3(vertex_input.position.xy, 0.0));
-
-    let frames: f32 = f32(vertex_input.uv.w - vertex_input.uv.z);
 
-    var current_animation_frame = fract(globals.time * animation_speed) * frames;
-
-    current_animation_frame = clamp(f32(vertex_input.uv.z) + current_animation_frame, f32(vertex_input.uv.z), f32(vertex_input.uv.w));
+#ifdef TILE_TRANSFORMS
+    let tile_transform = vertex_input.tile_transform;
+#else
+    let tile_transform = vec3<f32>(0.0);
+#endif
+#ifdef TILE_RENDER_SIZES
+    let render_size = vertex_input.render_size;
+#else
+    let render_size = vec2<f32>(0.0);
+#endif
+    var mesh_data: MeshOutput = get_mesh(vertex_input.v_index, vec3(vertex_input.position.xy, 0.0), tile_transform, render_size);
+#ifdef TILE_DEPTH
+    // Tiles further down the map are drawn in front, as chunks are with `ChunkZPolicy::WorldY`.
+    // `depth_range` holds the bottom and height of the projected tile centers of the map.
+    let tile_center = tile_pos_to_world_pos(tilemap_data.chunk_pos + vertex_input.position.xy, tilemap_data.grid_size);
+    let depth = 1.0 - (tile_center.y - tilemap_data.depth_range.x) / tilemap_data.depth_range.y;
+    mesh_data.world_position += mesh.model * vec4<f32>(0.0, 0.0, depth, 0.0);
+#endif
 
-    let texture_index: u32 = u32(current_animation_frame);
+    var texture_index: u32 = u32(vertex_input.uv.x);
+    let animation_id: u32 = u32(vertex_input.uv.z);
+    if (animation_id != 0u) {
+        let animation = animation_entry(animation_id);
+        // The tile position in the map, so that the offsets don't repeat from chunk to chunk.
+        var phase = tile_phase(tilemap_data.chunk_pos + vertex_input.position.xy) * tilemap_data.animation_phase;
+        if ((vertex_input.flags & TILE_FLAG_ANIMATION_STATE) != 0u) {
+            // Animations with a state play from the time they were started at.
+            phase = 0.0;
+        }
+        // The seconds the animation has been playing for.
+        var time = vertex_input.animation_time;
+        if ((vertex_input.flags & TILE_FLAG_ANIMATION_HOLD) == 0u) {
+            time = globals.time - time;
+            if (time < 0.0) {
+                // The elapsed time wraps every hour.
+                time += 3600.0;
+            }
+        }
+        // The number of times the animation has been played through.
+        var progress = time * animation.z;
+        if (animation.w > 0.5) {
+            progress = time / animation.z;
+        }
+        if ((vertex_input.flags & TILE_FLAG_ANIMATION_ONCE) != 0u) {
+            // Played once, the last frame is held.
+        } else if ((vertex_input.flags & TILE_FLAG_ANIMATION_PING_PONG) != 0u) {
+            let bounce = fract((progress + phase) * 0.5) * 2.0;
+            progress = select(bounce, 2.0 - bounce, bounce > 1.0);
+        } else {
+            progress = fract(progress + phase);
+        }
+        progress = clamp(progress, 0.0, 0.99999);
+        if ((vertex_input.flags & TILE_FLAG_ANIMATION_REVERSE) != 0u) {
+            progress = 0.99999 - progress;
+        }
+
+        if (animation.w > 0.5) {
+            // A list of frames, each with the time its display ends at.
+            let first_frame = u32(animation.x);
+            let frame_count = u32(animation.y);
+            let frame_time = progress * animation.z;
+            for (var i = 0u; i < frame_count; i++) {
+                let frame = animation_entry(first_frame + i);
+                texture_index = u32(frame.x);
+                if (frame_time < frame.y) {
+                    break;
+                }
+            }
+        } else {
+            let frames: f32 = animation.y - animation.x;
+            texture_index = u32(clamp(animation.x + progress * frames, animation.x, animation.y));
+        }
+    }
 
     #ifdef ATLAS
     // Get the top-left corner of the current frame in the texture, accounting for padding around the whole texture
@@ -63,9 +141,11 @@ fn vertex(vertex_input: VertexInput) -> MeshVertexOutput {
     let sprite_sheet_y: f32 = tilemap_data.spacing.y + floor(f32(texture_index / columns)) * (tilemap_data.tile_size.y + tilemap_data.spacing.y);
 
     let start_u: f32 = sprite_sheet_x / tilemap_data.texture_size.x;
-    let end_u: f32 = (sprite_sheet_x + tilemap_data.tile_size.x) / tilemap_data.texture_size.x;
+    // Oversized tiles span the region of their render size, from the top left of their frame.
+    let frame_size = select(tilemap_data.tile_size, render_size, render_size.x > 0.0 && render_size.y > 0.0);
+    let end_u: f32 = (sprite_sheet_x + frame_size.x) / tilemap_data.texture_size.x;
     let start_v: f32 = sprite_sheet_y / tilemap_data.texture_size.y;
-    let end_v: f32 = (sprite_sheet_y + tilemap_data.tile_size.y) / tilemap_data.texture_size.y;
+    let end_v: f32 = (sprite_sheet_y + frame_size.y) / tilemap_data.texture_size.y;
     #else
     let start_u: f32 = 0.0;
     let end_u: f32 = 1.0;
@@ -131,7 +211,19 @@ fn vertex(vertex_input: VertexInput) -> MeshVertexOutput {
     out.tile_id = i32(texture_index);
     // out.uv = out.uv + 1e-5;
     out.position = view.clip_from_world * mesh_data.world_position;
-    out.color = vertex_input.color;
+    var color = vertex_input.color;
+    if (vertex_input.color_period > 0.0) {
+        let pulse = 0.5 - 0.5 * cos(globals.time * 6.2831855 / vertex_input.color_period);
+        color = mix(color, vertex_input.color_to, pulse);
+    }
+    out.color = color * tilemap_data.color;
     out.storage_position = vec2<u32>(vertex_input.position.xy);
+#ifdef TILE_VERTEX_DATA
+    out.vertex_data = vertex_input.vertex_data;
+#endif
+    if ((vertex_input.flags & TILE_FLAG_HIDDEN) != 0u) {
+        // Hidden tiles are collapsed to a point outside of the clip volume, so nothing is drawn.
+        out.position = vec4<f32>(2.0, 2.0, 2.0, 1.0);
+    }
     return out;
 }
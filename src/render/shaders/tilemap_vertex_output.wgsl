@@ -6,4 +6,7 @@ struct MeshVertexOutput {
     @location(1) color: vec4<f32>,
     @location(2) @interpolate(flat) tile_id: i32,
     @location(3) storage_position: vec2<u32>,
+#ifdef TILE_VERTEX_DATA
+    @location(4) @interpolate(flat) vertex_data: vec4<f32>,
+#endif
 }
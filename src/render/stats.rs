@@ -0,0 +1,324 @@
+use std::sync::{Arc, RwLock};
+
+use bevy::{
+    core_pipeline::core_2d::{Opaque2d, Transparent2d},
+    prelude::*,
+    render::{
+        render_phase::{ViewBinnedRenderPhases, ViewSortedRenderPhases},
+        sync_world::MainEntity,
+        Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+
+use super::chunk::{ChunkId, RenderChunk2dStorage};
+use crate::map::TilemapId;
+
+/// What it took to render a tilemap during a frame, as reported by [`TilemapRenderStats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TilemapFrameStats {
+    /// The number of render chunks of the tilemap.
+    pub chunks: usize,
+    /// The number of chunks that survived visibility and frustum culling.
+    pub visible_chunks: usize,
+    /// The number of draw calls issued for the tilemap, over all views.
+    pub draw_calls: usize,
+    /// The number of chunks whose mesh was rebuilt.
+    pub remeshed_chunks: usize,
+}
+
+impl TilemapFrameStats {
+    fn add(&mut self, other: &TilemapFrameStats) {
+        self.chunks += other.chunks;
+        self.visible_chunks += other.visible_chunks;
+        self.draw_calls += other.draw_calls;
+        self.remeshed_chunks += other.remeshed_chunks;
+    }
+}
+
+/// Reports the chunks, draw calls and remeshes of every tilemap during the last rendered frame,
+/// to help tune chunk sizes and the [`RemeshPolicy`](crate::prelude::RemeshPolicy).
+///
+/// The numbers are measured in the render world, so they lag a frame behind.
+///
+/// Requires the [`TilemapRenderStatsPlugin`].
+#[derive(Resource, Clone, Default, Debug)]
+pub struct TilemapRenderStats {
+    // Arc and RwLock let the render world write its numbers back to the main world.
+    stats: Arc<RwLock<HashMap<Entity, TilemapFrameStats>>>,
+}
+
+impl TilemapRenderStats {
+    /// Returns the stats of `tilemap`, if it was rendered during the last frame.
+    pub fn get(&self, tilemap: Entity) -> Option<TilemapFrameStats> {
+        self.stats.read().ok()?.get(&tilemap).copied()
+    }
+
+    /// Returns the stats of every tilemap, ordered by entity.
+    pub fn all(&self) -> Vec<(Entity, TilemapFrameStats)> {
+        let mut all = self.stats.read().map_or_else(
+            |_| Vec::new(),
+            |stats| {
+                stats
+                    .iter()
+                    .map(|(tilemap, stats)| (*tilemap, *stats))
+                    .collect()
+            },
+        );
+        all.sort_by_key(|(tilemap, _)| *tilemap);
+        all
+    }
+
+    /// Returns the stats of all tilemaps together.
+    pub fn total(&self) -> TilemapFrameStats {
+        let mut total = TilemapFrameStats::default();
+        for (_, stats) in self.all() {
+            total.add(&stats);
+        }
+        total
+    }
+}
+
+/// The chunks remeshed by `prepare` this frame, per render world tilemap entity.
+#[derive(Resource, Default)]
+pub(crate) struct RemeshedChunks(pub HashMap<Entity, usize>);
+
+/// Adds the [`TilemapRenderStats`] resource, and the system measuring tilemaps every frame.
+pub struct TilemapRenderStatsPlugin;
+
+impl Plugin for TilemapRenderStatsPlugin {
+    fn build(&self, app: &mut App) {
+        let stats = TilemapRenderStats::default();
+        app.insert_resource(stats.clone());
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(stats)
+                .init_resource::<RemeshedChunks>()
+                .add_systems(Render, update_render_stats.in_set(RenderSet::Cleanup));
+        }
+    }
+}
+
+fn update_render_stats(
+    stats: Res<TilemapRenderStats>,
+    chunk_storage: Res<RenderChunk2dStorage>,
+    mut remeshed: ResMut<RemeshedChunks>,
+    visible_chunks: Query<&TilemapId, With<ChunkId>>,
+    transparent_phases: Res<ViewSortedRenderPhases<Transparent2d>>,
+    opaque_phases: Res<ViewBinnedRenderPhases<Opaque2d>>,
+    main_entities: Query<&MainEntity>,
+) {
+    let mut measured: HashMap<Entity, TilemapFrameStats> = HashMap::default();
+    for chunk in chunk_storage.iter() {
+        measured
+            .entry(Entity::from_bits(chunk.tilemap_id))
+            .or_default()
+            .chunks += 1;
+    }
+    for tilemap_id in visible_chunks.iter() {
+        measured.entry(tilemap_id.0).or_default().visible_chunks += 1;
+    }
+    // Items merged into the batch of a previous item have an empty range and cost no draw call.
+    for phase in transparent_phases.values() {
+        for item in phase.items.iter() {
+            if item.batch_range.is_empty() {
+                continue;
+            }
+            if let Ok(tilemap_id) = visible_chunks.get(item.entity.0) {
+                measured.entry(tilemap_id.0).or_default().draw_calls += 1;
+            }
+        }
+    }
+    // Opaque chunks are never batched.
+    for phase in opaque_phases.values() {
+        for (_, (entity, _)) in phase.non_mesh_items.iter() {
+            if let Ok(tilemap_id) = visible_chunks.get(*entity) {
+                measured.entry(tilemap_id.0).or_default().draw_calls += 1;
+            }
+        }
+    }
+    for (tilemap, remeshed_chunks) in remeshed.0.drain() {
+        measured.entry(tilemap).or_default().remeshed_chunks = remeshed_chunks;
+    }
+
+    // Tilemaps are measured by their render entity, and reported by their main world entity.
+    let measured = measured
+        .into_iter()
+        .filter_map(|(tilemap, stats)| Some((main_entities.get(tilemap).ok()?.id(), stats)))
+        .collect();
+    if let Ok(mut stats) = stats.stats.write() {
+        *stats = measured;
+    }
+}
+
+#[cfg(feature = "stats_overlay")]
+pub use overlay::*;
+
+#[cfg(feature = "stats_overlay")]
+mod overlay {
+    use std::fmt::Write;
+
+    use bevy::{prelude::*, ui::UiSystem};
+
+    use super::{TilemapRenderStats, TilemapRenderStatsPlugin};
+
+    /// Controls the on-screen overlay of the [`TilemapStatsOverlayPlugin`].
+    #[derive(Resource, Clone, Debug)]
+    pub struct TilemapStatsOverlay {
+        /// Whether the overlay is shown. Flip it, e.g. from a key binding, to toggle the overlay.
+        pub enabled: bool,
+        pub font_size: f32,
+        pub color: Color,
+    }
+
+    impl Default for TilemapStatsOverlay {
+        /// By default, the overlay is shown in small yellow text.
+        fn default() -> Self {
+            Self {
+                enabled: true,
+                font_size: 14.0,
+                color: Color::srgb(1.0, 1.0, 0.0),
+            }
+        }
+    }
+
+    /// Shows the [`TilemapRenderStats`] of every tilemap as text in the top left corner of the
+    /// first active 2D camera, to make performance tuning visible during development.
+    ///
+    /// The text is a UI node targeting that camera only, so it neither moves with the camera nor
+    /// shows up in the views of other cameras. Toggle it with the [`TilemapStatsOverlay`]
+    /// resource.
+    pub struct TilemapStatsOverlayPlugin;
+
+    impl Plugin for TilemapStatsOverlayPlugin {
+        fn build(&self, app: &mut App) {
+            if !app.is_plugin_added::<TilemapRenderStatsPlugin>() {
+                app.add_plugins(TilemapRenderStatsPlugin);
+            }
+            app.init_resource::<TilemapStatsOverlay>()
+                .add_systems(PostUpdate, update_stats_overlay.before(UiSystem::Prepare));
+        }
+    }
+
+    /// Marks the text entity of the overlay.
+    #[derive(Component)]
+    struct TilemapStatsOverlayText;
+
+    /// The distance in pixels between the overlay and the corner of the screen.
+    const MARGIN: f32 = 8.0;
+
+    fn update_stats_overlay(
+        mut commands: Commands,
+        overlay: Res<TilemapStatsOverlay>,
+        stats: Res<TilemapRenderStats>,
+        cameras: Query<(Entity, &Camera), With<OrthographicProjection>>,
+        mut texts: Query<
+            (
+                Entity,
+                &mut Text,
+                &mut TargetCamera,
+                &mut TextFont,
+                &mut TextColor,
+            ),
+            With<TilemapStatsOverlayText>,
+        >,
+    ) {
+        let camera = cameras
+            .iter()
+            .filter(|(_, camera)| camera.is_active)
+            .min_by_key(|(_, camera)| camera.order);
+        let (Some((camera, _)), true) = (camera, overlay.enabled) else {
+            for (text, ..) in texts.iter() {
+                commands.entity(text).despawn();
+            }
+            return;
+        };
+
+        let content = stats_text(&stats);
+
+        let Ok((_, mut text, mut target, mut font, mut color)) = texts.get_single_mut() else {
+            commands.spawn((
+                TilemapStatsOverlayText,
+                Text::new(content),
+                TextFont {
+                    font_size: overlay.font_size,
+                    ..Default::default()
+                },
+                TextColor(overlay.color),
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(MARGIN),
+                    top: Val::Px(MARGIN),
+                    ..Default::default()
+                },
+                TargetCamera(camera),
+            ));
+            return;
+        };
+        if text.0 != content {
+            text.0 = content;
+        }
+        if target.0 != camera {
+            target.0 = camera;
+        }
+        if font.font_size != overlay.font_size {
+            font.font_size = overlay.font_size;
+        }
+        if color.0 != overlay.color {
+            color.0 = overlay.color;
+        }
+    }
+
+    fn stats_text(stats: &TilemapRenderStats) -> String {
+        let all = stats.all();
+        let total = stats.total();
+        let mut text = format!(
+            "tilemaps: {}  chunks: {} ({} visible)  draw calls: {}  remeshed: {}",
+            all.len(),
+            total.chunks,
+            total.visible_chunks,
+            total.draw_calls,
+            total.remeshed_chunks,
+        );
+        for (tilemap, stats) in all {
+            let _ = write!(
+                text,
+                "\n{tilemap}: {} chunks ({} visible), {} draw calls, {} remeshed",
+                stats.chunks, stats.visible_chunks, stats.draw_calls, stats.remeshed_chunks,
+            );
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::chunk::tests::add_tile;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn stats_are_reported_for_main_world_tilemaps() {
+        let main_map = Entity::from_raw(0);
+        let stats = TilemapRenderStats::default();
+        let mut render_world = World::new();
+        render_world.spawn_empty();
+        let render_map = render_world.spawn(MainEntity::from(main_map)).id();
+        assert_ne!(render_map, main_map);
+
+        let mut chunk_storage = RenderChunk2dStorage::default();
+        add_tile(&mut chunk_storage, Entity::from_raw(7), render_map);
+        render_world.insert_resource(chunk_storage);
+        render_world.insert_resource(RemeshedChunks([(render_map, 1)].into_iter().collect()));
+        render_world.insert_resource(stats.clone());
+        render_world.init_resource::<ViewSortedRenderPhases<Transparent2d>>();
+        render_world.init_resource::<ViewBinnedRenderPhases<Opaque2d>>();
+        render_world.run_system_once(update_render_stats).unwrap();
+
+        assert_eq!(stats.get(render_map), None);
+        let frame = stats.get(main_map).unwrap();
+        assert_eq!(frame.chunks, 1);
+        assert_eq!(frame.remeshed_chunks, 1);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::render::extract::ExtractedTilemapTexture;
 use crate::{TilemapSpacing, TilemapTexture, TilemapTextureSize, TilemapTileSize};
 use bevy::asset::Assets;
+use bevy::log::{error, warn};
 use bevy::prelude::{ResMut, Resource};
 use bevy::{
     prelude::{Image, Res, UVec2},
@@ -16,9 +17,49 @@ use bevy::{
     },
     utils::{HashMap, HashSet},
 };
+use std::sync::{Arc, RwLock};
 
+use super::capabilities::TilemapRenderCapabilities;
 use super::ModifiedImageIds;
 
+/// How a tilemap texture is uploaded to the GPU, see [`TilemapTextureStrategies`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TilemapTextureStrategy {
+    /// Every tile is copied into a layer of a texture array.
+    Array,
+    /// The texture has more tiles than the device supports texture array layers, so the image is
+    /// sampled as an atlas instead, like with the `atlas` feature.
+    Atlas,
+    /// The texture has more tiles than the device supports texture array layers, and can't be
+    /// sampled as an atlas because it is made of separate images. Tilemaps using it aren't drawn.
+    Unsupported,
+}
+
+/// Reports how each tilemap texture was uploaded to the GPU, once it was.
+///
+/// Textures with more tiles than the device's `max_texture_array_layers`, often 256 on WebGL2,
+/// don't fit in a texture array. Single image textures fall back to being sampled as an atlas,
+/// which may bleed between tiles at some scales, and other textures aren't drawn. Both cases are
+/// also logged.
+#[derive(Resource, Clone, Default, Debug)]
+pub struct TilemapTextureStrategies {
+    // Arc and RwLock let the render world write the strategies back to the main world.
+    strategies: Arc<RwLock<HashMap<TilemapTexture, TilemapTextureStrategy>>>,
+}
+
+impl TilemapTextureStrategies {
+    /// Returns how `texture` was uploaded, if it was yet.
+    pub fn get(&self, texture: &TilemapTexture) -> Option<TilemapTextureStrategy> {
+        self.strategies.read().ok()?.get(texture).copied()
+    }
+
+    fn set(&self, texture: &TilemapTexture, strategy: TilemapTextureStrategy) {
+        if let Ok(mut strategies) = self.strategies.write() {
+            strategies.insert(texture.clone_weak(), strategy);
+        }
+    }
+}
+
 #[derive(Resource, Default, Debug, Clone)]
 pub struct TextureArrayCache {
     textures: HashMap<TilemapTexture, GpuImage>,
@@ -36,6 +77,8 @@ pub struct TextureArrayCache {
     prepare_queue: HashSet<TilemapTexture>,
     queue_queue: HashSet<TilemapTexture>,
     bad_flag_queue: HashSet<TilemapTexture>,
+    /// The textures sampled as atlases, because they have too many tiles for a texture array.
+    atlas_textures: HashSet<TilemapTexture>,
 }
 
 impl TextureArrayCache {
@@ -135,12 +178,20 @@ impl TextureArrayCache {
         self.textures.contains_key(texture)
     }
 
+    /// Returns true if `texture` is sampled as an atlas instead of a texture array.
+    pub fn is_atlas(&self, texture: &TilemapTexture) -> bool {
+        self.atlas_textures.contains(texture)
+    }
+
     /// Prepares each texture array texture
     pub fn prepare(
         &mut self,
         render_device: &RenderDevice,
         render_images: &Res<RenderAssets<GpuImage>>,
+        capabilities: &TilemapRenderCapabilities,
+        strategies: &TilemapTextureStrategies,
     ) {
+        let max_layers = capabilities.max_texture_array_layers;
         let prepare_queue = self.prepare_queue.drain().collect::<Vec<_>>();
         for texture in prepare_queue.iter() {
             // Fixes issue where default handle causes a crash. There should be a better
@@ -163,6 +214,36 @@ impl TextureArrayCache {
                         *count
                     };
 
+                    if count > max_layers {
+                        match texture {
+                            TilemapTexture::Single(handle) => {
+                                let Some(gpu_image) = render_images.get(handle) else {
+                                    self.prepare_queue.insert(texture.clone_weak());
+                                    continue;
+                                };
+                                warn!(
+                                    "Tilemap texture {handle:?} has {count} tiles, more than the \
+                                    {max_layers} texture array layers this device supports. \
+                                    Sampling it as an atlas instead."
+                                );
+                                self.textures
+                                    .insert(texture.clone_weak(), gpu_image.clone());
+                                self.atlas_textures.insert(texture.clone_weak());
+                                strategies.set(texture, TilemapTextureStrategy::Atlas);
+                            }
+                            _ => {
+                                error!(
+                                    "Tilemap texture has {count} images, more than the \
+                                    {max_layers} texture array layers this device supports. \
+                                    Tilemaps using it won't be drawn."
+                                );
+                                strategies.set(texture, TilemapTextureStrategy::Unsupported);
+                            }
+                        }
+                        continue;
+                    }
+                    strategies.set(texture, TilemapTextureStrategy::Array);
+
                     let gpu_texture = render_device.create_texture(&TextureDescriptor {
                         label: Some("texture_array"),
                         size: Extent3d {
@@ -225,6 +306,7 @@ impl TextureArrayCache {
                     if let Some(gpu_image) = render_images.get(handle) {
                         self.textures
                             .insert(texture.clone_weak(), gpu_image.clone());
+                        strategies.set(texture, TilemapTextureStrategy::Array);
                     } else {
                         self.prepare_queue.insert(texture.clone_weak());
                     }
@@ -368,4 +450,5 @@ pub fn remove_modified_textures(
     texture_cache.prepare_queue.retain(texture_is_unmodified);
     texture_cache.queue_queue.retain(texture_is_unmodified);
     texture_cache.bad_flag_queue.retain(texture_is_unmodified);
+    texture_cache.atlas_textures.retain(texture_is_unmodified);
 }
@@ -0,0 +1,190 @@
+//! No renderer or `App` is involved, which makes these a quick way to try out the helper
+//! functions of this crate without setting up a window.
+//!
+//! ```
+//! use bevy::prelude::World;
+//! use bevy_ecs_tilemap::prelude::*;
+//! use bevy_ecs_tilemap::test_utils::{spawn_test_map, tile_at};
+//!
+//! let mut world = World::new();
+//! let map = spawn_test_map(&mut world, TilemapSize { x: 4, y: 4 }, TilemapType::Square);
+//!
+//! let tile = tile_at(&world, map, TilePos { x: 1, y: 2 }).unwrap();
+//! assert_eq!(world.get::<TilePos>(tile), Some(&TilePos { x: 1, y: 2 }));
+//! ```
+
+use std::time::Duration;
+
+use bevy::app::{App, Plugin, PluginGroup, PluginGroupBuilder};
+use bevy::core::{FrameCountPlugin, TaskPoolPlugin, TypeRegistrationPlugin};
+use bevy::hierarchy::{BuildChildren, HierarchyPlugin};
+use bevy::prelude::{ChildBuild, Entity, Transform, World};
+use bevy::time::{Time, TimePlugin, TimeUpdateStrategy, Virtual};
+use bevy::transform::TransformPlugin;
+
+use crate::map::{TilemapGridSize, TilemapId, TilemapSize, TilemapTileSize, TilemapType};
+use crate::tiles::{TileBundle, TilePos, TileStorage, TileTextureIndex};
+use crate::TilemapCorePlugin;
+
+/// The tile size (and grid size) used by the maps spawned in this module.
+pub const TEST_TILE_SIZE: TilemapTileSize = TilemapTileSize { x: 16.0, y: 16.0 };
+
+/// Spawns a tilemap entity with an empty [`TileStorage`].
+///
+/// The map gets a [`TilemapSize`], [`TilemapType`], [`TilemapGridSize`], [`TilemapTileSize`],
+/// [`TileStorage`] and [`Transform`], which is everything the non-rendering helpers rely on.
+pub fn spawn_empty_test_map(world: &mut World, size: TilemapSize, map_type: TilemapType) -> Entity {
+    world
+        .spawn((
+            size,
+            map_type,
+            TilemapGridSize::from(TEST_TILE_SIZE),
+            TEST_TILE_SIZE,
+            TileStorage::empty(size),
+            Transform::default(),
+        ))
+        .id()
+}
+
+/// Spawns a tilemap entity and fills every position with a tile using texture index `0`.
+///
+/// Tiles are spawned as children of the map, the same way [`fill_tilemap`](crate::helpers::filling::fill_tilemap)
+/// does it.
+pub fn spawn_test_map(world: &mut World, size: TilemapSize, map_type: TilemapType) -> Entity {
+    let tilemap_entity = spawn_empty_test_map(world, size, map_type);
+    let tilemap_id = TilemapId(tilemap_entity);
+
+    let mut tile_storage = TileStorage::empty(size);
+    world.entity_mut(tilemap_entity).with_children(|parent| {
+        for x in 0..size.x {
+            for y in 0..size.y {
+                let tile_pos = TilePos { x, y };
+                let tile_entity = parent
+                    .spawn(TileBundle {
+                        position: tile_pos,
+                        tilemap_id,
+                        texture_index: TileTextureIndex(0),
+                        ..Default::default()
+                    })
+                    .id();
+                tile_storage.set(&tile_pos, tile_entity);
+            }
+        }
+    });
+    world.entity_mut(tilemap_entity).insert(tile_storage);
+
+    tilemap_entity
+}
+
+/// Looks up the tile entity at `tile_pos` in the [`TileStorage`] of `tilemap`.
+///
+/// Returns `None` if `tilemap` has no storage, `tile_pos` is out of bounds, or there is no tile
+/// at that position.
+pub fn tile_at(world: &World, tilemap: Entity, tile_pos: TilePos) -> Option<Entity> {
+    world
+        .get::<TileStorage>(tilemap)
+        .and_then(|tile_storage| tile_storage.checked_get(&tile_pos))
+}
+
+/// The time every update of an app with [`MinimalTilemapPlugins`] advances by: one frame at 60
+/// frames per second.
+pub const TEST_FRAME_TIME: Duration = Duration::from_nanos(16_666_667);
+
+/// The plugins needed to run the non-rendering systems of this crate headlessly, e.g. to test
+/// gameplay systems that manipulate tilemaps in CI.
+///
+/// Adds the tasks, time, transform and hierarchy plugins, the [`TilemapCorePlugin`], and a
+/// [`ManualFrameTimePlugin`] so that every update advances time by the same amount and runs
+/// are deterministic. Use [`StepApp`] to advance the app.
+///
+/// ```
+/// use bevy::prelude::App;
+/// use bevy_ecs_tilemap::prelude::*;
+/// use bevy_ecs_tilemap::test_utils::{spawn_test_map, tile_at, MinimalTilemapPlugins, StepApp};
+/// use std::time::Duration;
+///
+/// let mut app = App::new();
+/// app.add_plugins(MinimalTilemapPlugins);
+/// let map = spawn_test_map(app.world_mut(), TilemapSize { x: 4, y: 4 }, TilemapType::Square);
+/// let tile = tile_at(app.world(), map, TilePos::new(1, 1)).unwrap();
+///
+/// *app.world_mut().get_mut::<TilePos>(tile).unwrap() = TilePos::new(2, 1);
+/// app.step_frames(1);
+/// assert_eq!(app.world().get::<TilePosOld>(tile).unwrap().0, TilePos::new(2, 1));
+///
+/// app.step_time(Duration::from_secs(3));
+/// let time = app.world().resource::<bevy::time::Time>();
+/// assert!(time.elapsed() >= Duration::from_secs(3));
+/// ```
+pub struct MinimalTilemapPlugins;
+
+impl PluginGroup for MinimalTilemapPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(TaskPoolPlugin::default())
+            .add(TypeRegistrationPlugin)
+            .add(FrameCountPlugin)
+            .add(TimePlugin)
+            .add(ManualFrameTimePlugin::default())
+            .add(TransformPlugin)
+            .add(HierarchyPlugin)
+            .add(TilemapCorePlugin)
+    }
+}
+
+/// Makes every update advance time by `frame_time`, instead of the real time that passed.
+pub struct ManualFrameTimePlugin {
+    pub frame_time: Duration,
+}
+
+impl Default for ManualFrameTimePlugin {
+    /// By default, time advances by [`TEST_FRAME_TIME`].
+    fn default() -> Self {
+        Self {
+            frame_time: TEST_FRAME_TIME,
+        }
+    }
+}
+
+impl Plugin for ManualFrameTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(self.frame_time));
+    }
+}
+
+/// Helpers for stepping an [`App`] by hand in tests.
+pub trait StepApp {
+    /// Runs `frames` updates.
+    fn step_frames(&mut self, frames: u32) -> &mut Self;
+
+    /// Runs a single update that advances time by `duration`, e.g. to let a timer run out.
+    fn step_time(&mut self, duration: Duration) -> &mut Self;
+}
+
+impl StepApp for App {
+    fn step_frames(&mut self, frames: u32) -> &mut Self {
+        for _ in 0..frames {
+            self.update();
+        }
+        self
+    }
+
+    fn step_time(&mut self, duration: Duration) -> &mut Self {
+        let previous = self.world_mut().remove_resource::<TimeUpdateStrategy>();
+        self.insert_resource(TimeUpdateStrategy::ManualDuration(duration));
+        // Virtual time clamps long frames, which is exactly what this is for.
+        let mut virtual_time = self.world_mut().resource_mut::<Time<Virtual>>();
+        let max_delta = virtual_time.max_delta();
+        virtual_time.set_max_delta(max_delta.max(duration));
+        self.update();
+        self.world_mut()
+            .resource_mut::<Time<Virtual>>()
+            .set_max_delta(max_delta);
+        if let Some(previous) = previous {
+            self.insert_resource(previous);
+        } else {
+            self.world_mut().remove_resource::<TimeUpdateStrategy>();
+        }
+        self
+    }
+}
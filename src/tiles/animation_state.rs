@@ -0,0 +1,231 @@
+use bevy::prelude::*;
+
+use super::{AnimatedTile, AnimatedTileFrames};
+
+/// How an animation with an [`AnimationState`] plays back.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AnimationMode {
+    /// Plays the animation over and over.
+    #[default]
+    Loop,
+    /// Plays the animation forwards, then backwards, over and over.
+    PingPong,
+    /// Plays the animation once, then holds its last frame.
+    Once,
+}
+
+/// Controls the playback of the [`AnimatedTile`](super::AnimatedTile) or
+/// [`AnimatedTileFrames`](super::AnimatedTileFrames) of a tile, which keeps being animated on
+/// the GPU.
+///
+/// Animations start playing from their first frame when the component is added, and keep their
+/// current frame while paused. Tiles given a state in the same frame play in lockstep, as the
+/// [`TilemapAnimationPhase`](crate::map::TilemapAnimationPhase) doesn't apply to them. Without
+/// this component, animations loop forwards, from a time shared by all tiles.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::tiles::{AnimationMode, AnimationState};
+/// fn open_door(mut commands: Commands, door: Single<Entity, With<TilePos>>) {
+///     commands.entity(*door).insert((
+///         AnimatedTile { start: 8, end: 12, speed: 2.0 },
+///         AnimationState::new(AnimationMode::Once),
+///     ));
+/// }
+///
+/// fn toggle_pause(mut states: Query<&mut AnimationState>) {
+///     for mut state in states.iter_mut() {
+///         state.paused = !state.paused;
+///     }
+/// }
+/// ```
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq)]
+#[reflect(Component)]
+pub struct AnimationState {
+    pub mode: AnimationMode,
+    /// Plays the animation from its last frame to its first.
+    pub reverse: bool,
+    /// Holds the current frame of the animation.
+    pub paused: bool,
+    /// The [`Time::elapsed_secs_wrapped`] the animation started at, moved forward by the time
+    /// spent paused. `None` until the animation starts.
+    start_time: Option<f32>,
+    /// The seconds into the animation it was paused at, or its duration once an
+    /// [`AnimationMode::Once`] animation finished.
+    paused_at: Option<f32>,
+    /// Whether an [`AnimationMode::Once`] animation reached its last frame.
+    finished: bool,
+}
+
+impl AnimationState {
+    pub fn new(mode: AnimationMode) -> Self {
+        Self {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    /// Plays the animation backwards.
+    pub fn reversed(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+
+    /// Restarts the animation from its first frame. A paused animation stays paused.
+    pub fn restart(&mut self) {
+        self.start_time = None;
+        if self.finished {
+            self.finished = false;
+            self.paused_at = None;
+        }
+        if self.paused_at.is_some() {
+            self.paused_at = Some(0.0);
+        }
+    }
+
+    /// Returns true once an [`AnimationMode::Once`] animation reached its last frame.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns the seconds the animation has been playing for, not counting pauses.
+    ///
+    /// With [`AnimationMode::Once`], the animation is over once this reaches the duration of the
+    /// animation: `1 / speed` for an [`AnimatedTile`](super::AnimatedTile).
+    pub fn elapsed_secs<T: Default>(&self, time: &Time<T>) -> f32 {
+        match (self.paused_at, self.start_time) {
+            (Some(paused_at), _) => paused_at,
+            (None, Some(start_time)) => elapsed_since(time, start_time),
+            (None, None) => 0.0,
+        }
+    }
+
+    /// The time of the animation sent to the GPU: the seconds into the animation while paused,
+    /// or the time the animation started at otherwise.
+    pub(crate) fn gpu_time(&self) -> f32 {
+        self.paused_at.or(self.start_time).unwrap_or_default()
+    }
+
+    /// Returns true if the GPU should hold the frame at [`Self::gpu_time`].
+    pub(crate) fn is_holding(&self) -> bool {
+        self.paused_at.is_some()
+    }
+}
+
+/// The seconds from `start_time` to now, accounting for the wrapping of the elapsed time.
+fn elapsed_since<T: Default>(time: &Time<T>, start_time: f32) -> f32 {
+    let elapsed = time.elapsed_secs_wrapped() - start_time;
+    if elapsed < 0.0 {
+        elapsed + time.wrap_period().as_secs_f32()
+    } else {
+        elapsed
+    }
+}
+
+/// The seconds a single play through of the animation of a tile takes.
+fn animation_duration(range: Option<&AnimatedTile>, frames: Option<&AnimatedTileFrames>) -> f32 {
+    match (frames, range) {
+        (Some(frames), _) => frames.duration(),
+        (None, Some(range)) if range.speed > 0.0 => 1.0 / range.speed,
+        _ => f32::INFINITY,
+    }
+}
+
+/// Starts, pauses and resumes animations whose [`AnimationState`] changed, and holds the last
+/// frame of [`AnimationMode::Once`] animations that finished, as the elapsed time the GPU plays
+/// them from wraps around.
+pub(crate) fn update_animation_states(
+    time: Res<Time>,
+    mut states: Query<(
+        &mut AnimationState,
+        Option<&AnimatedTile>,
+        Option<&AnimatedTileFrames>,
+    )>,
+) {
+    let now = time.elapsed_secs_wrapped();
+    for (mut state, range, frames) in states.iter_mut() {
+        if state.is_changed() {
+            // The component is already marked as changed, so the render world picks this up.
+            let state = state.bypass_change_detection();
+            if state.finished && state.mode != AnimationMode::Once {
+                state.finished = false;
+                state.paused_at = None;
+            }
+            let start_time = *state.start_time.get_or_insert(now);
+            match (state.paused, state.paused_at) {
+                (true, None) => state.paused_at = Some(elapsed_since(&time, start_time)),
+                (false, Some(paused_at)) if !state.finished => {
+                    state.start_time = Some(now - paused_at);
+                    state.paused_at = None;
+                }
+                _ => {}
+            }
+        }
+
+        if state.mode == AnimationMode::Once && state.paused_at.is_none() {
+            let duration = animation_duration(range, frames);
+            if state.elapsed_secs(&time) >= duration {
+                state.paused_at = Some(duration);
+                state.finished = true;
+            }
+        }
+    }
+}
+
+/// Marks the [`TileTextureIndex`](super::TileTextureIndex) of tiles that lost their
+/// [`AnimationState`] as changed, so that the render world goes back to looping.
+pub(crate) fn refresh_removed_animation_states(
+    mut removed: RemovedComponents<AnimationState>,
+    mut texture_indices: Query<&mut super::TileTextureIndex>,
+) {
+    for tile_entity in removed.read() {
+        if let Ok(mut texture_index) = texture_indices.get_mut(tile_entity) {
+            texture_index.set_changed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn finished_once_animations_hold_their_last_frame() {
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        let tile = world
+            .spawn((
+                AnimatedTile {
+                    start: 0,
+                    end: 4,
+                    speed: 2.0,
+                },
+                AnimationState::new(AnimationMode::Once),
+            ))
+            .id();
+
+        world.run_system_once(update_animation_states).unwrap();
+        let state = *world.get::<AnimationState>(tile).unwrap();
+        assert!(!state.is_finished());
+        assert!(!state.is_holding());
+
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        world.run_system_once(update_animation_states).unwrap();
+        let mut state = *world.get::<AnimationState>(tile).unwrap();
+        assert!(state.is_finished());
+        assert!(state.is_holding());
+        assert_eq!(state.gpu_time(), 0.5);
+
+        state.restart();
+        assert!(!state.is_finished());
+        assert!(!state.is_holding());
+    }
+}
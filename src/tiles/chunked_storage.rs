@@ -0,0 +1,446 @@
+use bevy::{
+    ecs::entity::{EntityMapper, MapEntities},
+    math::{IVec2, UVec2, Vec2},
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::map::{
+    TilemapGridSize, TilemapId, TilemapRenderSettings, TilemapSize, TilemapSpacing, TilemapTexture,
+    TilemapTileSize, TilemapType,
+};
+
+use super::{TileBundle, TilePos, TileStorage};
+
+#[cfg(feature = "render")]
+type ChunkTilemapBundle = crate::TilemapBundle;
+#[cfg(not(feature = "render"))]
+type ChunkTilemapBundle = crate::StandardTilemapBundle;
+
+/// Stores the tile entities of an unbounded tilemap, whose tiles can be set at any `i32`
+/// position and which grows in every direction as tiles are added.
+///
+/// Tiles are grouped into chunks of `chunk_size` tiles, and each chunk is rendered by a tilemap
+/// of its own, spawned as a child of the entity with the storage. Chunk tilemaps take their
+/// texture, sizes and map type from the components of that entity, see
+/// [`ChunkedTilemapBundle`], and are kept in sync by the [`TilemapPlugin`](crate::TilemapPlugin).
+/// Tiles are regular tile entities of the chunk tilemaps, so their [`TilePos`] is relative to
+/// their chunk.
+///
+/// For hexagonal and staggered isometric maps, the chunk size must be even along both axes.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// fn spawn_world(mut commands: Commands) {
+///     let mut storage = ChunkedTileStorage::new(UVec2::new(32, 32));
+///     let root = commands.spawn_empty().id();
+///     for x in -100..100 {
+///         storage.spawn_tile(root, IVec2::new(x, -x), &mut commands);
+///     }
+///     commands.entity(root).insert(ChunkedTilemapBundle {
+///         storage,
+///         tile_size: TilemapTileSize { x: 16.0, y: 16.0 },
+///         grid_size: TilemapGridSize { x: 16.0, y: 16.0 },
+///         ..Default::default()
+///     });
+/// }
+///
+/// let storage = ChunkedTileStorage::new(UVec2::new(32, 32));
+/// assert_eq!(storage.chunk_pos(IVec2::new(-1, 40)), IVec2::new(-1, 1));
+/// assert_eq!(storage.local_pos(IVec2::new(-1, 40)), TilePos::new(31, 8));
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct ChunkedTileStorage {
+    chunk_size: UVec2,
+    chunks: HashMap<IVec2, TileChunk>,
+    /// Chunks whose tiles changed since their tilemap was last synced.
+    dirty: HashSet<IVec2>,
+}
+
+/// The tiles of one chunk of a [`ChunkedTileStorage`], and the tilemap rendering them.
+#[derive(Debug, Clone)]
+struct TileChunk {
+    tilemap: Entity,
+    tiles: Vec<Option<Entity>>,
+}
+
+impl Default for ChunkedTileStorage {
+    /// By default, chunks are 64x64 tiles, the default render chunk size.
+    fn default() -> Self {
+        Self::new(crate::map::CHUNK_SIZE_2D)
+    }
+}
+
+impl MapEntities for ChunkedTileStorage {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        for chunk in self.chunks.values_mut() {
+            chunk.tilemap = entity_mapper.map_entity(chunk.tilemap);
+            for entity in chunk.tiles.iter_mut().flatten() {
+                *entity = entity_mapper.map_entity(*entity);
+            }
+        }
+    }
+}
+
+impl ChunkedTileStorage {
+    /// Creates an empty storage with chunks of `chunk_size` tiles.
+    pub fn new(chunk_size: UVec2) -> Self {
+        Self {
+            chunk_size: chunk_size.max(UVec2::ONE),
+            chunks: HashMap::default(),
+            dirty: HashSet::default(),
+        }
+    }
+
+    /// Returns the size of the chunks, in tiles.
+    pub fn chunk_size(&self) -> UVec2 {
+        self.chunk_size
+    }
+
+    /// Returns the position of the chunk containing the tile at `tile_pos`, in chunks.
+    pub fn chunk_pos(&self, tile_pos: IVec2) -> IVec2 {
+        tile_pos.div_euclid(self.chunk_size.as_ivec2())
+    }
+
+    /// Returns the position of the tile at `tile_pos` inside of its chunk tilemap.
+    pub fn local_pos(&self, tile_pos: IVec2) -> TilePos {
+        tile_pos
+            .rem_euclid(self.chunk_size.as_ivec2())
+            .as_uvec2()
+            .into()
+    }
+
+    /// Returns the position in the storage of the tile at `local_pos` of the chunk at
+    /// `chunk_pos`.
+    pub fn tile_pos(&self, chunk_pos: IVec2, local_pos: &TilePos) -> IVec2 {
+        chunk_pos * self.chunk_size.as_ivec2() + UVec2::from(local_pos).as_ivec2()
+    }
+
+    /// Returns the index of a tile in the array of its chunk.
+    fn index(&self, local_pos: &TilePos) -> usize {
+        (local_pos.y * self.chunk_size.x + local_pos.x) as usize
+    }
+
+    /// Gets the tile entity at `tile_pos`, if there is one.
+    pub fn get(&self, tile_pos: IVec2) -> Option<Entity> {
+        let chunk = self.chunks.get(&self.chunk_pos(tile_pos))?;
+        chunk.tiles[self.index(&self.local_pos(tile_pos))]
+    }
+
+    /// Returns the tilemap of the chunk at `chunk_pos`, if the chunk was created.
+    pub fn chunk_tilemap(&self, chunk_pos: IVec2) -> Option<Entity> {
+        self.chunks.get(&chunk_pos).map(|chunk| chunk.tilemap)
+    }
+
+    /// Returns an iterator over the positions of the created chunks and their tilemaps.
+    pub fn chunks(&self) -> impl Iterator<Item = (IVec2, Entity)> + '_ {
+        self.chunks
+            .iter()
+            .map(|(chunk_pos, chunk)| (*chunk_pos, chunk.tilemap))
+    }
+
+    /// Returns an iterator over the positions of all tiles and their entities, in no particular
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, Entity)> + '_ {
+        let width = self.chunk_size.x;
+        self.chunks.iter().flat_map(move |(chunk_pos, chunk)| {
+            chunk
+                .tiles
+                .iter()
+                .enumerate()
+                .filter_map(move |(index, tile)| {
+                    let local_pos = TilePos::new(index as u32 % width, index as u32 / width);
+                    Some((self.tile_pos(*chunk_pos, &local_pos), (*tile)?))
+                })
+        })
+    }
+
+    /// Returns the number of tiles in the storage.
+    pub fn len(&self) -> usize {
+        self.chunks
+            .values()
+            .map(|chunk| chunk.tiles.iter().flatten().count())
+            .sum()
+    }
+
+    /// Returns true if the storage has no tiles.
+    pub fn is_empty(&self) -> bool {
+        self.chunks
+            .values()
+            .all(|chunk| chunk.tiles.iter().all(Option::is_none))
+    }
+
+    /// Returns the [`TilemapId`] that tiles at `tile_pos` must have, creating the chunk if it
+    /// doesn't exist yet. The chunk tilemap is spawned as a child of `root`, the entity with the
+    /// storage.
+    pub fn tilemap_id(
+        &mut self,
+        root: Entity,
+        tile_pos: IVec2,
+        commands: &mut Commands,
+    ) -> TilemapId {
+        let chunk_pos = self.chunk_pos(tile_pos);
+        let tile_count = (self.chunk_size.x * self.chunk_size.y) as usize;
+        let chunk = self.chunks.entry(chunk_pos).or_insert_with(|| TileChunk {
+            tilemap: commands
+                .spawn(ChunkedTilemapChunk(chunk_pos))
+                .set_parent(root)
+                .id(),
+            tiles: vec![None; tile_count],
+        });
+        TilemapId(chunk.tilemap)
+    }
+
+    /// Sets the tile entity at `tile_pos`. The tile must be a child of the chunk tilemap
+    /// returned by [`tilemap_id`](Self::tilemap_id), at the [`local_pos`](Self::local_pos) of
+    /// `tile_pos`. Returns the entity that was replaced, if any.
+    ///
+    /// Use [`spawn_tile`](Self::spawn_tile) to do all of this at once.
+    pub fn set(
+        &mut self,
+        root: Entity,
+        tile_pos: IVec2,
+        tile_entity: Entity,
+        commands: &mut Commands,
+    ) -> Option<Entity> {
+        self.tilemap_id(root, tile_pos, commands);
+        let chunk_pos = self.chunk_pos(tile_pos);
+        let index = self.index(&self.local_pos(tile_pos));
+        self.dirty.insert(chunk_pos);
+        self.chunks.get_mut(&chunk_pos)?.tiles[index].replace(tile_entity)
+    }
+
+    /// Spawns a tile at `tile_pos`, despawning the tile that was there, and returns the new
+    /// tile's commands to add more components.
+    pub fn spawn_tile<'a>(
+        &mut self,
+        root: Entity,
+        tile_pos: IVec2,
+        commands: &'a mut Commands,
+    ) -> EntityCommands<'a> {
+        let tilemap_id = self.tilemap_id(root, tile_pos, commands);
+        let tile_entity = commands
+            .spawn(TileBundle {
+                position: self.local_pos(tile_pos),
+                tilemap_id,
+                ..Default::default()
+            })
+            .set_parent(tilemap_id.0)
+            .id();
+        if let Some(replaced) = self.set(root, tile_pos, tile_entity, commands) {
+            commands.entity(replaced).despawn_recursive();
+        }
+        commands.entity(tile_entity)
+    }
+
+    /// Removes the tile entity at `tile_pos` from the storage and returns it, without despawning
+    /// it.
+    pub fn remove(&mut self, tile_pos: IVec2) -> Option<Entity> {
+        let chunk_pos = self.chunk_pos(tile_pos);
+        let index = self.index(&self.local_pos(tile_pos));
+        let removed = self.chunks.get_mut(&chunk_pos)?.tiles[index].take();
+        if removed.is_some() {
+            self.dirty.insert(chunk_pos);
+        }
+        removed
+    }
+
+    /// Removes the tile at `tile_pos` from the storage and despawns it.
+    pub fn despawn_tile(&mut self, tile_pos: IVec2, commands: &mut Commands) {
+        if let Some(tile_entity) = self.remove(tile_pos) {
+            commands.entity(tile_entity).despawn_recursive();
+        }
+    }
+
+    /// Returns the position of a chunk tilemap relative to the entity with the storage.
+    pub fn chunk_offset(
+        &self,
+        chunk_pos: IVec2,
+        grid_size: &TilemapGridSize,
+        map_type: &TilemapType,
+    ) -> Vec2 {
+        // Tile positions can't be negative, so the offset is measured between two positions
+        // shifted by an even number of chunks, which every map type repeats the same way over.
+        let origin = chunk_pos * self.chunk_size.as_ivec2();
+        let period = 2 * self.chunk_size.as_ivec2();
+        let shift = (-origin).max(IVec2::ZERO).div_euclid(period) * period + period;
+        let center = |tile_pos: IVec2| TilePos::from(tile_pos.as_uvec2());
+        center(origin + shift).center_in_world(grid_size, map_type)
+            - center(shift).center_in_world(grid_size, map_type)
+    }
+}
+
+/// The position, in chunks, of a tilemap rendering a chunk of a [`ChunkedTileStorage`].
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+pub struct ChunkedTilemapChunk(pub IVec2);
+
+/// The components of an unbounded tilemap, whose tiles are stored in a [`ChunkedTileStorage`].
+///
+/// The tilemap settings are copied to the tilemaps of its chunks.
+#[derive(Bundle, Debug, Default, Clone)]
+pub struct ChunkedTilemapBundle {
+    pub storage: ChunkedTileStorage,
+    pub grid_size: TilemapGridSize,
+    pub map_type: TilemapType,
+    pub spacing: TilemapSpacing,
+    pub texture: TilemapTexture,
+    pub tile_size: TilemapTileSize,
+    pub render_settings: TilemapRenderSettings,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub inherited_visibility: InheritedVisibility,
+    pub view_visibility: ViewVisibility,
+}
+
+/// Creates the tilemaps of new chunks of [`ChunkedTileStorage`]s, updates the storages of chunks
+/// whose tiles changed, and copies changed settings to every chunk tilemap.
+#[allow(clippy::type_complexity)]
+pub(crate) fn sync_chunked_tilemaps(
+    mut commands: Commands,
+    mut roots: Query<(
+        &mut ChunkedTileStorage,
+        Ref<TilemapGridSize>,
+        Ref<TilemapType>,
+        Ref<TilemapSpacing>,
+        Ref<TilemapTexture>,
+        Ref<TilemapTileSize>,
+        Ref<TilemapRenderSettings>,
+    )>,
+    chunk_tilemaps: Query<Has<TilemapSize>, With<ChunkedTilemapChunk>>,
+) {
+    for (mut storage, grid_size, map_type, spacing, texture, tile_size, render_settings) in
+        roots.iter_mut()
+    {
+        let settings_changed = grid_size.is_changed()
+            || map_type.is_changed()
+            || spacing.is_changed()
+            || texture.is_changed()
+            || tile_size.is_changed()
+            || render_settings.is_changed();
+        if !settings_changed && storage.dirty.is_empty() {
+            continue;
+        }
+
+        let storage = &mut *storage;
+        let size = TilemapSize::from(storage.chunk_size);
+        for (chunk_pos, chunk) in storage.chunks.iter() {
+            let is_dirty = storage.dirty.contains(chunk_pos);
+            // Chunk tilemaps are spawned empty, and get their bundle here. Those spawned by
+            // commands that weren't applied yet aren't found, and are new as well.
+            let is_new = !chunk_tilemaps.get(chunk.tilemap).unwrap_or(false);
+            if !settings_changed && !is_dirty && !is_new {
+                continue;
+            }
+            let Some(mut chunk_commands) = commands.get_entity(chunk.tilemap) else {
+                continue;
+            };
+            let mut chunk_storage = TileStorage::empty(size);
+            for (index, tile_entity) in chunk.tiles.iter().enumerate() {
+                if let Some(tile_entity) = tile_entity {
+                    let index = index as u32;
+                    let local_pos = TilePos::new(index % size.x, index / size.x);
+                    chunk_storage.set(&local_pos, *tile_entity);
+                }
+            }
+            if settings_changed || is_new {
+                let offset = storage.chunk_offset(*chunk_pos, &grid_size, &map_type);
+                chunk_commands.insert(ChunkTilemapBundle {
+                    grid_size: *grid_size,
+                    map_type: *map_type,
+                    size,
+                    spacing: *spacing,
+                    storage: chunk_storage,
+                    texture: texture.clone(),
+                    tile_size: *tile_size,
+                    transform: Transform::from_translation(offset.extend(0.0)),
+                    render_settings: *render_settings,
+                    ..Default::default()
+                });
+            } else {
+                chunk_commands.insert(chunk_storage);
+            }
+        }
+        storage.dirty.clear();
+        // Chunk tilemaps that were despawned with their tiles are forgotten.
+        storage
+            .chunks
+            .retain(|_, chunk| commands.get_entity(chunk.tilemap).is_some());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::schedule::ScheduleBuildSettings;
+
+    use super::*;
+    use crate::test_utils::{MinimalTilemapPlugins, StepApp};
+
+    fn spawn_root(app: &mut App) -> Entity {
+        app.world_mut()
+            .spawn(ChunkedTilemapBundle {
+                storage: ChunkedTileStorage::new(UVec2::new(4, 4)),
+                ..Default::default()
+            })
+            .id()
+    }
+
+    fn spawn_tiles(
+        mut commands: Commands,
+        mut roots: Query<(Entity, &mut ChunkedTileStorage)>,
+        mut spawned: Local<bool>,
+    ) {
+        if std::mem::replace(&mut *spawned, true) {
+            return;
+        }
+        for (root, mut storage) in roots.iter_mut() {
+            storage.spawn_tile(root, IVec2::new(-1, 5), &mut commands);
+        }
+    }
+
+    fn chunk_storage(app: &App, root: Entity, tile_pos: IVec2) -> Option<&TileStorage> {
+        let storage = app.world().get::<ChunkedTileStorage>(root)?;
+        let chunk_tilemap = storage.chunk_tilemap(storage.chunk_pos(tile_pos))?;
+        app.world().get::<TileStorage>(chunk_tilemap)
+    }
+
+    #[test]
+    fn chunk_tilemaps_are_synced() {
+        let mut app = App::new();
+        app.add_plugins(MinimalTilemapPlugins);
+        let root = spawn_root(&mut app);
+        app.step_frames(1);
+        app.add_systems(Update, spawn_tiles).step_frames(1);
+
+        let storage = app.world().get::<ChunkedTileStorage>(root).unwrap();
+        let chunk_tilemap = storage.chunk_tilemap(IVec2::new(-1, 1)).unwrap();
+        let tile = storage.get(IVec2::new(-1, 5)).unwrap();
+        assert!(app.world().get::<TilemapType>(chunk_tilemap).is_some());
+        let chunk_storage = chunk_storage(&app, root, IVec2::new(-1, 5)).unwrap();
+        assert_eq!(chunk_storage.get(&TilePos::new(3, 1)), Some(tile));
+    }
+
+    #[test]
+    fn chunks_created_by_pending_commands_are_new() {
+        let mut app = App::new();
+        app.add_plugins(MinimalTilemapPlugins)
+            .edit_schedule(PostUpdate, |schedule| {
+                schedule.set_build_settings(ScheduleBuildSettings {
+                    auto_insert_apply_deferred: false,
+                    ..Default::default()
+                });
+            });
+        let root = spawn_root(&mut app);
+        // The settings of the root are only changed in its first frame.
+        app.step_frames(1);
+        app.add_systems(PostUpdate, spawn_tiles.before(sync_chunked_tilemaps))
+            .step_frames(2);
+
+        let storage = app.world().get::<ChunkedTileStorage>(root).unwrap();
+        let chunk_tilemap = storage.chunk_tilemap(IVec2::new(-1, 1)).unwrap();
+        assert!(app.world().get::<TilemapType>(chunk_tilemap).is_some());
+        assert!(chunk_storage(&app, root, IVec2::new(-1, 5)).is_some());
+    }
+}
@@ -0,0 +1,95 @@
+use bevy::prelude::Component;
+
+use super::TilePos;
+use crate::map::TilemapSize;
+
+/// A value of type `T` for every tile position of a tilemap, stored densely next to its
+/// [`TileStorage`](super::TileStorage) instead of on the tile entities.
+///
+/// Meant for data that is computed for the whole map at once and read a lot, like distance
+/// fields, region ids or costs, where a component per tile would be slow to update and query.
+/// Positions without a tile entity have a value too.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct TileDataLayer<T: Send + Sync + 'static> {
+    data: Vec<T>,
+    size: TilemapSize,
+}
+
+impl<T: Clone + Send + Sync + 'static> TileDataLayer<T> {
+    /// Creates a layer of `size` with every value set to `value`.
+    pub fn new(size: TilemapSize, value: T) -> Self {
+        Self {
+            data: vec![value; size.count()],
+            size,
+        }
+    }
+
+    /// Sets every value to `value`.
+    pub fn fill(&mut self, value: T) {
+        self.data.fill(value);
+    }
+
+    /// Resizes the layer, keeping the values of the positions within both sizes and setting new
+    /// positions to `value`.
+    pub fn resize(&mut self, size: TilemapSize, value: T) {
+        let mut resized = Self::new(size, value);
+        for y in 0..self.size.y.min(size.y) {
+            for x in 0..self.size.x.min(size.x) {
+                let tile_pos = TilePos::new(x, y);
+                resized.data[tile_pos.to_index(&size)] =
+                    self.data[tile_pos.to_index(&self.size)].clone();
+            }
+        }
+        *self = resized;
+    }
+}
+
+impl<T: Send + Sync + 'static> TileDataLayer<T> {
+    /// Returns the size of the tilemap the layer covers.
+    pub fn size(&self) -> TilemapSize {
+        self.size
+    }
+
+    /// Returns the value at `tile_pos`, or `None` if it lies outside of the layer.
+    pub fn get(&self, tile_pos: &TilePos) -> Option<&T> {
+        if !tile_pos.within_map_bounds(&self.size) {
+            return None;
+        }
+        self.data.get(tile_pos.to_index(&self.size))
+    }
+
+    /// Returns the value at `tile_pos` mutably, or `None` if it lies outside of the layer.
+    pub fn get_mut(&mut self, tile_pos: &TilePos) -> Option<&mut T> {
+        if !tile_pos.within_map_bounds(&self.size) {
+            return None;
+        }
+        self.data.get_mut(tile_pos.to_index(&self.size))
+    }
+
+    /// Sets the value at `tile_pos`, returning the previous one. Does nothing and returns `None`
+    /// if it lies outside of the layer.
+    pub fn set(&mut self, tile_pos: &TilePos, value: T) -> Option<T> {
+        self.get_mut(tile_pos)
+            .map(|current| std::mem::replace(current, value))
+    }
+
+    /// Returns an iterator over every position of the layer and its value, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = (TilePos, &T)> {
+        let width = self.size.x.max(1);
+        self.data.iter().enumerate().map(move |(index, value)| {
+            let index = index as u32;
+            (TilePos::new(index % width, index / width), value)
+        })
+    }
+
+    /// Returns the values in row order, i.e. the value of `tile_pos` is at
+    /// [`TilePos::to_index`].
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    /// Returns the values in row order mutably.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+}
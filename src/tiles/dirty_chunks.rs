@@ -0,0 +1,186 @@
+use bevy::{
+    prelude::*,
+    render::extract_resource::ExtractResource,
+    utils::{HashMap, HashSet},
+};
+
+use super::{TileColor, TileFlip, TilePos, TilePosOld, TileStorage, TileTextureIndex, TileVisible};
+use crate::helpers::transform::{chunk_aabb, chunk_index_to_world_space, map_tile_to_chunk};
+use crate::map::{
+    is_frozen, TilemapGridSize, TilemapId, TilemapRenderSettings, TilemapStatic, TilemapTileSize,
+    TilemapType,
+};
+
+/// A render chunk whose tiles changed during the frame, see [`DirtyTileChunks`].
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub struct DirtyChunk {
+    pub tilemap: Entity,
+    /// The index of the chunk, in chunks of the `render_chunk_size` of the tilemap.
+    pub index: UVec2,
+    /// The area covered by the chunk, in world space.
+    pub rect: Rect,
+}
+
+/// The render chunks whose tiles were placed, changed, moved or removed during the current
+/// frame, e.g. so that a lighting bake or a fluid simulation only updates the matching regions
+/// of its own render targets.
+///
+/// Insert this resource to opt into tracking. It is filled in [`PostUpdate`], after transforms
+/// are propagated, and extracted to the render world along with the tiles, so render systems
+/// see the chunks of the frame they render.
+///
+/// Tiles count as changed when their [`TilePos`], [`TileTextureIndex`], [`TileColor`],
+/// [`TileVisible`] or [`TileFlip`] change.
+#[derive(Resource, ExtractResource, Clone, Debug, Default)]
+pub struct DirtyTileChunks {
+    chunks: Vec<DirtyChunk>,
+    /// Tiles removed since the chunks were last recorded.
+    removed: Vec<(Entity, TilePos)>,
+}
+
+impl DirtyTileChunks {
+    /// Returns the dirty chunks of every tilemap.
+    pub fn iter(&self) -> impl Iterator<Item = &DirtyChunk> {
+        self.chunks.iter()
+    }
+
+    /// Returns the dirty chunks of `tilemap`.
+    pub fn get(&self, tilemap: Entity) -> impl Iterator<Item = &DirtyChunk> {
+        self.chunks
+            .iter()
+            .filter(move |chunk| chunk.tilemap == tilemap)
+    }
+
+    /// Returns true if no chunk changed during the frame.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+}
+
+/// Records the chunks of the tiles changed during the frame, replacing those of the last frame.
+#[allow(clippy::type_complexity)]
+pub(crate) fn record_dirty_chunks(
+    mut dirty_chunks: ResMut<DirtyTileChunks>,
+    changed_tiles: Query<
+        (&TilePos, Option<&TilePosOld>, &TilemapId),
+        Or<(
+            Changed<TilePos>,
+            Changed<TileTextureIndex>,
+            Changed<TileColor>,
+            Changed<TileVisible>,
+            Changed<TileFlip>,
+        )>,
+    >,
+    tilemaps: Query<(
+        &GlobalTransform,
+        &TileStorage,
+        &TilemapGridSize,
+        &TilemapTileSize,
+        &TilemapType,
+        &TilemapRenderSettings,
+    )>,
+    static_tilemaps: Query<Ref<TilemapStatic>>,
+) {
+    let dirty_chunks = dirty_chunks.as_mut();
+    let mut tiles: HashMap<Entity, Vec<TilePos>> = HashMap::default();
+    for (tilemap, tile_pos) in dirty_chunks.removed.drain(..) {
+        tiles.entry(tilemap).or_default().push(tile_pos);
+    }
+    for (tile_pos, tile_pos_old, tilemap_id) in changed_tiles.iter() {
+        let positions = tiles.entry(tilemap_id.0).or_default();
+        positions.push(*tile_pos);
+        // The old position is where a moved tile has to be erased.
+        if let Some(tile_pos_old) = tile_pos_old.filter(|old| old.0 != *tile_pos) {
+            positions.push(tile_pos_old.0);
+        }
+    }
+
+    dirty_chunks.chunks.clear();
+    for (tilemap, positions) in tiles {
+        if is_frozen(&static_tilemaps, tilemap) {
+            continue;
+        }
+        let Ok((transform, storage, grid_size, tile_size, map_type, render_settings)) =
+            tilemaps.get(tilemap)
+        else {
+            continue;
+        };
+        let chunk_size = render_settings.render_chunk_size.max(UVec2::ONE);
+        let aabb = chunk_aabb(chunk_size, grid_size, tile_size, map_type);
+        let indices: HashSet<UVec2> = positions
+            .iter()
+            .filter(|tile_pos| tile_pos.within_map_bounds(&storage.size))
+            .map(|tile_pos| {
+                let grid_pos = storage.axes.to_grid_pos(tile_pos, &storage.size);
+                map_tile_to_chunk(&grid_pos, chunk_size)
+            })
+            .collect();
+        let mut indices: Vec<UVec2> = indices.into_iter().collect();
+        indices.sort_by_key(|index| (index.y, index.x));
+
+        for index in indices {
+            let origin = chunk_index_to_world_space(index, chunk_size, grid_size, map_type);
+            let min = origin + aabb.min().truncate();
+            let max = origin + aabb.max().truncate();
+            // The tilemap may be rotated, so all four corners are transformed.
+            let mut rect = Rect::from_center_size(
+                transform.transform_point(min.extend(0.0)).truncate(),
+                Vec2::ZERO,
+            );
+            for corner in [Vec2::new(max.x, min.y), Vec2::new(min.x, max.y), max] {
+                let corner = transform.transform_point(corner.extend(0.0)).truncate();
+                rect = rect.union_point(corner);
+            }
+            dirty_chunks.chunks.push(DirtyChunk {
+                tilemap,
+                index,
+                rect,
+            });
+        }
+    }
+}
+
+/// Remembers where removed tiles were. Runs as an observer, as the tile's position is gone
+/// afterwards.
+pub(crate) fn record_removed_dirty_tile(
+    trigger: Trigger<OnRemove, TilePos>,
+    dirty_chunks: Option<ResMut<DirtyTileChunks>>,
+    tiles: Query<(&TilePos, &TilemapId)>,
+) {
+    let Some(mut dirty_chunks) = dirty_chunks else {
+        return;
+    };
+    if let Ok((tile_pos, tilemap_id)) = tiles.get(trigger.entity()) {
+        dirty_chunks.removed.push((tilemap_id.0, *tile_pos));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TilemapStatic;
+    use crate::test_utils::{spawn_test_map, tile_at, MinimalTilemapPlugins, StepApp};
+
+    #[test]
+    fn static_tilemaps_only_record_the_frame_they_froze_in() {
+        let mut app = App::new();
+        app.add_plugins(MinimalTilemapPlugins)
+            .init_resource::<DirtyTileChunks>();
+        let size = crate::map::TilemapSize { x: 4, y: 4 };
+        let map = spawn_test_map(app.world_mut(), size, TilemapType::Square);
+        app.world_mut()
+            .entity_mut(map)
+            .insert((TilemapRenderSettings::default(), TilemapStatic));
+
+        app.step_frames(1);
+        assert_eq!(
+            app.world().resource::<DirtyTileChunks>().get(map).count(),
+            1
+        );
+
+        let tile = tile_at(app.world(), map, TilePos::new(1, 1)).unwrap();
+        app.world_mut().get_mut::<TileTextureIndex>(tile).unwrap().0 = 3;
+        app.step_frames(1);
+        assert!(app.world().resource::<DirtyTileChunks>().is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+
+use super::TilePos;
+use crate::map::TilemapId;
+
+/// Triggered on a tilemap when one of its tiles is set: spawned with a [`TilePos`], or given a
+/// new one by inserting it.
+///
+/// Observe it on a tilemap entity to keep data derived from that map, like navigation data,
+/// minimaps or autotiles, up to date in one place. Observers have full access to the tile
+/// entity, which already has all of the components it was spawned with.
+///
+/// Changing a tile's position by mutating its [`TilePos`] in place doesn't trigger this.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// fn watch_tilemap(mut commands: Commands, tilemap: Single<Entity, With<TileStorage>>) {
+///     commands.entity(*tilemap).observe(
+///         |trigger: Trigger<OnTileSet>, tiles: Query<&TileTextureIndex>| {
+///             if let Ok(index) = tiles.get(trigger.event().tile) {
+///                 info!("tile {:?} set to {}", trigger.event().tile_pos, index.0);
+///             }
+///         },
+///     );
+/// }
+/// ```
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OnTileSet {
+    pub tile: Entity,
+    pub tile_pos: TilePos,
+}
+
+/// Triggered on a tilemap when one of its tiles is removed: despawned, or stripped of its
+/// [`TilePos`]. Inserting a new [`TilePos`] triggers this for the old position first, followed
+/// by an [`OnTileSet`].
+///
+/// Observers run after the tile was removed, so a despawned tile entity doesn't exist anymore.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OnTileRemoved {
+    pub tile: Entity,
+    pub tile_pos: TilePos,
+}
+
+/// Triggers [`OnTileSet`] on the tilemap of a tile whose [`TilePos`] was inserted.
+pub(crate) fn trigger_tile_hooks_on_insert(
+    trigger: Trigger<OnInsert, TilePos>,
+    tiles: Query<(&TilePos, &TilemapId)>,
+    mut commands: Commands,
+) {
+    let tile = trigger.entity();
+    if let Ok((tile_pos, tilemap_id)) = tiles.get(tile) {
+        commands.trigger_targets(
+            OnTileSet {
+                tile,
+                tile_pos: *tile_pos,
+            },
+            tilemap_id.0,
+        );
+    }
+}
+
+/// Triggers [`OnTileRemoved`] on the tilemap of a tile whose [`TilePos`] is being replaced or
+/// removed.
+pub(crate) fn trigger_tile_hooks_on_replace(
+    trigger: Trigger<OnReplace, TilePos>,
+    tiles: Query<(&TilePos, &TilemapId)>,
+    mut commands: Commands,
+) {
+    let tile = trigger.entity();
+    if let Ok((tile_pos, tilemap_id)) = tiles.get(tile) {
+        commands.trigger_targets(
+            OnTileRemoved {
+                tile,
+                tile_pos: *tile_pos,
+            },
+            tilemap_id.0,
+        );
+    }
+}
@@ -1,13 +1,38 @@
+mod animation_state;
+mod chunked_storage;
+mod data_layer;
+mod dirty_chunks;
+mod hooks;
+mod motion;
+mod recent_changes;
+mod rect;
+mod stable_id;
 mod storage;
+mod texture_index_check;
+mod vertex_data;
 
+pub use animation_state::*;
 use bevy::{
-    math::{UVec2, Vec2},
-    prelude::{Bundle, Color, Component, Reflect, ReflectComponent},
+    math::{Dir2, IVec2, UVec2, Vec2},
+    prelude::{
+        Bundle, Changed, Color, Commands, Component, DetectChanges, DetectChangesMut, Entity,
+        Query, Ref, Reflect, ReflectComponent, RemovedComponents, With,
+    },
     render::sync_world::SyncToRenderWorld,
 };
+pub use chunked_storage::*;
+pub use data_layer::*;
+pub use dirty_chunks::*;
+pub use hooks::*;
+pub use motion::*;
+pub use recent_changes::*;
+pub use rect::*;
+pub use stable_id::*;
 pub use storage::*;
+pub use texture_index_check::*;
+pub use vertex_data::*;
 
-use crate::map::TilemapId;
+use crate::map::{TilemapId, TilemapInvalidate};
 use crate::TilemapSize;
 
 /// A tile position in the tilemap grid.
@@ -34,6 +59,54 @@ impl TilePos {
     pub fn within_map_bounds(&self, map_size: &TilemapSize) -> bool {
         self.x < map_size.x && self.y < map_size.y
     }
+
+    /// Adds `offset` to `self`.
+    ///
+    /// Returns `None` if either coordinate of the result would be negative or overflow.
+    ///
+    /// Example:
+    /// ```
+    /// # use bevy::math::IVec2;
+    /// # use bevy_ecs_tilemap::prelude::TilePos;
+    /// let tile_pos = TilePos::new(1, 1);
+    /// assert_eq!(tile_pos.checked_add(IVec2::new(2, -1)), Some(TilePos::new(3, 0)));
+    /// assert_eq!(tile_pos.checked_add(IVec2::new(-2, 0)), None);
+    /// ```
+    pub fn checked_add(&self, offset: IVec2) -> Option<TilePos> {
+        Some(TilePos {
+            x: self.x.checked_add_signed(offset.x)?,
+            y: self.y.checked_add_signed(offset.y)?,
+        })
+    }
+
+    /// Adds `offset` to `self`, clamping the result to the bounds of a tilemap of the specified
+    /// size.
+    ///
+    /// `map_size` must not be empty.
+    pub fn offset_clamped(&self, offset: IVec2, map_size: &TilemapSize) -> TilePos {
+        TilePos {
+            x: self
+                .x
+                .saturating_add_signed(offset.x)
+                .min(map_size.x.saturating_sub(1)),
+            y: self
+                .y
+                .saturating_add_signed(offset.y)
+                .min(map_size.y.saturating_sub(1)),
+        }
+    }
+}
+
+impl From<TilePos> for IVec2 {
+    fn from(pos: TilePos) -> Self {
+        IVec2::new(pos.x as i32, pos.y as i32)
+    }
+}
+
+impl From<&TilePos> for IVec2 {
+    fn from(pos: &TilePos) -> Self {
+        IVec2::new(pos.x as i32, pos.y as i32)
+    }
 }
 
 impl From<TilePos> for UVec2 {
@@ -73,7 +146,7 @@ impl From<&TilePos> for Vec2 {
 pub struct TileTextureIndex(pub u32);
 
 /// A custom color for the tile.
-#[derive(Component, Reflect, Default, Clone, Copy, Debug)]
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
 #[reflect(Component)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TileColor(pub Color);
@@ -84,7 +157,157 @@ impl From<Color> for TileColor {
     }
 }
 
+/// Makes a tile's color pulse from `from` to `to` and back every `period` seconds, e.g. to
+/// highlight objectives or warn of hazards.
+///
+/// The color is evaluated by the shader from the global time, so animating thousands of tiles
+/// costs no work on the CPU after the first frame. While present, it takes the place of the tile's
+/// [`TileColor`]. A `period` of zero or less disables the animation.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileColorAnimation {
+    pub from: Color,
+    pub to: Color,
+    /// The seconds it takes to go from `from` to `to` and back.
+    pub period: f32,
+}
+
+impl Default for TileColorAnimation {
+    /// By default, the tile pulses from white to transparent once per second.
+    fn default() -> Self {
+        Self {
+            from: Color::WHITE,
+            to: Color::NONE,
+            period: 1.0,
+        }
+    }
+}
+
+/// Marks the [`TileColor`] of tiles that lost their [`TileColorAnimation`] as changed, so that the
+/// render world goes back to the static color.
+pub(crate) fn refresh_removed_color_animations(
+    mut removed: RemovedComponents<TileColorAnimation>,
+    mut colors: Query<&mut TileColor>,
+) {
+    for tile_entity in removed.read() {
+        if let Ok(mut color) = colors.get_mut(tile_entity) {
+            color.set_changed();
+        }
+    }
+}
+
+/// Moves a tile forwards or backwards in the drawing order of its render chunk, e.g. to draw a
+/// tall banner over the tile behind it, or a bridge under the tiles in front of it, without
+/// moving it to another layer.
+///
+/// On y-sorted tilemaps, tiles are drawn from the top of the screen down, and the bias moves a
+/// tile down by that many grid heights: a bias of `1.0` draws it like a tile whose center is
+/// [`TilemapGridSize::y`](crate::map::TilemapGridSize) lower. On square maps that is the tile
+/// below it, but rows are closer together on other map types, e.g. half a grid height apart on
+/// isometric diamond maps, where `0.5` is enough to draw a tile like the one below it. Otherwise,
+/// tiles are drawn in storage order, and tiles with a larger bias are drawn after those with a
+/// smaller one.
+/// Either way, a tile can't be drawn over the tiles of another chunk or tilemap this way.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileSortBias(pub f32);
+
+/// Marks the [`TileTextureIndex`] of tiles that lost their [`TileSortBias`] as changed, so that
+/// the render world goes back to the default drawing order.
+pub(crate) fn refresh_removed_sort_biases(
+    mut removed: RemovedComponents<TileSortBias>,
+    mut texture_indices: Query<&mut TileTextureIndex>,
+) {
+    for tile_entity in removed.read() {
+        if let Ok(mut texture_index) = texture_indices.get_mut(tile_entity) {
+            texture_index.set_changed();
+        }
+    }
+}
+
+/// Moves a tile away from its place on the grid by a distance in the tilemap's local space, e.g.
+/// to scatter debris or props, without moving it to another [`TilePos`].
+///
+/// Tiles moved beyond the bounds of their render chunk may be culled while still in view.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilePosOffset(pub Vec2);
+
+/// Rotates a tile counterclockwise around its center, by an angle in radians.
+///
+/// Unlike the quarter turns of a [`TileFlip`], this rotates the quad of the tile rather than its
+/// texture, so its corners may stick out of its grid cell.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileRotation(pub f32);
+
+/// Marks the [`TileTextureIndex`] of tiles that lost their [`TilePosOffset`] or
+/// [`TileRotation`] as changed, so that the render world puts them back on the grid.
+pub(crate) fn refresh_removed_tile_transforms(
+    mut removed_offsets: RemovedComponents<TilePosOffset>,
+    mut removed_rotations: RemovedComponents<TileRotation>,
+    mut texture_indices: Query<&mut TileTextureIndex>,
+) {
+    for tile_entity in removed_offsets.read().chain(removed_rotations.read()) {
+        if let Ok(mut texture_index) = texture_indices.get_mut(tile_entity) {
+            texture_index.set_changed();
+        }
+    }
+}
+
+/// Draws tiles at a size other than the [`TilemapTileSize`](crate::map::TilemapTileSize), in
+/// pixels, standing on the bottom edge of their grid cell rather than centered on it, e.g. 16x32
+/// trees on a 16x16 grid.
+///
+/// Insert it on a tile, or on a tilemap for all of its tiles, e.g. for a Tiled tileset of tall
+/// tiles. The size of a tile overrides the size of its tilemap.
+///
+/// Tiles sampled from an atlas use the region of that size starting at the top left corner of
+/// their texture index, so a 16x32 tree in a 16x16 tileset uses the index of its top half.
+/// Texture arrays are stretched over the quad instead, so give them a tilemap tile size matching
+/// the render size. Tiles are still y-sorted by their grid cell, so tall tiles are drawn over the
+/// tiles behind them.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileRenderSize(pub Vec2);
+
+/// Rebuilds the tilemaps whose [`TileRenderSize`] changed, and marks the [`TileTextureIndex`] of
+/// tiles that lost theirs as changed, so that the render world resizes them.
+pub(crate) fn refresh_tile_render_sizes(
+    mut commands: Commands,
+    changed_tilemaps: Query<
+        (Entity, Ref<TileRenderSize>, Ref<TileStorage>),
+        Changed<TileRenderSize>,
+    >,
+    tilemaps: Query<(), With<TileStorage>>,
+    mut removed: RemovedComponents<TileRenderSize>,
+    mut texture_indices: Query<&mut TileTextureIndex>,
+) {
+    for (tilemap, render_size, storage) in changed_tilemaps.iter() {
+        // Tilemaps spawned with a render size are extracted with it in the first place.
+        if !(render_size.is_added() && storage.is_added()) {
+            commands.entity(tilemap).insert(TilemapInvalidate::All);
+        }
+    }
+    for entity in removed.read() {
+        if tilemaps.contains(entity) {
+            commands.entity(entity).insert(TilemapInvalidate::All);
+        } else if let Ok(mut texture_index) = texture_indices.get_mut(entity) {
+            texture_index.set_changed();
+        }
+    }
+}
+
 /// Hides or shows a tile based on the boolean. Default: True
+///
+/// Toggling visibility only rewrites a flag read by the shader rather than rebuilding the chunk
+/// mesh, so it is cheap enough for blinking tiles. Hidden tiles still cost a few vertices, so
+/// despawn tiles that stay hidden for good.
 #[derive(Component, Reflect, Clone, Copy, Debug, Hash, PartialEq, Eq)]
 #[reflect(Component)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -108,6 +331,53 @@ pub struct TileFlip {
     pub d: bool, // anti
 }
 
+/// The collision shape of a tile, used by [`sample_ground_height`](crate::helpers::collision::sample_ground_height).
+///
+/// Tiles without this component are treated as [`TileCollisionShape::Full`].
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TileCollisionShape {
+    /// The whole tile is solid.
+    #[default]
+    Full,
+    /// Only the bottom half of the tile is solid.
+    HalfBottom,
+    /// Only the top half of the tile is solid.
+    HalfTop,
+    /// A 45 degree slope rising from the bottom left corner to the top right corner.
+    SlopeUpRight,
+    /// A 45 degree slope rising from the bottom right corner to the top left corner.
+    SlopeUpLeft,
+    /// A platform that is only solid at its top edge, and can be passed through from below.
+    OneWay,
+}
+
+impl TileCollisionShape {
+    /// Returns the height of the tile's top surface at `x`, both given as fractions of the tile
+    /// size, measured from the bottom left corner of the tile.
+    pub fn surface_height(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            TileCollisionShape::Full | TileCollisionShape::HalfTop | TileCollisionShape::OneWay => {
+                1.0
+            }
+            TileCollisionShape::HalfBottom => 0.5,
+            TileCollisionShape::SlopeUpRight => x,
+            TileCollisionShape::SlopeUpLeft => 1.0 - x,
+        }
+    }
+}
+
+/// The direction a tile moves things standing on it, e.g. a conveyor belt.
+///
+/// The direction is in the tilemap's local space. See [`crate::helpers::flow`] for helpers that
+/// use it.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileFlow(pub Dir2);
+
 /// This an optional tile bundle with default components.
 #[derive(Bundle, Default, Clone, Copy, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -131,6 +401,8 @@ pub struct TilePosOld(pub TilePos);
 /// A component that is attached to a Tile entity that
 /// tells the GPU how to animate the tile.
 /// Currently all frames must be aligned in your tilemap.
+///
+/// Add an [`AnimationState`] to pause, reverse or play the animation once.
 #[derive(Component, Reflect, Clone, Copy, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnimatedTile {
@@ -141,3 +413,70 @@ pub struct AnimatedTile {
     /// The speed the animation plays back at.
     pub speed: f32,
 }
+
+/// One frame of an [`AnimatedTileFrames`] animation.
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileAnimationFrame {
+    /// The frame index in the tilemap atlas/array.
+    pub texture_index: u32,
+    /// How long the frame is shown, in seconds.
+    pub duration: f32,
+}
+
+/// Tells the GPU to animate a tile through a list of frames that don't have to be next to each
+/// other in the tilemap, each shown for its own duration, like the animations authored in Tiled.
+///
+/// It takes precedence over an [`AnimatedTile`] on the same tile.
+///
+/// ```
+/// # use bevy_ecs_tilemap::tiles::AnimatedTileFrames;
+/// // Tiled frames are a tile id and a duration in milliseconds.
+/// let torch = AnimatedTileFrames::from_millis([(12, 100), (40, 100), (13, 250)]);
+/// assert_eq!(torch.duration(), 0.45);
+///
+/// let blink = AnimatedTileFrames::uniform([3, 7], 0.5);
+/// assert_eq!(blink.frames[1].texture_index, 7);
+/// ```
+#[derive(Component, Reflect, Clone, Debug, Default, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnimatedTileFrames {
+    pub frames: Vec<TileAnimationFrame>,
+}
+
+impl AnimatedTileFrames {
+    /// Creates an animation showing each of `texture_indices` for `duration` seconds.
+    pub fn uniform(texture_indices: impl IntoIterator<Item = u32>, duration: f32) -> Self {
+        Self {
+            frames: texture_indices
+                .into_iter()
+                .map(|texture_index| TileAnimationFrame {
+                    texture_index,
+                    duration,
+                })
+                .collect(),
+        }
+    }
+
+    /// Creates an animation from texture indices and frame durations in milliseconds.
+    pub fn from_millis(frames: impl IntoIterator<Item = (u32, u32)>) -> Self {
+        Self {
+            frames: frames
+                .into_iter()
+                .map(|(texture_index, millis)| TileAnimationFrame {
+                    texture_index,
+                    duration: millis as f32 / 1000.0,
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the duration of a whole cycle of the animation, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.frames
+            .iter()
+            .map(|frame| frame.duration.max(0.0))
+            .sum()
+    }
+}
@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+
+use super::{TilePos, TilePosOld, TileTextureIndex};
+use crate::map::{is_frozen, TilemapAxes, TilemapId, TilemapStatic, TilemapYAxis};
+
+/// Draws a tile gliding from its [`TilePosOld`] to its [`TilePos`] when it moves, instead of
+/// jumping to its new position, e.g. for pushable blocks.
+///
+/// Only the rendered tile glides: its [`TilePos`] and storage slot change at once. A tile moving
+/// again before its glide is over glides on from where it is drawn. Each frame a tile glides, the
+/// render chunk it is in is remeshed.
+///
+/// Tiles moved in `FixedUpdate` should use [`TileMotion::fixed_timestep`], which glides over one
+/// fixed timestep and follows [`Time<Fixed>`], so that tiles moving every step move smoothly
+/// rather than in bursts.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// # use bevy_ecs_tilemap::tiles::TileMotion;
+/// fn make_pushable(mut commands: Commands, block: Single<Entity, With<TilePos>>) {
+///     commands.entity(*block).insert(TileMotion::new(0.15));
+/// }
+///
+/// fn push_right(mut blocks: Query<&mut TilePos, With<TileMotion>>) {
+///     for mut tile_pos in blocks.iter_mut() {
+///         tile_pos.x += 1;
+///     }
+/// }
+/// ```
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct TileMotion {
+    /// The seconds a glide lasts. Ignored if `fixed_timestep` is set.
+    pub duration: f32,
+    /// Glides over one fixed timestep, for tiles moved in `FixedUpdate`.
+    pub fixed_timestep: bool,
+    /// The offset, in tiles, the tile is drawn at from its position.
+    offset: Vec2,
+    /// The offset at the start of the current glide.
+    from: Vec2,
+    /// The seconds into the current glide, or the [`Time<Fixed>`] elapsed at its start.
+    elapsed: f32,
+}
+
+impl Default for TileMotion {
+    /// By default, tiles glide over a tenth of a second.
+    fn default() -> Self {
+        Self::new(0.1)
+    }
+}
+
+impl TileMotion {
+    /// Glides over `duration` seconds.
+    pub fn new(duration: f32) -> Self {
+        Self {
+            duration,
+            fixed_timestep: false,
+            offset: Vec2::ZERO,
+            from: Vec2::ZERO,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Glides over one fixed timestep.
+    pub fn fixed_timestep() -> Self {
+        Self {
+            fixed_timestep: true,
+            ..Self::new(0.0)
+        }
+    }
+
+    /// Returns the offset, in tiles, the tile is drawn at from its [`TilePos`].
+    pub fn offset(&self) -> Vec2 {
+        self.offset
+    }
+
+    /// Returns true if the tile is drawn away from its [`TilePos`].
+    pub fn is_moving(&self) -> bool {
+        self.offset != Vec2::ZERO
+    }
+
+    /// The offset in the y-up grid positions of the render world.
+    pub(crate) fn grid_offset(&self, axes: &TilemapAxes) -> Vec2 {
+        match axes.y_axis {
+            TilemapYAxis::Up => self.offset,
+            TilemapYAxis::Down => Vec2::new(self.offset.x, -self.offset.y),
+        }
+    }
+}
+
+/// Starts glides for tiles that moved, and advances the glides in progress.
+pub(crate) fn update_tile_motions(
+    time: Res<Time>,
+    fixed_time: Res<Time<Fixed>>,
+    mut tiles: Query<(&mut TileMotion, Ref<TilePos>, &TilePosOld, &TilemapId)>,
+    static_tilemaps: Query<Ref<TilemapStatic>>,
+) {
+    for (mut motion, tile_pos, tile_pos_old, tilemap_id) in tiles.iter_mut() {
+        if is_frozen(&static_tilemaps, tilemap_id.0) {
+            continue;
+        }
+        let moved = tile_pos.is_changed() && !tile_pos.is_added();
+        // Only changes of the offset are seen by the render world.
+        let state = motion.bypass_change_detection();
+        let offset = if state.fixed_timestep {
+            let step = fixed_time.elapsed_secs();
+            if moved {
+                state.from = tile_offset(tile_pos_old, &tile_pos);
+                state.elapsed = step;
+            } else if state.elapsed != step {
+                state.from = Vec2::ZERO;
+            }
+            state.from * (1.0 - fixed_time.overstep_fraction())
+        } else {
+            if moved {
+                state.from = state.offset + tile_offset(tile_pos_old, &tile_pos);
+                state.elapsed = 0.0;
+            } else {
+                state.elapsed += time.delta_secs();
+            }
+            let progress = if state.duration > 0.0 {
+                (state.elapsed / state.duration).min(1.0)
+            } else {
+                1.0
+            };
+            state.from * (1.0 - progress)
+        };
+        if state.offset != offset {
+            state.offset = offset;
+            motion.set_changed();
+        }
+    }
+}
+
+/// The offset from `tile_pos` to `tile_pos_old`, in tiles.
+fn tile_offset(tile_pos_old: &TilePosOld, tile_pos: &TilePos) -> Vec2 {
+    Vec2::from(tile_pos_old.0) - Vec2::from(tile_pos)
+}
+
+/// Marks the [`TileTextureIndex`] of tiles that lost their [`TileMotion`] as changed, so that
+/// the render world draws them at their position again.
+pub(crate) fn refresh_removed_tile_motions(
+    mut removed: RemovedComponents<TileMotion>,
+    mut texture_indices: Query<&mut TileTextureIndex>,
+) {
+    for tile_entity in removed.read() {
+        if let Ok(mut texture_index) = texture_indices.get_mut(tile_entity) {
+            texture_index.set_changed();
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{TileColor, TileFlip, TilePos, TileStorage, TileTextureIndex, TileVisible};
+use crate::map::{is_frozen, TilemapId, TilemapStatic};
+
+/// What happened to a tile.
+#[derive(Reflect, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum TileChangeKind {
+    /// A tile was spawned, or given a [`TilePos`].
+    Placed,
+    /// A tile was despawned, or lost its [`TilePos`].
+    Removed,
+    /// A tile was moved to this position, or its [`TileTextureIndex`], [`TileColor`],
+    /// [`TileVisible`] or [`TileFlip`] changed.
+    Changed,
+}
+
+/// A single entry of [`RecentTileChanges`].
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub struct RecentTileChange {
+    pub tile_pos: TilePos,
+    pub kind: TileChangeKind,
+    /// The elapsed [`Time`] when the change was recorded, in seconds.
+    pub time: f32,
+}
+
+/// Remembers the most recent tile changes of every tilemap, e.g. to briefly flash tiles that were
+/// just placed or removed.
+///
+/// Insert this resource to opt into tracking. Each tilemap keeps at most `capacity` changes, and
+/// the oldest ones are dropped first.
+///
+/// The tiles spawned in the same frame as the [`TileStorage`] of their tilemap, e.g. when a level
+/// is loaded, are not recorded, so that they don't flood the buffer.
+#[derive(Resource, Clone, Debug)]
+pub struct RecentTileChanges {
+    capacity: usize,
+    maps: HashMap<Entity, VecDeque<RecentTileChange>>,
+}
+
+impl Default for RecentTileChanges {
+    /// By default, the last 256 changes of each tilemap are kept.
+    fn default() -> Self {
+        Self::with_capacity(256)
+    }
+}
+
+impl RecentTileChanges {
+    /// Creates a new buffer keeping up to `capacity` changes per tilemap.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            maps: HashMap::default(),
+        }
+    }
+
+    /// The number of changes kept per tilemap.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Records a change to a tile of `tilemap`, dropping the oldest change if the buffer is full.
+    pub fn push(&mut self, tilemap: Entity, change: RecentTileChange) {
+        if self.capacity == 0 {
+            return;
+        }
+        let changes = self.maps.entry(tilemap).or_default();
+        if changes.len() == self.capacity {
+            changes.pop_front();
+        }
+        changes.push_back(change);
+    }
+
+    /// Returns the recorded changes of `tilemap`, oldest first.
+    pub fn get(&self, tilemap: Entity) -> impl Iterator<Item = &RecentTileChange> {
+        self.maps.get(&tilemap).into_iter().flatten()
+    }
+
+    /// Returns the changes of `tilemap` recorded at or after `time`, oldest first.
+    pub fn since(&self, tilemap: Entity, time: f32) -> impl Iterator<Item = &RecentTileChange> {
+        self.get(tilemap)
+            .skip_while(move |change| change.time < time)
+    }
+
+    /// Forgets all recorded changes.
+    pub fn clear(&mut self) {
+        self.maps.clear();
+    }
+}
+
+/// Records placed and changed tiles, and forgets about despawned tilemaps.
+#[allow(clippy::type_complexity)]
+pub(crate) fn record_placed_tiles(
+    time: Res<Time>,
+    mut recent_changes: ResMut<RecentTileChanges>,
+    changed_tiles: Query<
+        (Ref<TilePos>, &TilemapId),
+        Or<(
+            Changed<TilePos>,
+            Changed<TileTextureIndex>,
+            Changed<TileColor>,
+            Changed<TileVisible>,
+            Changed<TileFlip>,
+        )>,
+    >,
+    tilemaps: Query<Ref<TileStorage>>,
+    static_tilemaps: Query<Ref<TilemapStatic>>,
+) {
+    for (tile_pos, tilemap_id) in changed_tiles.iter() {
+        // Tiles spawned along with their tilemap are part of its initial state.
+        let spawned_with_tilemap = tile_pos.is_added()
+            && tilemaps
+                .get(tilemap_id.0)
+                .is_ok_and(|storage| storage.is_added());
+        if spawned_with_tilemap || is_frozen(&static_tilemaps, tilemap_id.0) {
+            continue;
+        }
+        recent_changes.push(
+            tilemap_id.0,
+            RecentTileChange {
+                tile_pos: *tile_pos,
+                kind: if tile_pos.is_added() {
+                    TileChangeKind::Placed
+                } else {
+                    TileChangeKind::Changed
+                },
+                time: time.elapsed_secs(),
+            },
+        );
+    }
+
+    recent_changes
+        .maps
+        .retain(|tilemap, _| tilemaps.contains(*tilemap));
+}
+
+/// Records removed tiles. Runs as an observer, as the tile's position is gone afterwards.
+pub(crate) fn record_removed_tile(
+    trigger: Trigger<OnRemove, TilePos>,
+    time: Option<Res<Time>>,
+    recent_changes: Option<ResMut<RecentTileChanges>>,
+    tiles: Query<(&TilePos, &TilemapId)>,
+) {
+    let (Some(time), Some(mut recent_changes)) = (time, recent_changes) else {
+        return;
+    };
+    if let Ok((tile_pos, tilemap_id)) = tiles.get(trigger.entity()) {
+        recent_changes.push(
+            tilemap_id.0,
+            RecentTileChange {
+                tile_pos: *tile_pos,
+                kind: TileChangeKind::Removed,
+                time: time.elapsed_secs(),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{TilemapSize, TilemapType};
+    use crate::test_utils::{spawn_test_map, tile_at, MinimalTilemapPlugins, StepApp};
+    use crate::tiles::TileBundle;
+
+    fn recorded(app: &App, tilemap: Entity) -> Vec<(TilePos, TileChangeKind)> {
+        app.world()
+            .resource::<RecentTileChanges>()
+            .get(tilemap)
+            .map(|change| (change.tile_pos, change.kind))
+            .collect()
+    }
+
+    fn test_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(MinimalTilemapPlugins)
+            .init_resource::<RecentTileChanges>();
+        let size = TilemapSize { x: 4, y: 4 };
+        let map = spawn_test_map(app.world_mut(), size, TilemapType::Square);
+        app.step_frames(1);
+        (app, map)
+    }
+
+    #[test]
+    fn tiles_spawned_with_their_tilemap_are_not_recorded() {
+        let (mut app, map) = test_app();
+        assert!(recorded(&app, map).is_empty());
+
+        let tile_pos = TilePos::new(1, 1);
+        let old_tile = tile_at(app.world(), map, tile_pos).unwrap();
+        app.world_mut().despawn(old_tile);
+        let tile = app
+            .world_mut()
+            .spawn(TileBundle {
+                position: tile_pos,
+                tilemap_id: TilemapId(map),
+                ..Default::default()
+            })
+            .id();
+        app.world_mut()
+            .get_mut::<TileStorage>(map)
+            .unwrap()
+            .set(&tile_pos, tile);
+        app.step_frames(1);
+        assert_eq!(
+            recorded(&app, map),
+            [
+                (tile_pos, TileChangeKind::Removed),
+                (tile_pos, TileChangeKind::Placed)
+            ]
+        );
+    }
+
+    #[test]
+    fn changed_tiles_are_recorded() {
+        let (mut app, map) = test_app();
+        let tile = tile_at(app.world(), map, TilePos::new(2, 3)).unwrap();
+        app.world_mut().get_mut::<TileTextureIndex>(tile).unwrap().0 = 5;
+        app.step_frames(1);
+        *app.world_mut().get_mut::<TilePos>(tile).unwrap() = TilePos::new(0, 3);
+        app.step_frames(1);
+        assert_eq!(
+            recorded(&app, map),
+            [
+                (TilePos::new(2, 3), TileChangeKind::Changed),
+                (TilePos::new(0, 3), TileChangeKind::Changed)
+            ]
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use bevy::math::{IRect, IVec2};
+use bevy::prelude::Reflect;
+
+use super::TilePos;
+use crate::TilemapSize;
+
+/// A rectangular region of tiles, starting at `origin` and spanning `size` tiles.
+///
+/// Example:
+/// ```
+/// # use bevy_ecs_tilemap::prelude::{TilePos, TileRect, TilemapSize};
+/// let a = TileRect::new(TilePos::new(0, 0), TilemapSize { x: 4, y: 4 });
+/// let b = TileRect::new(TilePos::new(2, 3), TilemapSize { x: 4, y: 4 });
+/// assert_eq!(
+///     a.intersect(&b),
+///     TileRect::new(TilePos::new(2, 3), TilemapSize { x: 2, y: 1 })
+/// );
+/// assert_eq!(a.union(&b).size, TilemapSize { x: 6, y: 7 });
+/// assert_eq!(a.intersect(&b).iter().count(), 2);
+/// ```
+#[derive(Reflect, Default, Clone, Copy, Debug, PartialEq)]
+pub struct TileRect {
+    pub origin: TilePos,
+    pub size: TilemapSize,
+}
+
+impl TileRect {
+    pub const fn new(origin: TilePos, size: TilemapSize) -> Self {
+        Self { origin, size }
+    }
+
+    /// Creates the rectangle covering a whole tilemap of the given size.
+    pub const fn from_map_size(map_size: TilemapSize) -> Self {
+        Self::new(TilePos::new(0, 0), map_size)
+    }
+
+    /// Creates the smallest rectangle containing both `a` and `b`.
+    pub fn from_corners(a: TilePos, b: TilePos) -> Self {
+        let origin = TilePos::new(a.x.min(b.x), a.y.min(b.y));
+        Self::new(
+            origin,
+            TilemapSize {
+                x: a.x.max(b.x) - origin.x + 1,
+                y: a.y.max(b.y) - origin.y + 1,
+            },
+        )
+    }
+
+    /// The first position past the rectangle along each axis.
+    pub fn end(&self) -> TilePos {
+        TilePos::new(
+            self.origin.x.saturating_add(self.size.x),
+            self.origin.y.saturating_add(self.size.y),
+        )
+    }
+
+    /// Returns `true` if the rectangle contains no tiles.
+    pub fn is_empty(&self) -> bool {
+        self.size.x == 0 || self.size.y == 0
+    }
+
+    /// Checks to see if `tile_pos` lies within the rectangle.
+    pub fn contains(&self, tile_pos: &TilePos) -> bool {
+        let end = self.end();
+        tile_pos.x >= self.origin.x
+            && tile_pos.y >= self.origin.y
+            && tile_pos.x < end.x
+            && tile_pos.y < end.y
+    }
+
+    /// Returns the part of the rectangle that overlaps `other`.
+    ///
+    /// If the rectangles don't overlap, the result is empty.
+    pub fn intersect(&self, other: &TileRect) -> TileRect {
+        let (end, other_end) = (self.end(), other.end());
+        let origin = TilePos::new(
+            self.origin.x.max(other.origin.x),
+            self.origin.y.max(other.origin.y),
+        );
+        TileRect::new(
+            origin,
+            TilemapSize {
+                x: end.x.min(other_end.x).saturating_sub(origin.x),
+                y: end.y.min(other_end.y).saturating_sub(origin.y),
+            },
+        )
+    }
+
+    /// Returns the smallest rectangle containing both rectangles.
+    ///
+    /// Empty rectangles are ignored.
+    pub fn union(&self, other: &TileRect) -> TileRect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let (end, other_end) = (self.end(), other.end());
+        let origin = TilePos::new(
+            self.origin.x.min(other.origin.x),
+            self.origin.y.min(other.origin.y),
+        );
+        TileRect::new(
+            origin,
+            TilemapSize {
+                x: end.x.max(other_end.x) - origin.x,
+                y: end.y.max(other_end.y) - origin.y,
+            },
+        )
+    }
+
+    /// Returns the part of the rectangle that lies within a tilemap of the given size.
+    pub fn clamp_to_map(&self, map_size: &TilemapSize) -> TileRect {
+        self.intersect(&TileRect::from_map_size(*map_size))
+    }
+
+    /// Returns an iterator over every position in the rectangle, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = TilePos> {
+        let (origin, end) = (self.origin, self.end());
+        (origin.y..end.y).flat_map(move |y| (origin.x..end.x).map(move |x| TilePos::new(x, y)))
+    }
+}
+
+impl From<TileRect> for IRect {
+    /// The resulting rectangle spans from `origin` to [`TileRect::end`].
+    fn from(rect: TileRect) -> Self {
+        IRect::from_corners(
+            IVec2::from(rect.origin),
+            IVec2::new(rect.end().x as i32, rect.end().y as i32),
+        )
+    }
+}
+
+impl From<IRect> for TileRect {
+    /// The inverse of the conversion into [`IRect`]. Any part of the rectangle with negative
+    /// coordinates is cut off.
+    fn from(rect: IRect) -> Self {
+        let min = rect.min.max(IVec2::ZERO).as_uvec2();
+        let max = rect.max.max(IVec2::ZERO).as_uvec2();
+        TileRect::new(
+            min.into(),
+            TilemapSize {
+                x: max.x.saturating_sub(min.x),
+                y: max.y.saturating_sub(min.y),
+            },
+        )
+    }
+}
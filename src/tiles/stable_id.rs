@@ -0,0 +1,73 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::TilePos;
+
+/// An id for a tile that, unlike its `Entity`, stays the same across sessions.
+///
+/// Ids are assigned automatically to every tile while the [`TileStableIdAllocator`] resource
+/// exists. Tiles that already have an id, e.g. because they were loaded from a save, keep it.
+#[derive(Component, Reflect, Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileStableId(pub u64);
+
+/// Hands out [`TileStableId`]s, and keeps track of which tile has which id.
+///
+/// Insert this resource to opt into stable ids. Only `next` needs to be saved; the lookup table is
+/// rebuilt from the tiles present in the world.
+#[derive(Resource, Reflect, Default, Clone, Debug)]
+#[reflect(Resource)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileStableIdAllocator {
+    /// The id the next new tile will get.
+    pub next: u64,
+    #[reflect(ignore)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    entities: HashMap<TileStableId, Entity>,
+    #[reflect(ignore)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    ids: HashMap<Entity, TileStableId>,
+}
+
+impl TileStableIdAllocator {
+    /// Reserves a new id.
+    pub fn allocate(&mut self) -> TileStableId {
+        let id = TileStableId(self.next);
+        self.next += 1;
+        id
+    }
+
+    /// Gets the tile entity with the given id, if it exists.
+    pub fn get(&self, id: TileStableId) -> Option<Entity> {
+        self.entities.get(&id).copied()
+    }
+}
+
+/// Assigns ids to new tiles and keeps the allocator's lookup table up to date.
+pub(crate) fn assign_tile_stable_ids(
+    mut commands: Commands,
+    mut allocator: ResMut<TileStableIdAllocator>,
+    new_tiles: Query<Entity, (With<TilePos>, Without<TileStableId>)>,
+    added_ids: Query<(Entity, &TileStableId), Added<TileStableId>>,
+    mut removed_ids: RemovedComponents<TileStableId>,
+) {
+    for entity in removed_ids.read() {
+        if let Some(id) = allocator.ids.remove(&entity) {
+            allocator.entities.remove(&id);
+        }
+    }
+
+    // Ids can come from a save, so make sure they are never handed out again.
+    for (entity, id) in added_ids.iter() {
+        allocator.next = allocator.next.max(id.0 + 1);
+        allocator.entities.insert(*id, entity);
+        allocator.ids.insert(entity, *id);
+    }
+
+    for entity in new_tiles.iter() {
+        let id = allocator.allocate();
+        allocator.entities.insert(id, entity);
+        allocator.ids.insert(entity, id);
+        commands.entity(entity).insert(id);
+    }
+}
@@ -1,22 +1,46 @@
 use bevy::{
     ecs::{
         entity::{EntityMapper, MapEntities},
+        query::{QueryData, QueryFilter, ROQueryItem},
         reflect::ReflectMapEntities,
     },
+    math::bounding::Aabb2d,
     prelude::*,
+    tasks::{ComputeTaskPool, TaskPool},
 };
 
-use crate::map::TilemapSize;
+use crate::map::{TilemapAxes, TilemapGridSize, TilemapSize, TilemapTileSize, TilemapType};
 
-use super::TilePos;
+use super::{TilePos, TileRect};
+use crate::map::TilemapId;
 
 /// Used to store tile entities for fast look up.
 /// Tile entities are stored in a grid. The grid is always filled with None.
+///
+/// Despawning a tile entity, or removing its [`TilePos`], clears its slot in the storage of its
+/// tilemap, so that the storage doesn't keep pointing at a despawned entity.
+///
+/// ```
+/// use bevy::prelude::App;
+/// use bevy_ecs_tilemap::prelude::*;
+/// use bevy_ecs_tilemap::test_utils::{spawn_test_map, tile_at, MinimalTilemapPlugins};
+///
+/// let mut app = App::new();
+/// app.add_plugins(MinimalTilemapPlugins);
+/// let map = spawn_test_map(app.world_mut(), TilemapSize { x: 4, y: 4 }, TilemapType::Square);
+///
+/// let tile = tile_at(app.world(), map, TilePos::new(1, 2)).unwrap();
+/// app.world_mut().despawn(tile);
+/// assert_eq!(tile_at(app.world(), map, TilePos::new(1, 2)), None);
+/// ```
 #[derive(Component, Reflect, Default, Debug, Clone)]
 #[reflect(Component, MapEntities)]
 pub struct TileStorage {
     tiles: Vec<Option<Entity>>,
     pub size: TilemapSize,
+    /// Decides how tiles are laid out in memory, and how tile positions relate to the world. This
+    /// is the only place the axes of a tilemap are stored.
+    pub axes: TilemapAxes,
 }
 
 impl MapEntities for TileStorage {
@@ -30,18 +54,30 @@ impl MapEntities for TileStorage {
 impl TileStorage {
     /// Creates a new tile storage that is empty.
     pub fn empty(size: TilemapSize) -> Self {
+        Self::empty_with_axes(size, TilemapAxes::default())
+    }
+
+    /// Creates a new tile storage that is empty, using the given [`TilemapAxes`].
+    pub fn empty_with_axes(size: TilemapSize, axes: TilemapAxes) -> Self {
         Self {
             tiles: vec![None; size.count()],
             size,
+            axes,
         }
     }
 
+    /// Returns the index of `tile_pos` in the underlying grid.
+    #[inline]
+    fn index(&self, tile_pos: &TilePos) -> usize {
+        self.axes.to_index(tile_pos, &self.size)
+    }
+
     /// Gets a tile entity for the given tile position, if an entity is associated with that tile
     /// position.
     ///
     /// Panics if the given `tile_pos` doesn't lie within the extents of the underlying tile map.
     pub fn get(&self, tile_pos: &TilePos) -> Option<Entity> {
-        self.tiles[tile_pos.to_index(&self.size)]
+        self.tiles[self.index(tile_pos)]
     }
 
     /// Gets a tile entity for the given tile position, if:
@@ -51,7 +87,7 @@ impl TileStorage {
     /// otherwise it returns `None`.
     pub fn checked_get(&self, tile_pos: &TilePos) -> Option<Entity> {
         if tile_pos.within_map_bounds(&self.size) {
-            self.tiles[tile_pos.to_index(&self.size)]
+            self.tiles[self.index(tile_pos)]
         } else {
             None
         }
@@ -63,7 +99,8 @@ impl TileStorage {
     ///
     /// Panics if the given `tile_pos` doesn't lie within the extents of the underlying tile map.
     pub fn set(&mut self, tile_pos: &TilePos, tile_entity: Entity) {
-        self.tiles[tile_pos.to_index(&self.size)].replace(tile_entity);
+        let index = self.index(tile_pos);
+        self.tiles[index].replace(tile_entity);
     }
 
     /// Sets a tile entity for the given tile position, if the tile position lies within the
@@ -72,7 +109,8 @@ impl TileStorage {
     /// If there is an entity already at that position, it will be replaced.
     pub fn checked_set(&mut self, tile_pos: &TilePos, tile_entity: Entity) {
         if tile_pos.within_map_bounds(&self.size) {
-            self.tiles[tile_pos.to_index(&self.size)].replace(tile_entity);
+            let index = self.index(tile_pos);
+            self.tiles[index].replace(tile_entity);
         }
     }
 
@@ -86,12 +124,144 @@ impl TileStorage {
         self.tiles.iter_mut()
     }
 
+    /// Returns an iterator with every position in the grid, along with its tile entity, if any.
+    pub fn iter_with_pos(&self) -> impl Iterator<Item = (TilePos, Option<Entity>)> + use<'_> {
+        self.tiles
+            .iter()
+            .enumerate()
+            .map(|(index, tile)| (self.axes.from_index(index, &self.size), *tile))
+    }
+
+    /// Returns an iterator with every tile entity in the grid, along with its position.
+    ///
+    /// Example:
+    /// ```
+    /// # use bevy::prelude::Entity;
+    /// # use bevy_ecs_tilemap::prelude::*;
+    /// let mut storage = TileStorage::empty_with_axes(TilemapSize { x: 4, y: 3 }, TilemapAxes::Y_DOWN);
+    /// storage.set(&TilePos::new(2, 1), Entity::from_raw(7));
+    /// let tiles: Vec<_> = storage.iter_some().collect();
+    /// assert_eq!(tiles, vec![(TilePos::new(2, 1), Entity::from_raw(7))]);
+    /// ```
+    pub fn iter_some(&self) -> impl Iterator<Item = (TilePos, Entity)> + use<'_> {
+        self.tiles.iter().enumerate().filter_map(|(index, tile)| {
+            tile.map(|tile| (self.axes.from_index(index, &self.size), tile))
+        })
+    }
+
+    /// Returns an iterator with every tile entity within `rect`, along with its position, row by
+    /// row. The part of `rect` outside of the map is ignored.
+    pub fn iter_rect(&self, rect: &TileRect) -> impl Iterator<Item = (TilePos, Entity)> + use<'_> {
+        rect.clamp_to_map(&self.size)
+            .iter()
+            .filter_map(|tile_pos| Some((tile_pos, self.get(&tile_pos)?)))
+    }
+
+    /// Returns an iterator with the items of `query` for the tile entities within `rect` that
+    /// match it, along with their positions.
+    ///
+    /// Only the tiles of the rect are looked up, which makes localized checks independent of the
+    /// number of tiles in the world.
+    ///
+    /// Example:
+    /// ```
+    /// # use bevy::ecs::system::SystemState;
+    /// # use bevy::prelude::*;
+    /// # use bevy_ecs_tilemap::prelude::*;
+    /// # use bevy_ecs_tilemap::test_utils::{spawn_test_map, tile_at};
+    /// #[derive(Component)]
+    /// struct Burnable;
+    ///
+    /// # let mut world = World::new();
+    /// let map = spawn_test_map(&mut world, TilemapSize { x: 16, y: 16 }, TilemapType::Square);
+    /// for tile_pos in [TilePos::new(4, 5), TilePos::new(12, 12)] {
+    ///     let tile = tile_at(&world, map, tile_pos).unwrap();
+    ///     world.entity_mut(tile).insert(Burnable);
+    /// }
+    ///
+    /// let mut state = SystemState::<Query<(), With<Burnable>>>::new(&mut world);
+    /// let burnable = state.get(&world);
+    /// let storage = world.get::<TileStorage>(map).unwrap();
+    /// // The tiles within two tiles of a fire at (5, 5).
+    /// let near_fire = TileRect::from_corners(TilePos::new(3, 3), TilePos::new(7, 7));
+    /// let burning: Vec<_> = storage
+    ///     .query_rect(&near_fire, &burnable)
+    ///     .map(|(tile_pos, _)| tile_pos)
+    ///     .collect();
+    /// assert_eq!(burning, vec![TilePos::new(4, 5)]);
+    /// ```
+    pub fn query_rect<'a, 'w, 's, D: QueryData, F: QueryFilter>(
+        &'a self,
+        rect: &TileRect,
+        query: &'a Query<'w, 's, D, F>,
+    ) -> impl Iterator<Item = (TilePos, ROQueryItem<'a, D>)> + use<'a, 'w, 's, D, F> {
+        self.iter_rect(rect)
+            .filter_map(|(tile_pos, entity)| Some((tile_pos, query.get(entity).ok()?)))
+    }
+
+    /// Calls `f` on every tile entity in the grid and its position, in parallel on the
+    /// [`ComputeTaskPool`], e.g. to gather data from a large map without a query on [`TilePos`].
+    ///
+    /// Example:
+    /// ```
+    /// # use std::sync::atomic::{AtomicU32, Ordering};
+    /// # use bevy::prelude::World;
+    /// # use bevy_ecs_tilemap::prelude::*;
+    /// # use bevy_ecs_tilemap::test_utils::spawn_test_map;
+    /// # let mut world = World::new();
+    /// let map = spawn_test_map(&mut world, TilemapSize { x: 64, y: 64 }, TilemapType::Square);
+    /// let storage = world.get::<TileStorage>(map).unwrap();
+    /// let on_diagonal = AtomicU32::new(0);
+    /// storage.par_for_each(|tile_pos, _| {
+    ///     if tile_pos.x == tile_pos.y {
+    ///         on_diagonal.fetch_add(1, Ordering::Relaxed);
+    ///     }
+    /// });
+    /// assert_eq!(on_diagonal.into_inner(), 64);
+    /// ```
+    pub fn par_for_each(&self, f: impl Fn(TilePos, Entity) + Sync) {
+        if self.tiles.is_empty() {
+            return;
+        }
+        let task_pool = ComputeTaskPool::get_or_init(TaskPool::default);
+        let batch_size = self
+            .tiles
+            .len()
+            .div_ceil(task_pool.thread_num().max(1))
+            .max(1);
+        let f = &f;
+        task_pool.scope(|scope| {
+            for (batch, tiles) in self.tiles.chunks(batch_size).enumerate() {
+                scope.spawn(async move {
+                    for (offset, tile) in tiles.iter().enumerate() {
+                        if let Some(tile) = tile {
+                            let index = batch * batch_size + offset;
+                            f(self.axes.from_index(index, &self.size), *tile);
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    /// Returns the number of bytes the storage occupies, including the capacity of its grid.
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>() + self.tiles.capacity() * size_of::<Option<Entity>>()
+    }
+
+    /// Frees any capacity of the grid beyond the size of the map, e.g. left over from
+    /// deserializing or cloning a larger storage into this one.
+    pub fn compact(&mut self) {
+        self.tiles.shrink_to_fit();
+    }
+
     /// Removes any stored `Entity` at the given tile position, leaving `None` in its place and
     /// returning the `Entity`.
     ///
     /// Panics if the given `tile_pos` doesn't lie within the extents of the underlying tile map.
     pub fn remove(&mut self, tile_pos: &TilePos) -> Option<Entity> {
-        self.tiles[tile_pos.to_index(&self.size)].take()
+        let index = self.index(tile_pos);
+        self.tiles[index].take()
     }
 
     /// Remove any stored `Entity` at the given tile position, leaving `None` in its place and
@@ -99,7 +269,8 @@ impl TileStorage {
     ///
     /// Checks that the given `tile_pos` lies within the extents of the underlying map.
     pub fn checked_remove(&mut self, tile_pos: &TilePos) -> Option<Entity> {
-        self.tiles.get_mut(tile_pos.to_index(&self.size))?.take()
+        let index = self.index(tile_pos);
+        self.tiles.get_mut(index)?.take()
     }
 
     /// Removes all stored `Entity`s, leaving `None` in their place and
@@ -119,4 +290,133 @@ impl TileStorage {
     pub fn drain(&mut self) -> impl Iterator<Item = Entity> + use<'_> {
         self.tiles.iter_mut().filter_map(|opt| opt.take())
     }
+
+    /// Removes all stored `Entity`s, leaving `None` in their place and returning them along with
+    /// their positions in an iterator.
+    pub fn drain_with_pos(&mut self) -> impl Iterator<Item = (TilePos, Entity)> + use<'_> {
+        let (axes, size) = (self.axes, self.size);
+        self.tiles
+            .iter_mut()
+            .enumerate()
+            .filter_map(move |(index, tile)| Some((axes.from_index(index, &size), tile.take()?)))
+    }
+
+    /// Returns the tile entities that may overlap the given world-space `aabb`.
+    ///
+    /// This is meant as a broad-phase for tile-based physics: every tile touching `aabb` is
+    /// returned, along with some that only come close to it. It works for all [`TilemapType`]s and
+    /// takes the tilemap's transform into account.
+    ///
+    /// Example:
+    /// ```
+    /// # use bevy::math::bounding::Aabb2d;
+    /// # use bevy::prelude::{GlobalTransform, Vec2, World};
+    /// # use bevy_ecs_tilemap::prelude::*;
+    /// # use bevy_ecs_tilemap::test_utils::{spawn_test_map, tile_at};
+    /// # let mut world = World::new();
+    /// let map = spawn_test_map(&mut world, TilemapSize { x: 8, y: 8 }, TilemapType::Square);
+    /// let storage = world.get::<TileStorage>(map).unwrap();
+    /// let grid_size = world.get::<TilemapGridSize>(map).unwrap();
+    /// let tile_size = world.get::<TilemapTileSize>(map).unwrap();
+    ///
+    /// // A small body sitting on the center of tile (2, 3).
+    /// let body = Aabb2d::new(Vec2::new(32.0, 48.0), Vec2::splat(2.0));
+    /// let candidates: Vec<_> = storage
+    ///     .entities_in_world_aabb(
+    ///         body,
+    ///         grid_size,
+    ///         tile_size,
+    ///         &TilemapType::Square,
+    ///         &GlobalTransform::default(),
+    ///     )
+    ///     .collect();
+    /// assert!(candidates.contains(&tile_at(&world, map, TilePos { x: 2, y: 3 }).unwrap()));
+    /// assert!(!candidates.contains(&tile_at(&world, map, TilePos { x: 6, y: 6 }).unwrap()));
+    /// ```
+    pub fn entities_in_world_aabb(
+        &self,
+        aabb: Aabb2d,
+        grid_size: &TilemapGridSize,
+        tile_size: &TilemapTileSize,
+        map_type: &TilemapType,
+        map_transform: &GlobalTransform,
+    ) -> impl Iterator<Item = Entity> + use<'_> {
+        // Bring the AABB into the tilemap's local space, and grow it by half a tile so we can
+        // compare it against tile centers.
+        let world_to_map = map_transform.affine().inverse();
+        let half_tile = Vec2::from(grid_size).max(tile_size.into()) / 2.0;
+        let (local_min, local_max) = bounds_of(
+            [
+                aabb.min,
+                Vec2::new(aabb.min.x, aabb.max.y),
+                aabb.max,
+                Vec2::new(aabb.max.x, aabb.min.y),
+            ]
+            .map(|corner| world_to_map.transform_point3(corner.extend(0.0)).truncate()),
+        );
+        let (local_min, local_max) = (local_min - half_tile, local_max + half_tile);
+
+        // Tiles of every map type lie on a (possibly skewed) lattice, with every other row or
+        // column shifted by up to half a tile. Tile centers two steps apart cancel that shift out,
+        // which gives us the lattice basis to estimate the range of tile positions to look at.
+        let origin = TilePos::new(0, 0).center_in_world(grid_size, map_type);
+        let basis_x = (TilePos::new(2, 0).center_in_world(grid_size, map_type) - origin) / 2.0;
+        let basis_y = (TilePos::new(0, 2).center_in_world(grid_size, map_type) - origin) / 2.0;
+        let map_to_tile = Mat2::from_cols(basis_x, basis_y).inverse();
+        let (min_index, max_index) = bounds_of(
+            [
+                local_min,
+                Vec2::new(local_min.x, local_max.y),
+                local_max,
+                Vec2::new(local_max.x, local_min.y),
+            ]
+            .map(|corner| map_to_tile * (corner - origin)),
+        );
+
+        // One extra tile on each side accounts for the shifted rows or columns.
+        let min_index = (min_index.floor() - Vec2::ONE).max(Vec2::ZERO).as_ivec2();
+        let max_index = (max_index.ceil() + Vec2::ONE)
+            .min(Vec2::new(self.size.x as f32, self.size.y as f32) - Vec2::ONE)
+            .as_ivec2();
+
+        let grid_size = *grid_size;
+        let map_type = *map_type;
+        (min_index.y..=max_index.y)
+            .flat_map(move |y| {
+                (min_index.x..=max_index.x).map(move |x| TilePos::new(x as u32, y as u32))
+            })
+            .filter(move |tile_pos| {
+                let center = tile_pos.center_in_world(&grid_size, &map_type);
+                center.cmpge(local_min).all() && center.cmple(local_max).all()
+            })
+            .filter_map(|grid_pos| self.get(&self.axes.to_grid_pos(&grid_pos, &self.size)))
+    }
+}
+
+/// Returns the component-wise minimum and maximum of `points`.
+fn bounds_of(points: [Vec2; 4]) -> (Vec2, Vec2) {
+    points
+        .iter()
+        .fold((Vec2::MAX, Vec2::MIN), |(min, max), point| {
+            (min.min(*point), max.max(*point))
+        })
+}
+
+/// Clears the storage slots of removed tiles. Runs as an observer, as the tile's position is gone
+/// afterwards.
+pub(crate) fn clear_removed_tile_slots(
+    trigger: Trigger<OnRemove, TilePos>,
+    tiles: Query<(&TilePos, &TilemapId)>,
+    mut storages: Query<&mut TileStorage>,
+) {
+    let Ok((tile_pos, tilemap_id)) = tiles.get(trigger.entity()) else {
+        return;
+    };
+    let Ok(mut storage) = storages.get_mut(tilemap_id.0) else {
+        return;
+    };
+    // The slot may already hold another tile, e.g. when the storage was updated first.
+    if storage.checked_get(tile_pos) == Some(trigger.entity()) {
+        storage.remove(tile_pos);
+    }
 }
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use super::{TilePos, TileStorage, TileTextureIndex};
+use crate::map::{
+    is_frozen, TilemapId, TilemapSpacing, TilemapStatic, TilemapTexture, TilemapTileSize,
+};
+
+/// Decides how [`TileTextureIndex`]es are checked against the number of tiles in the texture of
+/// their tilemap. Indices past the last tile otherwise sample another tile or garbage, without
+/// any error.
+///
+/// Tiles are checked when their index changes, and all the tiles of a tilemap when its texture
+/// changes or finishes loading. Insert it as a resource to change the default, which is
+/// [`TileTextureIndexCheck::Debug`].
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TileTextureIndexCheck {
+    /// Never checks.
+    Off,
+    /// Warns about invalid indices in debug builds only.
+    #[default]
+    Debug,
+    /// Warns about invalid indices in every build.
+    Warn,
+    /// Panics on the first invalid index, in every build, e.g. for tests loading every level.
+    Strict,
+}
+
+impl TileTextureIndexCheck {
+    fn enabled(&self) -> bool {
+        match self {
+            TileTextureIndexCheck::Off => false,
+            TileTextureIndexCheck::Debug => cfg!(debug_assertions),
+            TileTextureIndexCheck::Warn | TileTextureIndexCheck::Strict => true,
+        }
+    }
+}
+
+pub(crate) fn texture_index_check_enabled(check: Option<Res<TileTextureIndexCheck>>) -> bool {
+    check
+        .map_or(TileTextureIndexCheck::default(), |check| *check)
+        .enabled()
+}
+
+/// Checks the [`TileTextureIndex`] of changed tiles, and of every tile of tilemaps whose texture
+/// changed, against the number of tiles in the texture.
+#[allow(clippy::type_complexity)]
+pub(crate) fn check_tile_texture_indices(
+    check: Option<Res<TileTextureIndexCheck>>,
+    images: Option<Res<Assets<Image>>>,
+    mut pending: Local<HashSet<Entity>>,
+    tilemaps: Query<(
+        Entity,
+        Ref<TilemapTexture>,
+        &TilemapTileSize,
+        &TilemapSpacing,
+        &TileStorage,
+    )>,
+    changed_tiles: Query<(Entity, &TilemapId), Changed<TileTextureIndex>>,
+    tiles: Query<(&TilePos, &TileTextureIndex)>,
+    static_tilemaps: Query<Ref<TilemapStatic>>,
+) {
+    let Some(images) = images else {
+        return;
+    };
+    let strict = check.is_some_and(|check| *check == TileTextureIndexCheck::Strict);
+
+    // Tilemaps whose texture is still loading are pending, and all of their tiles are checked
+    // once it is loaded.
+    let mut changed: HashMap<Entity, Vec<Entity>> = HashMap::default();
+    for (tile_entity, tilemap_id) in changed_tiles.iter() {
+        if !pending.contains(&tilemap_id.0) && !is_frozen(&static_tilemaps, tilemap_id.0) {
+            changed.entry(tilemap_id.0).or_default().push(tile_entity);
+        }
+    }
+    for (tilemap, texture, ..) in tilemaps.iter() {
+        if texture.is_changed() {
+            pending.insert(tilemap);
+            changed.remove(&tilemap);
+        }
+    }
+
+    // Returns false if the texture of the tilemap isn't loaded yet.
+    let check_tiles = |tilemap: Entity, tile_entities: &mut dyn Iterator<Item = Entity>| {
+        let Ok((_, texture, tile_size, spacing, _)) = tilemaps.get(tilemap) else {
+            return true;
+        };
+        let Some(tile_count) = texture.tile_count(&images, tile_size, spacing) else {
+            return false;
+        };
+        let mut invalid = tile_entities
+            .filter_map(|tile| tiles.get(tile).ok())
+            .filter(|(_, texture_index)| texture_index.0 >= tile_count);
+        let Some((tile_pos, texture_index)) = invalid.next() else {
+            return true;
+        };
+        let message = format!(
+            "The tile at {tile_pos:?} of tilemap {tilemap} has the texture index {}, but the \
+             texture of the tilemap only has {tile_count} tiles ({} more tiles of the tilemap \
+             are out of range).",
+            texture_index.0,
+            invalid.count()
+        );
+        if strict {
+            panic!("{message}");
+        }
+        warn!("{message}");
+        true
+    };
+
+    for (tilemap, tile_entities) in changed {
+        if !check_tiles(tilemap, &mut tile_entities.into_iter()) {
+            pending.insert(tilemap);
+        }
+    }
+    pending.retain(|tilemap| {
+        let Ok((.., storage)) = tilemaps.get(*tilemap) else {
+            return false;
+        };
+        !check_tiles(*tilemap, &mut storage.iter().flatten().copied())
+    });
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use super::TileTextureIndex;
+use crate::TilemapSystemSet;
+
+/// Custom data of a tile, handed to the shaders of tilemaps whose `MaterialTilemap` returns true
+/// from `MaterialTilemap::tile_vertex_data`, e.g. the heat or the wetness of the tile.
+///
+/// The shaders get it as the `vertex_data` of the `VertexInput` at `@location(7)`, and the
+/// default vertex shader passes it on to the fragment shader as the `vertex_data` of the
+/// `MeshVertexOutput`, both only when `TILE_VERTEX_DATA` is defined. Tiles without it get zeros.
+///
+/// Set it directly, or have it filled from a component of your own by a
+/// [`TileVertexDataPlugin`]. Changing it remeshes the render chunk of the tile.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileVertexData(pub Vec4);
+
+/// Fills the [`TileVertexData`] of tiles from their `C` component, whenever it changes, and
+/// removes it along with the component.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_ecs_tilemap::prelude::*;
+/// #[derive(Component)]
+/// struct Heat(f32);
+///
+/// App::new().add_plugins(TileVertexDataPlugin::new(|heat: &Heat| Vec4::new(heat.0, 0.0, 0.0, 0.0)));
+/// ```
+pub struct TileVertexDataPlugin<C: Component> {
+    to_data: Arc<dyn Fn(&C) -> Vec4 + Send + Sync>,
+}
+
+impl<C: Component> TileVertexDataPlugin<C> {
+    /// Fills the vertex data of tiles with what `to_data` returns for their `C` component.
+    pub fn new(to_data: impl Fn(&C) -> Vec4 + Send + Sync + 'static) -> Self {
+        Self {
+            to_data: Arc::new(to_data),
+        }
+    }
+}
+
+impl<C: Component> Plugin for TileVertexDataPlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TileVertexDataSource::<C> {
+            to_data: self.to_data.clone(),
+        })
+        .add_systems(
+            PostUpdate,
+            (
+                fill_tile_vertex_data::<C>,
+                remove_filled_tile_vertex_data::<C>,
+            )
+                .in_set(TilemapSystemSet::ExtractionPrep),
+        );
+    }
+}
+
+#[derive(Resource)]
+struct TileVertexDataSource<C: Component> {
+    to_data: Arc<dyn Fn(&C) -> Vec4 + Send + Sync>,
+}
+
+fn fill_tile_vertex_data<C: Component>(
+    mut commands: Commands,
+    source: Res<TileVertexDataSource<C>>,
+    mut tiles: Query<(Entity, &C, Option<&mut TileVertexData>), Changed<C>>,
+) {
+    for (tile_entity, component, vertex_data) in tiles.iter_mut() {
+        let data = TileVertexData((source.to_data)(component));
+        match vertex_data {
+            Some(mut vertex_data) => {
+                vertex_data.set_if_neq(data);
+            }
+            None => {
+                commands.entity(tile_entity).insert(data);
+            }
+        }
+    }
+}
+
+fn remove_filled_tile_vertex_data<C: Component>(
+    mut commands: Commands,
+    mut removed: RemovedComponents<C>,
+) {
+    for tile_entity in removed.read() {
+        if let Some(mut tile) = commands.get_entity(tile_entity) {
+            tile.remove::<TileVertexData>();
+        }
+    }
+}
+
+/// Marks the [`TileTextureIndex`] of tiles that lost their [`TileVertexData`] as changed, so
+/// that the render world gives them zeros again.
+pub(crate) fn refresh_removed_tile_vertex_data(
+    mut removed: RemovedComponents<TileVertexData>,
+    mut texture_indices: Query<&mut TileTextureIndex>,
+) {
+    for tile_entity in removed.read() {
+        if let Ok(mut texture_index) = texture_indices.get_mut(tile_entity) {
+            texture_index.set_changed();
+        }
+    }
+}